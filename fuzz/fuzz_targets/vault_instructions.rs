@@ -0,0 +1,18 @@
+#![no_main]
+
+use kvault_fuzz::{FuzzHarness, FuzzInput, MAX_STEPS};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: FuzzInput| {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let mut harness = FuzzHarness::new().await;
+        for step in input.steps.iter().take(MAX_STEPS) {
+            harness.run_step(step).await;
+        }
+    });
+});
@@ -0,0 +1,133 @@
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+// Longer sequences mostly replay failed transactions, each step costs a bank transaction
+pub const MAX_STEPS: usize = 24;
+
+#[derive(Arbitrary, Debug)]
+pub struct FuzzInput {
+    pub steps: Vec<FuzzStep>,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct FuzzStep {
+    pub action: FuzzAction,
+    // Applied to the accounts of the instruction built for the action, if any
+    pub mutation: Option<AccountMutation>,
+}
+
+/// Users and reserves are picked by index, modulo the number of users and reserves of
+/// the harness. The reserve index past the vault reserves picks a reserve of another
+/// lending market, which is not allocated in the vault
+#[derive(Arbitrary, Debug)]
+pub enum FuzzAction {
+    Deposit {
+        user: u8,
+        amount: u64,
+    },
+    Withdraw {
+        user: u8,
+        reserve: u8,
+        shares: u64,
+    },
+    WithdrawFromAvailable {
+        user: u8,
+        shares: u64,
+    },
+    WithdrawFromReserves {
+        user: u8,
+        reserves: Vec<u8>,
+        shares: u64,
+    },
+    RedeemInKind {
+        user: u8,
+        reserve: u8,
+        shares: u64,
+    },
+    RequestWithdrawal {
+        user: u8,
+        shares: u64,
+    },
+    // Requests are picked by id, modulo the number of requests made so far plus one, so
+    // that the id of the next request is tried as well
+    CancelWithdrawalRequest {
+        user: u8,
+        request: u8,
+    },
+    FulfilWithdrawalRequest {
+        user: u8,
+        request: u8,
+        reserve: u8,
+    },
+    FulfilWithdrawalRequestFromAvailable {
+        user: u8,
+        request: u8,
+    },
+    ClaimWithdrawalRequest {
+        user: u8,
+        request: u8,
+    },
+    Invest {
+        reserve: u8,
+    },
+    Rebalance,
+    // `entry` is written as is in the instruction data, values past the last config
+    // field are expected to be rejected
+    UpdateVaultConfig {
+        entry: u8,
+        data: Vec<u8>,
+    },
+    WithdrawPendingFees {
+        reserve: u8,
+    },
+    GiveUpPendingFees {
+        amount: u64,
+    },
+    TopupRewards {
+        amount: u64,
+    },
+    AdvanceTime {
+        seconds: u32,
+    },
+}
+
+/// Permutation of the accounts passed to an instruction, the indexes wrap around the
+/// number of accounts
+#[derive(Arbitrary, Debug)]
+pub enum AccountMutation {
+    Swap { first: u8, second: u8 },
+    // Replaces an account with one of the accounts known to the harness
+    Replace { index: u8, pool_index: u8 },
+    Truncate { len: u8 },
+    Duplicate { index: u8 },
+}
+
+impl AccountMutation {
+    pub fn apply(&self, instruction: &mut Instruction, account_pool: &[Pubkey]) {
+        let accounts = &mut instruction.accounts;
+        if accounts.is_empty() {
+            return;
+        }
+        let wrap = |idx: u8, len: usize| usize::from(idx) % len;
+
+        match *self {
+            AccountMutation::Swap { first, second } => {
+                let len = accounts.len();
+                accounts.swap(wrap(first, len), wrap(second, len));
+            }
+            AccountMutation::Replace { index, pool_index } => {
+                if !account_pool.is_empty() {
+                    let idx = wrap(index, accounts.len());
+                    accounts[idx].pubkey = account_pool[wrap(pool_index, account_pool.len())];
+                }
+            }
+            AccountMutation::Truncate { len } => {
+                accounts.truncate(wrap(len, accounts.len()));
+            }
+            AccountMutation::Duplicate { index } => {
+                let account = accounts[wrap(index, accounts.len())].clone();
+                accounts.push(account);
+            }
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use anchor_lang::AccountDeserialize;
+use kamino_lending::{fraction::Fraction, utils::FractionExtra};
+use kvault_integration_tests::{
+    kvault_client::{
+        accounts::VaultReserve,
+        instructions,
+        kamino_vault::{
+            utils::consts::INITIAL_DEPOSIT_AMOUNT, VaultConfigField, VaultState, WithdrawalRequest,
+        },
+        math::{self, ReserveSnapshot},
+        pda,
+    },
+    vault::{BASE_DECIMALS, ONE_TOKEN},
+    ReserveFixture, TestContext, VaultFixture,
+};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+use crate::actions::{FuzzAction, FuzzStep};
+
+const VAULT_RESERVE_COUNT: usize = 2;
+const USER_COUNT: usize = 3;
+const USER_BASE_BALANCE: u64 = 10_000 * ONE_TOKEN;
+const FOREIGN_RESERVE_LIQUIDITY: u64 = 1_000_000 * ONE_TOKEN;
+const MAX_REWARDS_TOPUP: u64 = 100 * ONE_TOKEN;
+const MAX_TIME_ADVANCE_SECONDS: u64 = 30 * 24 * 60 * 60;
+
+// The invest rounding losses are paid by the payer and end up in the vault, at most one
+// token lamport for each reserve an instruction invests in
+const ROUNDING_TOLERANCE_PER_TRANSACTION: u64 = VAULT_RESERVE_COUNT as u64;
+
+/// A vault allocated to two reserves, three users holding base tokens and ctoken accounts,
+/// and a reserve of another lending market and mint that the vault does not allocate to.
+///
+/// Nothing earns interest in this setup, the only value the users can gain on top of
+/// their own tokens comes from the admin: the initial deposit of the vault, the rewards
+/// and the invest rounding losses.
+pub struct FuzzHarness {
+    ctx: TestContext,
+    vault: VaultFixture,
+    foreign_reserve: ReserveFixture,
+    users: Vec<Keypair>,
+    // Candidates for the account replacements of the mutations
+    account_pool: Vec<Pubkey>,
+    rewards_topped_up: u64,
+    successful_transactions: u64,
+    next_request_id: u64,
+}
+
+impl FuzzHarness {
+    pub async fn new() -> Self {
+        let mut ctx = TestContext::start().await;
+        let vault = ctx.setup_vault(VAULT_RESERVE_COUNT).await;
+
+        let foreign_market = ctx.create_lending_market();
+        let foreign_mint = ctx.create_mint(BASE_DECIMALS);
+        let foreign_reserve = ctx
+            .create_reserve(
+                &foreign_market,
+                &foreign_mint,
+                BASE_DECIMALS,
+                FOREIGN_RESERVE_LIQUIDITY,
+                FOREIGN_RESERVE_LIQUIDITY,
+            )
+            .await;
+
+        let mut users = Vec::with_capacity(USER_COUNT);
+        for _ in 0..USER_COUNT {
+            let user = ctx.create_user(&vault, USER_BASE_BALANCE).await;
+            for reserve in vault.reserves.iter().chain([&foreign_reserve]) {
+                ctx.create_ata(&user.pubkey(), &reserve.collateral_mint, &spl_token::ID)
+                    .await;
+            }
+            users.push(user);
+        }
+
+        let mut harness = Self {
+            ctx,
+            vault,
+            foreign_reserve,
+            users,
+            account_pool: vec![],
+            rewards_topped_up: 0,
+            successful_transactions: 0,
+            next_request_id: 0,
+        };
+        harness.account_pool = harness.collect_account_pool();
+        harness
+    }
+
+    /// Runs the step and checks that the program did not panic and that the vault
+    /// invariants still hold
+    pub async fn run_step(&mut self, step: &FuzzStep) {
+        if let FuzzAction::AdvanceTime { seconds } = step.action {
+            self.ctx
+                .advance_time(bounded(u64::from(seconds), MAX_TIME_ADVANCE_SECONDS))
+                .await;
+            return;
+        }
+
+        let mut instruction = self.instruction_for(&step.action);
+        if let Some(mutation) = &step.mutation {
+            mutation.apply(&mut instruction, &self.account_pool);
+        }
+        let signers = self.signers_for(&mut instruction);
+        let signers: Vec<&Keypair> = signers.iter().collect();
+
+        // Mutations can make the transaction invalid before it reaches the program
+        let Ok(outcome) = self.ctx.try_process(&[instruction], &signers).await else {
+            return;
+        };
+        assert!(
+            !outcome.logs_contain("panicked"),
+            "{step:?} panicked, logs:\n{}",
+            outcome.logs.join("\n")
+        );
+
+        if outcome.result.is_ok() {
+            self.successful_transactions += 1;
+            match step.action {
+                FuzzAction::TopupRewards { amount } => {
+                    self.rewards_topped_up += bounded(amount, MAX_REWARDS_TOPUP);
+                }
+                FuzzAction::UpdateVaultConfig { .. } => {
+                    self.ctx.reload_vault(&mut self.vault).await;
+                }
+                _ => {}
+            }
+        }
+
+        self.check_invariants(step).await;
+    }
+
+    fn instruction_for(&self, action: &FuzzAction) -> Instruction {
+        let vault = &self.vault.accounts;
+        let admin = self.ctx.admin.pubkey();
+        // Up to twice what a user holds, so that the insufficient funds paths are hit too
+        let max_amount = 2 * USER_BASE_BALANCE;
+
+        match action {
+            FuzzAction::Deposit { user, amount } => {
+                instructions::deposit(vault, self.user(*user), bounded(*amount, max_amount))
+            }
+            FuzzAction::Withdraw {
+                user,
+                reserve,
+                shares,
+            } => instructions::withdraw(
+                vault,
+                self.user(*user),
+                &self.reserve(*reserve),
+                bounded(*shares, max_amount),
+            ),
+            FuzzAction::WithdrawFromAvailable { user, shares } => {
+                instructions::withdraw_from_available(
+                    vault,
+                    self.user(*user),
+                    bounded(*shares, max_amount),
+                )
+            }
+            FuzzAction::WithdrawFromReserves {
+                user,
+                reserves,
+                shares,
+            } => {
+                let reserves: Vec<VaultReserve> = reserves
+                    .iter()
+                    .take(VAULT_RESERVE_COUNT + 1)
+                    .map(|reserve| self.reserve(*reserve))
+                    .collect();
+                instructions::withdraw_from_reserves(
+                    vault,
+                    self.user(*user),
+                    &reserves,
+                    bounded(*shares, max_amount),
+                    0,
+                )
+            }
+            FuzzAction::RedeemInKind {
+                user,
+                reserve,
+                shares,
+            } => instructions::redeem_in_kind(
+                vault,
+                self.user(*user),
+                &self.reserve(*reserve),
+                bounded(*shares, max_amount),
+            ),
+            FuzzAction::RequestWithdrawal { user, shares } => instructions::request_withdrawal(
+                vault,
+                self.user(*user),
+                self.next_request_id,
+                bounded(*shares, max_amount),
+            ),
+            FuzzAction::CancelWithdrawalRequest { user, request } => {
+                instructions::cancel_withdrawal_request(
+                    vault,
+                    self.user(*user),
+                    self.request_id(*request),
+                )
+            }
+            FuzzAction::FulfilWithdrawalRequest {
+                user,
+                request,
+                reserve,
+            } => instructions::fulfil_withdrawal_request(
+                vault,
+                self.user(*user),
+                self.request_id(*request),
+                &self.reserve(*reserve),
+            ),
+            FuzzAction::FulfilWithdrawalRequestFromAvailable { user, request } => {
+                instructions::fulfil_withdrawal_request_from_available(
+                    vault,
+                    self.user(*user),
+                    self.request_id(*request),
+                )
+            }
+            FuzzAction::ClaimWithdrawalRequest { user, request } => {
+                instructions::claim_withdrawal_request(
+                    vault,
+                    self.user(*user),
+                    self.request_id(*request),
+                )
+            }
+            FuzzAction::Invest { reserve } => {
+                instructions::invest(vault, admin, &self.reserve(*reserve), false)
+            }
+            FuzzAction::Rebalance => instructions::rebalance(vault, admin, &[]),
+            FuzzAction::UpdateVaultConfig { entry, data } => {
+                let mut instruction = instructions::update_vault_config(
+                    vault,
+                    admin,
+                    VaultConfigField::PerformanceFeeBps,
+                    data.clone(),
+                );
+                // The entry is the first argument, right after the 8 bytes discriminator
+                instruction.data[8] = *entry;
+                instruction
+            }
+            FuzzAction::WithdrawPendingFees { reserve } => {
+                instructions::withdraw_pending_fees(vault, &self.reserve(*reserve))
+            }
+            FuzzAction::GiveUpPendingFees { amount } => {
+                instructions::give_up_pending_fees(vault, *amount)
+            }
+            FuzzAction::TopupRewards { amount } => {
+                instructions::topup_rewards(vault, admin, bounded(*amount, MAX_REWARDS_TOPUP))
+            }
+            FuzzAction::AdvanceTime { .. } => {
+                unreachable!("Time is advanced without a transaction")
+            }
+        }
+    }
+
+    fn user(&self, idx: u8) -> Pubkey {
+        self.users[usize::from(idx) % self.users.len()].pubkey()
+    }
+
+    fn request_id(&self, idx: u8) -> u64 {
+        bounded(u64::from(idx), self.next_request_id)
+    }
+
+    fn reserve(&self, idx: u8) -> VaultReserve {
+        let idx = usize::from(idx) % (self.vault.reserves.len() + 1);
+        self.vault
+            .reserves
+            .get(idx)
+            .unwrap_or(&self.foreign_reserve)
+            .vault_reserve()
+    }
+
+    /// Keypairs of the signer accounts of the instruction, accounts nobody can sign for
+    /// after a mutation are passed as non signers
+    fn signers_for(&self, instruction: &mut Instruction) -> Vec<Keypair> {
+        let payer = self.ctx.payer();
+        let mut signers: Vec<Keypair> = vec![];
+
+        for account in instruction.accounts.iter_mut() {
+            if !account.is_signer
+                || account.pubkey == payer
+                || signers
+                    .iter()
+                    .any(|signer| signer.pubkey() == account.pubkey)
+            {
+                continue;
+            }
+
+            match [&self.ctx.admin]
+                .into_iter()
+                .chain(&self.users)
+                .find(|keypair| keypair.pubkey() == account.pubkey)
+            {
+                Some(keypair) => signers.push(keypair.insecure_clone()),
+                None => account.is_signer = false,
+            }
+        }
+
+        signers
+    }
+
+    // Every account the unmutated instructions of the harness reference
+    fn collect_account_pool(&self) -> Vec<Pubkey> {
+        let vault = &self.vault.accounts;
+        let admin = self.ctx.admin.pubkey();
+        let reserves: Vec<VaultReserve> = (0..=VAULT_RESERVE_COUNT as u8)
+            .map(|idx| self.reserve(idx))
+            .collect();
+
+        let mut sample_instructions = vec![
+            instructions::rebalance(vault, admin, &[]),
+            instructions::topup_rewards(vault, admin, 0),
+        ];
+        for reserve in reserves.iter() {
+            sample_instructions.push(instructions::invest(vault, admin, reserve, false));
+            for user in self.users.iter() {
+                sample_instructions.push(instructions::withdraw(vault, user.pubkey(), reserve, 0));
+                sample_instructions.push(instructions::redeem_in_kind(
+                    vault,
+                    user.pubkey(),
+                    reserve,
+                    0,
+                ));
+                sample_instructions.push(instructions::fulfil_withdrawal_request(
+                    vault,
+                    user.pubkey(),
+                    0,
+                    reserve,
+                ));
+            }
+        }
+
+        let mut account_pool: Vec<Pubkey> = vec![];
+        for account in sample_instructions
+            .iter()
+            .flat_map(|instruction| instruction.accounts.iter())
+        {
+            if !account_pool.contains(&account.pubkey) {
+                account_pool.push(account.pubkey);
+            }
+        }
+        account_pool
+    }
+
+    async fn check_invariants(&mut self, step: &FuzzStep) {
+        let state = self.ctx.vault_state(&self.vault.vault_state()).await;
+        self.next_request_id = state.withdrawal_queue.next_request_id;
+
+        // The vault holds at least the base tokens its accounting relies on
+        let token_vault_balance = self
+            .ctx
+            .token_balance(&self.vault.accounts.token_vault)
+            .await;
+        let accounted_tokens = state.token_available
+            + state.withdrawal_queue.claimable_tokens
+            + state.reward_info.rewards_available;
+        assert!(
+            token_vault_balance >= accounted_tokens,
+            "After {step:?}: token vault holds {token_vault_balance}, the vault accounts for \
+             {accounted_tokens}"
+        );
+
+        // The ctokens of an allocation are exactly the ones held in its ctoken vault
+        for allocation in state
+            .vault_allocation_strategy
+            .iter()
+            .filter(|allocation| allocation.reserve != Pubkey::default())
+        {
+            let ctoken_vault_balance = self.ctx.token_balance(&allocation.ctoken_vault).await;
+            assert_eq!(
+                allocation.ctoken_allocation, ctoken_vault_balance,
+                "After {step:?}: ctoken allocation of {} does not match its ctoken vault",
+                allocation.reserve
+            );
+        }
+
+        let users_value = self.users_value(&state).await;
+        let max_users_value = USER_COUNT as u64 * USER_BASE_BALANCE
+            + INITIAL_DEPOSIT_AMOUNT
+            + self.rewards_topped_up
+            + ROUNDING_TOLERANCE_PER_TRANSACTION * self.successful_transactions;
+        assert!(
+            users_value <= Fraction::from(max_users_value),
+            "After {step:?}: the users hold {} worth of tokens, shares and ctokens, more than \
+             the {max_users_value} brought into the vault",
+            users_value.to_display()
+        );
+    }
+
+    /// Value of everything the users hold: base tokens, shares in their accounts or
+    /// escrowed in withdrawal requests, claimable tokens and ctokens redeemed in kind
+    async fn users_value(&mut self, state: &VaultState) -> Fraction {
+        let clock = self.ctx.clock().await;
+        let timestamp = u64::try_from(clock.unix_timestamp).unwrap();
+
+        let mut reserve_snapshots = vec![];
+        for allocation in state
+            .vault_allocation_strategy
+            .iter()
+            .filter(|allocation| allocation.reserve != Pubkey::default())
+        {
+            let mut reserve = *self.ctx.reserve_state(&allocation.reserve).await;
+            // The reserves have a zero borrow rate, refreshing them would not change
+            // their exchange rate
+            reserve.last_update.slot = clock.slot;
+            reserve.last_update.stale = 0;
+            reserve_snapshots.push(ReserveSnapshot::new(allocation.reserve, reserve));
+        }
+        let share_price = math::share_price(state, &reserve_snapshots, clock.slot, timestamp)
+            .expect("Share price of the vault cannot be computed");
+
+        let reserves: Vec<ReserveFixture> = self
+            .vault
+            .reserves
+            .iter()
+            .chain([&self.foreign_reserve])
+            .copied()
+            .collect();
+        let users: Vec<Pubkey> = self.users.iter().map(Signer::pubkey).collect();
+
+        let mut users_value = Fraction::ZERO;
+        for user in users {
+            let mut tokens = self.ctx.base_balance(&self.vault, &user).await;
+            let mut shares = self.ctx.shares_balance(&self.vault, &user).await;
+
+            for request_id in 0..self.next_request_id {
+                let withdrawal_request =
+                    pda::withdrawal_request(&self.vault.vault_state(), &user, request_id);
+                if let Some(account) = self.ctx.get_account(&withdrawal_request).await {
+                    if let Ok(request) =
+                        WithdrawalRequest::try_deserialize(&mut account.data.as_slice())
+                    {
+                        tokens += request.tokens_claimable;
+                        shares += request.shares_escrowed;
+                    }
+                }
+            }
+
+            users_value += Fraction::from(tokens) + Fraction::from(shares) * share_price;
+
+            for reserve in reserves.iter() {
+                let user_ctoken_ata = spl_associated_token_account::get_associated_token_address(
+                    &user,
+                    &reserve.collateral_mint,
+                );
+                let ctokens = self.ctx.token_balance(&user_ctoken_ata).await;
+                if ctokens > 0 {
+                    let reserve_state = self.ctx.reserve_state(&reserve.address).await;
+                    users_value += reserve_state
+                        .collateral_exchange_rate()
+                        .fraction_collateral_to_liquidity(Fraction::from(ctokens));
+                }
+            }
+        }
+
+        users_value
+    }
+}
+
+fn bounded(value: u64, max: u64) -> u64 {
+    value % (max + 1)
+}
@@ -0,0 +1,26 @@
+//! Fuzzing of the kvault instruction surface.
+//!
+//! Each input is a sequence of kvault instructions with arbitrary arguments, run
+//! against a fresh `solana-program-test` bank set up by the integration test harness.
+//! The accounts of any instruction can be permuted, replaced by other accounts of the
+//! setup, truncated or duplicated, and `update_vault_config` gets arbitrary entries and
+//! payloads. After every step the harness checks that the program did not panic, that
+//! the token and ctoken vaults back the vault accounting, and that the users cannot
+//! end up with more value than they brought in.
+//!
+//! The programs are loaded from their .so files, both have to be in `SBF_OUT_DIR`:
+//!
+//! ```sh
+//! cargo build-sbf --manifest-path programs/kvault/Cargo.toml
+//! solana program dump -um KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD \
+//!     target/deploy/kamino_lending.so
+//! SBF_OUT_DIR=$PWD/target/deploy cargo +nightly fuzz run vault_instructions
+//! ```
+//!
+//! Every input boots its own bank, expect a few executions per second.
+
+mod actions;
+mod harness;
+
+pub use actions::{AccountMutation, FuzzAction, FuzzInput, FuzzStep, MAX_STEPS};
+pub use harness::FuzzHarness;
@@ -0,0 +1,186 @@
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey, Result},
+    Discriminator,
+};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use kamino_lending::Reserve;
+use kamino_vault::{GlobalConfig, KaminoVaultError, VaultState};
+
+use crate::pda;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VaultReserve {
+    pub address: Pubkey,
+    pub lending_market: Pubkey,
+    pub lending_market_authority: Pubkey,
+    pub liquidity_supply: Pubkey,
+    pub collateral_mint: Pubkey,
+}
+
+impl VaultReserve {
+    pub fn new(address: Pubkey, reserve: &Reserve) -> Self {
+        Self {
+            address,
+            lending_market: reserve.lending_market,
+            lending_market_authority: pda::lending_market_authority(&reserve.lending_market),
+            liquidity_supply: reserve.liquidity.supply_vault,
+            collateral_mint: reserve.collateral.mint_pubkey,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultAccounts {
+    pub program_id: Pubkey,
+    pub vault_state: Pubkey,
+    pub vault_admin_authority: Pubkey,
+    pub base_vault_authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_vault: Pubkey,
+    pub token_program: Pubkey,
+    pub shares_mint: Pubkey,
+    pub shares_token_program: Pubkey,
+    pub permissioned_deposits: bool,
+    pub fee_recipients: Vec<Pubkey>,
+    pub fee_shares_recipient: Pubkey,
+    // Ordered as the allocations of the vault, which is the order the program expects
+    // them in the remaining accounts
+    pub reserves: Vec<VaultReserve>,
+}
+
+impl VaultAccounts {
+    /// `reserves` can be given in any order and may contain reserves the vault is not
+    /// allocated to, every allocation of the vault must however be present
+    pub fn new(
+        program_id: Pubkey,
+        vault_state: Pubkey,
+        vault: &VaultState,
+        shares_token_program: Pubkey,
+        reserves: &[VaultReserve],
+    ) -> Result<Self> {
+        let reserves = vault
+            .vault_allocation_strategy
+            .iter()
+            .filter(|allocation| allocation.reserve != Pubkey::default())
+            .map(|allocation| {
+                reserves
+                    .iter()
+                    .find(|reserve| reserve.address == allocation.reserve)
+                    .copied()
+                    .ok_or_else(|| {
+                        anchor_lang::error!(KaminoVaultError::ReserveNotProvidedInTheAccounts)
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            program_id,
+            vault_state,
+            vault_admin_authority: vault.vault_admin_authority,
+            base_vault_authority: vault.base_vault_authority,
+            token_mint: vault.token_mint,
+            token_vault: vault.token_vault,
+            token_program: vault.token_program,
+            shares_mint: vault.shares_mint,
+            shares_token_program,
+            permissioned_deposits: vault.vault_has_permissioned_deposits(),
+            fee_recipients: vault
+                .fee_recipients
+                .iter()
+                .filter(|fee_recipient| fee_recipient.recipient != Pubkey::default())
+                .map(|fee_recipient| fee_recipient.recipient)
+                .collect(),
+            fee_shares_recipient: vault.fee_shares_recipient,
+            reserves,
+        })
+    }
+
+    pub fn reserve(&self, address: &Pubkey) -> Result<&VaultReserve> {
+        self.reserves
+            .iter()
+            .find(|reserve| reserve.address == *address)
+            .ok_or_else(|| anchor_lang::error!(KaminoVaultError::ReserveNotPartOfAllocations))
+    }
+
+    pub fn global_config(&self) -> Pubkey {
+        pda::global_config_program_id(&self.program_id)
+    }
+
+    pub fn event_authority(&self) -> Pubkey {
+        pda::event_authority_program_id(&self.program_id)
+    }
+
+    pub fn ctoken_vault(&self, reserve: &Pubkey) -> Pubkey {
+        pda::ctoken_vault_program_id(&self.program_id, &self.vault_state, reserve)
+    }
+
+    pub fn user_token_ata(&self, user: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(user, &self.token_mint, &self.token_program)
+    }
+
+    pub fn user_shares_ata(&self, user: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(
+            user,
+            &self.shares_mint,
+            &self.shares_token_program,
+        )
+    }
+
+    /// Owners of the shares token accounts the fee shares are minted to: the fee recipients,
+    /// or the fee shares recipient if the vault has none
+    pub fn fee_shares_recipients(&self) -> Vec<Pubkey> {
+        if self.fee_recipients.is_empty() {
+            vec![self.fee_shares_recipient]
+        } else {
+            self.fee_recipients.clone()
+        }
+    }
+
+    /// Remaining accounts as laid out by the program: all the reserves of the vault,
+    /// then the instruction specific accounts, then the lending markets of the reserves
+    pub fn remaining_accounts(&self, extra_accounts: Vec<AccountMeta>) -> Vec<AccountMeta> {
+        let mut lending_markets: Vec<Pubkey> = Vec::with_capacity(self.reserves.len());
+        for reserve in self.reserves.iter() {
+            if !lending_markets.contains(&reserve.lending_market) {
+                lending_markets.push(reserve.lending_market);
+            }
+        }
+
+        self.reserves
+            .iter()
+            .map(|reserve| AccountMeta::new(reserve.address, false))
+            .chain(extra_accounts)
+            .chain(
+                lending_markets
+                    .into_iter()
+                    .map(|lending_market| AccountMeta::new_readonly(lending_market, false)),
+            )
+            .collect()
+    }
+}
+
+pub fn decode_vault_state(data: &[u8]) -> Result<Box<VaultState>> {
+    decode_zero_copy(data)
+}
+
+pub fn decode_global_config(data: &[u8]) -> Result<Box<GlobalConfig>> {
+    decode_zero_copy(data)
+}
+
+pub fn decode_reserve(data: &[u8]) -> Result<Box<Reserve>> {
+    decode_zero_copy(data)
+}
+
+fn decode_zero_copy<T: Discriminator + bytemuck::Pod>(data: &[u8]) -> Result<Box<T>> {
+    let discriminator_len = T::discriminator().len();
+    if data.len() < discriminator_len + std::mem::size_of::<T>() {
+        return Err(anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into());
+    }
+    if data[..discriminator_len] != T::discriminator() {
+        return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+
+    Ok(Box::new(bytemuck::pod_read_unaligned(
+        &data[discriminator_len..discriminator_len + std::mem::size_of::<T>()],
+    )))
+}
@@ -0,0 +1,1418 @@
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey},
+    solana_program::{instruction::Instruction, system_program, sysvar},
+    InstructionData, ToAccountMetas,
+};
+use anchor_spl::{associated_token::get_associated_token_address_with_program_id, token_2022};
+use kamino_vault::{
+    accounts, instruction, utils::global_config::UpdateGlobalConfigMode,
+    UpdateReserveWhitelistMode, VaultConfigField,
+};
+
+use crate::{
+    accounts::{VaultAccounts, VaultReserve},
+    pda,
+};
+
+fn build_instruction(
+    program_id: Pubkey,
+    accounts: impl ToAccountMetas,
+    data: impl InstructionData,
+    remaining_accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let mut account_metas = accounts.to_account_metas(None);
+    account_metas.extend(remaining_accounts);
+
+    Instruction {
+        program_id,
+        accounts: account_metas,
+        data: data.data(),
+    }
+}
+
+fn deposit_accounts(vault: &VaultAccounts, user: Pubkey) -> accounts::Deposit {
+    accounts::Deposit {
+        user,
+        vault_state: vault.vault_state,
+        global_config: vault.global_config(),
+        token_vault: vault.token_vault,
+        token_mint: vault.token_mint,
+        base_vault_authority: vault.base_vault_authority,
+        shares_mint: vault.shares_mint,
+        user_token_ata: vault.user_token_ata(&user),
+        user_shares_ata: vault.user_shares_ata(&user),
+        klend_program: kamino_lending::ID,
+        token_program: vault.token_program,
+        shares_token_program: vault.shares_token_program,
+        event_authority: vault.event_authority(),
+        program: vault.program_id,
+    }
+}
+
+fn deposit_remaining_accounts(vault: &VaultAccounts, user: &Pubkey) -> Vec<AccountMeta> {
+    let allowlist_entry = if vault.permissioned_deposits {
+        vec![AccountMeta::new_readonly(
+            pda::depositor_allowlist_entry_program_id(&vault.program_id, &vault.vault_state, user),
+            false,
+        )]
+    } else {
+        vec![]
+    };
+
+    vault.remaining_accounts(allowlist_entry)
+}
+
+fn withdraw_from_available_accounts(
+    vault: &VaultAccounts,
+    user: Pubkey,
+) -> accounts::WithdrawFromAvailable {
+    accounts::WithdrawFromAvailable {
+        user,
+        vault_state: vault.vault_state,
+        global_config: vault.global_config(),
+        token_vault: vault.token_vault,
+        base_vault_authority: vault.base_vault_authority,
+        user_token_ata: vault.user_token_ata(&user),
+        token_mint: vault.token_mint,
+        user_shares_ata: vault.user_shares_ata(&user),
+        shares_mint: vault.shares_mint,
+        token_program: vault.token_program,
+        shares_token_program: vault.shares_token_program,
+        klend_program: kamino_lending::ID,
+        event_authority: vault.event_authority(),
+        program: vault.program_id,
+    }
+}
+
+fn withdraw_from_invested_accounts(
+    vault: &VaultAccounts,
+    reserve: &VaultReserve,
+) -> accounts::WithdrawFromInvested {
+    accounts::WithdrawFromInvested {
+        vault_state: vault.vault_state,
+        reserve: reserve.address,
+        ctoken_vault: vault.ctoken_vault(&reserve.address),
+        lending_market: reserve.lending_market,
+        lending_market_authority: reserve.lending_market_authority,
+        reserve_liquidity_supply: reserve.liquidity_supply,
+        reserve_collateral_mint: reserve.collateral_mint,
+        reserve_collateral_token_program: anchor_spl::token::ID,
+        instruction_sysvar_account: sysvar::instructions::ID,
+    }
+}
+
+fn reserve_whitelist_entry(
+    program_id: &Pubkey,
+    reserve: &Pubkey,
+    whitelisted: bool,
+) -> Option<Pubkey> {
+    whitelisted.then(|| pda::reserve_whitelist_entry_program_id(program_id, reserve))
+}
+
+pub fn init_vault(
+    program_id: Pubkey,
+    admin_authority: Pubkey,
+    vault_state: Pubkey,
+    base_token_mint: Pubkey,
+    token_program: Pubkey,
+    shares_token_program: Pubkey,
+) -> Instruction {
+    build_instruction(
+        program_id,
+        accounts::InitVault {
+            admin_authority,
+            vault_state,
+            base_vault_authority: pda::base_vault_authority_program_id(&program_id, &vault_state),
+            token_vault: pda::token_vault_program_id(&program_id, &vault_state),
+            base_token_mint,
+            shares_mint: pda::shares_mint_program_id(&program_id, &vault_state),
+            admin_token_account: get_associated_token_address_with_program_id(
+                &admin_authority,
+                &base_token_mint,
+                &token_program,
+            ),
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            token_program,
+            shares_token_program,
+        },
+        instruction::InitVault {},
+        vec![],
+    )
+}
+
+/// `reserve` does not have to be part of the vault allocations yet
+pub fn update_reserve_allocation(
+    vault: &VaultAccounts,
+    signer: Pubkey,
+    reserve: &VaultReserve,
+    reserve_whitelisted: bool,
+    weight: u64,
+    cap: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::UpdateReserveAllocation {
+            signer,
+            vault_state: vault.vault_state,
+            base_vault_authority: vault.base_vault_authority,
+            reserve_collateral_mint: reserve.collateral_mint,
+            reserve: reserve.address,
+            ctoken_vault: vault.ctoken_vault(&reserve.address),
+            reserve_whitelist_entry: reserve_whitelist_entry(
+                &vault.program_id,
+                &reserve.address,
+                reserve_whitelisted,
+            ),
+            reserve_collateral_token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::UpdateReserveAllocation { weight, cap },
+        vec![],
+    )
+}
+
+pub fn deposit(vault: &VaultAccounts, user: Pubkey, max_amount: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        deposit_accounts(vault, user),
+        instruction::Deposit { max_amount },
+        deposit_remaining_accounts(vault, &user),
+    )
+}
+
+pub fn buy(vault: &VaultAccounts, user: Pubkey, max_amount: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        deposit_accounts(vault, user),
+        instruction::Buy { max_amount },
+        deposit_remaining_accounts(vault, &user),
+    )
+}
+
+pub fn deposit_with_min_shares(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    max_amount: u64,
+    min_shares_out: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        deposit_accounts(vault, user),
+        instruction::DepositWithMinShares {
+            max_amount,
+            min_shares_out,
+        },
+        deposit_remaining_accounts(vault, &user),
+    )
+}
+
+pub fn mint_shares(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    shares_out: u64,
+    max_tokens_in: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        deposit_accounts(vault, user),
+        instruction::MintShares {
+            shares_out,
+            max_tokens_in,
+        },
+        deposit_remaining_accounts(vault, &user),
+    )
+}
+
+fn withdraw_accounts(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserve: &VaultReserve,
+) -> accounts::Withdraw {
+    accounts::Withdraw {
+        withdraw_from_available: withdraw_from_available_accounts(vault, user),
+        withdraw_from_reserve_accounts: withdraw_from_invested_accounts(vault, reserve),
+        event_authority: vault.event_authority(),
+        program: vault.program_id,
+    }
+}
+
+pub fn withdraw(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserve: &VaultReserve,
+    shares_amount: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        withdraw_accounts(vault, user, reserve),
+        instruction::Withdraw { shares_amount },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn withdraw_with_min_tokens_out(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserve: &VaultReserve,
+    shares_amount: u64,
+    min_tokens_out: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        withdraw_accounts(vault, user, reserve),
+        instruction::WithdrawWithMinTokensOut {
+            shares_amount,
+            min_tokens_out,
+        },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn withdraw_assets(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserve: &VaultReserve,
+    tokens_out: u64,
+    max_shares_in: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        withdraw_accounts(vault, user, reserve),
+        instruction::WithdrawAssets {
+            tokens_out,
+            max_shares_in,
+        },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn sell(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserve: &VaultReserve,
+    shares_amount: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        withdraw_accounts(vault, user, reserve),
+        instruction::Sell { shares_amount },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn withdraw_from_available(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    shares_amount: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        withdraw_from_available_accounts(vault, user),
+        instruction::WithdrawFromAvailable { shares_amount },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn withdraw_from_available_with_min_tokens_out(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    shares_amount: u64,
+    min_tokens_out: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        withdraw_from_available_accounts(vault, user),
+        instruction::WithdrawFromAvailableWithMinTokensOut {
+            shares_amount,
+            min_tokens_out,
+        },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+/// The reserves are withdrawn from in the given order
+pub fn withdraw_from_reserves(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserves_to_withdraw_from: &[VaultReserve],
+    shares_amount: u64,
+    min_tokens_out: u64,
+) -> Instruction {
+    let withdraw_from_invested_groups = reserves_to_withdraw_from
+        .iter()
+        .flat_map(|reserve| withdraw_from_invested_accounts(vault, reserve).to_account_metas(None))
+        .collect();
+
+    build_instruction(
+        vault.program_id,
+        accounts::WithdrawFromReserves {
+            withdraw_from_available: withdraw_from_available_accounts(vault, user),
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::WithdrawFromReserves {
+            shares_amount,
+            min_tokens_out,
+            reserves_to_withdraw_from_count: reserves_to_withdraw_from.len() as u8,
+        },
+        vault.remaining_accounts(withdraw_from_invested_groups),
+    )
+}
+
+pub fn invest(
+    vault: &VaultAccounts,
+    payer: Pubkey,
+    reserve: &VaultReserve,
+    reserve_whitelisted: bool,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::Invest {
+            payer,
+            payer_token_account: vault.user_token_ata(&payer),
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            token_vault: vault.token_vault,
+            token_mint: vault.token_mint,
+            base_vault_authority: vault.base_vault_authority,
+            ctoken_vault: vault.ctoken_vault(&reserve.address),
+            reserve: reserve.address,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority,
+            reserve_liquidity_supply: reserve.liquidity_supply,
+            reserve_collateral_mint: reserve.collateral_mint,
+            reserve_whitelist_entry: reserve_whitelist_entry(
+                &vault.program_id,
+                &reserve.address,
+                reserve_whitelisted,
+            ),
+            klend_program: kamino_lending::ID,
+            reserve_collateral_token_program: anchor_spl::token::ID,
+            token_program: vault.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::Invest {},
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+/// `whitelisted_reserves` lists the vault reserves that have a whitelist entry, one
+/// `RebalanceReserve` group is passed per reserve of the vault
+pub fn rebalance(
+    vault: &VaultAccounts,
+    payer: Pubkey,
+    whitelisted_reserves: &[Pubkey],
+) -> Instruction {
+    let rebalance_reserve_groups = vault
+        .reserves
+        .iter()
+        .flat_map(|reserve| {
+            accounts::RebalanceReserve {
+                vault_state: vault.vault_state,
+                reserve: reserve.address,
+                ctoken_vault: vault.ctoken_vault(&reserve.address),
+                lending_market: reserve.lending_market,
+                lending_market_authority: reserve.lending_market_authority,
+                reserve_liquidity_supply: reserve.liquidity_supply,
+                reserve_collateral_mint: reserve.collateral_mint,
+                reserve_whitelist_entry: reserve_whitelist_entry(
+                    &vault.program_id,
+                    &reserve.address,
+                    whitelisted_reserves.contains(&reserve.address),
+                ),
+                reserve_collateral_token_program: anchor_spl::token::ID,
+            }
+            .to_account_metas(None)
+        })
+        .collect();
+
+    build_instruction(
+        vault.program_id,
+        accounts::Rebalance {
+            payer,
+            payer_token_account: vault.user_token_ata(&payer),
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            token_vault: vault.token_vault,
+            token_mint: vault.token_mint,
+            base_vault_authority: vault.base_vault_authority,
+            klend_program: kamino_lending::ID,
+            reserve_collateral_token_program: anchor_spl::token::ID,
+            token_program: vault.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::Rebalance {},
+        vault.remaining_accounts(rebalance_reserve_groups),
+    )
+}
+
+pub fn update_vault_config(
+    vault: &VaultAccounts,
+    signer: Pubkey,
+    entry: VaultConfigField,
+    data: Vec<u8>,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::UpdateVaultConfig {
+            signer,
+            global_config: vault.global_config(),
+            vault_state: vault.vault_state,
+            klend_program: kamino_lending::ID,
+        },
+        instruction::UpdateVaultConfig { entry, data },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn withdraw_pending_fees(vault: &VaultAccounts, reserve: &VaultReserve) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::WithdrawPendingFees {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            reserve: reserve.address,
+            token_vault: vault.token_vault,
+            ctoken_vault: vault.ctoken_vault(&reserve.address),
+            base_vault_authority: vault.base_vault_authority,
+            token_ata: vault.user_token_ata(&vault.vault_admin_authority),
+            token_mint: vault.token_mint,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority,
+            reserve_liquidity_supply: reserve.liquidity_supply,
+            reserve_collateral_mint: reserve.collateral_mint,
+            klend_program: kamino_lending::ID,
+            token_program: vault.token_program,
+            reserve_collateral_token_program: anchor_spl::token::ID,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        },
+        instruction::WithdrawPendingFees {},
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+/// The fees are sent to the associated token accounts of the vault fee recipients
+pub fn distribute_pending_fees(vault: &VaultAccounts, reserve: &VaultReserve) -> Instruction {
+    let fee_recipient_token_accounts = vault
+        .fee_recipients
+        .iter()
+        .map(|recipient| AccountMeta::new(vault.user_token_ata(recipient), false))
+        .collect();
+
+    build_instruction(
+        vault.program_id,
+        accounts::DistributePendingFees {
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            reserve: reserve.address,
+            token_vault: vault.token_vault,
+            ctoken_vault: vault.ctoken_vault(&reserve.address),
+            base_vault_authority: vault.base_vault_authority,
+            token_mint: vault.token_mint,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority,
+            reserve_liquidity_supply: reserve.liquidity_supply,
+            reserve_collateral_mint: reserve.collateral_mint,
+            klend_program: kamino_lending::ID,
+            token_program: vault.token_program,
+            reserve_collateral_token_program: anchor_spl::token::ID,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        },
+        instruction::DistributePendingFees {},
+        vault.remaining_accounts(fee_recipient_token_accounts),
+    )
+}
+
+fn fee_shares_recipient_accounts(vault: &VaultAccounts) -> Vec<AccountMeta> {
+    vault
+        .fee_shares_recipients()
+        .iter()
+        .map(|recipient| AccountMeta::new(vault.user_shares_ata(recipient), false))
+        .collect()
+}
+
+pub fn convert_pending_fees_to_shares(vault: &VaultAccounts) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::ConvertPendingFeesToShares {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            base_vault_authority: vault.base_vault_authority,
+            shares_mint: vault.shares_mint,
+            klend_program: kamino_lending::ID,
+            shares_token_program: vault.shares_token_program,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::ConvertPendingFeesToShares {},
+        vault.remaining_accounts(fee_shares_recipient_accounts(vault)),
+    )
+}
+
+pub fn mint_fee_shares(vault: &VaultAccounts) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::MintFeeShares {
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            base_vault_authority: vault.base_vault_authority,
+            shares_mint: vault.shares_mint,
+            shares_token_program: vault.shares_token_program,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::MintFeeShares {},
+        fee_shares_recipient_accounts(vault),
+    )
+}
+
+fn first_loss_shares_accounts(
+    vault: &VaultAccounts,
+    first_loss_depositor: Pubkey,
+) -> accounts::FirstLossShares {
+    accounts::FirstLossShares {
+        first_loss_depositor,
+        vault_state: vault.vault_state,
+        base_vault_authority: vault.base_vault_authority,
+        first_loss_depositor_shares_ata: vault.user_shares_ata(&first_loss_depositor),
+        shares_mint: vault.shares_mint,
+        first_loss_escrow: pda::first_loss_escrow_program_id(&vault.program_id, &vault.vault_state),
+        shares_token_program: vault.shares_token_program,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+    }
+}
+
+pub fn deposit_first_loss_shares(
+    vault: &VaultAccounts,
+    first_loss_depositor: Pubkey,
+    shares_amount: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        first_loss_shares_accounts(vault, first_loss_depositor),
+        instruction::DepositFirstLossShares { shares_amount },
+        shares_transfer_hook_accounts(vault, &vault.base_vault_authority),
+    )
+}
+
+pub fn request_first_loss_withdrawal(
+    vault: &VaultAccounts,
+    first_loss_depositor: Pubkey,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::RequestFirstLossWithdrawal {
+            first_loss_depositor,
+            vault_state: vault.vault_state,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::RequestFirstLossWithdrawal {},
+        vec![],
+    )
+}
+
+pub fn withdraw_first_loss_shares(
+    vault: &VaultAccounts,
+    first_loss_depositor: Pubkey,
+    shares_amount: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::WithdrawFirstLossShares {
+            first_loss_depositor,
+            vault_state: vault.vault_state,
+            base_vault_authority: vault.base_vault_authority,
+            first_loss_depositor_shares_ata: vault.user_shares_ata(&first_loss_depositor),
+            shares_mint: vault.shares_mint,
+            first_loss_escrow: pda::first_loss_escrow_program_id(
+                &vault.program_id,
+                &vault.vault_state,
+            ),
+            klend_program: kamino_lending::ID,
+            shares_token_program: vault.shares_token_program,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::WithdrawFirstLossShares { shares_amount },
+        vault.remaining_accounts(shares_transfer_hook_accounts(vault, &first_loss_depositor)),
+    )
+}
+
+pub fn burn_absorbed_first_loss_shares(vault: &VaultAccounts) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::BurnAbsorbedFirstLossShares {
+            vault_state: vault.vault_state,
+            base_vault_authority: vault.base_vault_authority,
+            shares_mint: vault.shares_mint,
+            first_loss_escrow: pda::first_loss_escrow_program_id(
+                &vault.program_id,
+                &vault.vault_state,
+            ),
+            shares_token_program: vault.shares_token_program,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::BurnAbsorbedFirstLossShares {},
+        vec![],
+    )
+}
+
+pub fn pause_vault_operations(
+    vault: &VaultAccounts,
+    signer: Pubkey,
+    operations: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::PauseVaultOperations {
+            signer,
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+        },
+        instruction::PauseVaultOperations { operations },
+        vec![],
+    )
+}
+
+pub fn pause_global_operations(program_id: Pubkey, signer: Pubkey, operations: u64) -> Instruction {
+    build_instruction(
+        program_id,
+        accounts::PauseGlobalOperations {
+            signer,
+            global_config: pda::global_config_program_id(&program_id),
+        },
+        instruction::PauseGlobalOperations { operations },
+        vec![],
+    )
+}
+
+pub fn close_vault(vault: &VaultAccounts) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::CloseVault {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            base_vault_authority: vault.base_vault_authority,
+            token_vault: vault.token_vault,
+            token_mint: vault.token_mint,
+            vault_admin_token_ata: vault.user_token_ata(&vault.vault_admin_authority),
+            shares_mint: vault.shares_mint,
+            withdrawal_queue_escrow: pda::withdrawal_queue_escrow_program_id(
+                &vault.program_id,
+                &vault.vault_state,
+            ),
+            first_loss_escrow: pda::first_loss_escrow_program_id(
+                &vault.program_id,
+                &vault.vault_state,
+            ),
+            token_program: vault.token_program,
+            shares_token_program: vault.shares_token_program,
+            reserve_collateral_token_program: anchor_spl::token::ID,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::CloseVault {},
+        vault
+            .reserves
+            .iter()
+            .map(|reserve| AccountMeta::new(vault.ctoken_vault(&reserve.address), false))
+            .collect(),
+    )
+}
+
+pub fn update_admin(vault: &VaultAccounts, pending_admin: Pubkey) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::UpdateAdmin {
+            pending_admin,
+            vault_state: vault.vault_state,
+        },
+        instruction::UpdateAdmin {},
+        vec![],
+    )
+}
+
+pub fn give_up_pending_fees(vault: &VaultAccounts, max_amount_to_give_up: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::GiveUpPendingFees {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            klend_program: kamino_lending::ID,
+        },
+        instruction::GiveUpPendingFees {
+            max_amount_to_give_up,
+        },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+fn shares_metadata_accounts(vault: &VaultAccounts) -> (Pubkey, Option<Pubkey>, Option<Pubkey>) {
+    if vault.shares_token_program == token_2022::ID {
+        (vault.shares_mint, None, Some(token_2022::ID))
+    } else {
+        (
+            pda::shares_metadata(&vault.shares_mint),
+            Some(anchor_spl::metadata::mpl_token_metadata::ID),
+            None,
+        )
+    }
+}
+
+pub fn initialize_shares_metadata(
+    vault: &VaultAccounts,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Instruction {
+    let (shares_metadata, metadata_program, shares_token_program) = shares_metadata_accounts(vault);
+
+    build_instruction(
+        vault.program_id,
+        accounts::InitializeSharesMetadata {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            shares_mint: vault.shares_mint,
+            base_vault_authority: vault.base_vault_authority,
+            shares_metadata,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            metadata_program,
+            shares_token_program,
+        },
+        instruction::InitializeSharesMetadata { name, symbol, uri },
+        vec![],
+    )
+}
+
+pub fn update_shares_metadata(
+    vault: &VaultAccounts,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Instruction {
+    let (shares_metadata, metadata_program, shares_token_program) = shares_metadata_accounts(vault);
+
+    build_instruction(
+        vault.program_id,
+        accounts::UpdateSharesMetadata {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            base_vault_authority: vault.base_vault_authority,
+            shares_metadata,
+            metadata_program,
+            // Token-2022 metadata may need to be reallocated, which is paid by the admin
+            system_program: shares_token_program.map(|_| system_program::ID),
+            shares_token_program,
+        },
+        instruction::UpdateSharesMetadata { name, symbol, uri },
+        vec![],
+    )
+}
+
+pub fn remove_allocation(vault: &VaultAccounts, reserve: Pubkey) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::RemoveAllocation {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            reserve,
+        },
+        instruction::RemoveAllocation {},
+        vec![],
+    )
+}
+
+pub fn init_global_config(program_id: Pubkey, payer: Pubkey) -> Instruction {
+    build_instruction(
+        program_id,
+        accounts::InitGlobalConfig {
+            payer,
+            global_config: pda::global_config_program_id(&program_id),
+            program_data: pda::program_data_program_id(&program_id),
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::InitGlobalConfig {},
+        vec![],
+    )
+}
+
+pub fn update_global_config(
+    program_id: Pubkey,
+    global_admin: Pubkey,
+    update: UpdateGlobalConfigMode,
+) -> Instruction {
+    build_instruction(
+        program_id,
+        accounts::UpdateGlobalConfig {
+            global_admin,
+            global_config: pda::global_config_program_id(&program_id),
+        },
+        instruction::UpdateGlobalConfig { update },
+        vec![],
+    )
+}
+
+pub fn update_global_config_admin(program_id: Pubkey, pending_admin: Pubkey) -> Instruction {
+    build_instruction(
+        program_id,
+        accounts::UpdateGlobalConfigAdmin {
+            pending_admin,
+            global_config: pda::global_config_program_id(&program_id),
+        },
+        instruction::UpdateGlobalConfigAdmin {},
+        vec![],
+    )
+}
+
+pub fn add_update_whitelisted_reserve(
+    program_id: Pubkey,
+    global_admin: Pubkey,
+    reserve: Pubkey,
+    update: UpdateReserveWhitelistMode,
+) -> Instruction {
+    build_instruction(
+        program_id,
+        accounts::AddUpdateWhitelistedReserve {
+            global_admin,
+            global_config: pda::global_config_program_id(&program_id),
+            reserve,
+            reserve_whitelist_entry: pda::reserve_whitelist_entry_program_id(&program_id, &reserve),
+            system_program: system_program::ID,
+        },
+        instruction::AddUpdateWhitelistedReserve { update },
+        vec![],
+    )
+}
+
+pub fn topup_rewards(vault: &VaultAccounts, payer: Pubkey, amount: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::TopupRewards {
+            payer,
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            token_mint: vault.token_mint,
+            token_vault: vault.token_vault,
+            payer_token_ta: vault.user_token_ata(&payer),
+            token_program: vault.token_program,
+        },
+        instruction::TopupRewards { amount },
+        vec![],
+    )
+}
+
+pub fn withdraw_rewards(vault: &VaultAccounts, amount: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::WithdrawRewards {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            token_mint: vault.token_mint,
+            token_vault: vault.token_vault,
+            base_vault_authority: vault.base_vault_authority,
+            withdraw_token_account: vault.user_token_ata(&vault.vault_admin_authority),
+            token_program: vault.token_program,
+        },
+        instruction::WithdrawRewards { amount },
+        vec![],
+    )
+}
+
+fn redeem_in_kind_accounts(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserve: &VaultReserve,
+) -> accounts::RedeemInKind {
+    accounts::RedeemInKind {
+        user,
+        vault_state: vault.vault_state,
+        global_config: vault.global_config(),
+        base_vault_authority: vault.base_vault_authority,
+        reserve: reserve.address,
+        ctoken_vault: vault.ctoken_vault(&reserve.address),
+        user_ctoken_ta: get_associated_token_address_with_program_id(
+            &user,
+            &reserve.collateral_mint,
+            &anchor_spl::token::ID,
+        ),
+        ctoken_mint: reserve.collateral_mint,
+        user_shares_ta: vault.user_shares_ata(&user),
+        shares_mint: vault.shares_mint,
+        reserve_collateral_token_program: anchor_spl::token::ID,
+        shares_token_program: vault.shares_token_program,
+        klend_program: kamino_lending::ID,
+        event_authority: vault.event_authority(),
+        program: vault.program_id,
+    }
+}
+
+pub fn redeem_in_kind(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserve: &VaultReserve,
+    shares_amount: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        redeem_in_kind_accounts(vault, user, reserve),
+        instruction::RedeemInKind { shares_amount },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn redeem_in_kind_with_min_ctokens_out(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    reserve: &VaultReserve,
+    shares_amount: u64,
+    min_ctokens_out: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        redeem_in_kind_accounts(vault, user, reserve),
+        instruction::RedeemInKindWithMinCtokensOut {
+            shares_amount,
+            min_ctokens_out,
+        },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+fn preview_accounts(vault: &VaultAccounts) -> accounts::Preview {
+    accounts::Preview {
+        vault_state: vault.vault_state,
+        global_config: vault.global_config(),
+        klend_program: kamino_lending::ID,
+        instruction_sysvar_account: sysvar::instructions::ID,
+    }
+}
+
+// The preview instructions are meant to be simulated, the result is in the return data
+// and the transaction must request a heap frame of at least `PREVIEW_HEAP_FRAME_BYTES`
+// through the compute budget program
+
+pub fn preview_deposit(vault: &VaultAccounts, max_amount: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        preview_accounts(vault),
+        instruction::PreviewDeposit { max_amount },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn preview_withdraw(vault: &VaultAccounts, shares_amount: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        preview_accounts(vault),
+        instruction::PreviewWithdraw { shares_amount },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn preview_redeem_in_kind(
+    vault: &VaultAccounts,
+    shares_amount: u64,
+    reserve: Pubkey,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        preview_accounts(vault),
+        instruction::PreviewRedeemInKind {
+            shares_amount,
+            reserve,
+        },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn convert_to_shares(vault: &VaultAccounts, assets: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        preview_accounts(vault),
+        instruction::ConvertToShares { assets },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn convert_to_assets(vault: &VaultAccounts, shares: u64) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        preview_accounts(vault),
+        instruction::ConvertToAssets { shares },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn propose_vault_config_change(
+    vault: &VaultAccounts,
+    entry: VaultConfigField,
+    data: Vec<u8>,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::ProposeVaultConfigChange {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::ProposeVaultConfigChange { entry, data },
+        vec![],
+    )
+}
+
+pub fn cancel_vault_config_change(vault: &VaultAccounts, entry: VaultConfigField) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::CancelVaultConfigChange {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::CancelVaultConfigChange { entry },
+        vec![],
+    )
+}
+
+pub fn execute_vault_config_change(
+    vault: &VaultAccounts,
+    signer: Pubkey,
+    entry: VaultConfigField,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::ExecuteVaultConfigChange {
+            signer,
+            vault_state: vault.vault_state,
+            klend_program: kamino_lending::ID,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::ExecuteVaultConfigChange { entry },
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+fn withdrawal_request(vault: &VaultAccounts, user: &Pubkey, request_id: u64) -> Pubkey {
+    pda::withdrawal_request_program_id(&vault.program_id, &vault.vault_state, user, request_id)
+}
+
+/// Accounts Token-2022 needs to invoke the shares transfer hook on a transfer to
+/// `destination_owner`, they are ignored as long as the shares mint has no hook program
+fn shares_transfer_hook_accounts(
+    vault: &VaultAccounts,
+    destination_owner: &Pubkey,
+) -> Vec<AccountMeta> {
+    if vault.shares_token_program != token_2022::ID {
+        return vec![];
+    }
+
+    vec![
+        AccountMeta::new_readonly(vault.program_id, false),
+        AccountMeta::new_readonly(
+            pda::extra_account_metas_program_id(&vault.program_id, &vault.shares_mint),
+            false,
+        ),
+        AccountMeta::new_readonly(vault.vault_state, false),
+        AccountMeta::new_readonly(
+            pda::depositor_allowlist_entry_program_id(
+                &vault.program_id,
+                &vault.vault_state,
+                destination_owner,
+            ),
+            false,
+        ),
+    ]
+}
+
+fn withdrawal_queue_escrow(vault: &VaultAccounts) -> Pubkey {
+    pda::withdrawal_queue_escrow_program_id(&vault.program_id, &vault.vault_state)
+}
+
+/// `request_id` is the `next_request_id` of the vault withdrawal queue, the request account
+/// is derived from it
+pub fn request_withdrawal(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    request_id: u64,
+    shares_amount: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::RequestWithdrawal {
+            user,
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            base_vault_authority: vault.base_vault_authority,
+            withdrawal_request: withdrawal_request(vault, &user, request_id),
+            user_shares_ata: vault.user_shares_ata(&user),
+            shares_mint: vault.shares_mint,
+            withdrawal_queue_escrow: withdrawal_queue_escrow(vault),
+            shares_token_program: vault.shares_token_program,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::RequestWithdrawal { shares_amount },
+        shares_transfer_hook_accounts(vault, &vault.base_vault_authority),
+    )
+}
+
+pub fn cancel_withdrawal_request(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    request_id: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::CancelWithdrawalRequest {
+            user,
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            base_vault_authority: vault.base_vault_authority,
+            withdrawal_request: withdrawal_request(vault, &user, request_id),
+            user_shares_ata: vault.user_shares_ata(&user),
+            shares_mint: vault.shares_mint,
+            withdrawal_queue_escrow: withdrawal_queue_escrow(vault),
+            shares_token_program: vault.shares_token_program,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::CancelWithdrawalRequest {},
+        shares_transfer_hook_accounts(vault, &user),
+    )
+}
+
+fn fulfil_withdrawal_request_from_available_accounts(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    request_id: u64,
+) -> accounts::FulfilWithdrawalRequestFromAvailable {
+    accounts::FulfilWithdrawalRequestFromAvailable {
+        vault_state: vault.vault_state,
+        global_config: vault.global_config(),
+        withdrawal_request: withdrawal_request(vault, &user, request_id),
+        token_vault: vault.token_vault,
+        base_vault_authority: vault.base_vault_authority,
+        token_mint: vault.token_mint,
+        shares_mint: vault.shares_mint,
+        withdrawal_queue_escrow: withdrawal_queue_escrow(vault),
+        token_program: vault.token_program,
+        shares_token_program: vault.shares_token_program,
+        klend_program: kamino_lending::ID,
+        event_authority: vault.event_authority(),
+        program: vault.program_id,
+    }
+}
+
+pub fn fulfil_withdrawal_request(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    request_id: u64,
+    reserve: &VaultReserve,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::FulfilWithdrawalRequest {
+            fulfil_from_available: fulfil_withdrawal_request_from_available_accounts(
+                vault, user, request_id,
+            ),
+            withdraw_from_reserve_accounts: withdraw_from_invested_accounts(vault, reserve),
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::FulfilWithdrawalRequest {},
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn fulfil_withdrawal_request_from_available(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    request_id: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        fulfil_withdrawal_request_from_available_accounts(vault, user, request_id),
+        instruction::FulfilWithdrawalRequestFromAvailable {},
+        vault.remaining_accounts(vec![]),
+    )
+}
+
+pub fn claim_withdrawal_request(
+    vault: &VaultAccounts,
+    user: Pubkey,
+    request_id: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::ClaimWithdrawalRequest {
+            user,
+            vault_state: vault.vault_state,
+            global_config: vault.global_config(),
+            base_vault_authority: vault.base_vault_authority,
+            withdrawal_request: withdrawal_request(vault, &user, request_id),
+            token_vault: vault.token_vault,
+            token_mint: vault.token_mint,
+            user_token_ata: vault.user_token_ata(&user),
+            user_shares_ata: vault.user_shares_ata(&user),
+            shares_mint: vault.shares_mint,
+            withdrawal_queue_escrow: withdrawal_queue_escrow(vault),
+            token_program: vault.token_program,
+            shares_token_program: vault.shares_token_program,
+            event_authority: vault.event_authority(),
+            program: vault.program_id,
+        },
+        instruction::ClaimWithdrawalRequest {},
+        shares_transfer_hook_accounts(vault, &user),
+    )
+}
+
+pub fn add_depositor_to_allowlist(vault: &VaultAccounts, depositor: Pubkey) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::AddDepositorToAllowlist {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            depositor,
+            depositor_allowlist_entry: pda::depositor_allowlist_entry_program_id(
+                &vault.program_id,
+                &vault.vault_state,
+                &depositor,
+            ),
+            system_program: system_program::ID,
+        },
+        instruction::AddDepositorToAllowlist {},
+        vec![],
+    )
+}
+
+pub fn remove_depositor_from_allowlist(vault: &VaultAccounts, depositor: Pubkey) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::RemoveDepositorFromAllowlist {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            depositor_allowlist_entry: pda::depositor_allowlist_entry_program_id(
+                &vault.program_id,
+                &vault.vault_state,
+                &depositor,
+            ),
+        },
+        instruction::RemoveDepositorFromAllowlist {},
+        vec![],
+    )
+}
+
+pub fn initialize_shares_transfer_hook(vault: &VaultAccounts) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::InitializeSharesTransferHook {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            shares_mint: vault.shares_mint,
+            extra_account_meta_list: pda::extra_account_metas_program_id(
+                &vault.program_id,
+                &vault.shares_mint,
+            ),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeSharesTransferHook {},
+        vec![],
+    )
+}
+
+/// Enables or disables the shares transfer hook, along with the permissioned share
+/// transfers of the vault. The hook has to be initialized before it is enabled
+pub fn update_shares_transfer_hook(vault: &VaultAccounts, enabled: bool) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::UpdateSharesTransferHook {
+            vault_admin_authority: vault.vault_admin_authority,
+            vault_state: vault.vault_state,
+            base_vault_authority: vault.base_vault_authority,
+            shares_mint: vault.shares_mint,
+            extra_account_meta_list: pda::extra_account_metas_program_id(
+                &vault.program_id,
+                &vault.shares_mint,
+            ),
+            shares_token_program: vault.shares_token_program,
+        },
+        instruction::UpdateSharesTransferHook { enabled },
+        vec![],
+    )
+}
+
+/// Transfer of shares between the shares accounts of two users, with the accounts of the
+/// shares transfer hook
+pub fn transfer_shares(
+    vault: &VaultAccounts,
+    owner: Pubkey,
+    destination_owner: Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut instruction = token_2022::spl_token_2022::instruction::transfer_checked(
+        &vault.shares_token_program,
+        &vault.user_shares_ata(&owner),
+        &vault.shares_mint,
+        &vault.user_shares_ata(&destination_owner),
+        &owner,
+        &[],
+        amount,
+        decimals,
+    )
+    .unwrap();
+    instruction
+        .accounts
+        .extend(shares_transfer_hook_accounts(vault, &destination_owner));
+    instruction
+}
+
+/// Token-2022 invokes the hook itself on shares transfers, the hook rejects any direct
+/// invocation
+pub fn shares_transfer_hook(
+    vault: &VaultAccounts,
+    source_token: Pubkey,
+    destination_token: Pubkey,
+    destination_owner: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) -> Instruction {
+    build_instruction(
+        vault.program_id,
+        accounts::SharesTransferHook {
+            source_token,
+            shares_mint: vault.shares_mint,
+            destination_token,
+            owner,
+            extra_account_meta_list: pda::extra_account_metas_program_id(
+                &vault.program_id,
+                &vault.shares_mint,
+            ),
+            vault_state: vault.vault_state,
+            destination_allowlist_entry: pda::depositor_allowlist_entry_program_id(
+                &vault.program_id,
+                &vault.vault_state,
+                &destination_owner,
+            ),
+        },
+        instruction::SharesTransferHook { amount },
+        vec![],
+    )
+}
@@ -0,0 +1,15 @@
+#![allow(clippy::result_large_err)]
+
+//! Off-chain helpers for integrating with the Kamino lending vault program:
+//! typed instruction builders, PDA derivations and the share price / AUM math
+//! used on-chain and an off-chain vault simulator, without pulling in the program
+//! entrypoint.
+
+pub mod accounts;
+pub mod instructions;
+pub mod math;
+pub mod pda;
+pub mod simulation;
+
+pub use kamino_vault;
+pub use kamino_vault::{VaultConfigField, VaultState};
@@ -0,0 +1,121 @@
+use std::cell::{Ref, RefCell, RefMut};
+
+use anchor_lang::{
+    prelude::{Pubkey, Result},
+    solana_program::clock::Slot,
+};
+use kamino_lending::{fraction::Fraction, utils::AnyAccountLoader, Reserve};
+use kamino_vault::{
+    operations::vault_operations::{
+        self,
+        common::{self, Holdings},
+    },
+    VaultState,
+};
+
+/// Owned copy of a reserve account, usable wherever the vault operations expect a
+/// reserve loader.
+///
+/// The on-chain staleness checks still apply: the reserve must have been refreshed
+/// at the slot the computation is made for, otherwise the operations fail with
+/// `ReserveIsStale`.
+pub struct ReserveSnapshot {
+    pub address: Pubkey,
+    pub reserve: RefCell<Reserve>,
+}
+
+impl ReserveSnapshot {
+    pub fn new(address: Pubkey, reserve: Reserve) -> Self {
+        Self {
+            address,
+            reserve: RefCell::new(reserve),
+        }
+    }
+}
+
+impl<'a> AnyAccountLoader<'a, Reserve> for &ReserveSnapshot {
+    fn get_mut(&self) -> Result<RefMut<Reserve>> {
+        Ok(self.reserve.borrow_mut())
+    }
+
+    fn get(&self) -> Result<Ref<Reserve>> {
+        Ok(self.reserve.borrow())
+    }
+
+    fn get_pubkey(&self) -> Pubkey {
+        self.address
+    }
+}
+
+// The reserves passed to the functions below must be the reserves of the vault, in
+// the order of its allocations
+
+pub fn holdings(vault: &VaultState, reserves: &[ReserveSnapshot], slot: Slot) -> Result<Holdings> {
+    common::holdings(vault, reserves.iter(), slot)
+}
+
+/// AUM of the vault net of the pending fees, with the management and performance fees
+/// accrued up to `timestamp` as the program would charge them
+pub fn aum(
+    vault: &VaultState,
+    reserves: &[ReserveSnapshot],
+    slot: Slot,
+    timestamp: u64,
+) -> Result<Fraction> {
+    let mut vault = Box::new(*vault);
+    refresh_and_compute_aum(&mut vault, reserves, slot, timestamp)
+}
+
+/// Value of one share in token lamports, one share is worth one token lamport while
+/// no shares are issued
+pub fn share_price(
+    vault: &VaultState,
+    reserves: &[ReserveSnapshot],
+    slot: Slot,
+    timestamp: u64,
+) -> Result<Fraction> {
+    let mut vault = Box::new(*vault);
+    let aum = refresh_and_compute_aum(&mut vault, reserves, slot, timestamp)?;
+
+    if vault.shares_issued == 0 {
+        return Ok(Fraction::ONE);
+    }
+
+    Ok(aum / Fraction::from(vault.shares_issued))
+}
+
+pub fn convert_to_shares(
+    vault: &VaultState,
+    reserves: &[ReserveSnapshot],
+    assets: u64,
+    slot: Slot,
+    timestamp: u64,
+) -> Result<u64> {
+    let mut vault = Box::new(*vault);
+    vault_operations::convert_to_shares(&mut vault, reserves.iter(), assets, slot, timestamp)
+}
+
+pub fn convert_to_assets(
+    vault: &VaultState,
+    reserves: &[ReserveSnapshot],
+    shares: u64,
+    slot: Slot,
+    timestamp: u64,
+) -> Result<u64> {
+    let mut vault = Box::new(*vault);
+    vault_operations::convert_to_assets(&mut vault, reserves.iter(), shares, slot, timestamp)
+}
+
+fn refresh_and_compute_aum(
+    vault: &mut VaultState,
+    reserves: &[ReserveSnapshot],
+    slot: Slot,
+    timestamp: u64,
+) -> Result<Fraction> {
+    vault_operations::refresh_rewards(vault, timestamp)?;
+
+    let Holdings { invested, .. } = common::holdings(vault, reserves.iter(), slot)?;
+    vault_operations::charge_fees(vault, &invested, timestamp)?;
+
+    vault.compute_aum(&invested.total)
+}
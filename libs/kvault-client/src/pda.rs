@@ -0,0 +1,20 @@
+use anchor_lang::prelude::Pubkey;
+use kamino_lending::utils::seeds::LENDING_MARKET_AUTH;
+pub use kamino_vault::utils::pda::*;
+
+pub fn lending_market_authority(lending_market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[LENDING_MARKET_AUTH, lending_market.as_ref()],
+        &kamino_lending::ID,
+    )
+    .0
+}
+
+pub fn shares_metadata(shares_mint: &Pubkey) -> Pubkey {
+    let metadata_program = anchor_spl::metadata::mpl_token_metadata::ID;
+    Pubkey::find_program_address(
+        &[b"metadata", metadata_program.as_ref(), shares_mint.as_ref()],
+        &metadata_program,
+    )
+    .0
+}
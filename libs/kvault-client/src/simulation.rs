@@ -0,0 +1,410 @@
+//! Off-chain simulation of a vault over time.
+//!
+//! The vault is driven through the same `vault_operations` functions the program
+//! calls, the reserves are mocked and only the fields the vault reads are kept
+//! consistent: the liquidity and collateral supplies backing the exchange rate and
+//! the last update slot. Every step refreshes the reserves at the current slot, as the
+//! handlers do through the klend refresh CPI before touching the vault.
+
+use anchor_lang::{
+    err,
+    prelude::{Pubkey, Result},
+    solana_program::clock::Slot,
+};
+use kamino_lending::{fraction::Fraction, utils::FractionExtra, Reserve};
+use kamino_vault::{
+    operations::{
+        effects::{InvestingDirection, WithdrawEffects},
+        vault_operations::{self, common, ReserveToWithdrawFrom, WithdrawAmount},
+    },
+    utils::consts::SECONDS_PER_YEAR_U64,
+    GlobalConfig, KaminoVaultError, VaultState,
+};
+
+use crate::math::ReserveSnapshot;
+
+pub struct SimulatedReserve {
+    pub snapshot: ReserveSnapshot,
+    // Yearly rate at which the liquidity supplied to the reserve grows
+    pub supply_rate_bps: u64,
+}
+
+impl SimulatedReserve {
+    pub fn new(
+        address: Pubkey,
+        lending_market: Pubkey,
+        available_liquidity: u64,
+        borrowed_liquidity: u64,
+        collateral_supply: u64,
+        supply_rate_bps: u64,
+    ) -> Self {
+        let mut reserve: Reserve = bytemuck::Zeroable::zeroed();
+        reserve.lending_market = lending_market;
+        reserve.liquidity.available_amount = available_liquidity;
+        reserve.liquidity.borrowed_amount_sf = Fraction::from(borrowed_liquidity).to_bits();
+        reserve.collateral.mint_total_supply = collateral_supply;
+
+        Self {
+            snapshot: ReserveSnapshot::new(address, reserve),
+            supply_rate_bps,
+        }
+    }
+
+    pub fn address(&self) -> Pubkey {
+        self.snapshot.address
+    }
+
+    fn accrue_interest(&self, seconds: u64) {
+        let reserve = &mut self.snapshot.reserve.borrow_mut();
+        let borrowed = Fraction::from_bits(reserve.liquidity.borrowed_amount_sf);
+        let total_liquidity = Fraction::from(reserve.liquidity.available_amount) + borrowed;
+        let period_rate = Fraction::from_bps(self.supply_rate_bps) * u128::from(seconds)
+            / u128::from(SECONDS_PER_YEAR_U64);
+
+        reserve.liquidity.borrowed_amount_sf = (borrowed + total_liquidity * period_rate).to_bits();
+    }
+
+    fn socialize_loss(&self, liquidity_amount: u64) {
+        let reserve = &mut self.snapshot.reserve.borrow_mut();
+        let borrowed = Fraction::from_bits(reserve.liquidity.borrowed_amount_sf);
+
+        reserve.liquidity.borrowed_amount_sf = borrowed
+            .saturating_sub(Fraction::from(liquidity_amount))
+            .to_bits();
+    }
+
+    fn refresh(&self, slot: Slot) {
+        let reserve = &mut self.snapshot.reserve.borrow_mut();
+        reserve.last_update.slot = slot;
+        reserve.last_update.stale = 0;
+    }
+
+    fn supply_liquidity(&self, liquidity_amount: u64, collateral_amount: u64) {
+        let reserve = &mut self.snapshot.reserve.borrow_mut();
+        reserve.liquidity.available_amount += liquidity_amount;
+        reserve.collateral.mint_total_supply += collateral_amount;
+    }
+
+    fn redeem_collateral(&self, liquidity_amount: u64, collateral_amount: u64) {
+        let reserve = &mut self.snapshot.reserve.borrow_mut();
+        reserve.liquidity.available_amount -= liquidity_amount;
+        reserve.collateral.mint_total_supply -= collateral_amount;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimulationStep {
+    Deposit {
+        amount: u64,
+    },
+    // Withdraws from the available tokens first, then from the reserves in allocation order
+    Withdraw {
+        shares_amount: u64,
+    },
+    Invest {
+        reserve: Pubkey,
+    },
+    ChargeFees,
+    RefreshRewards,
+    TopupRewards {
+        amount: u64,
+    },
+    // Moves the clock forward and accrues the supply interest of every reserve
+    AdvanceTime {
+        seconds: u64,
+        slots: u64,
+    },
+    SetSupplyRate {
+        reserve: Pubkey,
+        supply_rate_bps: u64,
+    },
+    SocializeLoss {
+        reserve: Pubkey,
+        liquidity_amount: u64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AllocationSnapshot {
+    pub reserve: Pubkey,
+    pub ctoken_allocation: u64,
+    pub liquidity_amount: Fraction,
+    pub target_allocation_weight: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationSnapshot {
+    pub slot: Slot,
+    pub timestamp: u64,
+    pub token_available: u64,
+    pub shares_issued: u64,
+    pub aum: Fraction,
+    pub share_price: Fraction,
+    pub pending_fees: Fraction,
+    pub cumulative_mgmt_fees: Fraction,
+    pub cumulative_perf_fees: Fraction,
+    pub rewards_available: u64,
+    pub allocations: Vec<AllocationSnapshot>,
+}
+
+pub struct VaultSimulator {
+    pub vault: Box<VaultState>,
+    pub global_config: Box<GlobalConfig>,
+    // Ordered as the allocations of the vault
+    pub reserves: Vec<SimulatedReserve>,
+    pub slot: Slot,
+    pub timestamp: u64,
+}
+
+impl VaultSimulator {
+    /// `reserves` can be given in any order, every allocation of the vault must be present
+    pub fn new(
+        vault: Box<VaultState>,
+        global_config: Box<GlobalConfig>,
+        mut reserves: Vec<SimulatedReserve>,
+        slot: Slot,
+        timestamp: u64,
+    ) -> Result<Self> {
+        let mut ordered_reserves = Vec::with_capacity(vault.get_reserves_count());
+        for allocation in vault
+            .vault_allocation_strategy
+            .iter()
+            .filter(|allocation| allocation.reserve != Pubkey::default())
+        {
+            let Some(index) = reserves
+                .iter()
+                .position(|reserve| reserve.address() == allocation.reserve)
+            else {
+                return err!(KaminoVaultError::ReserveNotProvidedInTheAccounts);
+            };
+            ordered_reserves.push(reserves.swap_remove(index));
+        }
+
+        let simulator = Self {
+            vault,
+            global_config,
+            reserves: ordered_reserves,
+            slot,
+            timestamp,
+        };
+        simulator.refresh_reserves();
+
+        Ok(simulator)
+    }
+
+    /// Applies the steps in order and snapshots the vault after each of them
+    pub fn run(&mut self, steps: &[SimulationStep]) -> Result<Vec<SimulationSnapshot>> {
+        steps
+            .iter()
+            .map(|step| {
+                self.apply(step)?;
+                self.snapshot()
+            })
+            .collect()
+    }
+
+    pub fn apply(&mut self, step: &SimulationStep) -> Result<()> {
+        self.refresh_reserves();
+
+        match *step {
+            SimulationStep::Deposit { amount } => {
+                vault_operations::deposit(
+                    &mut self.vault,
+                    reserves_iter(&self.reserves),
+                    amount,
+                    0,
+                    self.slot,
+                    self.timestamp,
+                )?;
+            }
+            SimulationStep::Withdraw { shares_amount } => {
+                self.withdraw(shares_amount)?;
+            }
+            SimulationStep::Invest { reserve } => {
+                self.invest(&reserve)?;
+            }
+            SimulationStep::ChargeFees => {
+                let holdings =
+                    common::holdings(&self.vault, reserves_iter(&self.reserves), self.slot)?;
+                vault_operations::charge_fees(&mut self.vault, &holdings.invested, self.timestamp)?;
+            }
+            SimulationStep::RefreshRewards => {
+                vault_operations::refresh_rewards(&mut self.vault, self.timestamp)?;
+            }
+            SimulationStep::TopupRewards { amount } => {
+                vault_operations::topup_rewards(&mut self.vault, amount, self.timestamp)?;
+            }
+            SimulationStep::AdvanceTime { seconds, slots } => {
+                for reserve in self.reserves.iter() {
+                    reserve.accrue_interest(seconds);
+                }
+                self.timestamp += seconds;
+                self.slot += slots;
+                self.refresh_reserves();
+            }
+            SimulationStep::SetSupplyRate {
+                reserve,
+                supply_rate_bps,
+            } => {
+                self.reserve_mut(&reserve)?.supply_rate_bps = supply_rate_bps;
+            }
+            SimulationStep::SocializeLoss {
+                reserve,
+                liquidity_amount,
+            } => {
+                self.reserve(&reserve)?.socialize_loss(liquidity_amount);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> Result<SimulationSnapshot> {
+        let holdings = common::holdings(&self.vault, reserves_iter(&self.reserves), self.slot)?;
+        let aum = self.vault.compute_aum(&holdings.invested.total)?;
+        let share_price = if self.vault.shares_issued == 0 {
+            Fraction::ONE
+        } else {
+            aum / Fraction::from(self.vault.shares_issued)
+        };
+
+        let allocations = self
+            .vault
+            .vault_allocation_strategy
+            .iter()
+            .filter(|allocation| allocation.reserve != Pubkey::default())
+            .map(|allocation| AllocationSnapshot {
+                reserve: allocation.reserve,
+                ctoken_allocation: allocation.ctoken_allocation,
+                liquidity_amount: holdings
+                    .invested
+                    .in_reserve(&allocation.reserve)
+                    .liquidity_amount,
+                target_allocation_weight: allocation.target_allocation_weight,
+            })
+            .collect();
+
+        Ok(SimulationSnapshot {
+            slot: self.slot,
+            timestamp: self.timestamp,
+            token_available: self.vault.token_available,
+            shares_issued: self.vault.shares_issued,
+            aum,
+            share_price,
+            pending_fees: self.vault.get_pending_fees(),
+            cumulative_mgmt_fees: self.vault.get_cumulative_mgmt_fees(),
+            cumulative_perf_fees: self.vault.get_cumulative_perf_fees(),
+            rewards_available: self.vault.reward_info.rewards_available,
+            allocations,
+        })
+    }
+
+    fn invest(&mut self, reserve_address: &Pubkey) -> Result<()> {
+        vault_operations::refresh_rewards(&mut self.vault, self.timestamp)?;
+
+        let reserve = self
+            .reserves
+            .iter()
+            .find(|reserve| reserve.address() == *reserve_address)
+            .ok_or_else(|| anchor_lang::error!(KaminoVaultError::ReserveNotPartOfAllocations))?;
+        let invest_effects = {
+            let reserve_state = reserve.snapshot.reserve.borrow();
+            // Whitelist entries are not simulated, vaults restricted to whitelisted
+            // reserves cannot invest
+            vault_operations::invest(
+                &mut self.vault,
+                reserves_iter(&self.reserves),
+                &reserve_state,
+                reserve_address,
+                self.slot,
+                self.timestamp,
+                None,
+            )?
+        };
+
+        match invest_effects.direction {
+            InvestingDirection::Add => reserve.supply_liquidity(
+                invest_effects.liquidity_amount,
+                invest_effects.collateral_amount,
+            ),
+            InvestingDirection::Subtract => reserve.redeem_collateral(
+                invest_effects.liquidity_amount,
+                invest_effects.collateral_amount,
+            ),
+        }
+
+        Ok(())
+    }
+
+    fn withdraw(&mut self, shares_amount: u64) -> Result<()> {
+        let reserve_states: Vec<_> = self
+            .reserves
+            .iter()
+            .map(|reserve| reserve.snapshot.reserve.borrow())
+            .collect();
+        let reserves_to_withdraw_from: Vec<ReserveToWithdrawFrom> = self
+            .reserves
+            .iter()
+            .zip(reserve_states.iter())
+            .map(|(reserve, reserve_state)| {
+                Ok(ReserveToWithdrawFrom {
+                    reserve_address: &reserve.snapshot.address,
+                    reserve_state,
+                    ctokens_owned: self
+                        .vault
+                        .allocation_for_reserve(&reserve.snapshot.address)?
+                        .ctoken_allocation,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let WithdrawEffects { reserves, .. } = vault_operations::withdraw_from_reserves(
+            &mut self.vault,
+            &self.global_config,
+            &reserves_to_withdraw_from,
+            reserves_iter(&self.reserves),
+            self.timestamp,
+            self.slot,
+            WithdrawAmount::Shares {
+                shares_amount,
+                min_tokens_out: 0,
+            },
+        )?;
+
+        drop(reserves_to_withdraw_from);
+        drop(reserve_states);
+
+        for reserve_effects in reserves.iter() {
+            self.reserve(&reserve_effects.reserve)?.redeem_collateral(
+                reserve_effects.invested_liquidity_to_disinvest,
+                reserve_effects.invested_to_disinvest_ctokens,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn refresh_reserves(&self) {
+        for reserve in self.reserves.iter() {
+            reserve.refresh(self.slot);
+        }
+    }
+
+    fn reserve(&self, address: &Pubkey) -> Result<&SimulatedReserve> {
+        self.reserves
+            .iter()
+            .find(|reserve| reserve.address() == *address)
+            .ok_or_else(|| anchor_lang::error!(KaminoVaultError::ReserveNotPartOfAllocations))
+    }
+
+    fn reserve_mut(&mut self, address: &Pubkey) -> Result<&mut SimulatedReserve> {
+        self.reserves
+            .iter_mut()
+            .find(|reserve| reserve.address() == *address)
+            .ok_or_else(|| anchor_lang::error!(KaminoVaultError::ReserveNotPartOfAllocations))
+    }
+}
+
+fn reserves_iter(reserves: &[SimulatedReserve]) -> impl Iterator<Item = &ReserveSnapshot> + Clone {
+    reserves.iter().map(|reserve| &reserve.snapshot)
+}
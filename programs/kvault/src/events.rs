@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 
+use crate::{operations::effects::InvestingDirection, VaultConfigField};
 
 #[event]
 pub struct DepositUserAtaBalanceEvent {
@@ -13,6 +14,13 @@ pub struct DepositResultEvent {
     pub crank_funds_to_deposit: u64,
 }
 
+#[event]
+pub struct DepositTruncatedByCapEvent {
+    pub requested_amount: u64,
+    pub token_to_deposit: u64,
+    pub deposit_cap: u64,
+}
+
 #[event]
 pub struct SharesToWithdrawEvent {
     pub shares_amount: u64,
@@ -32,3 +40,101 @@ pub struct RedeemInKindResultEvent {
     pub shares_to_burn: u64,
     pub ctokens_to_send_to_user: u64,
 }
+
+#[event]
+pub struct HighWaterMarkUpdatedEvent {
+    pub previous_high_water_mark_sf: u128,
+    pub high_water_mark_sf: u128,
+}
+
+#[event]
+pub struct FirstLossAbsorbedEvent {
+    pub loss_absorbed_sf: u128,
+    pub shares_absorbed: u64,
+    pub first_loss_shares_remaining: u64,
+}
+
+#[event]
+pub struct FirstLossSharesBurnedEvent {
+    pub shares_burned: u64,
+}
+
+#[event]
+pub struct VaultConfigChangeProposedEvent {
+    pub entry: VaultConfigField,
+    pub value: u64,
+    pub activation_timestamp: u64,
+}
+
+#[event]
+pub struct VaultConfigChangeCancelledEvent {
+    pub entry: VaultConfigField,
+    pub value: u64,
+}
+
+#[event]
+pub struct VaultConfigChangeExecutedEvent {
+    pub entry: VaultConfigField,
+    pub value: u64,
+}
+
+#[event]
+pub struct WithdrawalRequestedEvent {
+    pub request_id: u64,
+    pub shares_amount: u64,
+}
+
+#[event]
+pub struct WithdrawalRequestCancelledEvent {
+    pub request_id: u64,
+    pub shares_returned: u64,
+}
+
+#[event]
+pub struct WithdrawalRequestFulfilledEvent {
+    pub request_id: u64,
+    pub shares_burned: u64,
+    pub tokens_claimable: u64,
+    pub shares_remaining: u64,
+}
+
+#[event]
+pub struct WithdrawalRequestClaimedEvent {
+    pub request_id: u64,
+    pub tokens_claimed: u64,
+    pub shares_returned: u64,
+}
+
+#[event]
+pub struct InvestResultEvent {
+    pub reserve: Pubkey,
+    pub direction: InvestingDirection,
+    pub liquidity_amount: u64,
+    pub collateral_amount: u64,
+    pub rounding_loss: u64,
+}
+
+#[event]
+pub struct FeeSharesMintedEvent {
+    pub recipient: Pubkey,
+    pub fee_shares: u64,
+}
+
+#[event]
+pub struct FirstLossWithdrawalRequestedEvent {
+    pub first_loss_shares: u64,
+    pub unlock_timestamp: u64,
+}
+
+#[event]
+pub struct FirstLossSharesWithdrawnEvent {
+    pub shares_withdrawn: u64,
+    pub first_loss_shares_remaining: u64,
+}
+
+#[event]
+pub struct VaultClosedEvent {
+    pub token_available: u64,
+    pub crank_funds: u64,
+    pub tokens_to_admin: u64,
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    utils::consts::{DEPOSITOR_ALLOWLIST_ENTRY_SIZE, DEPOSITOR_ALLOWLIST_SEED},
+    xmsg, DepositorAllowlistEntry, VaultState,
+};
+
+pub fn process(ctx: Context<AddDepositorToAllowlist>) -> Result<()> {
+    let depositor_allowlist_entry = &mut ctx.accounts.depositor_allowlist_entry;
+
+    depositor_allowlist_entry.vault_state = ctx.accounts.vault_state.key();
+    depositor_allowlist_entry.depositor = ctx.accounts.depositor.key();
+
+    xmsg!(
+        "Allowlisted depositor {depositor} for vault {vault}",
+        depositor = ctx.accounts.depositor.key(),
+        vault = ctx.accounts.vault_state.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AddDepositorToAllowlist<'info> {
+    #[account(mut)]
+    pub vault_admin_authority: Signer<'info>,
+
+    #[account(has_one = vault_admin_authority)]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: any wallet can be allowlisted
+    pub depositor: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = vault_admin_authority,
+        space = 8 + DEPOSITOR_ALLOWLIST_ENTRY_SIZE,
+        seeds = [DEPOSITOR_ALLOWLIST_SEED, vault_state.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_allowlist_entry: Account<'info, DepositorAllowlistEntry>,
+
+    pub system_program: Program<'info, System>,
+}
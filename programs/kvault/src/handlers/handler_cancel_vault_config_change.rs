@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::VaultConfigChangeCancelledEvent,
+    operations::vault_config_operations::{self, VaultConfigField},
+    VaultState,
+};
+
+pub fn process(ctx: Context<CancelVaultConfigChange>, entry: VaultConfigField) -> Result<()> {
+    let vault = &mut ctx.accounts.vault_state.load_mut()?;
+
+    let cancelled_change = vault_config_operations::cancel_vault_config_change(vault, entry)?;
+
+    emit_cpi!(VaultConfigChangeCancelledEvent {
+        entry,
+        value: cancelled_change.value,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelVaultConfigChange<'info> {
+    pub vault_admin_authority: Signer<'info>,
+
+    #[account(mut,
+        has_one = vault_admin_authority,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+}
@@ -0,0 +1,105 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{accessor::amount, Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    events::WithdrawalRequestCancelledEvent,
+    operations::vault_operations,
+    utils::{
+        consts::{GLOBAL_CONFIG_STATE_SEEDS, PAUSE_WITHDRAWALS, WITHDRAWAL_QUEUE_ESCROW_SEED},
+        token_ops::{shares, tokens::VaultTransferAccounts},
+    },
+    GlobalConfig, KaminoVaultError, VaultState, WithdrawalRequest,
+};
+
+// The remaining accounts are the accounts of the shares transfer hook, if the shares mint has one
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelWithdrawalRequest<'info>>,
+) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state
+        .check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_WITHDRAWALS)?;
+
+    let user_shares_before = ctx.accounts.user_shares_ata.amount;
+    let escrow_shares_before = ctx.accounts.withdrawal_queue_escrow.amount;
+
+    let shares_to_return = vault_operations::cancel_withdrawal_request(
+        vault_state,
+        &mut ctx.accounts.withdrawal_request,
+    )?;
+
+    shares::transfer_to_token_account(
+        &VaultTransferAccounts {
+            token_program: ctx.accounts.shares_token_program.to_account_info(),
+            token_vault: ctx.accounts.withdrawal_queue_escrow.to_account_info(),
+            token_ata: ctx.accounts.user_shares_ata.to_account_info(),
+            token_mint: ctx.accounts.shares_mint.to_account_info(),
+            base_vault_authority: ctx.accounts.base_vault_authority.to_account_info(),
+            vault_state: ctx.accounts.vault_state.to_account_info(),
+        },
+        ctx.remaining_accounts,
+        u8::try_from(vault_state.base_vault_authority_bump).unwrap(),
+        shares_to_return,
+        ctx.accounts.shares_mint.decimals,
+    )?;
+
+    let user_shares_after = amount(&ctx.accounts.user_shares_ata.to_account_info())?;
+    let escrow_shares_after = amount(&ctx.accounts.withdrawal_queue_escrow.to_account_info())?;
+
+    require!(
+        user_shares_after - user_shares_before == shares_to_return
+            && escrow_shares_before - escrow_shares_after == shares_to_return,
+        KaminoVaultError::WithdrawalRequestEscrowAmountDoesNotMatch
+    );
+
+    emit_cpi!(WithdrawalRequestCancelledEvent {
+        request_id: ctx.accounts.withdrawal_request.request_id,
+        shares_returned: shares_to_return,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelWithdrawalRequest<'info> {
+    pub user: Signer<'info>,
+
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        has_one = vault_state,
+        has_one = user,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(mut,
+        token::mint = shares_mint,
+        token::authority = user,
+        token::token_program = shares_token_program,
+    )]
+    pub user_shares_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut,
+        seeds = [WITHDRAWAL_QUEUE_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+        token::token_program = shares_token_program,
+    )]
+    pub withdrawal_queue_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_token_program: Interface<'info, TokenInterface>,
+}
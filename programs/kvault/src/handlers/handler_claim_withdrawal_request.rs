@@ -0,0 +1,154 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{accessor::amount, Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    events::WithdrawalRequestClaimedEvent,
+    operations::{effects::ClaimWithdrawalRequestEffects, vault_operations},
+    utils::{
+        consts::{GLOBAL_CONFIG_STATE_SEEDS, PAUSE_WITHDRAWALS, WITHDRAWAL_QUEUE_ESCROW_SEED},
+        token_ops::{
+            shares,
+            tokens::{self, VaultTransferAccounts},
+        },
+    },
+    GlobalConfig, KaminoVaultError, VaultState, WithdrawalRequest,
+};
+
+// The remaining accounts are the accounts of the shares transfer hook, if the shares mint has one
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, ClaimWithdrawalRequest<'info>>,
+) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state
+        .check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_WITHDRAWALS)?;
+    let base_vault_authority_bump = u8::try_from(vault_state.base_vault_authority_bump).unwrap();
+
+    let token_vault_before = ctx.accounts.token_vault.amount;
+    let user_ata_before = ctx.accounts.user_token_ata.amount;
+    let user_shares_before = ctx.accounts.user_shares_ata.amount;
+
+    let ClaimWithdrawalRequestEffects {
+        tokens_to_send_to_user,
+        shares_to_return_to_user,
+    } = vault_operations::claim_withdrawal_request(
+        vault_state,
+        &mut ctx.accounts.withdrawal_request,
+    )?;
+
+    tokens::transfer_to_token_account(
+        &VaultTransferAccounts {
+            token_program: ctx.accounts.token_program.to_account_info(),
+            token_vault: ctx.accounts.token_vault.to_account_info(),
+            token_ata: ctx.accounts.user_token_ata.to_account_info(),
+            token_mint: ctx.accounts.token_mint.to_account_info(),
+            base_vault_authority: ctx.accounts.base_vault_authority.to_account_info(),
+            vault_state: ctx.accounts.vault_state.to_account_info(),
+        },
+        base_vault_authority_bump,
+        tokens_to_send_to_user,
+        u8::try_from(vault_state.token_mint_decimals).unwrap(),
+    )?;
+
+    shares::transfer_to_token_account(
+        &VaultTransferAccounts {
+            token_program: ctx.accounts.shares_token_program.to_account_info(),
+            token_vault: ctx.accounts.withdrawal_queue_escrow.to_account_info(),
+            token_ata: ctx.accounts.user_shares_ata.to_account_info(),
+            token_mint: ctx.accounts.shares_mint.to_account_info(),
+            base_vault_authority: ctx.accounts.base_vault_authority.to_account_info(),
+            vault_state: ctx.accounts.vault_state.to_account_info(),
+        },
+        ctx.remaining_accounts,
+        base_vault_authority_bump,
+        shares_to_return_to_user,
+        ctx.accounts.shares_mint.decimals,
+    )?;
+
+    let token_vault_after = amount(&ctx.accounts.token_vault.to_account_info())?;
+    let user_ata_after = amount(&ctx.accounts.user_token_ata.to_account_info())?;
+    let user_shares_after = amount(&ctx.accounts.user_shares_ata.to_account_info())?;
+
+    require!(
+        token_vault_before - token_vault_after == tokens_to_send_to_user
+            && user_ata_after - user_ata_before == tokens_to_send_to_user,
+        KaminoVaultError::UserReceivedAmountDoesNotMatch
+    );
+    require!(
+        user_shares_after - user_shares_before == shares_to_return_to_user,
+        KaminoVaultError::WithdrawalRequestEscrowAmountDoesNotMatch
+    );
+
+    emit_cpi!(WithdrawalRequestClaimedEvent {
+        request_id: ctx.accounts.withdrawal_request.request_id,
+        tokens_claimed: tokens_to_send_to_user,
+        shares_returned: shares_to_return_to_user,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimWithdrawalRequest<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = token_vault,
+        has_one = token_mint,
+        has_one = token_program,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        has_one = vault_state,
+        has_one = user,
+        close = user,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(mut,
+        token::token_program = token_program,
+    )]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: has_one check on the vault state account
+    pub token_mint: AccountInfo<'info>,
+
+    #[account(mut,
+        token::mint = token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        token::mint = shares_mint,
+        token::authority = user,
+        token::token_program = shares_token_program,
+    )]
+    pub user_shares_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut,
+        seeds = [WITHDRAWAL_QUEUE_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+        token::token_program = shares_token_program,
+    )]
+    pub withdrawal_queue_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,171 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    events::VaultClosedEvent,
+    operations::vault_operations,
+    utils::{
+        consts::{FIRST_LOSS_ESCROW_SEED, WITHDRAWAL_QUEUE_ESCROW_SEED},
+        token_ops::{
+            shares,
+            tokens::{self, VaultTransferAccounts},
+        },
+    },
+    KaminoVaultError, VaultState,
+};
+
+// The remaining accounts are the ctoken vaults of the vault allocations, in the order of the
+// allocation strategy
+pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, CloseVault<'info>>) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    let base_vault_authority_bump = u8::try_from(vault_state.base_vault_authority_bump).unwrap();
+
+    vault_operations::close_vault(vault_state)?;
+    require!(
+        ctx.accounts.shares_mint.supply == 0,
+        KaminoVaultError::VaultNotEmpty
+    );
+
+    let reserves_count = vault_state.get_reserves_count();
+    let ctoken_vaults = ctx
+        .remaining_accounts
+        .get(..reserves_count)
+        .ok_or(anchor_lang::error::ErrorCode::AccountNotEnoughKeys)?;
+    for (ctoken_vault, allocation) in ctoken_vaults.iter().zip(
+        vault_state
+            .vault_allocation_strategy
+            .iter()
+            .filter(|allocation| allocation.reserve != Pubkey::default()),
+    ) {
+        require_keys_eq!(allocation.ctoken_vault, ctoken_vault.key());
+
+        tokens::close_vault_token_account(
+            ctx.accounts
+                .reserve_collateral_token_program
+                .to_account_info(),
+            ctoken_vault.clone(),
+            ctx.accounts.vault_admin_authority.to_account_info(),
+            ctx.accounts.vault_state.to_account_info(),
+            ctx.accounts.base_vault_authority.to_account_info(),
+            base_vault_authority_bump,
+        )?;
+    }
+
+    // The escrows are only created by the first withdrawal request and first-loss deposit
+    for shares_escrow in [
+        ctx.accounts.withdrawal_queue_escrow.to_account_info(),
+        ctx.accounts.first_loss_escrow.to_account_info(),
+    ] {
+        if !shares_escrow.data_is_empty() {
+            tokens::close_vault_token_account(
+                ctx.accounts.shares_token_program.to_account_info(),
+                shares_escrow,
+                ctx.accounts.vault_admin_authority.to_account_info(),
+                ctx.accounts.vault_state.to_account_info(),
+                ctx.accounts.base_vault_authority.to_account_info(),
+                base_vault_authority_bump,
+            )?;
+        }
+    }
+
+    // The shares mint cannot be closed, no shares can be minted once the vault state is gone
+    shares::revoke_mint_authority(
+        ctx.accounts.shares_token_program.to_account_info(),
+        ctx.accounts.shares_mint.to_account_info(),
+        ctx.accounts.vault_state.to_account_info(),
+        ctx.accounts.base_vault_authority.to_account_info(),
+        vault_state.base_vault_authority_bump,
+    )?;
+
+    // Crank funds and the tokens backing the initial deposit are all that is left
+    let tokens_to_admin = ctx.accounts.token_vault.amount;
+    tokens::transfer_to_token_account(
+        &VaultTransferAccounts {
+            token_program: ctx.accounts.token_program.to_account_info(),
+            token_vault: ctx.accounts.token_vault.to_account_info(),
+            token_ata: ctx.accounts.vault_admin_token_ata.to_account_info(),
+            token_mint: ctx.accounts.token_mint.to_account_info(),
+            base_vault_authority: ctx.accounts.base_vault_authority.to_account_info(),
+            vault_state: ctx.accounts.vault_state.to_account_info(),
+        },
+        base_vault_authority_bump,
+        tokens_to_admin,
+        ctx.accounts.token_mint.decimals,
+    )?;
+
+    tokens::close_vault_token_account(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.vault_admin_authority.to_account_info(),
+        ctx.accounts.vault_state.to_account_info(),
+        ctx.accounts.base_vault_authority.to_account_info(),
+        base_vault_authority_bump,
+    )?;
+
+    emit_cpi!(VaultClosedEvent {
+        token_available: vault_state.token_available,
+        crank_funds: vault_state.available_crank_funds,
+        tokens_to_admin,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseVault<'info> {
+    #[account(mut)]
+    pub vault_admin_authority: Signer<'info>,
+
+    #[account(mut,
+        has_one = vault_admin_authority,
+        has_one = base_vault_authority,
+        has_one = token_vault,
+        has_one = token_mint,
+        has_one = token_program,
+        has_one = shares_mint,
+        close = vault_admin_authority,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut,
+        token::mint = token_mint,
+        token::authority = vault_admin_authority,
+        token::token_program = token_program,
+    )]
+    pub vault_admin_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        mint::token_program = shares_token_program,
+    )]
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: closed in the handler if it was created
+    #[account(mut,
+        seeds = [WITHDRAWAL_QUEUE_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+    )]
+    pub withdrawal_queue_escrow: AccountInfo<'info>,
+
+    /// CHECK: closed in the handler if it was created
+    #[account(mut,
+        seeds = [FIRST_LOSS_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+    )]
+    pub first_loss_escrow: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
+    pub reserve_collateral_token_program: Program<'info, Token>,
+}
@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenInterface};
+use kamino_lending::{utils::FatAccountLoader, Reserve};
+
+use crate::{
+    events::FeeSharesMintedEvent,
+    handlers::handler_mint_fee_shares::{mint_to_fee_shares_recipients, FeeSharesMintAccounts},
+    operations::{klend_operations, vault_operations},
+    utils::cpi_mem::CpiMemoryLender,
+    VaultState,
+};
+
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, ConvertPendingFeesToShares<'info>>,
+) -> Result<()> {
+    let mut cpi_mem: CpiMemoryLender<'_> = CpiMemoryLender::build_cpi_memory_lender(
+        ctx.accounts.to_account_infos(),
+        ctx.remaining_accounts,
+    );
+
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    let clock = Clock::get()?;
+    let reserves_count = vault_state.get_reserves_count();
+
+    klend_operations::cpi_refresh_reserves(
+        &mut cpi_mem,
+        ctx.remaining_accounts.iter().take(reserves_count),
+        reserves_count,
+    )?;
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    let fee_shares = vault_operations::convert_pending_fees_to_shares(
+        vault_state,
+        reserves_iter,
+        clock.slot,
+        u64::try_from(clock.unix_timestamp).unwrap(),
+    )?;
+
+    let minted = mint_to_fee_shares_recipients(
+        vault_state,
+        FeeSharesMintAccounts {
+            shares_token_program: ctx.accounts.shares_token_program.to_account_info(),
+            shares_mint: ctx.accounts.shares_mint.to_account_info(),
+            vault_state: ctx.accounts.vault_state.to_account_info(),
+            base_vault_authority: ctx.accounts.base_vault_authority.to_account_info(),
+        },
+        &ctx.remaining_accounts[reserves_count..],
+        fee_shares,
+    )?;
+
+    for (recipient, fee_shares) in minted {
+        emit_cpi!(FeeSharesMintedEvent {
+            recipient,
+            fee_shares,
+        });
+    }
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ConvertPendingFeesToShares<'info> {
+    #[account(mut)]
+    pub vault_admin_authority: Signer<'info>,
+
+    #[account(mut,
+        has_one = vault_admin_authority,
+        has_one = base_vault_authority,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: has_one in the vault state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        mint::token_program = shares_token_program,
+    )]
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
+    // This context (list of accounts) has a lot of remaining accounts,
+    // - All reserves entries of this vault
+    // - The shares token accounts the fee shares are minted to, see `mint_fee_shares`
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+}
@@ -1,34 +1,68 @@
 use anchor_lang::{prelude::*, Accounts};
 use anchor_spl::{
-    token::{accessor::amount, Token},
+    token::accessor::amount,
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 use kamino_lending::{utils::FatAccountLoader, Reserve};
 
 use crate::{
-    events::{DepositResultEvent, DepositUserAtaBalanceEvent},
-    operations::{effects::DepositEffects, klend_operations, vault_operations},
+    events::{
+        DepositResultEvent, DepositTruncatedByCapEvent, DepositUserAtaBalanceEvent,
+        FirstLossAbsorbedEvent, HighWaterMarkUpdatedEvent,
+    },
+    operations::{
+        depositor_allowlist_operations,
+        effects::DepositEffects,
+        klend_operations,
+        vault_operations::{self, DepositAmount},
+    },
     utils::{
+        consts::{GLOBAL_CONFIG_STATE_SEEDS, PAUSE_DEPOSITS},
         cpi_mem::CpiMemoryLender,
         token_ops::{self, shares, tokens::UserTransferAccounts},
     },
-    KaminoVaultError, VaultState,
+    GlobalConfig, KaminoVaultError, VaultState,
 };
 
 pub fn process<'info>(
     ctx: Context<'_, '_, '_, 'info, Deposit<'info>>,
-    max_amount: u64,
+    deposit_amount: DepositAmount,
 ) -> Result<()> {
    
-    require!(max_amount > 0, KaminoVaultError::DepositAmountsZero);
+    let (max_amount, min_shares_out) = match deposit_amount {
+        DepositAmount::Tokens {
+            max_amount,
+            min_shares_out,
+        } => {
+            require!(max_amount > 0, KaminoVaultError::DepositAmountsZero);
+            (max_amount, min_shares_out)
+        }
+        DepositAmount::Shares {
+            shares_out,
+            max_tokens_in,
+        } => {
+            require!(shares_out > 0, KaminoVaultError::DepositAmountsZeroShares);
+            (max_tokens_in, shares_out)
+        }
+    };
 
     let mut cpi_mem = CpiMemoryLender::build_cpi_memory_lender(
         ctx.accounts.to_account_infos(),
         ctx.remaining_accounts,
     );
     let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state.check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_DEPOSITS)?;
     let reserves_count = vault_state.get_reserves_count();
 
+    if vault_state.vault_has_permissioned_deposits() {
+        depositor_allowlist_operations::check_depositor_allowlisted(
+            ctx.program_id,
+            &ctx.accounts.vault_state.key(),
+            &ctx.accounts.user.key(),
+            ctx.remaining_accounts.get(reserves_count),
+        )?;
+    }
+
     {
        
         klend_operations::cpi_refresh_reserves(
@@ -41,6 +75,10 @@ pub fn process<'info>(
     let user_initial_shares_balance = ctx.accounts.user_shares_ata.amount;
     let user_intial_ata_balance = ctx.accounts.user_token_ata.amount;
     let initial_vault_shares_issued = vault_state.shares_issued;
+    let initial_vault_pending_fee_shares = vault_state.pending_fee_shares;
+    let initial_high_water_mark_sf = vault_state.high_water_mark_sf;
+    let initial_first_loss_shares_pending_burn = vault_state.first_loss_shares_pending_burn;
+    let initial_cumulative_first_loss_absorbed_sf = vault_state.cumulative_first_loss_absorbed_sf;
     emit_cpi!(DepositUserAtaBalanceEvent {
         user_ata_balance: user_intial_ata_balance,
     });
@@ -51,22 +89,58 @@ pub fn process<'info>(
         .take(reserves_count)
         .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
 
+    let current_slot = Clock::get()?.slot;
+    let current_timestamp = Clock::get()?.unix_timestamp.try_into().unwrap();
     let DepositEffects {
         shares_to_mint,
         token_to_deposit,
         crank_funds_to_deposit,
-    } = vault_operations::deposit(
-        vault_state,
-        reserves_iter,
-        max_amount,
-        Clock::get()?.slot,
-        Clock::get()?.unix_timestamp.try_into().unwrap(),
-    )?;
+        truncated_by_deposit_cap,
+    } = match deposit_amount {
+        DepositAmount::Tokens { .. } => vault_operations::deposit(
+            vault_state,
+            reserves_iter,
+            max_amount,
+            min_shares_out,
+            current_slot,
+            current_timestamp,
+        )?,
+        DepositAmount::Shares { .. } => vault_operations::mint(
+            vault_state,
+            reserves_iter,
+            min_shares_out,
+            max_amount,
+            current_slot,
+            current_timestamp,
+        )?,
+    };
     emit_cpi!(DepositResultEvent {
         shares_to_mint,
         token_to_deposit,
         crank_funds_to_deposit,
     });
+    if vault_state.high_water_mark_sf != initial_high_water_mark_sf {
+        emit_cpi!(HighWaterMarkUpdatedEvent {
+            previous_high_water_mark_sf: initial_high_water_mark_sf,
+            high_water_mark_sf: vault_state.high_water_mark_sf,
+        });
+    }
+    if vault_state.first_loss_shares_pending_burn != initial_first_loss_shares_pending_burn {
+        emit_cpi!(FirstLossAbsorbedEvent {
+            loss_absorbed_sf: vault_state.cumulative_first_loss_absorbed_sf
+                - initial_cumulative_first_loss_absorbed_sf,
+            shares_absorbed: vault_state.first_loss_shares_pending_burn
+                - initial_first_loss_shares_pending_burn,
+            first_loss_shares_remaining: vault_state.first_loss_shares,
+        });
+    }
+    if truncated_by_deposit_cap {
+        emit_cpi!(DepositTruncatedByCapEvent {
+            requested_amount: max_amount,
+            token_to_deposit,
+            deposit_cap: vault_state.deposit_cap,
+        });
+    }
 
    
     token_ops::tokens::transfer_to_vault(
@@ -102,9 +176,26 @@ pub fn process<'info>(
         KaminoVaultError::DepositAmountGreaterThanRequestedAmount
     );
     require!(
-        initial_vault_shares_issued + user_shares_gained == vault_state.shares_issued,
+        initial_vault_shares_issued
+            + user_shares_gained
+            + (vault_state.pending_fee_shares - initial_vault_pending_fee_shares)
+            == vault_state.shares_issued
+                + (vault_state.first_loss_shares_pending_burn
+                    - initial_first_loss_shares_pending_burn),
         KaminoVaultError::SharesIssuedAmountDoesNotMatch,
     );
+    require!(
+        user_shares_gained >= min_shares_out,
+        KaminoVaultError::SharesToMintBelowMinSharesOut,
+    );
+    if vault_state.user_shares_cap > 0 && user_shares_balance_after > vault_state.user_shares_cap {
+        msg!(
+            "User shares balance {} above the per user shares cap {}",
+            user_shares_balance_after,
+            vault_state.user_shares_cap
+        );
+        return err!(KaminoVaultError::UserSharesCapExceeded);
+    }
 
     require!(
         user_intial_ata_balance - token_to_deposit - crank_funds_to_deposit
@@ -130,6 +221,12 @@ pub struct Deposit<'info> {
     )]
     pub vault_state: AccountLoader<'info, VaultState>,
 
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
     #[account(mut)]
     pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
@@ -160,9 +257,10 @@ pub struct Deposit<'info> {
 
     pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
     pub token_program: Interface<'info, TokenInterface>,
-    pub shares_token_program: Program<'info, Token>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
     // This context (list of accounts) has a lot of remaining accounts,
     // - All reserves entries of this vault
+    // - The `DepositorAllowlistEntry` of the user if the vault has permissioned deposits
     // - All of the associated lending market accounts
     // They are dynamically sized and ordered and cannot be declared here upfront
 }
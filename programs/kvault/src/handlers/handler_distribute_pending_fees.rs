@@ -0,0 +1,247 @@
+use std::convert::TryFrom;
+
+use anchor_lang::{prelude::*, solana_program::sysvar};
+use anchor_spl::{
+    token::Token,
+    token_interface::{accessor::amount, TokenAccount, TokenInterface},
+};
+use kamino_lending::{utils::FatAccountLoader, Reserve};
+
+use crate::{
+    operations::{
+        effects::{DistributePendingFeesEffects, WithdrawPendingFeesEffects},
+        klend_operations,
+        vault_checks::{post_transfer_withdraw_pending_fees_balance_checks, VaultAndUserBalances},
+        vault_operations,
+    },
+    utils::{
+        consts::{CTOKEN_VAULT_SEED, GLOBAL_CONFIG_STATE_SEEDS, PAUSE_FEE_WITHDRAWAL},
+        cpi_mem::CpiMemoryLender,
+        token_ops,
+    },
+    GlobalConfig, KaminoVaultError, VaultState,
+};
+
+pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, DistributePendingFees<'info>>) -> Result<()> {
+    let mut cpi_mem = CpiMemoryLender::build_cpi_memory_lender(
+        ctx.accounts.to_account_infos(),
+        ctx.remaining_accounts,
+    );
+
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state
+        .check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_FEE_WITHDRAWAL)?;
+    let reserves_count = vault_state.get_reserves_count();
+    let fee_recipients_count = vault_state.get_fee_recipients_count();
+
+    let fee_recipient_token_accounts = ctx
+        .remaining_accounts
+        .get(reserves_count..reserves_count + fee_recipients_count)
+        .ok_or(anchor_lang::error::ErrorCode::AccountNotEnoughKeys)?;
+    for (token_account_info, fee_recipient) in fee_recipient_token_accounts
+        .iter()
+        .zip(vault_state.fee_recipients.iter())
+    {
+        let token_account = InterfaceAccount::<TokenAccount>::try_from(token_account_info)?;
+        require!(
+            token_account.owner == fee_recipient.recipient
+                && token_account.mint == vault_state.token_mint
+                && *token_account_info.owner == vault_state.token_program,
+            KaminoVaultError::FeeRecipientTokenAccountInvalid
+        );
+    }
+
+    {
+        klend_operations::cpi_refresh_reserves(
+            &mut cpi_mem,
+            ctx.remaining_accounts.iter().take(reserves_count),
+            reserves_count,
+        )?;
+    }
+
+    let reserve = ctx.accounts.reserve.load()?;
+    let bump = vault_state.base_vault_authority_bump;
+    let reserve_address = ctx.accounts.reserve.to_account_info().key;
+
+    let token_vault_before = ctx.accounts.token_vault.amount;
+    let ctoken_vault_before = ctx.accounts.ctoken_vault.amount;
+    let recipients_balance_before = fee_recipients_balance(fee_recipient_token_accounts)?;
+    let reserve_supply_liquidity_before = ctx.accounts.reserve_liquidity_supply.amount;
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    let reserve_allocation = vault_state.allocation_for_reserve(reserve_address)?;
+    require_keys_eq!(
+        reserve_allocation.ctoken_vault,
+        ctx.accounts.ctoken_vault.key()
+    );
+
+    let DistributePendingFeesEffects {
+        withdraw_pending_fees_effects,
+        amounts_to_recipients,
+    } = vault_operations::distribute_pending_fees(
+        vault_state,
+        reserve_address,
+        &reserve,
+        reserves_iter,
+        Clock::get()?.slot,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+    )?;
+
+    let WithdrawPendingFeesEffects {
+        available_to_send_to_user,
+        invested_to_disinvest_ctokens,
+        invested_liquidity_to_send_to_user,
+        invested_liquidity_to_disinvest,
+    } = withdraw_pending_fees_effects;
+
+    msg!("DistributePendingFeesEffects: available_to_send_to_user={}, invested_to_disinvest_ctokens={}, invested_liquidity_to_send_to_user={}, invested_liquidity_to_disinvest={}, amounts_to_recipients={:?}",
+        available_to_send_to_user,
+        invested_to_disinvest_ctokens,
+        invested_liquidity_to_send_to_user,
+        invested_liquidity_to_disinvest,
+        &amounts_to_recipients[..fee_recipients_count]
+    );
+
+    drop(reserve);
+
+    if invested_to_disinvest_ctokens > 0 {
+        klend_operations::cpi_redeem_reserve_liquidity_from_distribute_pending_fees(
+            &ctx,
+            &mut cpi_mem,
+            bump as u8,
+            invested_to_disinvest_ctokens,
+        )?;
+    }
+
+    let token_vault_before_transfer = amount(&ctx.accounts.token_vault.to_account_info())?;
+    let liquidity_received = token_vault_before_transfer - token_vault_before;
+
+    require!(
+        liquidity_received >= invested_liquidity_to_send_to_user,
+        KaminoVaultError::NotEnoughLiquidityDisinvestedToSendToUser
+    );
+
+    for (token_account_info, amount_to_recipient) in fee_recipient_token_accounts
+        .iter()
+        .zip(amounts_to_recipients.iter())
+    {
+        token_ops::tokens::transfer_to_token_account(
+            &token_ops::tokens::VaultTransferAccounts {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                token_vault: ctx.accounts.token_vault.to_account_info(),
+                token_ata: token_account_info.clone(),
+                token_mint: ctx.accounts.token_mint.to_account_info(),
+                base_vault_authority: ctx.accounts.base_vault_authority.to_account_info(),
+                vault_state: ctx.accounts.vault_state.to_account_info(),
+            },
+            u8::try_from(vault_state.base_vault_authority_bump).unwrap(),
+            *amount_to_recipient,
+            u8::try_from(vault_state.token_mint_decimals).unwrap(),
+        )?;
+    }
+
+    let token_vault_after = amount(&ctx.accounts.token_vault.to_account_info())?;
+    let ctoken_vault_after = amount(&ctx.accounts.ctoken_vault.to_account_info())?;
+    let recipients_balance_after = fee_recipients_balance(fee_recipient_token_accounts)?;
+    let reserve_supply_liquidity_after =
+        amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+
+    post_transfer_withdraw_pending_fees_balance_checks(
+        VaultAndUserBalances {
+            reserve_supply_liquidity_balance: reserve_supply_liquidity_before,
+            vault_token_balance: token_vault_before,
+            vault_ctoken_balance: ctoken_vault_before,
+            user_token_balance: recipients_balance_before,
+            user_shares_balance: 0,
+        },
+        VaultAndUserBalances {
+            reserve_supply_liquidity_balance: reserve_supply_liquidity_after,
+            vault_token_balance: token_vault_after,
+            vault_ctoken_balance: ctoken_vault_after,
+            user_token_balance: recipients_balance_after,
+            user_shares_balance: 0,
+        },
+        withdraw_pending_fees_effects,
+    )?;
+
+    Ok(())
+}
+
+fn fee_recipients_balance(fee_recipient_token_accounts: &[AccountInfo]) -> Result<u64> {
+    fee_recipient_token_accounts
+        .iter()
+        .map(amount)
+        .sum::<Result<u64>>()
+}
+
+#[derive(Accounts)]
+pub struct DistributePendingFees<'info> {
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = token_vault,
+        has_one = token_mint,
+        has_one = token_program,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    /// CHECK: check in logic if there is allocation for this reserve
+    #[account(mut)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    #[account(mut,
+        token::token_program = token_program,
+    )]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Deterministic, PDA
+    #[account(mut,
+        seeds = [CTOKEN_VAULT_SEED, vault_state.key().as_ref(), reserve.key().as_ref()],
+        bump,
+        token::token_program = reserve_collateral_token_program,
+    )]
+    pub ctoken_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: This authority is stored in the vault state
+    #[account(mut)]
+    pub base_vault_authority: AccountInfo<'info>,
+
+    /// CHECK: has_one in the vault state
+    #[account(mut)]
+    pub token_mint: AccountInfo<'info>,
+
+    /// CPI accounts
+    /// CHECK: The account is checked on CPI calls
+    pub lending_market: AccountInfo<'info>,
+    /// CHECK: The account is checked on CPI calls
+    pub lending_market_authority: AccountInfo<'info>,
+    #[account(mut)]
+    pub reserve_liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: The account is checked on CPI calls
+    #[account(mut)]
+    pub reserve_collateral_mint: AccountInfo<'info>,
+
+    pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reserve_collateral_token_program: Program<'info, Token>,
+
+    /// CHECK: account constraints checked in account trait
+    #[account(address = sysvar::instructions::ID)]
+    pub instruction_sysvar_account: AccountInfo<'info>,
+    // This context (list of accounts) has a lot of remaining accounts,
+    // - All reserves entries of this vault
+    // - One token account of the vault token mint per configured fee recipient, in the order
+    //   of the vault fee recipients table
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+}
@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+use kamino_lending::{utils::FatAccountLoader, Reserve};
+
+use crate::{
+    events::VaultConfigChangeExecutedEvent,
+    operations::{
+        klend_operations,
+        vault_config_operations::{self, VaultConfigField},
+        vault_operations::{self, common::holdings},
+    },
+    utils::cpi_mem::CpiMemoryLender,
+    VaultState,
+};
+
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteVaultConfigChange<'info>>,
+    entry: VaultConfigField,
+) -> Result<()> {
+    let mut cpi_mem = CpiMemoryLender::build_cpi_memory_lender(
+        ctx.accounts.to_account_infos(),
+        ctx.remaining_accounts,
+    );
+    let vault = &mut ctx.accounts.vault_state.load_mut()?;
+    let reserves_count = vault.get_reserves_count();
+
+    klend_operations::cpi_refresh_reserves(
+        &mut cpi_mem,
+        ctx.remaining_accounts.iter().take(reserves_count),
+        reserves_count,
+    )?;
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    let clock = Clock::get()?;
+    let current_ts: u64 = clock.unix_timestamp.try_into().unwrap();
+
+    vault_operations::refresh_rewards(vault, current_ts)?;
+    let holdings = holdings(vault, reserves_iter, clock.slot)?;
+    vault_operations::charge_fees(vault, &holdings.invested, current_ts)?;
+
+    let executed_change =
+        vault_config_operations::execute_vault_config_change(vault, entry, current_ts)?;
+
+    emit_cpi!(VaultConfigChangeExecutedEvent {
+        entry,
+        value: executed_change.value,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteVaultConfigChange<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(mut)]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
+    // This context (list of accounts) has a lot of remaining accounts,
+    // - All reserves entries of this vault
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+}
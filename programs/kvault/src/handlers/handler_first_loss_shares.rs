@@ -0,0 +1,300 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{accessor::amount, Mint, TokenAccount, TokenInterface};
+use kamino_lending::{utils::FatAccountLoader, Reserve};
+
+use crate::{
+    events::{
+        FirstLossAbsorbedEvent, FirstLossSharesBurnedEvent, FirstLossSharesWithdrawnEvent,
+        FirstLossWithdrawalRequestedEvent,
+    },
+    operations::{klend_operations, vault_operations},
+    utils::{
+        consts::FIRST_LOSS_ESCROW_SEED,
+        cpi_mem::CpiMemoryLender,
+        token_ops::{
+            shares,
+            tokens::{UserTransferAccounts, VaultTransferAccounts},
+        },
+    },
+    KaminoVaultError, VaultState,
+};
+
+// The remaining accounts are the accounts of the shares transfer hook, if the shares mint has one
+pub fn deposit_first_loss_shares<'info>(
+    ctx: Context<'_, '_, '_, 'info, FirstLossShares<'info>>,
+    shares_amount: u64,
+) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+
+    let escrow_shares_before = ctx.accounts.first_loss_escrow.amount;
+
+    vault_operations::deposit_first_loss_shares(vault_state, shares_amount)?;
+
+    shares::transfer_to_vault(
+        &UserTransferAccounts {
+            token_program: ctx.accounts.shares_token_program.to_account_info(),
+            token_vault: ctx.accounts.first_loss_escrow.to_account_info(),
+            token_ata: ctx
+                .accounts
+                .first_loss_depositor_shares_ata
+                .to_account_info(),
+            token_mint: ctx.accounts.shares_mint.to_account_info(),
+            user_authority: ctx.accounts.first_loss_depositor.to_account_info(),
+        },
+        ctx.remaining_accounts,
+        shares_amount,
+        ctx.accounts.shares_mint.decimals,
+    )?;
+
+    let escrow_shares_after = amount(&ctx.accounts.first_loss_escrow.to_account_info())?;
+    require_eq!(
+        escrow_shares_after - escrow_shares_before,
+        shares_amount,
+        KaminoVaultError::TokensDepositedAmountDoesNotMatch
+    );
+
+    Ok(())
+}
+
+pub fn request_first_loss_withdrawal(ctx: Context<RequestFirstLossWithdrawal>) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+
+    let unlock_timestamp = vault_operations::request_first_loss_withdrawal(
+        vault_state,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+    )?;
+
+    emit_cpi!(FirstLossWithdrawalRequestedEvent {
+        first_loss_shares: vault_state.first_loss_shares,
+        unlock_timestamp,
+    });
+
+    Ok(())
+}
+
+pub fn withdraw_first_loss_shares<'info>(
+    ctx: Context<'_, '_, '_, 'info, WithdrawFirstLossShares<'info>>,
+    shares_amount: u64,
+) -> Result<()> {
+    let mut cpi_mem = CpiMemoryLender::build_cpi_memory_lender(
+        ctx.accounts.to_account_infos(),
+        ctx.remaining_accounts,
+    );
+
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    let reserves_count = vault_state.get_reserves_count();
+
+    klend_operations::cpi_refresh_reserves(
+        &mut cpi_mem,
+        ctx.remaining_accounts.iter().take(reserves_count),
+        reserves_count,
+    )?;
+
+    let depositor_shares_before = ctx.accounts.first_loss_depositor_shares_ata.amount;
+    let escrow_shares_before = ctx.accounts.first_loss_escrow.amount;
+    let initial_first_loss_shares_pending_burn = vault_state.first_loss_shares_pending_burn;
+    let initial_cumulative_first_loss_absorbed_sf = vault_state.cumulative_first_loss_absorbed_sf;
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    vault_operations::withdraw_first_loss_shares(
+        vault_state,
+        reserves_iter,
+        Clock::get()?.slot,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+        shares_amount,
+    )?;
+
+    if vault_state.first_loss_shares_pending_burn != initial_first_loss_shares_pending_burn {
+        emit_cpi!(FirstLossAbsorbedEvent {
+            loss_absorbed_sf: vault_state.cumulative_first_loss_absorbed_sf
+                - initial_cumulative_first_loss_absorbed_sf,
+            shares_absorbed: vault_state.first_loss_shares_pending_burn
+                - initial_first_loss_shares_pending_burn,
+            first_loss_shares_remaining: vault_state.first_loss_shares,
+        });
+    }
+
+    shares::transfer_to_token_account(
+        &VaultTransferAccounts {
+            token_program: ctx.accounts.shares_token_program.to_account_info(),
+            token_vault: ctx.accounts.first_loss_escrow.to_account_info(),
+            token_ata: ctx
+                .accounts
+                .first_loss_depositor_shares_ata
+                .to_account_info(),
+            token_mint: ctx.accounts.shares_mint.to_account_info(),
+            base_vault_authority: ctx.accounts.base_vault_authority.to_account_info(),
+            vault_state: ctx.accounts.vault_state.to_account_info(),
+        },
+        &ctx.remaining_accounts[reserves_count..],
+        u8::try_from(vault_state.base_vault_authority_bump).unwrap(),
+        shares_amount,
+        ctx.accounts.shares_mint.decimals,
+    )?;
+
+    let depositor_shares_after = amount(
+        &ctx.accounts
+            .first_loss_depositor_shares_ata
+            .to_account_info(),
+    )?;
+    let escrow_shares_after = amount(&ctx.accounts.first_loss_escrow.to_account_info())?;
+    require!(
+        depositor_shares_after - depositor_shares_before == shares_amount
+            && escrow_shares_before - escrow_shares_after == shares_amount,
+        KaminoVaultError::UserReceivedAmountDoesNotMatch
+    );
+
+    emit_cpi!(FirstLossSharesWithdrawnEvent {
+        shares_withdrawn: shares_amount,
+        first_loss_shares_remaining: vault_state.first_loss_shares,
+    });
+
+    Ok(())
+}
+
+pub fn burn_absorbed_first_loss_shares(ctx: Context<BurnAbsorbedFirstLossShares>) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+
+    let shares_to_burn = vault_operations::burn_absorbed_first_loss_shares(vault_state)?;
+
+    if shares_to_burn > 0 {
+        shares::burn_from_escrow(
+            ctx.accounts.shares_token_program.to_account_info(),
+            ctx.accounts.shares_mint.to_account_info(),
+            ctx.accounts.vault_state.to_account_info(),
+            ctx.accounts.base_vault_authority.to_account_info(),
+            ctx.accounts.first_loss_escrow.to_account_info(),
+            vault_state.base_vault_authority_bump,
+            shares_to_burn,
+        )?;
+    }
+
+    emit_cpi!(FirstLossSharesBurnedEvent {
+        shares_burned: shares_to_burn,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FirstLossShares<'info> {
+    #[account(mut)]
+    pub first_loss_depositor: Signer<'info>,
+
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = shares_mint,
+        constraint = vault_state.load()?.first_loss_depositor == first_loss_depositor.key()
+            @ KaminoVaultError::FirstLossDepositorIncorrect,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        token::mint = shares_mint,
+        token::authority = first_loss_depositor,
+        token::token_program = shares_token_program,
+    )]
+    pub first_loss_depositor_shares_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(init_if_needed,
+        payer = first_loss_depositor,
+        seeds = [FIRST_LOSS_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+        token::mint = shares_mint,
+        token::authority = base_vault_authority,
+        token::token_program = shares_token_program,
+    )]
+    pub first_loss_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestFirstLossWithdrawal<'info> {
+    pub first_loss_depositor: Signer<'info>,
+
+    #[account(mut,
+        constraint = vault_state.load()?.first_loss_depositor == first_loss_depositor.key()
+            @ KaminoVaultError::FirstLossDepositorIncorrect,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawFirstLossShares<'info> {
+    pub first_loss_depositor: Signer<'info>,
+
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = shares_mint,
+        constraint = vault_state.load()?.first_loss_depositor == first_loss_depositor.key()
+            @ KaminoVaultError::FirstLossDepositorIncorrect,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        token::mint = shares_mint,
+        token::authority = first_loss_depositor,
+        token::token_program = shares_token_program,
+    )]
+    pub first_loss_depositor_shares_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut,
+        seeds = [FIRST_LOSS_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+        token::token_program = shares_token_program,
+    )]
+    pub first_loss_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
+    // This context (list of accounts) has a lot of remaining accounts,
+    // - All reserves entries of this vault
+    // - The accounts of the shares transfer hook, if the shares mint has one
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BurnAbsorbedFirstLossShares<'info> {
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut,
+        seeds = [FIRST_LOSS_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+        token::token_program = shares_token_program,
+    )]
+    pub first_loss_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,282 @@
+use std::convert::TryFrom;
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{accessor::amount, Mint, TokenAccount, TokenInterface};
+use kamino_lending::{utils::FatAccountLoader, Reserve};
+
+use crate::{
+    events::WithdrawalRequestFulfilledEvent,
+    handlers::WithdrawFromInvested,
+    operations::{
+        effects::WithdrawEffects,
+        klend_operations,
+        vault_checks::{post_transfer_fulfil_withdrawal_request_checks, WithdrawalQueueBalances},
+        vault_operations,
+    },
+    utils::{
+        consts::{GLOBAL_CONFIG_STATE_SEEDS, PAUSE_WITHDRAWALS, WITHDRAWAL_QUEUE_ESCROW_SEED},
+        cpi_mem::CpiMemoryLender,
+        token_ops::shares,
+    },
+    GlobalConfig, KaminoVaultError, VaultState, WithdrawalRequest,
+};
+
+pub fn fulfil_withdrawal_request<'info>(
+    ctx: Context<'_, '_, '_, 'info, FulfilWithdrawalRequest<'info>>,
+) -> Result<()> {
+    let fulfil_from_available = &mut ctx.accounts.fulfil_from_available;
+    let withdraw_from_reserve = &ctx.accounts.withdraw_from_reserve_accounts;
+
+    require_keys_eq!(
+        fulfil_from_available.vault_state.key(),
+        withdraw_from_reserve.vault_state.key()
+    );
+
+    let fulfilled_event = fulfil_utils::fulfil(
+        fulfil_from_available,
+        Some(withdraw_from_reserve),
+        ctx.remaining_accounts,
+    )?;
+
+    emit_cpi!(fulfilled_event);
+
+    Ok(())
+}
+
+pub fn fulfil_withdrawal_request_from_available<'info>(
+    ctx: Context<'_, '_, '_, 'info, FulfilWithdrawalRequestFromAvailable<'info>>,
+) -> Result<()> {
+    let fulfilled_event = fulfil_utils::fulfil(ctx.accounts, None, ctx.remaining_accounts)?;
+
+    emit_cpi!(fulfilled_event);
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FulfilWithdrawalRequest<'info> {
+    pub fulfil_from_available: FulfilWithdrawalRequestFromAvailable<'info>,
+
+    /// CPI accounts
+    pub withdraw_from_reserve_accounts: WithdrawFromInvested<'info>,
+    // This context (list of accounts) has a lot of remaining accounts,
+    // - All reserves entries of this vault
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FulfilWithdrawalRequestFromAvailable<'info> {
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = token_vault,
+        has_one = token_mint,
+        has_one = token_program,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        has_one = vault_state,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(mut,
+        token::token_program = token_program,
+    )]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    /// CHECK: has_one check on the vault state account
+    pub token_mint: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut,
+        seeds = [WITHDRAWAL_QUEUE_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+        token::token_program = shares_token_program,
+    )]
+    pub withdrawal_queue_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
+
+    pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
+    // For fulfilling from available this context (list of accounts) has a lot of remaining
+    // accounts,
+    // - All reserves entries of this vault
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+}
+
+pub mod fulfil_utils {
+    use super::*;
+
+    pub fn fulfil<'info>(
+        ctx_fulfil_from_available: &mut FulfilWithdrawalRequestFromAvailable<'info>,
+        ctx_withdraw_from_reserve: Option<&WithdrawFromInvested<'info>>,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<WithdrawalRequestFulfilledEvent> {
+        let fulfil_from_available_accounts = ctx_fulfil_from_available;
+
+        let mut all_accounts = fulfil_from_available_accounts.to_account_infos();
+        if let Some(withdraw_from_reserve_accounts) = ctx_withdraw_from_reserve {
+            all_accounts.extend_from_slice(&withdraw_from_reserve_accounts.to_account_infos());
+        }
+
+        let mut cpi_mem =
+            CpiMemoryLender::build_cpi_memory_lender(all_accounts, remaining_accounts);
+
+        let vault_state = &mut fulfil_from_available_accounts.vault_state.load_mut()?;
+        let global_config = &fulfil_from_available_accounts.global_config.load()?;
+        vault_state.check_operation_not_paused(global_config, PAUSE_WITHDRAWALS)?;
+        let reserves_count = vault_state.get_reserves_count();
+
+        let balances_before = WithdrawalQueueBalances {
+            reserve_supply_liquidity_balance: ctx_withdraw_from_reserve
+                .map_or(0, |accounts| accounts.reserve_liquidity_supply.amount),
+            vault_token_balance: fulfil_from_available_accounts.token_vault.amount,
+            vault_ctoken_balance: ctx_withdraw_from_reserve
+                .map_or(0, |accounts| accounts.ctoken_vault.amount),
+            escrow_shares_balance: fulfil_from_available_accounts
+                .withdrawal_queue_escrow
+                .amount,
+        };
+
+        klend_operations::cpi_refresh_reserves(
+            &mut cpi_mem,
+            remaining_accounts.iter().take(reserves_count),
+            reserves_count,
+        )?;
+
+        let reserves_iter = remaining_accounts
+            .iter()
+            .take(reserves_count)
+            .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+        let (reserve_address, reserve, reserve_ctokens_owned) = match ctx_withdraw_from_reserve {
+            Some(withdraw_from_reserve_accounts) => {
+                let reserve_allocation = vault_state
+                    .allocation_for_reserve(&withdraw_from_reserve_accounts.reserve.key())?;
+                require_keys_eq!(
+                    reserve_allocation.ctoken_vault,
+                    withdraw_from_reserve_accounts.ctoken_vault.key()
+                );
+                (
+                    Some(withdraw_from_reserve_accounts.reserve.key()),
+                    Some(withdraw_from_reserve_accounts.reserve.load()?),
+                    Some(reserve_allocation.ctoken_allocation),
+                )
+            }
+            None => (None, None, None),
+        };
+
+        let withdrawal_request = &mut fulfil_from_available_accounts.withdrawal_request;
+        let withdraw_effects = vault_operations::fulfil_withdrawal_request(
+            vault_state,
+            global_config,
+            withdrawal_request,
+            reserve_address.as_ref(),
+            reserve.as_deref(),
+            reserves_iter,
+            Clock::get()?.unix_timestamp.try_into().unwrap(),
+            Clock::get()?.slot,
+            reserve_ctokens_owned,
+        )?;
+
+        let request_id = withdrawal_request.request_id;
+        let shares_remaining = withdrawal_request.shares_escrowed;
+
+        drop(reserve);
+
+        let WithdrawEffects {
+            shares_to_burn,
+            available_to_send_to_user,
+            invested_to_disinvest_ctokens,
+            invested_liquidity_to_send_to_user,
+            invested_liquidity_to_disinvest: _,
+            reserves: _,
+        } = withdraw_effects;
+
+        if shares_to_burn > 0 {
+            shares::burn_from_escrow(
+                fulfil_from_available_accounts
+                    .shares_token_program
+                    .to_account_info(),
+                fulfil_from_available_accounts.shares_mint.to_account_info(),
+                fulfil_from_available_accounts.vault_state.to_account_info(),
+                fulfil_from_available_accounts
+                    .base_vault_authority
+                    .to_account_info(),
+                fulfil_from_available_accounts
+                    .withdrawal_queue_escrow
+                    .to_account_info(),
+                vault_state.base_vault_authority_bump,
+                shares_to_burn,
+            )?;
+        }
+
+        if let Some(withdraw_from_reserve_accounts) = ctx_withdraw_from_reserve {
+            if invested_to_disinvest_ctokens > 0 {
+                klend_operations::cpi_redeem_reserve_liquidity_from_fulfil_withdrawal_request(
+                    fulfil_from_available_accounts,
+                    withdraw_from_reserve_accounts,
+                    &mut cpi_mem,
+                    u8::try_from(vault_state.base_vault_authority_bump).unwrap(),
+                    invested_to_disinvest_ctokens,
+                )?;
+            }
+        }
+
+        let token_vault_after =
+            amount(&fulfil_from_available_accounts.token_vault.to_account_info())?;
+        let liquidity_received = token_vault_after - balances_before.vault_token_balance;
+
+        require!(
+            liquidity_received >= invested_liquidity_to_send_to_user,
+            KaminoVaultError::NotEnoughLiquidityDisinvestedToSendToUser
+        );
+
+        let balances_after = WithdrawalQueueBalances {
+            reserve_supply_liquidity_balance: match ctx_withdraw_from_reserve {
+                Some(accounts) => amount(&accounts.reserve_liquidity_supply.to_account_info())?,
+                None => 0,
+            },
+            vault_token_balance: token_vault_after,
+            vault_ctoken_balance: match ctx_withdraw_from_reserve {
+                Some(accounts) => amount(&accounts.ctoken_vault.to_account_info())?,
+                None => 0,
+            },
+            escrow_shares_balance: amount(
+                &fulfil_from_available_accounts
+                    .withdrawal_queue_escrow
+                    .to_account_info(),
+            )?,
+        };
+
+        post_transfer_fulfil_withdrawal_request_checks(
+            balances_before,
+            balances_after,
+            withdraw_effects,
+        )?;
+
+        Ok(WithdrawalRequestFulfilledEvent {
+            request_id,
+            shares_burned: shares_to_burn,
+            tokens_claimable: available_to_send_to_user + invested_liquidity_to_send_to_user,
+            shares_remaining,
+        })
+    }
+}
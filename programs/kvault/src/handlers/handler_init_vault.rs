@@ -1,8 +1,5 @@
 use anchor_lang::{prelude::*, Accounts};
-use anchor_spl::{
-    token::Token,
-    token_interface::{Mint, TokenAccount, TokenInterface},
-};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use kamino_lending::{utils::FatAccountLoader, Reserve};
 
 use crate::{
@@ -15,6 +12,17 @@ use crate::{
 };
 
 pub fn process(ctx: Context<InitVault>) -> Result<()> {
+    token_ops::shares::initialize_mint(
+        ctx.accounts.shares_token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        ctx.accounts.admin_authority.to_account_info(),
+        ctx.accounts.shares_mint.to_account_info(),
+        ctx.accounts.vault_state.to_account_info(),
+        ctx.accounts.base_vault_authority.to_account_info(),
+        ctx.bumps.shares_mint,
+        ctx.accounts.base_token_mint.decimals,
+    )?;
+
     let vault = &mut ctx.accounts.vault_state.load_init()?;
 
     vault.vault_admin_authority = ctx.accounts.admin_authority.key();
@@ -30,7 +38,7 @@ pub fn process(ctx: Context<InitVault>) -> Result<()> {
     vault_operations::initialize(
         vault,
         ctx.accounts.base_token_mint.decimals,
-        ctx.accounts.shares_mint.decimals,
+        ctx.accounts.base_token_mint.decimals,
         u64::try_from(clock.unix_timestamp).unwrap(),
     )?;
 
@@ -44,10 +52,12 @@ pub fn process(ctx: Context<InitVault>) -> Result<()> {
         shares_to_mint,
         token_to_deposit,
         crank_funds_to_deposit,
+        ..
     } = vault_operations::deposit(
         vault,
         reserves_iter,
         INITIAL_DEPOSIT_AMOUNT,
+        0,
         clock.slot,
         clock.unix_timestamp.try_into().unwrap(),
     )?;
@@ -103,15 +113,13 @@ pub struct InitVault<'info> {
         )]
     pub base_token_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    #[account(init,
+    /// CHECK: created and initialized in the handler, with the Token-2022 extensions
+    /// when `shares_token_program` is Token-2022
+    #[account(mut,
         seeds=[SHARES_SEEDS, vault_state.key().as_ref()],
         bump,
-        payer = admin_authority,
-        mint::decimals = base_token_mint.decimals,
-        mint::authority = base_vault_authority,
-        mint::token_program = shares_token_program,
     )]
-    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub shares_mint: AccountInfo<'info>,
 
     #[account(mut,
         token::mint = base_token_mint,
@@ -122,5 +130,5 @@ pub struct InitVault<'info> {
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
     pub token_program: Interface<'info, TokenInterface>,
-    pub shares_token_program: Program<'info, Token>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
 }
@@ -1,7 +1,13 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{metadata::Metadata, token::Mint};
+use anchor_spl::{
+    metadata::Metadata,
+    token_interface::{Mint, TokenInterface},
+};
 
-use crate::{utils::metadata, VaultState};
+use crate::{
+    utils::{metadata, token_ops},
+    KaminoVaultError, VaultState,
+};
 
 pub fn process<'info>(
     ctx: Context<'_, '_, '_, 'info, InitializeSharesMetadata<'info>>,
@@ -12,18 +18,53 @@ pub fn process<'info>(
     let vault = &ctx.accounts.vault_state.load()?;
 
     msg!("name={}, symbol={}, uri={}", name, symbol, uri);
-    metadata::init(
-        ctx.accounts.vault_state.to_account_info(),
-        ctx.accounts.metadata_program.to_account_info(),
-        ctx.accounts.shares_mint.to_account_info(),
-        ctx.accounts.base_vault_authority.to_account_info(),
-        ctx.accounts.shares_metadata.to_account_info(),
-        ctx.accounts.vault_admin_authority.to_account_info(),
-        ctx.accounts.system_program.to_account_info(),
-        ctx.accounts.rent.to_account_info(),
-        vault.base_vault_authority_bump,
-        metadata::TokenMetadata { name, symbol, uri },
-    )?;
+    match &ctx.accounts.shares_token_program {
+        Some(shares_token_program)
+            if token_ops::shares::is_token_2022(&shares_token_program.to_account_info()) =>
+        {
+            require_keys_eq!(
+                *ctx.accounts.shares_mint.to_account_info().owner,
+                shares_token_program.key(),
+                KaminoVaultError::InvalidSharesMetadataAccounts
+            );
+            require_keys_eq!(
+                ctx.accounts.shares_metadata.key(),
+                ctx.accounts.shares_mint.key(),
+                KaminoVaultError::InvalidSharesMetadataAccounts
+            );
+
+            metadata::init_token_2022(
+                ctx.accounts.vault_state.to_account_info(),
+                shares_token_program.to_account_info(),
+                ctx.accounts.shares_metadata.to_account_info(),
+                ctx.accounts.base_vault_authority.to_account_info(),
+                ctx.accounts.vault_admin_authority.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                vault.base_vault_authority_bump,
+                metadata::TokenMetadata { name, symbol, uri },
+            )?;
+        }
+        _ => {
+            let metadata_program = ctx
+                .accounts
+                .metadata_program
+                .as_ref()
+                .ok_or(KaminoVaultError::InvalidSharesMetadataAccounts)?;
+
+            metadata::init(
+                ctx.accounts.vault_state.to_account_info(),
+                metadata_program.to_account_info(),
+                ctx.accounts.shares_mint.to_account_info(),
+                ctx.accounts.base_vault_authority.to_account_info(),
+                ctx.accounts.shares_metadata.to_account_info(),
+                ctx.accounts.vault_admin_authority.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+                vault.base_vault_authority_bump,
+                metadata::TokenMetadata { name, symbol, uri },
+            )?;
+        }
+    }
 
     Ok(())
 }
@@ -41,16 +82,18 @@ pub struct InitializeSharesMetadata<'info> {
     pub vault_state: AccountLoader<'info, VaultState>,
 
     /// CHECK: vault checks this
-    pub shares_mint: Account<'info, Mint>,
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
 
     /// CHECK: vault checks this
     pub base_vault_authority: AccountInfo<'info>,
 
-    /// CHECK: validated empty by the downstream metaplex metadata program
+    /// CHECK: validated empty by the downstream metaplex metadata program,
+    /// the shares mint itself for Token-2022 shares
     #[account(mut)]
     pub shares_metadata: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
-    pub metadata_program: Program<'info, Metadata>,
+    pub metadata_program: Option<Program<'info, Metadata>>,
+    pub shares_token_program: Option<Interface<'info, TokenInterface>>,
 }
@@ -25,7 +25,7 @@ use crate::{
         },
     },
     utils::{consts::*, cpi_mem::CpiMemoryLender},
-    ReserveWhitelistEntry, VaultState,
+    GlobalConfig, ReserveWhitelistEntry, VaultState,
 };
 
 pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, Invest<'info>>) -> Result<()> {
@@ -35,6 +35,7 @@ pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, Invest<'info>>) -> Result<
     );
 
     let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state.check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_INVEST)?;
     let bump = vault_state.base_vault_authority_bump;
 
     let reserves_count = vault_state.get_reserves_count();
@@ -182,7 +183,7 @@ pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, Invest<'info>>) -> Result<
     Ok(())
 }
 
-fn capture_aum<'info, T: AnyAccountLoader<'info, Reserve>>(
+pub fn capture_aum<'info, T: AnyAccountLoader<'info, Reserve>>(
     vault_state: &VaultState,
     reserves_iter: impl Iterator<Item = T>,
     current_slot: Slot,
@@ -210,6 +211,12 @@ pub struct Invest<'info> {
     )]
     pub vault_state: AccountLoader<'info, VaultState>,
 
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
     #[account(mut)]
     pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
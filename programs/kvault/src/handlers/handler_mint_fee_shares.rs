@@ -0,0 +1,117 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    events::FeeSharesMintedEvent,
+    operations::vault_operations::{self, common},
+    utils::{
+        consts::{GLOBAL_CONFIG_STATE_SEEDS, PAUSE_FEE_WITHDRAWAL},
+        token_ops::shares,
+    },
+    GlobalConfig, KaminoVaultError, VaultState,
+};
+
+// The remaining accounts are one shares token account per fee recipient, in the order of
+// the vault fee recipients table, or the shares token account of the fee shares recipient
+// if the vault has no fee recipients
+pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, MintFeeShares<'info>>) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state
+        .check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_FEE_WITHDRAWAL)?;
+
+    let fee_shares = vault_operations::claim_fee_shares(vault_state)?;
+
+    let minted = mint_to_fee_shares_recipients(
+        vault_state,
+        FeeSharesMintAccounts {
+            shares_token_program: ctx.accounts.shares_token_program.to_account_info(),
+            shares_mint: ctx.accounts.shares_mint.to_account_info(),
+            vault_state: ctx.accounts.vault_state.to_account_info(),
+            base_vault_authority: ctx.accounts.base_vault_authority.to_account_info(),
+        },
+        ctx.remaining_accounts,
+        fee_shares,
+    )?;
+
+    for (recipient, fee_shares) in minted {
+        emit_cpi!(FeeSharesMintedEvent {
+            recipient,
+            fee_shares,
+        });
+    }
+
+    Ok(())
+}
+
+pub(crate) struct FeeSharesMintAccounts<'info> {
+    pub shares_token_program: AccountInfo<'info>,
+    pub shares_mint: AccountInfo<'info>,
+    pub vault_state: AccountInfo<'info>,
+    pub base_vault_authority: AccountInfo<'info>,
+}
+
+/// Mints `fee_shares` to the shares token accounts of the fee recipients, split by their
+/// bps, or to the fee shares recipient. Returns the shares minted to each recipient
+pub(crate) fn mint_to_fee_shares_recipients<'info>(
+    vault_state: &VaultState,
+    accounts: FeeSharesMintAccounts<'info>,
+    recipient_shares_accounts: &[AccountInfo<'info>],
+    fee_shares: u64,
+) -> Result<Vec<(Pubkey, u64)>> {
+    let split = common::split_fee_shares(vault_state, fee_shares)?;
+    let recipient_shares_accounts = recipient_shares_accounts
+        .get(..split.len())
+        .ok_or(anchor_lang::error::ErrorCode::AccountNotEnoughKeys)?;
+
+    for (shares_account_info, (recipient, amount)) in
+        recipient_shares_accounts.iter().zip(split.iter())
+    {
+        let shares_account = InterfaceAccount::<TokenAccount>::try_from(shares_account_info)?;
+        require!(
+            shares_account.owner == *recipient
+                && shares_account.mint == vault_state.shares_mint
+                && *shares_account_info.owner == accounts.shares_token_program.key(),
+            KaminoVaultError::FeeRecipientTokenAccountInvalid
+        );
+
+        if *amount > 0 {
+            shares::mint(
+                accounts.shares_token_program.clone(),
+                accounts.shares_mint.clone(),
+                accounts.vault_state.clone(),
+                accounts.base_vault_authority.clone(),
+                shares_account_info.clone(),
+                vault_state.base_vault_authority_bump,
+                *amount,
+            )?;
+        }
+    }
+
+    Ok(split)
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MintFeeShares<'info> {
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    /// CHECK: has_one in the vault state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        mint::token_program = shares_token_program,
+    )]
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub shares_token_program: Interface<'info, TokenInterface>,
+}
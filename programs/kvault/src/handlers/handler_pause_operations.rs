@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    utils::consts::{GLOBAL_CONFIG_STATE_SEEDS, PAUSE_ALL_OPERATIONS},
+    GlobalConfig, KaminoVaultError, VaultState,
+};
+
+pub fn pause_vault_operations(ctx: Context<PauseVaultOperations>, operations: u64) -> Result<()> {
+    let vault = &mut ctx.accounts.vault_state.load_mut()?;
+    let global_config = ctx.accounts.global_config.load()?;
+    let signer = ctx.accounts.signer.key();
+
+    require!(
+        signer == vault.emergency_guardian
+            || signer == vault.vault_admin_authority
+            || signer == global_config.emergency_guardian
+            || signer == global_config.global_admin,
+        KaminoVaultError::PauseAuthorityIncorrect
+    );
+    require_gte!(
+        PAUSE_ALL_OPERATIONS,
+        operations,
+        KaminoVaultError::InvalidPausedOperations
+    );
+
+    msg!("Prv value is {:?}", vault.paused_operations);
+    vault.paused_operations |= operations;
+    msg!("New value is {:?}", vault.paused_operations);
+
+    Ok(())
+}
+
+pub fn pause_global_operations(ctx: Context<PauseGlobalOperations>, operations: u64) -> Result<()> {
+    let global_config = &mut ctx.accounts.global_config.load_mut()?;
+    let signer = ctx.accounts.signer.key();
+
+    require!(
+        signer == global_config.emergency_guardian || signer == global_config.global_admin,
+        KaminoVaultError::PauseAuthorityIncorrect
+    );
+    require_gte!(
+        PAUSE_ALL_OPERATIONS,
+        operations,
+        KaminoVaultError::InvalidPausedOperations
+    );
+
+    msg!("Prv value is {:?}", global_config.paused_operations);
+    global_config.paused_operations |= operations;
+    msg!("New value is {:?}", global_config.paused_operations);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PauseVaultOperations<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(mut)]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+}
+
+#[derive(Accounts)]
+pub struct PauseGlobalOperations<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+}
@@ -0,0 +1,313 @@
+use anchor_lang::{prelude::*, solana_program::sysvar};
+use kamino_lending::{
+    utils::{AnyAccountLoader, FatAccountLoader},
+    Reserve,
+};
+
+use crate::{
+    operations::{
+        effects::{DepositEffects, RedeemInKindEffects, WithdrawEffects},
+        klend_operations,
+        vault_operations::{self, ReserveToWithdrawFrom, WithdrawAmount},
+    },
+    utils::{
+        consts::{GLOBAL_CONFIG_STATE_SEEDS, VAULT_STATE_SIZE},
+        cpi_mem::CpiMemoryLender,
+        heap::{self, PREVIEW_HEAP_FRAME_BYTES},
+    },
+    GlobalConfig, KaminoVaultError, VaultState,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PreviewDepositResult {
+    pub shares_to_mint: u64,
+    pub token_to_deposit: u64,
+    pub crank_funds_to_deposit: u64,
+    pub high_water_mark_sf: u128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PreviewWithdrawResult {
+    pub shares_to_burn: u64,
+    pub available_to_send_to_user: u64,
+    pub invested_liquidity_to_send_to_user: u64,
+    pub high_water_mark_sf: u128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PreviewRedeemInKindResult {
+    pub shares_to_burn: u64,
+    pub ctokens_to_send_to_user: u64,
+    pub actual_liquidity_value: u64,
+    pub high_water_mark_sf: u128,
+}
+
+pub fn preview_deposit<'info>(
+    ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+    max_amount: u64,
+) -> Result<PreviewDepositResult> {
+    require!(max_amount > 0, KaminoVaultError::DepositAmountsZero);
+
+    let mut scratch =
+        preview_utils::refresh_reserves_and_copy_vault_state(ctx.accounts, ctx.remaining_accounts)?;
+    let vault_state = preview_utils::scratch_vault_state(&mut scratch);
+    let reserves_count = vault_state.get_reserves_count();
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    let DepositEffects {
+        shares_to_mint,
+        token_to_deposit,
+        crank_funds_to_deposit,
+        ..
+    } = vault_operations::deposit(
+        vault_state,
+        reserves_iter,
+        max_amount,
+        0,
+        Clock::get()?.slot,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+    )?;
+
+    Ok(PreviewDepositResult {
+        shares_to_mint,
+        token_to_deposit,
+        crank_funds_to_deposit,
+        high_water_mark_sf: vault_state.high_water_mark_sf,
+    })
+}
+
+pub fn preview_withdraw<'info>(
+    ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+    shares_amount: u64,
+) -> Result<PreviewWithdrawResult> {
+    let mut scratch =
+        preview_utils::refresh_reserves_and_copy_vault_state(ctx.accounts, ctx.remaining_accounts)?;
+    let vault_state = preview_utils::scratch_vault_state(&mut scratch);
+    let global_config = &ctx.accounts.global_config.load()?;
+    let reserves_count = vault_state.get_reserves_count();
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    let mut reserve_addresses = Vec::with_capacity(reserves_count);
+    let mut reserve_loaders = Vec::with_capacity(reserves_count);
+    let mut reserve_ctokens = Vec::with_capacity(reserves_count);
+    for reserve in reserves_iter.clone() {
+        let reserve_address = reserve.get_pubkey();
+        reserve_ctokens.push(
+            vault_state
+                .allocation_for_reserve(&reserve_address)?
+                .ctoken_allocation,
+        );
+        reserve_addresses.push(reserve_address);
+        reserve_loaders.push(reserve);
+    }
+    let reserve_states = reserve_loaders
+        .iter()
+        .map(|reserve| reserve.get())
+        .collect::<Result<Vec<_>>>()?;
+
+    let reserves_to_withdraw_from: Vec<ReserveToWithdrawFrom> = reserve_addresses
+        .iter()
+        .zip(reserve_states.iter())
+        .zip(reserve_ctokens.iter())
+        .map(
+            |((reserve_address, reserve_state), ctokens_owned)| ReserveToWithdrawFrom {
+                reserve_address,
+                reserve_state,
+                ctokens_owned: *ctokens_owned,
+            },
+        )
+        .collect();
+
+    let WithdrawEffects {
+        shares_to_burn,
+        available_to_send_to_user,
+        invested_liquidity_to_send_to_user,
+        ..
+    } = vault_operations::withdraw_from_reserves(
+        vault_state,
+        global_config,
+        &reserves_to_withdraw_from,
+        reserves_iter,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+        Clock::get()?.slot,
+        WithdrawAmount::Shares {
+            shares_amount,
+            min_tokens_out: 0,
+        },
+    )?;
+
+    Ok(PreviewWithdrawResult {
+        shares_to_burn,
+        available_to_send_to_user,
+        invested_liquidity_to_send_to_user,
+        high_water_mark_sf: vault_state.high_water_mark_sf,
+    })
+}
+
+pub fn preview_redeem_in_kind<'info>(
+    ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+    shares_amount: u64,
+    reserve: Pubkey,
+) -> Result<PreviewRedeemInKindResult> {
+    let mut scratch =
+        preview_utils::refresh_reserves_and_copy_vault_state(ctx.accounts, ctx.remaining_accounts)?;
+    let vault_state = preview_utils::scratch_vault_state(&mut scratch);
+    let global_config = &ctx.accounts.global_config.load()?;
+    let reserves_count = vault_state.get_reserves_count();
+    let clock = Clock::get()?;
+
+    let make_reserves_iter = || {
+        ctx.remaining_accounts
+            .iter()
+            .take(reserves_count)
+            .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap())
+    };
+
+    let reserve_loader = make_reserves_iter()
+        .find(|reserve_loader| reserve_loader.get_pubkey() == reserve)
+        .ok_or(KaminoVaultError::ReserveNotPartOfAllocations)?;
+    let reserve_state = reserve_loader.get()?;
+
+    let RedeemInKindEffects {
+        shares_to_burn,
+        ctokens_to_send_to_user,
+        actual_liquidity_value,
+        ..
+    } = vault_operations::redeem_in_kind(vault_operations::RedeemInKindParams {
+        vault_state,
+        global_config,
+        reserve_address: &reserve,
+        reserve_state: &reserve_state,
+        reserves_iter: make_reserves_iter(),
+        shares_amount,
+        min_ctokens_out: 0,
+        clock: &clock,
+    })?;
+
+    Ok(PreviewRedeemInKindResult {
+        shares_to_burn,
+        ctokens_to_send_to_user,
+        actual_liquidity_value,
+        high_water_mark_sf: vault_state.high_water_mark_sf,
+    })
+}
+
+pub fn convert_to_shares<'info>(
+    ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+    assets: u64,
+) -> Result<u64> {
+    let mut scratch =
+        preview_utils::refresh_reserves_and_copy_vault_state(ctx.accounts, ctx.remaining_accounts)?;
+    let vault_state = preview_utils::scratch_vault_state(&mut scratch);
+    let reserves_count = vault_state.get_reserves_count();
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    vault_operations::convert_to_shares(
+        vault_state,
+        reserves_iter,
+        assets,
+        Clock::get()?.slot,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+    )
+}
+
+pub fn convert_to_assets<'info>(
+    ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+    shares: u64,
+) -> Result<u64> {
+    let mut scratch =
+        preview_utils::refresh_reserves_and_copy_vault_state(ctx.accounts, ctx.remaining_accounts)?;
+    let vault_state = preview_utils::scratch_vault_state(&mut scratch);
+    let reserves_count = vault_state.get_reserves_count();
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    vault_operations::convert_to_assets(
+        vault_state,
+        reserves_iter,
+        shares,
+        Clock::get()?.slot,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+    )
+}
+
+#[derive(Accounts)]
+pub struct Preview<'info> {
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
+
+    /// CHECK: account constraints checked in account trait
+    #[account(address = sysvar::instructions::ID)]
+    pub instruction_sysvar_account: AccountInfo<'info>,
+    // This context (list of accounts) has a lot of remaining accounts,
+    // - All reserves entries of this vault
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+    // The vault state is copied to the heap, the transaction must request a heap frame
+    // of at least PREVIEW_HEAP_FRAME_BYTES, which is checked before the copy
+}
+
+pub mod preview_utils {
+    use super::*;
+
+    pub fn refresh_reserves_and_copy_vault_state<'info>(
+        accounts: &Preview<'info>,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<Vec<u128>> {
+        let mut cpi_mem = CpiMemoryLender::build_cpi_memory_lender(
+            accounts.to_account_infos(),
+            remaining_accounts,
+        );
+
+        let vault_state = accounts.vault_state.load()?;
+        let reserves_count = vault_state.get_reserves_count();
+
+        klend_operations::cpi_refresh_reserves(
+            &mut cpi_mem,
+            remaining_accounts.iter().take(reserves_count),
+            reserves_count,
+        )?;
+
+        heap::check_heap_frame_requested(
+            &accounts.instruction_sysvar_account,
+            PREVIEW_HEAP_FRAME_BYTES,
+        )?;
+        heap::extend_heap_frame(PREVIEW_HEAP_FRAME_BYTES);
+
+        let mut scratch = vec![0_u128; VAULT_STATE_SIZE / std::mem::size_of::<u128>()];
+        bytemuck::cast_slice_mut::<u128, u8>(&mut scratch)
+            .copy_from_slice(bytemuck::bytes_of(&*vault_state));
+
+        Ok(scratch)
+    }
+
+    pub fn scratch_vault_state(scratch: &mut [u128]) -> &mut VaultState {
+        bytemuck::from_bytes_mut(bytemuck::cast_slice_mut(scratch))
+    }
+}
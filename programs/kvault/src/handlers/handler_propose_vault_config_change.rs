@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::VaultConfigChangeProposedEvent,
+    operations::vault_config_operations::{self, VaultConfigField},
+    VaultState,
+};
+
+pub fn process(
+    ctx: Context<ProposeVaultConfigChange>,
+    entry: VaultConfigField,
+    data: &[u8],
+) -> Result<()> {
+    let vault = &mut ctx.accounts.vault_state.load_mut()?;
+    let current_ts: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+
+    let pending_change =
+        vault_config_operations::propose_vault_config_change(vault, entry, data, current_ts)?;
+
+    emit_cpi!(VaultConfigChangeProposedEvent {
+        entry,
+        value: pending_change.value,
+        activation_timestamp: pending_change.activation_timestamp,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ProposeVaultConfigChange<'info> {
+    pub vault_admin_authority: Signer<'info>,
+
+    #[account(mut,
+        has_one = vault_admin_authority,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+}
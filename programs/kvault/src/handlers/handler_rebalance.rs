@@ -0,0 +1,355 @@
+use std::collections::BTreeSet;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::{instructions::Instructions as SysInstructions, SysvarId},
+    Accounts,
+};
+use anchor_spl::{
+    token::Token,
+    token_interface::{self, accessor::amount, Mint, TokenAccount, TokenInterface},
+};
+use kamino_lending::{utils::FatAccountLoader, Reserve};
+
+use crate::{
+    events::InvestResultEvent,
+    handlers::handler_invest::capture_aum,
+    kmsg,
+    operations::{
+        effects::{InvestEffects, InvestingDirection, RebalanceReserveEffects},
+        klend_operations,
+        vault_checks::{post_transfer_rebalance_checks, RebalanceBalances, ReserveBalances},
+        vault_operations::{self, common::holdings, ReserveToRebalance},
+    },
+    utils::{consts::*, cpi_mem::CpiMemoryLender},
+    GlobalConfig, ReserveWhitelistEntry, VaultState,
+};
+
+pub fn process<'info>(ctx: Context<'_, '_, 'info, 'info, Rebalance<'info>>) -> Result<()> {
+    let reserves_count = ctx.accounts.vault_state.load()?.get_reserves_count();
+
+    let rebalance_reserves = rebalance_reserve_accounts(
+        ctx.program_id,
+        ctx.remaining_accounts
+            .get(reserves_count..)
+            .ok_or(anchor_lang::error::ErrorCode::AccountNotEnoughKeys)?,
+        reserves_count,
+    )?;
+
+    let mut all_accounts = ctx.accounts.to_account_infos();
+    for rebalance_reserve in rebalance_reserves.iter() {
+        require_keys_eq!(
+            ctx.accounts.vault_state.key(),
+            rebalance_reserve.vault_state.key()
+        );
+        all_accounts.extend_from_slice(&rebalance_reserve.to_account_infos());
+    }
+
+    let mut cpi_mem =
+        CpiMemoryLender::build_cpi_memory_lender(all_accounts, ctx.remaining_accounts);
+
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state.check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_INVEST)?;
+    let bump = vault_state.base_vault_authority_bump;
+
+    klend_operations::cpi_refresh_reserves(
+        &mut cpi_mem,
+        ctx.remaining_accounts.iter().take(reserves_count),
+        reserves_count,
+    )?;
+
+    let token_vault_before = amount(&ctx.accounts.token_vault.to_account_info())?;
+    let reserves_balances_before = reserves_balances(&rebalance_reserves)?;
+
+    let Clock {
+        slot: current_slot,
+        unix_timestamp,
+        ..
+    } = Clock::get()?;
+    let current_timestamp: u64 = unix_timestamp.try_into().unwrap();
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    vault_operations::refresh_rewards(vault_state, current_timestamp)?;
+
+    let initial_holdings_total =
+        holdings(vault_state, reserves_iter.clone(), current_slot)?.total_sum;
+
+    let reserve_addresses: Vec<Pubkey> = rebalance_reserves
+        .iter()
+        .map(|rebalance_reserve| rebalance_reserve.reserve.key())
+        .collect();
+    let reserve_states = rebalance_reserves
+        .iter()
+        .map(|rebalance_reserve| rebalance_reserve.reserve.load())
+        .collect::<Result<Vec<_>>>()?;
+    let reserves_to_rebalance: Vec<ReserveToRebalance> = rebalance_reserves
+        .iter()
+        .zip(reserve_addresses.iter())
+        .zip(reserve_states.iter())
+        .map(
+            |((rebalance_reserve, reserve_address), reserve_state)| ReserveToRebalance {
+                reserve_address,
+                reserve_state,
+                reserve_whitelist_entry: rebalance_reserve
+                    .reserve_whitelist_entry
+                    .as_ref()
+                    .map(|acc| acc.as_ref()),
+            },
+        )
+        .collect();
+
+    let rebalance_effects = vault_operations::rebalance(
+        vault_state,
+        reserves_iter.clone(),
+        &reserves_to_rebalance,
+        current_slot,
+        current_timestamp,
+    )?;
+
+    let aum_before_transfers = capture_aum(vault_state, reserves_iter.clone(), current_slot)?;
+
+    drop(reserves_to_rebalance);
+    drop(reserve_states);
+
+    let total_rounding_loss: u64 = rebalance_effects
+        .iter()
+        .map(|e| e.invest_effects.rounding_loss)
+        .sum();
+
+    if total_rounding_loss > 0 {
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.payer_token_account.to_account_info(),
+                    to: ctx.accounts.token_vault.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                },
+            ),
+            total_rounding_loss,
+            ctx.accounts.token_mint.decimals,
+        )?;
+    }
+
+    for RebalanceReserveEffects {
+        reserve_index,
+        invest_effects,
+    } in rebalance_effects.iter()
+    {
+        let InvestEffects {
+            direction,
+            liquidity_amount,
+            collateral_amount,
+            rounding_loss,
+        } = invest_effects;
+        let rebalance_reserve = &rebalance_reserves[*reserve_index];
+
+        kmsg!(
+            "InvestEffects reserve={} direction={:?} liquidity_amount={}, collateral_amount={}, rounding_loss={}",
+            reserve_addresses[*reserve_index],
+            direction,
+            liquidity_amount,
+            collateral_amount,
+            rounding_loss
+        );
+
+        if *liquidity_amount > 0 {
+            match direction {
+                InvestingDirection::Add => {
+                    klend_operations::cpi_deposit_reserve_liquidity_from_rebalance(
+                        ctx.accounts,
+                        rebalance_reserve,
+                        &mut cpi_mem,
+                        bump as u8,
+                        *liquidity_amount,
+                    )?;
+                }
+                InvestingDirection::Subtract => {
+                    klend_operations::cpi_redeem_reserve_liquidity_from_rebalance(
+                        ctx.accounts,
+                        rebalance_reserve,
+                        &mut cpi_mem,
+                        bump as u8,
+                        *collateral_amount,
+                    )?;
+                }
+            }
+        }
+    }
+
+    klend_operations::cpi_refresh_reserves(
+        &mut cpi_mem,
+        ctx.remaining_accounts.iter().take(reserves_count),
+        reserves_count,
+    )?;
+
+    drop(cpi_mem);
+
+    let aum_after_transfers = capture_aum(vault_state, reserves_iter.clone(), current_slot)?;
+    let final_holdings_total =
+        holdings(vault_state, reserves_iter.clone(), current_slot)?.total_sum;
+
+    let token_vault_after = amount(&ctx.accounts.token_vault.to_account_info())?;
+    let reserves_balances_after = reserves_balances(&rebalance_reserves)?;
+
+    post_transfer_rebalance_checks(
+        RebalanceBalances {
+            vault_token_balance: token_vault_before,
+            reserves: reserves_balances_before,
+        },
+        RebalanceBalances {
+            vault_token_balance: token_vault_after,
+            reserves: reserves_balances_after,
+        },
+        &rebalance_effects,
+        initial_holdings_total,
+        final_holdings_total,
+        aum_before_transfers,
+        aum_after_transfers,
+    )?;
+
+    for RebalanceReserveEffects {
+        reserve_index,
+        invest_effects,
+    } in rebalance_effects.into_iter()
+    {
+        emit_cpi!(InvestResultEvent {
+            reserve: reserve_addresses[reserve_index],
+            direction: invest_effects.direction,
+            liquidity_amount: invest_effects.liquidity_amount,
+            collateral_amount: invest_effects.collateral_amount,
+            rounding_loss: invest_effects.rounding_loss,
+        });
+    }
+
+    Ok(())
+}
+
+fn rebalance_reserve_accounts<'info>(
+    program_id: &Pubkey,
+    mut accounts: &'info [AccountInfo<'info>],
+    count: usize,
+) -> Result<Vec<RebalanceReserve<'info>>> {
+    let mut rebalance_reserves = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut bumps = RebalanceReserveBumps::default();
+        let mut reallocs = BTreeSet::new();
+        rebalance_reserves.push(RebalanceReserve::try_accounts(
+            program_id,
+            &mut accounts,
+            &[],
+            &mut bumps,
+            &mut reallocs,
+        )?);
+    }
+
+    Ok(rebalance_reserves)
+}
+
+fn reserves_balances(rebalance_reserves: &[RebalanceReserve]) -> Result<Vec<ReserveBalances>> {
+    rebalance_reserves
+        .iter()
+        .map(|rebalance_reserve| {
+            Ok(ReserveBalances {
+                reserve_supply_liquidity_balance: amount(
+                    &rebalance_reserve.reserve_liquidity_supply.to_account_info(),
+                )?,
+                vault_ctoken_balance: amount(&rebalance_reserve.ctoken_vault.to_account_info())?,
+            })
+        })
+        .collect()
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Rebalance<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut,
+        token::mint = token_mint,
+        token::authority = payer,
+    )]
+    pub payer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = token_vault,
+        has_one = token_mint,
+        has_one = token_program,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: has_one in vault_state
+    #[account(mut)]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: has_one check on the vault_state
+    #[account(mut)]
+    pub base_vault_authority: AccountInfo<'info>,
+
+    pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
+    pub reserve_collateral_token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// CHECK: Syvar Instruction allowing introspection, fixed address
+    #[account(address = SysInstructions::id())]
+    pub instruction_sysvar_account: AccountInfo<'info>,
+    // This context (list of accounts) has a lot of remaining accounts,
+    // - All reserves entries of this vault
+    // - One group of `RebalanceReserve` accounts per reserve entry of this vault
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+}
+
+#[derive(Accounts)]
+pub struct RebalanceReserve<'info> {
+    #[account(mut)]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: check in logic if there is allocation for this reserve
+    #[account(mut)]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    // Deterministic, PDA
+    #[account(mut,
+        seeds = [CTOKEN_VAULT_SEED, vault_state.key().as_ref(), reserve.key().as_ref()],
+        bump,
+        token::token_program = reserve_collateral_token_program,
+    )]
+    pub ctoken_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: on klend CPI call
+    pub lending_market: AccountInfo<'info>,
+    /// CHECK: on klend CPI call
+    pub lending_market_authority: AccountInfo<'info>,
+    /// CHECK: on klend CPI call
+    #[account(mut)]
+    pub reserve_liquidity_supply: AccountInfo<'info>,
+    /// CHECK: on klend CPI call
+    #[account(mut)]
+    pub reserve_collateral_mint: AccountInfo<'info>,
+
+    #[account(
+        seeds = [WHITELISTED_RESERVES_SEED, reserve.key().as_ref()],
+        bump
+    )]
+    pub reserve_whitelist_entry: Option<Account<'info, ReserveWhitelistEntry>>,
+
+    pub reserve_collateral_token_program: Program<'info, Token>,
+}
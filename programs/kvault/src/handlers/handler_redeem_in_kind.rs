@@ -3,7 +3,7 @@ use std::convert::TryFrom;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     token::Token,
-    token_interface::{accessor::amount, Mint, TokenAccount},
+    token_interface::{accessor::amount, Mint, TokenAccount, TokenInterface},
 };
 use kamino_lending::{
     fraction::Fraction,
@@ -13,7 +13,10 @@ use kamino_lending::{
 use solana_program::clock::Slot;
 
 use crate::{
-    events::{RedeemInKindResultEvent, SharesToWithdrawEvent},
+    events::{
+        FirstLossAbsorbedEvent, HighWaterMarkUpdatedEvent, RedeemInKindResultEvent,
+        SharesToWithdrawEvent,
+    },
     operations::{
         effects::RedeemInKindEffects,
         klend_operations,
@@ -21,7 +24,7 @@ use crate::{
         vault_operations::{self, common::underlying_inventory},
     },
     utils::{
-        consts::{CTOKEN_VAULT_SEED, GLOBAL_CONFIG_STATE_SEEDS},
+        consts::{CTOKEN_VAULT_SEED, GLOBAL_CONFIG_STATE_SEEDS, PAUSE_REDEEM_IN_KIND},
         cpi_mem::CpiMemoryLender,
         token_ops::{shares, tokens},
     },
@@ -31,6 +34,7 @@ use crate::{
 pub fn redeem_in_kind<'info>(
     ctx: Context<'_, '_, '_, 'info, RedeemInKind<'info>>,
     shares_amount: u64,
+    min_ctokens_out: u64,
 ) -> Result<()> {
     let all_accounts = ctx.accounts.to_account_infos();
 
@@ -39,6 +43,7 @@ pub fn redeem_in_kind<'info>(
 
     let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
     let global_config = &ctx.accounts.global_config.load()?;
+    vault_state.check_operation_not_paused(global_config, PAUSE_REDEEM_IN_KIND)?;
     let reserves_count = vault_state.get_reserves_count();
     let clock = Clock::get()?;
     let current_slot = clock.slot;
@@ -67,6 +72,9 @@ pub fn redeem_in_kind<'info>(
 
     let reserve = ctx.accounts.reserve.load()?;
     let reserve_address = &ctx.accounts.reserve.key();
+    let initial_high_water_mark_sf = vault_state.high_water_mark_sf;
+    let initial_first_loss_shares_pending_burn = vault_state.first_loss_shares_pending_burn;
+    let initial_cumulative_first_loss_absorbed_sf = vault_state.cumulative_first_loss_absorbed_sf;
 
     let RedeemInKindEffects {
         shares_to_burn,
@@ -80,6 +88,7 @@ pub fn redeem_in_kind<'info>(
         reserve_state: &reserve,
         reserves_iter: make_reserves_iter(),
         shares_amount,
+        min_ctokens_out,
         clock: &clock,
     })?;
 
@@ -90,6 +99,21 @@ pub fn redeem_in_kind<'info>(
         shares_to_burn,
         ctokens_to_send_to_user,
     });
+    if vault_state.high_water_mark_sf != initial_high_water_mark_sf {
+        emit_cpi!(HighWaterMarkUpdatedEvent {
+            previous_high_water_mark_sf: initial_high_water_mark_sf,
+            high_water_mark_sf: vault_state.high_water_mark_sf,
+        });
+    }
+    if vault_state.first_loss_shares_pending_burn != initial_first_loss_shares_pending_burn {
+        emit_cpi!(FirstLossAbsorbedEvent {
+            loss_absorbed_sf: vault_state.cumulative_first_loss_absorbed_sf
+                - initial_cumulative_first_loss_absorbed_sf,
+            shares_absorbed: vault_state.first_loss_shares_pending_burn
+                - initial_first_loss_shares_pending_burn,
+            first_loss_shares_remaining: vault_state.first_loss_shares,
+        });
+    }
 
    
     shares::burn(
@@ -213,7 +237,7 @@ pub struct RedeemInKind<'info> {
     pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
 
     pub reserve_collateral_token_program: Program<'info, Token>,
-    pub shares_token_program: Program<'info, Token>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
     pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
     // This context has remaining accounts:
     // - All reserves entries of this vault
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::{utils::consts::DEPOSITOR_ALLOWLIST_SEED, xmsg, DepositorAllowlistEntry, VaultState};
+
+pub fn process(ctx: Context<RemoveDepositorFromAllowlist>) -> Result<()> {
+    xmsg!(
+        "Removed depositor {depositor} from the allowlist of vault {vault}",
+        depositor = ctx.accounts.depositor_allowlist_entry.depositor,
+        vault = ctx.accounts.vault_state.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RemoveDepositorFromAllowlist<'info> {
+    #[account(mut)]
+    pub vault_admin_authority: Signer<'info>,
+
+    #[account(has_one = vault_admin_authority)]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(mut,
+        close = vault_admin_authority,
+        has_one = vault_state,
+        seeds = [DEPOSITOR_ALLOWLIST_SEED, vault_state.key().as_ref(), depositor_allowlist_entry.depositor.as_ref()],
+        bump
+    )]
+    pub depositor_allowlist_entry: Account<'info, DepositorAllowlistEntry>,
+}
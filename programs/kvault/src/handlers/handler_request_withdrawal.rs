@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{accessor::amount, Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    events::WithdrawalRequestedEvent,
+    operations::vault_operations,
+    utils::{
+        consts::{
+            GLOBAL_CONFIG_STATE_SEEDS, PAUSE_WITHDRAWALS, WITHDRAWAL_QUEUE_ESCROW_SEED,
+            WITHDRAWAL_REQUEST_SEED, WITHDRAWAL_REQUEST_SIZE,
+        },
+        token_ops::{shares, tokens::UserTransferAccounts},
+    },
+    GlobalConfig, KaminoVaultError, VaultState, WithdrawalRequest,
+};
+
+// The remaining accounts are the accounts of the shares transfer hook, if the shares mint has one
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, RequestWithdrawal<'info>>,
+    shares_amount: u64,
+) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state
+        .check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_WITHDRAWALS)?;
+    let current_ts: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+
+    let user_shares_before = ctx.accounts.user_shares_ata.amount;
+    let escrow_shares_before = ctx.accounts.withdrawal_queue_escrow.amount;
+    let shares_amount = std::cmp::min(shares_amount, user_shares_before);
+
+    vault_operations::request_withdrawal(
+        vault_state,
+        &mut ctx.accounts.withdrawal_request,
+        ctx.accounts.vault_state.key(),
+        ctx.accounts.user.key(),
+        shares_amount,
+        current_ts,
+    )?;
+
+    shares::transfer_to_vault(
+        &UserTransferAccounts {
+            token_program: ctx.accounts.shares_token_program.to_account_info(),
+            token_vault: ctx.accounts.withdrawal_queue_escrow.to_account_info(),
+            token_ata: ctx.accounts.user_shares_ata.to_account_info(),
+            token_mint: ctx.accounts.shares_mint.to_account_info(),
+            user_authority: ctx.accounts.user.to_account_info(),
+        },
+        ctx.remaining_accounts,
+        shares_amount,
+        ctx.accounts.shares_mint.decimals,
+    )?;
+
+    let user_shares_after = amount(&ctx.accounts.user_shares_ata.to_account_info())?;
+    let escrow_shares_after = amount(&ctx.accounts.withdrawal_queue_escrow.to_account_info())?;
+
+    require!(
+        user_shares_before - user_shares_after == shares_amount
+            && escrow_shares_after - escrow_shares_before == shares_amount,
+        KaminoVaultError::WithdrawalRequestEscrowAmountDoesNotMatch
+    );
+
+    emit_cpi!(WithdrawalRequestedEvent {
+        request_id: ctx.accounts.withdrawal_request.request_id,
+        shares_amount,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestWithdrawal<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut,
+        has_one = base_vault_authority,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(init,
+        payer = user,
+        space = 8 + WITHDRAWAL_REQUEST_SIZE,
+        seeds = [
+            WITHDRAWAL_REQUEST_SEED,
+            vault_state.key().as_ref(),
+            user.key().as_ref(),
+            &vault_state.load()?.withdrawal_queue.next_request_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(mut,
+        token::mint = shares_mint,
+        token::authority = user,
+        token::token_program = shares_token_program,
+    )]
+    pub user_shares_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(init_if_needed,
+        payer = user,
+        seeds = [WITHDRAWAL_QUEUE_ESCROW_SEED, vault_state.key().as_ref()],
+        bump,
+        token::mint = shares_mint,
+        token::authority = base_vault_authority,
+        token::token_program = shares_token_program,
+    )]
+    pub withdrawal_queue_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
@@ -0,0 +1,208 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            transfer_hook::TransferHookAccount, BaseStateWithExtensions, StateWithExtensions,
+        },
+    },
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+use spl_tlv_account_resolution::state::ExtraAccountMetaList;
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+
+use crate::{
+    operations::depositor_allowlist_operations,
+    utils::{
+        consts::{DEPOSITOR_ALLOWLIST_SEED, EXTRA_ACCOUNT_METAS_SEED},
+        token_ops::shares,
+    },
+    xmsg, KaminoVaultError, VaultState,
+};
+
+pub fn initialize(ctx: Context<InitializeSharesTransferHook>) -> Result<()> {
+    let extra_account_metas =
+        depositor_allowlist_operations::shares_transfer_hook_extra_account_metas(
+            &ctx.accounts.vault_state.key(),
+        )?;
+
+    ExtraAccountMetaList::init::<ExecuteInstruction>(
+        &mut ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?,
+        &extra_account_metas,
+    )?;
+
+    xmsg!(
+        "Initialized shares transfer hook for vault {vault} mint {mint}",
+        vault = ctx.accounts.vault_state.key(),
+        mint = ctx.accounts.shares_mint.key()
+    );
+
+    Ok(())
+}
+
+pub fn update(ctx: Context<UpdateSharesTransferHook>, enabled: bool) -> Result<()> {
+    let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+
+    require!(
+        shares::is_token_2022(&ctx.accounts.shares_token_program.to_account_info()),
+        KaminoVaultError::SharesMintHasNoTransferHook
+    );
+    // Token-2022 fails every transfer of a mint whose hook has no extra accounts list
+    require!(
+        !enabled || !ctx.accounts.extra_account_meta_list.data_is_empty(),
+        KaminoVaultError::SharesTransferHookNotInitialized
+    );
+
+    let hook_program_id = enabled.then_some(crate::ID);
+    shares::update_transfer_hook_program_id(
+        ctx.accounts.shares_token_program.to_account_info(),
+        ctx.accounts.shares_mint.to_account_info(),
+        ctx.accounts.vault_state.to_account_info(),
+        ctx.accounts.base_vault_authority.to_account_info(),
+        vault_state.base_vault_authority_bump,
+        hook_program_id,
+    )?;
+
+    msg!(
+        "Prv value is {:?}",
+        vault_state.permissioned_share_transfers
+    );
+    msg!("New value is {:?}", u8::from(enabled));
+    vault_state.permissioned_share_transfers = u8::from(enabled);
+
+    Ok(())
+}
+
+pub fn execute(ctx: Context<SharesTransferHook>, _amount: u64) -> Result<()> {
+    // Only Token-2022 can invoke the hook in the middle of a transfer, a direct call would let
+    // anyone pass arbitrary accounts
+    check_source_is_transferring(&ctx.accounts.source_token.to_account_info())?;
+
+    let vault_state = ctx.accounts.vault_state.load()?;
+
+    if !vault_state.vault_has_permissioned_share_transfers() {
+        return Ok(());
+    }
+
+    let destination_owner = ctx.accounts.destination_token.owner;
+    if destination_owner == vault_state.base_vault_authority {
+        return Ok(());
+    }
+
+    if !depositor_allowlist_operations::is_depositor_allowlisted(
+        ctx.program_id,
+        &ctx.accounts.vault_state.key(),
+        &destination_owner,
+        &ctx.accounts.destination_allowlist_entry,
+    ) {
+        msg!("Shares receiver {} is not allowlisted", destination_owner);
+        return err!(KaminoVaultError::DepositorNotAllowlisted);
+    }
+
+    Ok(())
+}
+
+fn check_source_is_transferring(source_token: &AccountInfo) -> Result<()> {
+    let data = source_token.try_borrow_data()?;
+    let source_token = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+    let transferring = source_token
+        .get_extension::<TransferHookAccount>()
+        .map(|extension| bool::from(extension.transferring))
+        .unwrap_or(false);
+    require!(
+        transferring,
+        KaminoVaultError::SharesTransferHookNotTransferring
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeSharesTransferHook<'info> {
+    #[account(mut)]
+    pub vault_admin_authority: Signer<'info>,
+
+    #[account(
+        has_one = vault_admin_authority,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: initialized as an `ExtraAccountMetaList` in the handler
+    #[account(
+        init,
+        payer = vault_admin_authority,
+        space = ExtraAccountMetaList::size_of(
+            depositor_allowlist_operations::SHARES_TRANSFER_HOOK_EXTRA_ACCOUNTS_COUNT
+        ).unwrap(),
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, shares_mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSharesTransferHook<'info> {
+    pub vault_admin_authority: Signer<'info>,
+
+    #[account(mut,
+        has_one = vault_admin_authority,
+        has_one = base_vault_authority,
+        has_one = shares_mint,
+    )]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: has_one check in vault_state
+    pub base_vault_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        mint::token_program = shares_token_program,
+    )]
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: may not be initialized yet, checked in the handler when enabling the hook
+    #[account(
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, shares_mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+
+    pub shares_token_program: Interface<'info, TokenInterface>,
+}
+
+// Accounts of the transfer hook interface `Execute` instruction, followed by
+// the extra accounts stored in the `ExtraAccountMetaList`
+#[derive(Accounts)]
+pub struct SharesTransferHook<'info> {
+    #[account(token::mint = shares_mint)]
+    pub source_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(token::mint = shares_mint)]
+    pub destination_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: owner or delegate of the source token account, checked by the token program
+    pub owner: AccountInfo<'info>,
+
+    /// CHECK: PDA of the transfer hook interface
+    #[account(
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, shares_mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+
+    #[account(has_one = shares_mint)]
+    pub vault_state: AccountLoader<'info, VaultState>,
+
+    /// CHECK: may not exist, checked in the handler
+    #[account(
+        seeds = [DEPOSITOR_ALLOWLIST_SEED, vault_state.key().as_ref(), destination_token.owner.as_ref()],
+        bump
+    )]
+    pub destination_allowlist_entry: AccountInfo<'info>,
+}
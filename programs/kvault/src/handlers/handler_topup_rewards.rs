@@ -2,12 +2,20 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{accessor, Mint, TokenAccount, TokenInterface};
 
 use crate::{
-    operations::vault_operations, utils::token_ops::tokens::UserTransferAccounts, KaminoVaultError,
-    VaultState,
+    operations::vault_operations,
+    utils::{
+        consts::{GLOBAL_CONFIG_STATE_SEEDS, PAUSE_REWARD_DISTRIBUTION},
+        token_ops::tokens::UserTransferAccounts,
+    },
+    GlobalConfig, KaminoVaultError, VaultState,
 };
 
 pub fn process(ctx: Context<TopupRewards>, amount: u64) -> Result<()> {
     let vault = &mut ctx.accounts.vault_state.load_mut()?;
+    vault.check_operation_not_paused(
+        &ctx.accounts.global_config.load()?,
+        PAUSE_REWARD_DISTRIBUTION,
+    )?;
     let current_ts: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
 
     vault_operations::topup_rewards(vault, amount, current_ts)?;
@@ -64,6 +72,12 @@ pub struct TopupRewards<'info> {
     )]
     pub vault_state: AccountLoader<'info, VaultState>,
 
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
     pub token_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(mut)]
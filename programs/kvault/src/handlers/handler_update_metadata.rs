@@ -1,7 +1,13 @@
 use anchor_lang::prelude::*;
-use anchor_spl::metadata::{Metadata, MetadataAccount};
+use anchor_spl::{
+    metadata::{Metadata, MetadataAccount},
+    token_interface::TokenInterface,
+};
 
-use crate::{utils::metadata, VaultState};
+use crate::{
+    utils::{metadata, token_ops},
+    KaminoVaultError, VaultState,
+};
 
 pub fn process<'info>(
     ctx: Context<'_, '_, '_, 'info, UpdateSharesMetadata<'info>>,
@@ -12,14 +18,59 @@ pub fn process<'info>(
     let vault = &ctx.accounts.vault_state.load()?;
 
     msg!("name={}, symbol={}, uri={}", name, symbol, uri);
-    metadata::update(
-        ctx.accounts.vault_state.to_account_info(),
-        ctx.accounts.metadata_program.to_account_info(),
-        ctx.accounts.base_vault_authority.to_account_info(),
-        ctx.accounts.shares_metadata.to_account_info(),
-        vault.base_vault_authority_bump,
-        metadata::TokenMetadata { name, symbol, uri },
-    )?;
+    match &ctx.accounts.shares_token_program {
+        Some(shares_token_program)
+            if token_ops::shares::is_token_2022(&shares_token_program.to_account_info()) =>
+        {
+            require_keys_eq!(
+                ctx.accounts.shares_metadata.key(),
+                vault.shares_mint,
+                KaminoVaultError::InvalidSharesMetadataAccounts
+            );
+            require_keys_eq!(
+                *ctx.accounts.shares_metadata.owner,
+                shares_token_program.key(),
+                KaminoVaultError::InvalidSharesMetadataAccounts
+            );
+            let system_program = ctx
+                .accounts
+                .system_program
+                .as_ref()
+                .ok_or(KaminoVaultError::InvalidSharesMetadataAccounts)?;
+
+            metadata::update_token_2022(
+                ctx.accounts.vault_state.to_account_info(),
+                shares_token_program.to_account_info(),
+                ctx.accounts.shares_metadata.to_account_info(),
+                ctx.accounts.base_vault_authority.to_account_info(),
+                ctx.accounts.vault_admin_authority.to_account_info(),
+                system_program.to_account_info(),
+                vault.base_vault_authority_bump,
+                metadata::TokenMetadata { name, symbol, uri },
+            )?;
+        }
+        _ => {
+            let metadata_program = ctx
+                .accounts
+                .metadata_program
+                .as_ref()
+                .ok_or(KaminoVaultError::InvalidSharesMetadataAccounts)?;
+
+            let shares_metadata =
+                Account::<MetadataAccount>::try_from(&ctx.accounts.shares_metadata)?;
+            require_keys_eq!(shares_metadata.update_authority, vault.base_vault_authority);
+            require_keys_eq!(shares_metadata.mint, vault.shares_mint);
+
+            metadata::update(
+                ctx.accounts.vault_state.to_account_info(),
+                metadata_program.to_account_info(),
+                ctx.accounts.base_vault_authority.to_account_info(),
+                ctx.accounts.shares_metadata.to_account_info(),
+                vault.base_vault_authority_bump,
+                metadata::TokenMetadata { name, symbol, uri },
+            )?;
+        }
+    }
 
     Ok(())
 }
@@ -38,13 +89,12 @@ pub struct UpdateSharesMetadata<'info> {
     /// CHECK: vault checks this
     pub base_vault_authority: AccountInfo<'info>,
 
-    /// CHECK: validated by the downstream metaplex metadata program
-    #[account(
-        mut,
-        constraint = shares_metadata.update_authority == vault_state.load()?.base_vault_authority,
-        constraint = shares_metadata.mint == vault_state.load()?.shares_mint
-    )]
-    pub shares_metadata: Account<'info, MetadataAccount>,
+    /// CHECK: metaplex metadata account checked in the handler, the shares mint itself
+    /// for Token-2022 shares
+    #[account(mut)]
+    pub shares_metadata: AccountInfo<'info>,
 
-    pub metadata_program: Program<'info, Metadata>,
+    pub metadata_program: Option<Program<'info, Metadata>>,
+    pub shares_token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Option<Program<'info, System>>,
 }
@@ -5,7 +5,8 @@ use crate::{
     operations::{
         klend_operations,
         vault_config_operations::{
-            self, check_if_signer_allowed_to_update_vault_config, VaultConfigField,
+            self, check_if_signer_allowed_to_update_vault_config,
+            check_vault_config_update_not_timelocked, VaultConfigField,
         },
         vault_operations::{self, common::holdings},
     },
@@ -23,6 +24,7 @@ pub fn process<'info>(
     let is_global_admin = ctx.accounts.signer.key() == global_config.global_admin;
     let is_vault_admin = ctx.accounts.signer.key() == vault.vault_admin_authority;
     check_if_signer_allowed_to_update_vault_config(&entry, data, is_global_admin, is_vault_admin)?;
+    check_vault_config_update_not_timelocked(vault, &entry)?;
 
    
     let mut cpi_mem = CpiMemoryLender::build_cpi_memory_lender(
@@ -1,4 +1,4 @@
-use std::convert::TryFrom;
+use std::{collections::BTreeSet, convert::TryFrom};
 
 use anchor_lang::{prelude::*, solana_program::sysvar};
 use anchor_spl::{
@@ -11,11 +11,13 @@ use crate::{
     operations::{
         effects::WithdrawEffects,
         klend_operations,
-        vault_checks::{post_transfer_withdraw_balance_checks, VaultAndUserBalances},
-        vault_operations,
+        vault_checks::{
+            post_transfer_withdraw_balance_checks, ReserveBalances, VaultAndUserWithdrawBalances,
+        },
+        vault_operations::{self, WithdrawAmount},
     },
     utils::{
-        consts::{CTOKEN_VAULT_SEED, GLOBAL_CONFIG_STATE_SEEDS},
+        consts::{CTOKEN_VAULT_SEED, GLOBAL_CONFIG_STATE_SEEDS, PAUSE_WITHDRAWALS},
         cpi_mem::CpiMemoryLender,
         token_ops::{self, shares},
     },
@@ -25,6 +27,7 @@ use crate::{
 pub fn withdraw<'info>(
     ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
     shares_amount: u64,
+    min_tokens_out: u64,
 ) -> Result<()> {
     let withdraw_from_available = &ctx.accounts.withdraw_from_available;
     let withdraw_from_reserve = &ctx.accounts.withdraw_from_reserve_accounts;
@@ -34,15 +37,61 @@ pub fn withdraw<'info>(
         withdraw_from_reserve.vault_state.key()
     );
 
-    let (shares_to_withdraw_event, withdraw_result_event) = withdraw_utils::withdraw(
-        withdraw_from_available,
-        Some(withdraw_from_reserve),
-        ctx.remaining_accounts,
-        shares_amount,
-    )?;
+    let (shares_to_withdraw_event, withdraw_result_event, high_water_mark_event, first_loss_event) =
+        withdraw_utils::withdraw(
+            withdraw_from_available,
+            std::slice::from_ref(withdraw_from_reserve),
+            ctx.remaining_accounts,
+            WithdrawAmount::Shares {
+                shares_amount,
+                min_tokens_out,
+            },
+        )?;
 
     emit_cpi!(shares_to_withdraw_event);
     emit_cpi!(withdraw_result_event);
+    if let Some(high_water_mark_event) = high_water_mark_event {
+        emit_cpi!(high_water_mark_event);
+    }
+    if let Some(first_loss_event) = first_loss_event {
+        emit_cpi!(first_loss_event);
+    }
+
+    Ok(())
+}
+
+pub fn withdraw_assets<'info>(
+    ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
+    tokens_out: u64,
+    max_shares_in: u64,
+) -> Result<()> {
+    let withdraw_from_available = &ctx.accounts.withdraw_from_available;
+    let withdraw_from_reserve = &ctx.accounts.withdraw_from_reserve_accounts;
+
+    require_keys_eq!(
+        withdraw_from_available.vault_state.key(),
+        withdraw_from_reserve.vault_state.key()
+    );
+
+    let (shares_to_withdraw_event, withdraw_result_event, high_water_mark_event, first_loss_event) =
+        withdraw_utils::withdraw(
+            withdraw_from_available,
+            std::slice::from_ref(withdraw_from_reserve),
+            ctx.remaining_accounts,
+            WithdrawAmount::Tokens {
+                tokens_out,
+                max_shares_in,
+            },
+        )?;
+
+    emit_cpi!(shares_to_withdraw_event);
+    emit_cpi!(withdraw_result_event);
+    if let Some(high_water_mark_event) = high_water_mark_event {
+        emit_cpi!(high_water_mark_event);
+    }
+    if let Some(first_loss_event) = first_loss_event {
+        emit_cpi!(first_loss_event);
+    }
 
     Ok(())
 }
@@ -50,12 +99,70 @@ pub fn withdraw<'info>(
 pub fn withdraw_from_available<'info>(
     ctx: Context<'_, '_, '_, 'info, WithdrawFromAvailable<'info>>,
     shares_amount: u64,
+    min_tokens_out: u64,
 ) -> Result<()> {
-    let (shares_to_withdraw_event, withdraw_result_event) =
-        withdraw_utils::withdraw(ctx.accounts, None, ctx.remaining_accounts, shares_amount)?;
+    let (shares_to_withdraw_event, withdraw_result_event, high_water_mark_event, first_loss_event) =
+        withdraw_utils::withdraw(
+            ctx.accounts,
+            &[],
+            ctx.remaining_accounts,
+            WithdrawAmount::Shares {
+                shares_amount,
+                min_tokens_out,
+            },
+        )?;
 
     emit_cpi!(shares_to_withdraw_event);
     emit_cpi!(withdraw_result_event);
+    if let Some(high_water_mark_event) = high_water_mark_event {
+        emit_cpi!(high_water_mark_event);
+    }
+    if let Some(first_loss_event) = first_loss_event {
+        emit_cpi!(first_loss_event);
+    }
+
+    Ok(())
+}
+
+pub fn withdraw_from_reserves<'info>(
+    ctx: Context<'_, '_, 'info, 'info, WithdrawFromReserves<'info>>,
+    shares_amount: u64,
+    min_tokens_out: u64,
+    reserves_to_withdraw_from_count: u8,
+) -> Result<()> {
+    let withdraw_from_available = &ctx.accounts.withdraw_from_available;
+    let reserves_count = withdraw_from_available
+        .vault_state
+        .load()?
+        .get_reserves_count();
+
+    let withdraw_from_reserves = withdraw_utils::withdraw_from_invested_accounts(
+        ctx.program_id,
+        ctx.remaining_accounts
+            .get(reserves_count..)
+            .ok_or(anchor_lang::error::ErrorCode::AccountNotEnoughKeys)?,
+        reserves_to_withdraw_from_count.into(),
+    )?;
+
+    let (shares_to_withdraw_event, withdraw_result_event, high_water_mark_event, first_loss_event) =
+        withdraw_utils::withdraw(
+            withdraw_from_available,
+            &withdraw_from_reserves,
+            ctx.remaining_accounts,
+            WithdrawAmount::Shares {
+                shares_amount,
+                min_tokens_out,
+            },
+        )?;
+
+    emit_cpi!(shares_to_withdraw_event);
+    emit_cpi!(withdraw_result_event);
+    if let Some(high_water_mark_event) = high_water_mark_event {
+        emit_cpi!(high_water_mark_event);
+    }
+    if let Some(first_loss_event) = first_loss_event {
+        emit_cpi!(first_loss_event);
+    }
 
     Ok(())
 }
@@ -73,6 +180,17 @@ pub struct Withdraw<'info> {
     // They are dynamically sized and ordered and cannot be declared here upfront
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawFromReserves<'info> {
+    pub withdraw_from_available: WithdrawFromAvailable<'info>,
+    // This context (list of accounts) has a lot of remaining accounts,
+    // - All reserves entries of this vault
+    // - One group of `WithdrawFromInvested` accounts per reserve to withdraw from, in order
+    // - All of the associated lending market accounts
+    // They are dynamically sized and ordered and cannot be declared here upfront
+}
+
 #[derive(Accounts)]
 pub struct WithdrawFromInvested<'info> {
     #[account(mut)]
@@ -154,7 +272,7 @@ pub struct WithdrawFromAvailable<'info> {
     pub shares_mint: Box<InterfaceAccount<'info, Mint>>,
 
     pub token_program: Interface<'info, TokenInterface>,
-    pub shares_token_program: Program<'info, Token>,
+    pub shares_token_program: Interface<'info, TokenInterface>,
 
     pub klend_program: Program<'info, kamino_lending::program::KaminoLending>,
     // For withdraw from available this context (list of accounts) has a lot of remaining accounts,
@@ -164,23 +282,54 @@ pub struct WithdrawFromAvailable<'info> {
 }
 
 pub mod withdraw_utils {
-    use crate::events::{SharesToWithdrawEvent, WithdrawResultEvent};
+    use crate::events::{
+        FirstLossAbsorbedEvent, HighWaterMarkUpdatedEvent, SharesToWithdrawEvent,
+        WithdrawResultEvent,
+    };
 
     use super::*;
 
+    pub fn withdraw_from_invested_accounts<'info>(
+        program_id: &Pubkey,
+        mut accounts: &'info [AccountInfo<'info>],
+        count: usize,
+    ) -> Result<Vec<WithdrawFromInvested<'info>>> {
+        let mut withdraw_from_reserves = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut bumps = WithdrawFromInvestedBumps::default();
+            let mut reallocs = BTreeSet::new();
+            withdraw_from_reserves.push(WithdrawFromInvested::try_accounts(
+                program_id,
+                &mut accounts,
+                &[],
+                &mut bumps,
+                &mut reallocs,
+            )?);
+        }
+
+        Ok(withdraw_from_reserves)
+    }
+
     pub fn withdraw<'info>(
         ctx_withdraw_from_available: &WithdrawFromAvailable<'info>,
-        ctx_withdraw_from_reserves: Option<&WithdrawFromInvested<'info>>,
+        ctx_withdraw_from_reserves: &[WithdrawFromInvested<'info>],
         remaining_accounts: &[AccountInfo<'info>],
-        shares_amount: u64,
-    ) -> Result<(SharesToWithdrawEvent, WithdrawResultEvent)> {
+        withdraw_amount: WithdrawAmount,
+    ) -> Result<(
+        SharesToWithdrawEvent,
+        WithdrawResultEvent,
+        Option<HighWaterMarkUpdatedEvent>,
+        Option<FirstLossAbsorbedEvent>,
+    )> {
         let withdraw_from_available_accounts = ctx_withdraw_from_available;
 
-        let should_withdraw_from_invested = ctx_withdraw_from_reserves.is_some();
-
         let mut all_accounts = withdraw_from_available_accounts.to_account_infos();
-        if should_withdraw_from_invested {
-            all_accounts.extend_from_slice(&ctx_withdraw_from_reserves.unwrap().to_account_infos());
+        for withdraw_from_reserve_accounts in ctx_withdraw_from_reserves {
+            require_keys_eq!(
+                withdraw_from_available_accounts.vault_state.key(),
+                withdraw_from_reserve_accounts.vault_state.key()
+            );
+            all_accounts.extend_from_slice(&withdraw_from_reserve_accounts.to_account_infos());
         }
 
         let mut cpi_mem =
@@ -189,27 +338,57 @@ pub mod withdraw_utils {
         let vault_state: &mut std::cell::RefMut<'_, VaultState> =
             &mut withdraw_from_available_accounts.vault_state.load_mut()?;
         let global_config = &withdraw_from_available_accounts.global_config.load()?;
+        vault_state.check_operation_not_paused(global_config, PAUSE_WITHDRAWALS)?;
         let reserves_count = vault_state.get_reserves_count();
+        let initial_high_water_mark_sf = vault_state.high_water_mark_sf;
+        let initial_first_loss_shares_pending_burn = vault_state.first_loss_shares_pending_burn;
+        let initial_cumulative_first_loss_absorbed_sf =
+            vault_state.cumulative_first_loss_absorbed_sf;
 
        
         let token_vault_before = withdraw_from_available_accounts.token_vault.amount;
         let user_ata_before = withdraw_from_available_accounts.user_token_ata.amount;
         let user_shares_before = withdraw_from_available_accounts.user_shares_ata.amount;
 
-        let (ctoken_vault_before, reserve_supply_liquidity_before) =
-            if should_withdraw_from_invested {
-                let withdraw_from_reserve_accounts = &ctx_withdraw_from_reserves.unwrap();
-                let ctoken_vault_before = withdraw_from_reserve_accounts.ctoken_vault.amount;
-                let reserve_supply_liquidity_before = withdraw_from_reserve_accounts
+        let reserves_balances_before: Vec<ReserveBalances> = ctx_withdraw_from_reserves
+            .iter()
+            .map(|withdraw_from_reserve_accounts| ReserveBalances {
+                reserve_supply_liquidity_balance: withdraw_from_reserve_accounts
                     .reserve_liquidity_supply
-                    .amount;
-                (ctoken_vault_before, reserve_supply_liquidity_before)
-            } else {
-                (0, 0)
-            };
+                    .amount,
+                vault_ctoken_balance: withdraw_from_reserve_accounts.ctoken_vault.amount,
+            })
+            .collect();
 
        
-        let shares_amount = std::cmp::min(shares_amount, user_shares_before);
+        let (withdraw_amount, shares_amount) = match withdraw_amount {
+            WithdrawAmount::Shares {
+                shares_amount,
+                min_tokens_out,
+            } => {
+                let shares_amount = std::cmp::min(shares_amount, user_shares_before);
+                (
+                    WithdrawAmount::Shares {
+                        shares_amount,
+                        min_tokens_out,
+                    },
+                    shares_amount,
+                )
+            }
+            WithdrawAmount::Tokens {
+                tokens_out,
+                max_shares_in,
+            } => {
+                let max_shares_in = std::cmp::min(max_shares_in, user_shares_before);
+                (
+                    WithdrawAmount::Tokens {
+                        tokens_out,
+                        max_shares_in,
+                    },
+                    max_shares_in,
+                )
+            }
+        };
         let shares_to_withdraw_event = SharesToWithdrawEvent {
             shares_amount,
             user_shares_before,
@@ -226,39 +405,44 @@ pub mod withdraw_utils {
             .take(reserves_count)
             .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
 
-        let (reserve_address_to_withdraw_from, reserve_state_to_withdraw_from, ctokens): (_, _, _) =
-            if should_withdraw_from_invested {
-                let withdraw_from_reserve_accounts = &ctx_withdraw_from_reserves.unwrap();
-
-                let reserve = withdraw_from_reserve_accounts.reserve.load()?;
-                let reserve_address = withdraw_from_reserve_accounts.reserve.to_account_info().key;
-
-                let reserve_allocation = vault_state
-                    .allocation_for_reserve(&withdraw_from_reserve_accounts.reserve.key())?;
-                require_keys_eq!(
-                    reserve_allocation.ctoken_vault,
-                    withdraw_from_reserve_accounts.ctoken_vault.key()
-                );
-
-                (
-                    Some(reserve_address),
-                    Some(reserve),
-                    Some(reserve_allocation.ctoken_allocation),
-                )
-            } else {
-                (None, None, None)
-            };
+        let mut reserve_addresses = Vec::with_capacity(ctx_withdraw_from_reserves.len());
+        let mut reserve_states = Vec::with_capacity(ctx_withdraw_from_reserves.len());
+        let mut reserve_ctokens = Vec::with_capacity(ctx_withdraw_from_reserves.len());
+        for withdraw_from_reserve_accounts in ctx_withdraw_from_reserves {
+            let reserve_allocation = vault_state
+                .allocation_for_reserve(&withdraw_from_reserve_accounts.reserve.key())?;
+            require_keys_eq!(
+                reserve_allocation.ctoken_vault,
+                withdraw_from_reserve_accounts.ctoken_vault.key()
+            );
+
+            reserve_addresses.push(withdraw_from_reserve_accounts.reserve.key());
+            reserve_states.push(withdraw_from_reserve_accounts.reserve.load()?);
+            reserve_ctokens.push(reserve_allocation.ctoken_allocation);
+        }
 
-        let withdraw_effects = vault_operations::withdraw(
+        let reserves_to_withdraw_from: Vec<vault_operations::ReserveToWithdrawFrom> =
+            reserve_addresses
+                .iter()
+                .zip(reserve_states.iter())
+                .zip(reserve_ctokens.iter())
+                .map(|((reserve_address, reserve_state), ctokens_owned)| {
+                    vault_operations::ReserveToWithdrawFrom {
+                        reserve_address,
+                        reserve_state,
+                        ctokens_owned: *ctokens_owned,
+                    }
+                })
+                .collect();
+
+        let withdraw_effects = vault_operations::withdraw_from_reserves(
             vault_state,
             global_config,
-            reserve_address_to_withdraw_from,
-            reserve_state_to_withdraw_from.as_deref(),
+            &reserves_to_withdraw_from,
             reserves_iter,
             Clock::get()?.unix_timestamp.try_into().unwrap(),
             Clock::get()?.slot,
-            shares_amount,
-            ctokens,
+            withdraw_amount,
         )?;
 
         let WithdrawEffects {
@@ -267,6 +451,7 @@ pub mod withdraw_utils {
             invested_to_disinvest_ctokens,
             invested_liquidity_to_send_to_user,
             invested_liquidity_to_disinvest: _,
+            reserves: ref reserves_effects,
         } = withdraw_effects;
 
         let withdraw_result_event = WithdrawResultEvent {
@@ -276,7 +461,23 @@ pub mod withdraw_utils {
             invested_liquidity_to_send_to_user,
         };
 
-        drop(reserve_state_to_withdraw_from);
+        let high_water_mark_event = (vault_state.high_water_mark_sf != initial_high_water_mark_sf)
+            .then_some(HighWaterMarkUpdatedEvent {
+                previous_high_water_mark_sf: initial_high_water_mark_sf,
+                high_water_mark_sf: vault_state.high_water_mark_sf,
+            });
+        let first_loss_event = (vault_state.first_loss_shares_pending_burn
+            != initial_first_loss_shares_pending_burn)
+            .then_some(FirstLossAbsorbedEvent {
+                loss_absorbed_sf: vault_state.cumulative_first_loss_absorbed_sf
+                    - initial_cumulative_first_loss_absorbed_sf,
+                shares_absorbed: vault_state.first_loss_shares_pending_burn
+                    - initial_first_loss_shares_pending_burn,
+                first_loss_shares_remaining: vault_state.first_loss_shares,
+            });
+
+        drop(reserves_to_withdraw_from);
+        drop(reserve_states);
 
        
         shares::burn(
@@ -294,14 +495,19 @@ pub mod withdraw_utils {
         )?;
 
        
-        if invested_to_disinvest_ctokens > 0 {
-            klend_operations::cpi_redeem_reserve_liquidity_from_withdraw(
-                ctx_withdraw_from_available,
-                ctx_withdraw_from_reserves.unwrap(),
-                &mut cpi_mem,
-                vault_state.base_vault_authority_bump as u8,
-                invested_to_disinvest_ctokens,
-            )?;
+        for (withdraw_from_reserve_accounts, reserve_effects) in ctx_withdraw_from_reserves
+            .iter()
+            .zip(reserves_effects.iter())
+        {
+            if reserve_effects.invested_to_disinvest_ctokens > 0 {
+                klend_operations::cpi_redeem_reserve_liquidity_from_withdraw(
+                    ctx_withdraw_from_available,
+                    withdraw_from_reserve_accounts,
+                    &mut cpi_mem,
+                    vault_state.base_vault_authority_bump as u8,
+                    reserve_effects.invested_to_disinvest_ctokens,
+                )?;
+            }
         }
 
         let token_vault_before_transfer_to_user = amount(
@@ -359,43 +565,44 @@ pub mod withdraw_utils {
                 .to_account_info(),
         )?;
 
-        let (ctoken_vault_after, reserve_supply_liquidity_after) = if should_withdraw_from_invested
-        {
-            let withdraw_from_reserve_accounts = &ctx_withdraw_from_reserves.unwrap();
-            let ctoken_vault_after = amount(
-                &withdraw_from_reserve_accounts
-                    .ctoken_vault
-                    .to_account_info(),
-            )?;
-            let reserve_supply_liquidity_after = amount(
-                &withdraw_from_reserve_accounts
-                    .reserve_liquidity_supply
-                    .to_account_info(),
-            )?;
-            (ctoken_vault_after, reserve_supply_liquidity_after)
-        } else {
-            (0, 0)
-        };
+        let mut reserves_balances_after = Vec::with_capacity(ctx_withdraw_from_reserves.len());
+        for withdraw_from_reserve_accounts in ctx_withdraw_from_reserves {
+            reserves_balances_after.push(ReserveBalances {
+                reserve_supply_liquidity_balance: amount(
+                    &withdraw_from_reserve_accounts
+                        .reserve_liquidity_supply
+                        .to_account_info(),
+                )?,
+                vault_ctoken_balance: amount(
+                    &withdraw_from_reserve_accounts
+                        .ctoken_vault
+                        .to_account_info(),
+                )?,
+            });
+        }
 
        
         post_transfer_withdraw_balance_checks(
-            VaultAndUserBalances {
-                reserve_supply_liquidity_balance: reserve_supply_liquidity_before,
+            VaultAndUserWithdrawBalances {
                 vault_token_balance: token_vault_before,
-                vault_ctoken_balance: ctoken_vault_before,
                 user_token_balance: user_ata_before,
                 user_shares_balance: user_shares_before,
+                reserves: reserves_balances_before,
             },
-            VaultAndUserBalances {
-                reserve_supply_liquidity_balance: reserve_supply_liquidity_after,
+            VaultAndUserWithdrawBalances {
                 vault_token_balance: token_vault_after,
-                vault_ctoken_balance: ctoken_vault_after,
                 user_token_balance: user_ata_after,
                 user_shares_balance: user_shares_after,
+                reserves: reserves_balances_after,
             },
             withdraw_effects,
         )?;
 
-        Ok((shares_to_withdraw_event, withdraw_result_event))
+        Ok((
+            shares_to_withdraw_event,
+            withdraw_result_event,
+            high_water_mark_event,
+            first_loss_event,
+        ))
     }
 }
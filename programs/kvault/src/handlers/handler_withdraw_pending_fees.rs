@@ -14,8 +14,12 @@ use crate::{
         vault_checks::{post_transfer_withdraw_pending_fees_balance_checks, VaultAndUserBalances},
         vault_operations,
     },
-    utils::{consts::CTOKEN_VAULT_SEED, cpi_mem::CpiMemoryLender, token_ops},
-    KaminoVaultError, VaultState,
+    utils::{
+        consts::{CTOKEN_VAULT_SEED, GLOBAL_CONFIG_STATE_SEEDS, PAUSE_FEE_WITHDRAWAL},
+        cpi_mem::CpiMemoryLender,
+        token_ops,
+    },
+    GlobalConfig, KaminoVaultError, VaultState,
 };
 
 pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawPendingFees<'info>>) -> Result<()> {
@@ -25,6 +29,8 @@ pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawPendingFees<'info>
     );
 
     let vault_state = &mut ctx.accounts.vault_state.load_mut()?;
+    vault_state
+        .check_operation_not_paused(&ctx.accounts.global_config.load()?, PAUSE_FEE_WITHDRAWAL)?;
     let reserves_count = vault_state.get_reserves_count();
 
     {
@@ -160,6 +166,12 @@ pub struct WithdrawPendingFees<'info> {
     )]
     pub vault_state: AccountLoader<'info, VaultState>,
 
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
     /// CHECK: check in logic if there is allocation for this reserve
     #[account(mut)]
     pub reserve: AccountLoader<'info, Reserve>,
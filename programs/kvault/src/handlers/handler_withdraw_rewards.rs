@@ -3,12 +3,19 @@ use anchor_spl::token_interface::{accessor, Mint, TokenAccount, TokenInterface};
 
 use crate::{
     operations::vault_operations,
-    utils::token_ops::tokens::{transfer_to_token_account, VaultTransferAccounts},
-    KaminoVaultError, VaultState,
+    utils::{
+        consts::{GLOBAL_CONFIG_STATE_SEEDS, PAUSE_REWARD_DISTRIBUTION},
+        token_ops::tokens::{transfer_to_token_account, VaultTransferAccounts},
+    },
+    GlobalConfig, KaminoVaultError, VaultState,
 };
 
 pub fn process(ctx: Context<WithdrawRewards>, amount: u64) -> Result<()> {
     let vault = &mut ctx.accounts.vault_state.load_mut()?;
+    vault.check_operation_not_paused(
+        &ctx.accounts.global_config.load()?,
+        PAUSE_REWARD_DISTRIBUTION,
+    )?;
     let current_ts: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
 
     let withdraw_amount = vault_operations::withdraw_rewards(vault, amount, current_ts)?;
@@ -73,6 +80,12 @@ pub struct WithdrawRewards<'info> {
     )]
     pub vault_state: AccountLoader<'info, VaultState>,
 
+    #[account(
+        seeds = [GLOBAL_CONFIG_STATE_SEEDS],
+        bump,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
     pub token_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(mut)]
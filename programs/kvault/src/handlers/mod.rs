@@ -1,12 +1,30 @@
+pub mod handler_add_depositor_to_allowlist;
 pub mod handler_add_update_whitelisted_reserve;
+pub mod handler_cancel_vault_config_change;
+pub mod handler_cancel_withdrawal_request;
+pub mod handler_claim_withdrawal_request;
+pub mod handler_close_vault;
+pub mod handler_convert_pending_fees_to_shares;
 pub mod handler_deposit;
+pub mod handler_distribute_pending_fees;
+pub mod handler_execute_vault_config_change;
+pub mod handler_first_loss_shares;
+pub mod handler_fulfil_withdrawal_request;
 pub mod handler_give_up_pending_fees;
 pub mod handler_init_vault;
 pub mod handler_initialize_global_config;
 pub mod handler_initialize_shares_metadata;
 pub mod handler_invest;
+pub mod handler_mint_fee_shares;
+pub mod handler_pause_operations;
+pub mod handler_preview;
+pub mod handler_propose_vault_config_change;
+pub mod handler_rebalance;
 pub mod handler_redeem_in_kind;
 pub mod handler_remove_allocation;
+pub mod handler_remove_depositor_from_allowlist;
+pub mod handler_request_withdrawal;
+pub mod handler_shares_transfer_hook;
 pub mod handler_topup_rewards;
 pub mod handler_update_admin;
 pub mod handler_update_global_config;
@@ -18,15 +36,33 @@ pub mod handler_withdraw;
 pub mod handler_withdraw_pending_fees;
 pub mod handler_withdraw_rewards;
 
+pub use handler_add_depositor_to_allowlist::*;
 pub use handler_add_update_whitelisted_reserve::*;
+pub use handler_cancel_vault_config_change::*;
+pub use handler_cancel_withdrawal_request::*;
+pub use handler_claim_withdrawal_request::*;
+pub use handler_close_vault::*;
+pub use handler_convert_pending_fees_to_shares::*;
 pub use handler_deposit::*;
+pub use handler_distribute_pending_fees::*;
+pub use handler_execute_vault_config_change::*;
+pub use handler_first_loss_shares::*;
+pub use handler_fulfil_withdrawal_request::*;
 pub use handler_give_up_pending_fees::*;
 pub use handler_init_vault::*;
 pub use handler_initialize_global_config::*;
 pub use handler_initialize_shares_metadata::*;
 pub use handler_invest::*;
+pub use handler_mint_fee_shares::*;
+pub use handler_pause_operations::*;
+pub use handler_preview::*;
+pub use handler_propose_vault_config_change::*;
+pub use handler_rebalance::*;
 pub use handler_redeem_in_kind::*;
 pub use handler_remove_allocation::*;
+pub use handler_remove_depositor_from_allowlist::*;
+pub use handler_request_withdrawal::*;
+pub use handler_shares_transfer_hook::*;
 pub use handler_topup_rewards::*;
 pub use handler_update_admin::*;
 pub use handler_update_global_config::*;
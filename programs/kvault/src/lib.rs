@@ -12,8 +12,10 @@ pub mod utils;
 use crate::handlers::*;
 pub use crate::operations::reserve_whitelist_operations::UpdateReserveWhitelistMode;
 pub use crate::operations::vault_config_operations::VaultConfigField;
+use crate::operations::vault_operations::DepositAmount;
 pub use crate::state::*;
 pub use program_id::KVAULT_PROGRAM_ID;
+use spl_transfer_hook_interface::instruction::TransferHookInstruction;
 
 declare_id!(KVAULT_PROGRAM_ID);
 
@@ -50,7 +52,13 @@ pub mod kamino_vault {
         ctx: Context<'_, '_, '_, 'info, Deposit<'info>>,
         max_amount: u64,
     ) -> Result<()> {
-        handler_deposit::process(ctx, max_amount)
+        handler_deposit::process(
+            ctx,
+            DepositAmount::Tokens {
+                max_amount,
+                min_shares_out: 0,
+            },
+        )
     }
 
     pub fn buy<'info>(
@@ -59,14 +67,64 @@ pub mod kamino_vault {
     ) -> Result<()> {
        
        
-        handler_deposit::process(ctx, max_amount)
+        handler_deposit::process(
+            ctx,
+            DepositAmount::Tokens {
+                max_amount,
+                min_shares_out: 0,
+            },
+        )
+    }
+
+    pub fn deposit_with_min_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, Deposit<'info>>,
+        max_amount: u64,
+        min_shares_out: u64,
+    ) -> Result<()> {
+        handler_deposit::process(
+            ctx,
+            DepositAmount::Tokens {
+                max_amount,
+                min_shares_out,
+            },
+        )
+    }
+
+    pub fn mint_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, Deposit<'info>>,
+        shares_out: u64,
+        max_tokens_in: u64,
+    ) -> Result<()> {
+        handler_deposit::process(
+            ctx,
+            DepositAmount::Shares {
+                shares_out,
+                max_tokens_in,
+            },
+        )
     }
 
     pub fn withdraw<'info>(
         ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
         shares_amount: u64,
     ) -> Result<()> {
-        handler_withdraw::withdraw(ctx, shares_amount)
+        handler_withdraw::withdraw(ctx, shares_amount, 0)
+    }
+
+    pub fn withdraw_with_min_tokens_out<'info>(
+        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
+        shares_amount: u64,
+        min_tokens_out: u64,
+    ) -> Result<()> {
+        handler_withdraw::withdraw(ctx, shares_amount, min_tokens_out)
+    }
+
+    pub fn withdraw_assets<'info>(
+        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
+        tokens_out: u64,
+        max_shares_in: u64,
+    ) -> Result<()> {
+        handler_withdraw::withdraw_assets(ctx, tokens_out, max_shares_in)
     }
 
     pub fn sell<'info>(
@@ -75,13 +133,17 @@ pub mod kamino_vault {
     ) -> Result<()> {
        
        
-        handler_withdraw::withdraw(ctx, shares_amount)
+        handler_withdraw::withdraw(ctx, shares_amount, 0)
     }
 
     pub fn invest<'info>(ctx: Context<'_, '_, '_, 'info, Invest<'info>>) -> Result<()> {
         handler_invest::process(ctx)
     }
 
+    pub fn rebalance<'info>(ctx: Context<'_, '_, 'info, 'info, Rebalance<'info>>) -> Result<()> {
+        handler_rebalance::process(ctx)
+    }
+
     pub fn update_vault_config<'info>(
         ctx: Context<'_, '_, '_, 'info, UpdateVaultConfig<'info>>,
         entry: VaultConfigField,
@@ -96,6 +158,66 @@ pub mod kamino_vault {
         handler_withdraw_pending_fees::process(ctx)
     }
 
+    pub fn distribute_pending_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, DistributePendingFees<'info>>,
+    ) -> Result<()> {
+        handler_distribute_pending_fees::process(ctx)
+    }
+
+    pub fn convert_pending_fees_to_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, ConvertPendingFeesToShares<'info>>,
+    ) -> Result<()> {
+        handler_convert_pending_fees_to_shares::process(ctx)
+    }
+
+    pub fn mint_fee_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintFeeShares<'info>>,
+    ) -> Result<()> {
+        handler_mint_fee_shares::process(ctx)
+    }
+
+    pub fn deposit_first_loss_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, FirstLossShares<'info>>,
+        shares_amount: u64,
+    ) -> Result<()> {
+        handler_first_loss_shares::deposit_first_loss_shares(ctx, shares_amount)
+    }
+
+    pub fn request_first_loss_withdrawal(ctx: Context<RequestFirstLossWithdrawal>) -> Result<()> {
+        handler_first_loss_shares::request_first_loss_withdrawal(ctx)
+    }
+
+    pub fn withdraw_first_loss_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawFirstLossShares<'info>>,
+        shares_amount: u64,
+    ) -> Result<()> {
+        handler_first_loss_shares::withdraw_first_loss_shares(ctx, shares_amount)
+    }
+
+    pub fn burn_absorbed_first_loss_shares(
+        ctx: Context<BurnAbsorbedFirstLossShares>,
+    ) -> Result<()> {
+        handler_first_loss_shares::burn_absorbed_first_loss_shares(ctx)
+    }
+
+    pub fn pause_vault_operations(
+        ctx: Context<PauseVaultOperations>,
+        operations: u64,
+    ) -> Result<()> {
+        handler_pause_operations::pause_vault_operations(ctx, operations)
+    }
+
+    pub fn pause_global_operations(
+        ctx: Context<PauseGlobalOperations>,
+        operations: u64,
+    ) -> Result<()> {
+        handler_pause_operations::pause_global_operations(ctx, operations)
+    }
+
+    pub fn close_vault<'info>(ctx: Context<'_, '_, '_, 'info, CloseVault<'info>>) -> Result<()> {
+        handler_close_vault::process(ctx)
+    }
+
     pub fn update_admin<'info>(ctx: Context<'_, '_, '_, 'info, UpdateAdmin<'info>>) -> Result<()> {
         handler_update_admin::process(ctx)
     }
@@ -129,7 +251,29 @@ pub mod kamino_vault {
         ctx: Context<'_, '_, '_, 'info, WithdrawFromAvailable<'info>>,
         shares_amount: u64,
     ) -> Result<()> {
-        handler_withdraw::withdraw_from_available(ctx, shares_amount)
+        handler_withdraw::withdraw_from_available(ctx, shares_amount, 0)
+    }
+
+    pub fn withdraw_from_available_with_min_tokens_out<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawFromAvailable<'info>>,
+        shares_amount: u64,
+        min_tokens_out: u64,
+    ) -> Result<()> {
+        handler_withdraw::withdraw_from_available(ctx, shares_amount, min_tokens_out)
+    }
+
+    pub fn withdraw_from_reserves<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawFromReserves<'info>>,
+        shares_amount: u64,
+        min_tokens_out: u64,
+        reserves_to_withdraw_from_count: u8,
+    ) -> Result<()> {
+        handler_withdraw::withdraw_from_reserves(
+            ctx,
+            shares_amount,
+            min_tokens_out,
+            reserves_to_withdraw_from_count,
+        )
     }
 
     pub fn remove_allocation(ctx: Context<RemoveAllocation>) -> Result<()> {
@@ -170,7 +314,149 @@ pub mod kamino_vault {
         ctx: Context<'_, '_, '_, 'info, RedeemInKind<'info>>,
         shares_amount: u64,
     ) -> Result<()> {
-        handler_redeem_in_kind::redeem_in_kind(ctx, shares_amount)
+        handler_redeem_in_kind::redeem_in_kind(ctx, shares_amount, 0)
+    }
+
+    pub fn redeem_in_kind_with_min_ctokens_out<'info>(
+        ctx: Context<'_, '_, '_, 'info, RedeemInKind<'info>>,
+        shares_amount: u64,
+        min_ctokens_out: u64,
+    ) -> Result<()> {
+        handler_redeem_in_kind::redeem_in_kind(ctx, shares_amount, min_ctokens_out)
+    }
+
+    pub fn preview_deposit<'info>(
+        ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+        max_amount: u64,
+    ) -> Result<PreviewDepositResult> {
+        handler_preview::preview_deposit(ctx, max_amount)
+    }
+
+    pub fn preview_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+        shares_amount: u64,
+    ) -> Result<PreviewWithdrawResult> {
+        handler_preview::preview_withdraw(ctx, shares_amount)
+    }
+
+    pub fn preview_redeem_in_kind<'info>(
+        ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+        shares_amount: u64,
+        reserve: Pubkey,
+    ) -> Result<PreviewRedeemInKindResult> {
+        handler_preview::preview_redeem_in_kind(ctx, shares_amount, reserve)
+    }
+
+    pub fn convert_to_shares<'info>(
+        ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+        assets: u64,
+    ) -> Result<u64> {
+        handler_preview::convert_to_shares(ctx, assets)
+    }
+
+    pub fn convert_to_assets<'info>(
+        ctx: Context<'_, '_, '_, 'info, Preview<'info>>,
+        shares: u64,
+    ) -> Result<u64> {
+        handler_preview::convert_to_assets(ctx, shares)
+    }
+
+    pub fn propose_vault_config_change(
+        ctx: Context<ProposeVaultConfigChange>,
+        entry: VaultConfigField,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        handler_propose_vault_config_change::process(ctx, entry, &data)
+    }
+
+    pub fn cancel_vault_config_change(
+        ctx: Context<CancelVaultConfigChange>,
+        entry: VaultConfigField,
+    ) -> Result<()> {
+        handler_cancel_vault_config_change::process(ctx, entry)
+    }
+
+    pub fn execute_vault_config_change<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteVaultConfigChange<'info>>,
+        entry: VaultConfigField,
+    ) -> Result<()> {
+        handler_execute_vault_config_change::process(ctx, entry)
+    }
+
+    pub fn request_withdrawal<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestWithdrawal<'info>>,
+        shares_amount: u64,
+    ) -> Result<()> {
+        handler_request_withdrawal::process(ctx, shares_amount)
+    }
+
+    pub fn cancel_withdrawal_request<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelWithdrawalRequest<'info>>,
+    ) -> Result<()> {
+        handler_cancel_withdrawal_request::process(ctx)
+    }
+
+    pub fn fulfil_withdrawal_request<'info>(
+        ctx: Context<'_, '_, '_, 'info, FulfilWithdrawalRequest<'info>>,
+    ) -> Result<()> {
+        handler_fulfil_withdrawal_request::fulfil_withdrawal_request(ctx)
+    }
+
+    pub fn fulfil_withdrawal_request_from_available<'info>(
+        ctx: Context<'_, '_, '_, 'info, FulfilWithdrawalRequestFromAvailable<'info>>,
+    ) -> Result<()> {
+        handler_fulfil_withdrawal_request::fulfil_withdrawal_request_from_available(ctx)
+    }
+
+    pub fn claim_withdrawal_request<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimWithdrawalRequest<'info>>,
+    ) -> Result<()> {
+        handler_claim_withdrawal_request::process(ctx)
+    }
+
+    pub fn add_depositor_to_allowlist(ctx: Context<AddDepositorToAllowlist>) -> Result<()> {
+        handler_add_depositor_to_allowlist::process(ctx)
+    }
+
+    pub fn remove_depositor_from_allowlist(
+        ctx: Context<RemoveDepositorFromAllowlist>,
+    ) -> Result<()> {
+        handler_remove_depositor_from_allowlist::process(ctx)
+    }
+
+    pub fn initialize_shares_transfer_hook(
+        ctx: Context<InitializeSharesTransferHook>,
+    ) -> Result<()> {
+        handler_shares_transfer_hook::initialize(ctx)
+    }
+
+    pub fn update_shares_transfer_hook(
+        ctx: Context<UpdateSharesTransferHook>,
+        enabled: bool,
+    ) -> Result<()> {
+        handler_shares_transfer_hook::update(ctx, enabled)
+    }
+
+    pub fn shares_transfer_hook(ctx: Context<SharesTransferHook>, amount: u64) -> Result<()> {
+        handler_shares_transfer_hook::execute(ctx, amount)
+    }
+
+    // Token-2022 invokes the transfer hook with the interface discriminator
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        match TransferHookInstruction::unpack(data)? {
+            TransferHookInstruction::Execute { amount } => {
+                __private::__global::shares_transfer_hook(
+                    program_id,
+                    accounts,
+                    &amount.to_le_bytes(),
+                )
+            }
+            _ => err!(anchor_lang::error::ErrorCode::InstructionFallbackNotFound),
+        }
     }
 }
 
@@ -363,6 +649,153 @@ pub enum KaminoVaultError {
 
     #[msg("Rewards are stale - must be refreshed before updating fees")]
     RewardsStaleForFeeUpdate,
+
+    #[msg("Vault config field does not support timelocked changes")]
+    ConfigFieldNotTimelockable,
+
+    #[msg("Vault config field is timelocked and must be changed through propose and execute")]
+    ConfigFieldTimelocked,
+
+    #[msg("No pending change for this vault config field")]
+    NoPendingConfigChange,
+
+    #[msg("Pending vault config change is not active yet")]
+    ConfigChangeNotActive,
+
+    #[msg("Config change delay is greater than maximum allowed")]
+    ConfigChangeDelayTooBig,
+
+    #[msg("Withdrawal request is not the next one in the queue")]
+    WithdrawalRequestNotNextInQueue,
+
+    #[msg("Withdrawal request is not pending")]
+    WithdrawalRequestNotPending,
+
+    #[msg("Withdrawal request has not been processed yet")]
+    WithdrawalRequestNotClaimable,
+
+    #[msg("Not enough liquidity to fulfil withdrawal request")]
+    NotEnoughLiquidityToFulfilWithdrawalRequest,
+
+    #[msg("Withdrawal queue escrow amount is not as expected after transfer")]
+    WithdrawalRequestEscrowAmountDoesNotMatch,
+
+    #[msg("Reserve was specified more than once")]
+    ReserveSpecifiedMultipleTimes,
+
+    #[msg("Shares to mint are less than the minimum shares out requested")]
+    SharesToMintBelowMinSharesOut,
+
+    #[msg("Tokens to send to the user are less than the minimum tokens out requested")]
+    TokensToSendBelowMinTokensOut,
+
+    #[msg("cTokens to send to the user are less than the minimum cTokens out requested")]
+    CTokensToSendBelowMinCTokensOut,
+
+    #[msg("Tokens required to mint the requested shares are more than the maximum tokens in")]
+    TokensToDepositAboveMaxTokensIn,
+
+    #[msg("Shares required to withdraw the requested tokens are more than the maximum shares in")]
+    SharesToBurnAboveMaxSharesIn,
+
+    #[msg("Vault AUM has reached the deposit cap")]
+    DepositCapReached,
+
+    #[msg("Deposit would take the vault AUM above the deposit cap")]
+    DepositCapExceeded,
+
+    #[msg("User shares balance would exceed the per user shares cap")]
+    UserSharesCapExceeded,
+
+    #[msg("Depositor is not allowlisted for this permissioned vault")]
+    DepositorNotAllowlisted,
+
+    #[msg("Shares metadata accounts do not match the shares token program")]
+    InvalidSharesMetadataAccounts,
+
+    #[msg("Fee recipients must be unique, non-zero and their bps must sum to 10000")]
+    InvalidFeeRecipients,
+
+    #[msg("No fee recipients are configured for this vault")]
+    FeeRecipientsNotConfigured,
+
+    #[msg("Fee recipient token account does not match the configured recipient")]
+    FeeRecipientTokenAccountInvalid,
+
+    #[msg("Pending fees must be converted to shares before taking fees in shares")]
+    PendingFeesMustBeConvertedToShares,
+
+    #[msg("Fee shares recipient is not set")]
+    FeeSharesRecipientNotSet,
+
+    #[msg("First-loss depositor cannot change while first-loss shares are outstanding")]
+    FirstLossSharesOutstanding,
+
+    #[msg("Signer is not the first-loss depositor of the vault")]
+    FirstLossDepositorIncorrect,
+
+    #[msg("Not enough first-loss shares to withdraw")]
+    NotEnoughFirstLossShares,
+
+    #[msg("Reserve utilization is above the vault maximum")]
+    ReserveUtilizationTooHigh,
+
+    #[msg("Reserve borrow rate is above the vault maximum")]
+    ReserveBorrowRateTooHigh,
+
+    #[msg("Reserve is not active or has socialized losses")]
+    ReserveDistressed,
+
+    #[msg("Operation is paused")]
+    OperationPaused,
+
+    #[msg("Paused operations contain unknown flags")]
+    InvalidPausedOperations,
+
+    #[msg("Signer is not allowed to pause operations")]
+    PauseAuthorityIncorrect,
+
+    #[msg("Vault is not accepting deposits in its current lifecycle state")]
+    VaultNotActive,
+
+    #[msg("Vault is withdraw-only, liquidity can only be moved out of reserves")]
+    VaultIsWithdrawOnly,
+
+    #[msg("Invalid vault lifecycle state transition")]
+    InvalidLifecycleStateTransition,
+
+    #[msg("Vault cannot be closed while it still has shares, allocations or claims outstanding")]
+    VaultNotEmpty,
+
+    #[msg("Transaction does not request a heap frame large enough for the instruction")]
+    HeapFrameTooSmall,
+
+    #[msg("Shares mint is not a Token-2022 mint with a transfer hook extension")]
+    SharesMintHasNoTransferHook,
+
+    #[msg("Shares transfer hook extra accounts are not initialized")]
+    SharesTransferHookNotInitialized,
+
+    #[msg("Shares transfer hook can only be invoked by Token-2022 during a transfer")]
+    SharesTransferHookNotTransferring,
+
+    #[msg("Fee recipients are configured for this vault, fees must be distributed to them")]
+    FeeRecipientsConfigured,
+
+    #[msg("First-loss shares withdrawal was not requested")]
+    FirstLossWithdrawalNotRequested,
+
+    #[msg("First-loss shares can only be withdrawn in the window after the notice period")]
+    FirstLossWithdrawalNotInWindow,
+
+    #[msg("Pending fees must be withdrawn or given up before the vault is closed")]
+    PendingFeesNotWithdrawn,
+
+    #[msg("Undistributed rewards must be withdrawn before the vault is closed")]
+    RewardsNotWithdrawn,
+
+    #[msg("Vault lifecycle state is not a known state")]
+    InvalidLifecycleState,
 }
 
 pub type KaminoVaultResult<T = ()> = std::result::Result<T, KaminoVaultError>;
@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+
+use crate::{utils::consts::DEPOSITOR_ALLOWLIST_SEED, DepositorAllowlistEntry, KaminoVaultError};
+
+pub const SHARES_TRANSFER_HOOK_EXTRA_ACCOUNTS_COUNT: usize = 2;
+
+// Position of the accounts in the transfer hook `Execute` instruction
+const DESTINATION_TOKEN_ACCOUNT_INDEX: u8 = 2;
+const VAULT_STATE_ACCOUNT_INDEX: u8 = 5;
+// Offset and length of the owner in a token account data
+const TOKEN_ACCOUNT_OWNER_OFFSET: u8 = 32;
+const TOKEN_ACCOUNT_OWNER_LENGTH: u8 = 32;
+
+pub fn depositor_allowlist_entry_address(
+    program_id: &Pubkey,
+    vault_state: &Pubkey,
+    depositor: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            DEPOSITOR_ALLOWLIST_SEED,
+            vault_state.as_ref(),
+            depositor.as_ref(),
+        ],
+        program_id,
+    )
+    .0
+}
+
+pub fn is_depositor_allowlisted(
+    program_id: &Pubkey,
+    vault_state: &Pubkey,
+    depositor: &Pubkey,
+    depositor_allowlist_entry: &AccountInfo,
+) -> bool {
+    if depositor_allowlist_entry.key()
+        != depositor_allowlist_entry_address(program_id, vault_state, depositor)
+        || depositor_allowlist_entry.owner != program_id
+    {
+        return false;
+    }
+
+    let Ok(data) = depositor_allowlist_entry.try_borrow_data() else {
+        return false;
+    };
+    match DepositorAllowlistEntry::try_deserialize(&mut &data[..]) {
+        Ok(entry) => entry.vault_state == *vault_state && entry.depositor == *depositor,
+        Err(_) => false,
+    }
+}
+
+pub fn check_depositor_allowlisted(
+    program_id: &Pubkey,
+    vault_state: &Pubkey,
+    depositor: &Pubkey,
+    depositor_allowlist_entry: Option<&AccountInfo>,
+) -> Result<()> {
+    let depositor_allowlist_entry =
+        depositor_allowlist_entry.ok_or(KaminoVaultError::DepositorNotAllowlisted)?;
+    if !is_depositor_allowlisted(
+        program_id,
+        vault_state,
+        depositor,
+        depositor_allowlist_entry,
+    ) {
+        msg!("Depositor {} is not allowlisted", depositor);
+        return err!(KaminoVaultError::DepositorNotAllowlisted);
+    }
+
+    Ok(())
+}
+
+pub fn shares_transfer_hook_extra_account_metas(
+    vault_state: &Pubkey,
+) -> Result<Vec<ExtraAccountMeta>> {
+    Ok(vec![
+        ExtraAccountMeta::new_with_pubkey(vault_state, false, false)?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: DEPOSITOR_ALLOWLIST_SEED.to_vec(),
+                },
+                Seed::AccountKey {
+                    index: VAULT_STATE_ACCOUNT_INDEX,
+                },
+                Seed::AccountData {
+                    account_index: DESTINATION_TOKEN_ACCOUNT_INDEX,
+                    data_index: TOKEN_ACCOUNT_OWNER_OFFSET,
+                    length: TOKEN_ACCOUNT_OWNER_LENGTH,
+                },
+            ],
+            false,
+            false,
+        )?,
+    ])
+}
@@ -1,11 +1,14 @@
-use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use anchor_lang::{prelude::Pubkey, AnchorDeserialize, AnchorSerialize};
 use kamino_lending::fraction::Fraction;
 
+use crate::MAX_FEE_RECIPIENTS;
+
 #[derive(Debug)]
 pub struct DepositEffects {
     pub shares_to_mint: u64,
     pub token_to_deposit: u64,
     pub crank_funds_to_deposit: u64,
+    pub truncated_by_deposit_cap: bool,
 }
 
 #[derive(Debug, Default)]
@@ -15,6 +18,15 @@ pub struct WithdrawEffects {
     pub invested_to_disinvest_ctokens: u64,
     pub invested_liquidity_to_send_to_user: u64,
     pub invested_liquidity_to_disinvest: u64,
+    pub reserves: Vec<WithdrawFromReserveEffects>,
+}
+
+#[derive(Debug, Default)]
+pub struct WithdrawFromReserveEffects {
+    pub reserve: Pubkey,
+    pub invested_to_disinvest_ctokens: u64,
+    pub invested_liquidity_to_send_to_user: u64,
+    pub invested_liquidity_to_disinvest: u64,
 }
 
 #[derive(Debug, Default)]
@@ -25,6 +37,12 @@ pub struct WithdrawPendingFeesEffects {
     pub invested_liquidity_to_disinvest: u64,
 }
 
+#[derive(Debug, Default)]
+pub struct DistributePendingFeesEffects {
+    pub withdraw_pending_fees_effects: WithdrawPendingFeesEffects,
+    pub amounts_to_recipients: [u64; MAX_FEE_RECIPIENTS],
+}
+
 #[derive(Debug, Copy, Clone, AnchorSerialize, AnchorDeserialize)]
 pub enum InvestingDirection {
     Add,
@@ -39,6 +57,12 @@ pub struct InvestEffects {
     pub rounding_loss: u64,
 }
 
+#[derive(Debug)]
+pub struct RebalanceReserveEffects {
+    pub reserve_index: usize,
+    pub invest_effects: InvestEffects,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct RedeemInKindEffects {
     pub shares_to_burn: u64,
@@ -46,3 +70,9 @@ pub struct RedeemInKindEffects {
     pub actual_liquidity_value: Fraction,
     pub vault_aum_before: Fraction,
 }
+
+#[derive(Debug, Default)]
+pub struct ClaimWithdrawalRequestEffects {
+    pub tokens_to_send_to_user: u64,
+    pub shares_to_return_to_user: u64,
+}
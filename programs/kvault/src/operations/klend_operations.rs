@@ -7,7 +7,10 @@ use anchor_lang::{
 use kamino_lending::utils::FatAccountLoader;
 
 use crate::{
-    handlers::{Invest, WithdrawFromAvailable, WithdrawFromInvested},
+    handlers::{
+        DistributePendingFees, FulfilWithdrawalRequestFromAvailable, Invest, Rebalance,
+        RebalanceReserve, WithdrawFromAvailable, WithdrawFromInvested,
+    },
     utils::{consts::BASE_VAULT_AUTHORITY_SEED, cpi_mem::CpiMemoryLender},
     KaminoVaultError, WithdrawPendingFees, MAX_RESERVES,
 };
@@ -153,6 +156,57 @@ pub fn cpi_redeem_reserve_liquidity_from_withdraw(
     .map_err(Into::into)
 }
 
+pub fn cpi_redeem_reserve_liquidity_from_fulfil_withdrawal_request(
+    from_available_ctx: &FulfilWithdrawalRequestFromAvailable,
+    from_invested_ctx: &WithdrawFromInvested,
+    cpi: &mut CpiMemoryLender,
+    base_vault_authority_bump: u8,
+    collateral_amount: u64,
+) -> Result<()> {
+    let from_available_accounts = from_available_ctx;
+    let from_invested_accounts = from_invested_ctx;
+    let accs = kamino_lending::accounts::RedeemReserveCollateral {
+        owner: from_available_accounts.base_vault_authority.key(),
+        lending_market: from_invested_accounts.lending_market.key(),
+        reserve: from_invested_accounts.reserve.key(),
+        lending_market_authority: from_invested_accounts.lending_market_authority.key(),
+        reserve_liquidity_mint: from_available_accounts.token_mint.key(),
+        reserve_collateral_mint: from_invested_accounts.reserve_collateral_mint.key(),
+        reserve_liquidity_supply: from_invested_accounts.reserve_liquidity_supply.key(),
+        user_source_collateral: from_invested_accounts.ctoken_vault.key(),
+        user_destination_liquidity: from_available_accounts.token_vault.key(),
+        collateral_token_program: from_invested_accounts
+            .reserve_collateral_token_program
+            .key(),
+        liquidity_token_program: from_available_accounts.token_program.key(),
+        instruction_sysvar_account: from_invested_accounts.instruction_sysvar_account.key(),
+    }
+    .to_account_metas(None);
+
+    let mut data = [0_u8; 40];
+    data[0..8]
+        .copy_from_slice(&kamino_lending::instruction::RedeemReserveCollateral::DISCRIMINATOR);
+    let mut writer = &mut data[8..40];
+    borsh::to_writer(&mut writer, &collateral_amount).unwrap();
+
+    let base_vault_authority_bump = vec![base_vault_authority_bump];
+    let vault_state_key = from_available_accounts.vault_state.key();
+    let inner_seeds = [
+        BASE_VAULT_AUTHORITY_SEED,
+        vault_state_key.as_ref(),
+        base_vault_authority_bump.as_ref(),
+    ];
+    let signer_seeds = &[&inner_seeds[..]];
+
+    cpi.program_invoke_signed(
+        &from_available_accounts.klend_program.key(),
+        &accs,
+        &data,
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
 pub fn cpi_redeem_reserve_liquidity_from_withdraw_pending_fees(
     ctx: &Context<WithdrawPendingFees>,
     cpi: &mut CpiMemoryLender,
@@ -199,6 +253,52 @@ pub fn cpi_redeem_reserve_liquidity_from_withdraw_pending_fees(
     .map_err(Into::into)
 }
 
+pub fn cpi_redeem_reserve_liquidity_from_distribute_pending_fees(
+    ctx: &Context<DistributePendingFees>,
+    cpi: &mut CpiMemoryLender,
+    base_vault_authority_bump: u8,
+    collateral_amount: u64,
+) -> Result<()> {
+    let accs = kamino_lending::accounts::RedeemReserveCollateral {
+        owner: ctx.accounts.base_vault_authority.key(),
+        lending_market: ctx.accounts.lending_market.key(),
+        reserve: ctx.accounts.reserve.key(),
+        lending_market_authority: ctx.accounts.lending_market_authority.key(),
+        reserve_liquidity_mint: ctx.accounts.token_mint.key(),
+        reserve_collateral_mint: ctx.accounts.reserve_collateral_mint.key(),
+        reserve_liquidity_supply: ctx.accounts.reserve_liquidity_supply.key(),
+        user_source_collateral: ctx.accounts.ctoken_vault.key(),
+        user_destination_liquidity: ctx.accounts.token_vault.key(),
+        collateral_token_program: ctx.accounts.reserve_collateral_token_program.key(),
+        liquidity_token_program: ctx.accounts.token_program.key(),
+        instruction_sysvar_account: ctx.accounts.instruction_sysvar_account.key(),
+    }
+    .to_account_metas(None);
+
+    let mut data = [0_u8; 40];
+    data[0..8]
+        .copy_from_slice(&kamino_lending::instruction::RedeemReserveCollateral::DISCRIMINATOR);
+    let mut writer = &mut data[8..40];
+    borsh::to_writer(&mut writer, &collateral_amount).unwrap();
+
+    let base_vault_authority_bump = vec![base_vault_authority_bump];
+    let vault_state_key = ctx.accounts.vault_state.key();
+    let inner_seeds = [
+        BASE_VAULT_AUTHORITY_SEED,
+        vault_state_key.as_ref(),
+        base_vault_authority_bump.as_ref(),
+    ];
+    let signer_seeds = &[&inner_seeds[..]];
+
+    cpi.program_invoke_signed(
+        &ctx.accounts.klend_program.key(),
+        &accs,
+        &data,
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
 pub fn cpi_redeem_reserve_liquidity_from_invest(
     ctx: &Context<Invest>,
     cpi: &mut CpiMemoryLender,
@@ -244,3 +344,97 @@ pub fn cpi_redeem_reserve_liquidity_from_invest(
     )
     .map_err(Into::into)
 }
+
+pub fn cpi_deposit_reserve_liquidity_from_rebalance(
+    ctx_accounts: &Rebalance,
+    reserve_accounts: &RebalanceReserve,
+    cpi: &mut CpiMemoryLender,
+    base_vault_authority_bump: u8,
+    liquidity_amount: u64,
+) -> Result<()> {
+    let accs = kamino_lending::accounts::DepositReserveLiquidity {
+        owner: ctx_accounts.base_vault_authority.key(),
+        reserve: reserve_accounts.reserve.key(),
+        lending_market: reserve_accounts.lending_market.key(),
+        lending_market_authority: reserve_accounts.lending_market_authority.key(),
+        reserve_liquidity_mint: ctx_accounts.token_mint.key(),
+        reserve_liquidity_supply: reserve_accounts.reserve_liquidity_supply.key(),
+        reserve_collateral_mint: reserve_accounts.reserve_collateral_mint.key(),
+        user_source_liquidity: ctx_accounts.token_vault.key(),
+        user_destination_collateral: reserve_accounts.ctoken_vault.key(),
+        collateral_token_program: ctx_accounts.reserve_collateral_token_program.key(),
+        liquidity_token_program: ctx_accounts.token_program.key(),
+        instruction_sysvar_account: ctx_accounts.instruction_sysvar_account.key(),
+    }
+    .to_account_metas(None);
+
+    let mut data = [0_u8; 40];
+    data[0..8]
+        .copy_from_slice(&kamino_lending::instruction::DepositReserveLiquidity::DISCRIMINATOR);
+    let mut writer = &mut data[8..40];
+    borsh::to_writer(&mut writer, &liquidity_amount).unwrap();
+
+    let base_vault_authority_bump = vec![base_vault_authority_bump];
+    let vault_state_key = ctx_accounts.vault_state.key();
+    let inner_seeds = [
+        BASE_VAULT_AUTHORITY_SEED,
+        vault_state_key.as_ref(),
+        base_vault_authority_bump.as_ref(),
+    ];
+    let signer_seeds = &[&inner_seeds[..]];
+
+    cpi.program_invoke_signed(
+        &ctx_accounts.klend_program.key(),
+        &accs,
+        &data,
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+pub fn cpi_redeem_reserve_liquidity_from_rebalance(
+    ctx_accounts: &Rebalance,
+    reserve_accounts: &RebalanceReserve,
+    cpi: &mut CpiMemoryLender,
+    base_vault_authority_bump: u8,
+    collateral_amount: u64,
+) -> Result<()> {
+    let accs = kamino_lending::accounts::RedeemReserveCollateral {
+        owner: ctx_accounts.base_vault_authority.key(),
+        lending_market: reserve_accounts.lending_market.key(),
+        reserve: reserve_accounts.reserve.key(),
+        lending_market_authority: reserve_accounts.lending_market_authority.key(),
+        reserve_liquidity_mint: ctx_accounts.token_mint.key(),
+        reserve_collateral_mint: reserve_accounts.reserve_collateral_mint.key(),
+        reserve_liquidity_supply: reserve_accounts.reserve_liquidity_supply.key(),
+        user_source_collateral: reserve_accounts.ctoken_vault.key(),
+        user_destination_liquidity: ctx_accounts.token_vault.key(),
+        collateral_token_program: ctx_accounts.reserve_collateral_token_program.key(),
+        liquidity_token_program: ctx_accounts.token_program.key(),
+        instruction_sysvar_account: ctx_accounts.instruction_sysvar_account.key(),
+    }
+    .to_account_metas(None);
+
+    let mut data = [0_u8; 40];
+    data[0..8]
+        .copy_from_slice(&kamino_lending::instruction::RedeemReserveCollateral::DISCRIMINATOR);
+    let mut writer = &mut data[8..40];
+    borsh::to_writer(&mut writer, &collateral_amount).unwrap();
+
+    let base_vault_authority_bump = vec![base_vault_authority_bump];
+    let vault_state_key = ctx_accounts.vault_state.key();
+    let inner_seeds = [
+        BASE_VAULT_AUTHORITY_SEED,
+        vault_state_key.as_ref(),
+        base_vault_authority_bump.as_ref(),
+    ];
+    let signer_seeds = &[&inner_seeds[..]];
+
+    cpi.program_invoke_signed(
+        &ctx_accounts.klend_program.key(),
+        &accs,
+        &data,
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
@@ -1,3 +1,4 @@
+pub mod depositor_allowlist_operations;
 pub mod effects;
 pub mod klend_operations;
 pub mod reserve_whitelist_operations;
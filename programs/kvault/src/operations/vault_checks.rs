@@ -3,7 +3,8 @@ use fixed::types::U68F60 as Fraction;
 use kamino_lending::utils::FractionExtra;
 
 use super::effects::{
-    InvestEffects, InvestingDirection, WithdrawEffects, WithdrawPendingFeesEffects,
+    InvestEffects, InvestingDirection, RebalanceReserveEffects, WithdrawEffects,
+    WithdrawPendingFeesEffects,
 };
 use crate::{require_msg, KaminoVaultError};
 
@@ -15,12 +16,36 @@ pub struct VaultAndUserBalances {
     pub user_shares_balance: u64,
 }
 
+pub struct ReserveBalances {
+    pub reserve_supply_liquidity_balance: u64,
+    pub vault_ctoken_balance: u64,
+}
+
+pub struct VaultAndUserWithdrawBalances {
+    pub vault_token_balance: u64,
+    pub user_token_balance: u64,
+    pub user_shares_balance: u64,
+    pub reserves: Vec<ReserveBalances>,
+}
+
+pub struct RebalanceBalances {
+    pub vault_token_balance: u64,
+    pub reserves: Vec<ReserveBalances>,
+}
+
 pub struct VaultBalances {
     pub reserve_supply_liquidity_balance: u64,
     pub vault_token_balance: u64,
     pub vault_ctoken_balance: u64,
 }
 
+pub struct WithdrawalQueueBalances {
+    pub reserve_supply_liquidity_balance: u64,
+    pub vault_token_balance: u64,
+    pub vault_ctoken_balance: u64,
+    pub escrow_shares_balance: u64,
+}
+
 pub struct RedeemInKindPostCheckAmounts {
     pub user_share_balance: u64,
     pub vault_ctoken_balance: u64,
@@ -67,48 +92,77 @@ pub fn post_redeem_in_kind_checks(
 }
 
 pub fn post_transfer_withdraw_balance_checks(
-    amounts_before: VaultAndUserBalances,
-    amounts_after: VaultAndUserBalances,
+    amounts_before: VaultAndUserWithdrawBalances,
+    amounts_after: VaultAndUserWithdrawBalances,
     withdraw_effects: WithdrawEffects,
 ) -> Result<()> {
     let WithdrawEffects {
         shares_to_burn,
         available_to_send_to_user,
-        invested_to_disinvest_ctokens,
+        invested_to_disinvest_ctokens: _,
         invested_liquidity_to_send_to_user,
-        invested_liquidity_to_disinvest,
+        invested_liquidity_to_disinvest: _,
+        reserves,
     } = withdraw_effects;
 
+    require_eq!(amounts_before.reserves.len(), reserves.len());
+    require_eq!(amounts_after.reserves.len(), reserves.len());
+
    
     let token_vault_diff: i128 = i128::from(amounts_before.vault_token_balance)
         - i128::from(amounts_after.vault_token_balance);
-    let ctoken_vault_decrease =
-        amounts_before.vault_ctoken_balance - amounts_after.vault_ctoken_balance;
 
     let user_ata_increase = i128::from(amounts_after.user_token_balance)
         - i128::from(amounts_before.user_token_balance);
     let user_shares_diff = amounts_before.user_shares_balance - amounts_after.user_shares_balance;
-    let reserve_supply_liquidity_diff = i128::from(amounts_before.reserve_supply_liquidity_balance)
-        - i128::from(amounts_after.reserve_supply_liquidity_balance);
+
+    let mut total_reserve_supply_liquidity_diff: i128 = 0;
+    for ((reserve_before, reserve_after), reserve_effects) in amounts_before
+        .reserves
+        .iter()
+        .zip(amounts_after.reserves.iter())
+        .zip(reserves.iter())
+    {
+        let ctoken_vault_decrease =
+            reserve_before.vault_ctoken_balance - reserve_after.vault_ctoken_balance;
+        let reserve_supply_liquidity_diff =
+            i128::from(reserve_before.reserve_supply_liquidity_balance)
+                - i128::from(reserve_after.reserve_supply_liquidity_balance);
+
+        require_msg!(
+            ctoken_vault_decrease == reserve_effects.invested_to_disinvest_ctokens,
+            KaminoVaultError::LiquidityToWithdrawDoesNotMatch,
+            &format!(
+                "C token amounts to disinvest and result are diff {ctoken_vault_decrease} {}",
+                reserve_effects.invested_to_disinvest_ctokens
+            )
+        );
+
+        require_msg!(
+            reserve_supply_liquidity_diff
+                == i128::from(reserve_effects.invested_liquidity_to_disinvest),
+            KaminoVaultError::DisinvestedLiquidityAmountDoesNotMatch,
+            &format!(
+                "Reserve liquidity diff and result are diff {reserve_supply_liquidity_diff} {}",
+                reserve_effects.invested_liquidity_to_disinvest
+            )
+        );
+
+        total_reserve_supply_liquidity_diff += reserve_supply_liquidity_diff;
+    }
 
     let total_amount_sent_to_user =
         i128::from(available_to_send_to_user) + i128::from(invested_liquidity_to_send_to_user);
 
     require_msg!(
-        total_amount_sent_to_user == reserve_supply_liquidity_diff + token_vault_diff,
+        total_amount_sent_to_user == total_reserve_supply_liquidity_diff + token_vault_diff,
         KaminoVaultError::AmountToWithdrawDoesNotMatch,
         &format!(
             "Amount to send to user and result are diff {total_amount_sent_to_user} {}",
-            reserve_supply_liquidity_diff + token_vault_diff
+            total_reserve_supply_liquidity_diff + token_vault_diff
         )
     );
 
-    require_msg!(
-        ctoken_vault_decrease == invested_to_disinvest_ctokens,
-        KaminoVaultError::LiquidityToWithdrawDoesNotMatch,
-        &format!("C token amounts to disinvest and result are diff {ctoken_vault_decrease} {invested_to_disinvest_ctokens}")
-    );
-
     require_msg!(
         user_ata_increase == total_amount_sent_to_user,
         KaminoVaultError::UserReceivedAmountDoesNotMatch,
@@ -121,6 +175,54 @@ pub fn post_transfer_withdraw_balance_checks(
         &format!("Shares ata diff and result are diff {user_shares_diff} {shares_to_burn}")
     );
 
+    Ok(())
+}
+
+pub fn post_transfer_fulfil_withdrawal_request_checks(
+    amounts_before: WithdrawalQueueBalances,
+    amounts_after: WithdrawalQueueBalances,
+    withdraw_effects: WithdrawEffects,
+) -> Result<()> {
+    let WithdrawEffects {
+        shares_to_burn,
+        available_to_send_to_user: _,
+        invested_to_disinvest_ctokens,
+        invested_liquidity_to_send_to_user: _,
+        invested_liquidity_to_disinvest,
+        reserves: _,
+    } = withdraw_effects;
+
+    let token_vault_increase = i128::from(amounts_after.vault_token_balance)
+        - i128::from(amounts_before.vault_token_balance);
+    let ctoken_vault_decrease =
+        amounts_before.vault_ctoken_balance - amounts_after.vault_ctoken_balance;
+    let escrow_shares_decrease =
+        amounts_before.escrow_shares_balance - amounts_after.escrow_shares_balance;
+    let reserve_supply_liquidity_diff = i128::from(amounts_before.reserve_supply_liquidity_balance)
+        - i128::from(amounts_after.reserve_supply_liquidity_balance);
+
+    require_msg!(
+        token_vault_increase == reserve_supply_liquidity_diff,
+        KaminoVaultError::AmountToWithdrawDoesNotMatch,
+        &format!(
+            "Token vault increase and reserve liquidity decrease are diff {token_vault_increase} {reserve_supply_liquidity_diff}"
+        )
+    );
+
+    require_msg!(
+        ctoken_vault_decrease == invested_to_disinvest_ctokens,
+        KaminoVaultError::LiquidityToWithdrawDoesNotMatch,
+        &format!("C token amounts to disinvest and result are diff {ctoken_vault_decrease} {invested_to_disinvest_ctokens}")
+    );
+
+    require_msg!(
+        escrow_shares_decrease == shares_to_burn,
+        KaminoVaultError::SharesBurnedAmountDoesNotMatch,
+        &format!(
+            "Escrow shares diff and result are diff {escrow_shares_decrease} {shares_to_burn}"
+        )
+    );
+
     require_msg!(
         reserve_supply_liquidity_diff == i128::from(invested_liquidity_to_disinvest),
         KaminoVaultError::DisinvestedLiquidityAmountDoesNotMatch,
@@ -252,6 +354,97 @@ pub fn post_transfer_invest_checks(
    
    
    
+    require_gte!(
+        aum_after_transfers,
+        aum_before_transfers,
+        KaminoVaultError::AUMDecreasedAfterInvest
+    );
+
+    Ok(())
+}
+
+pub fn post_transfer_rebalance_checks(
+    amounts_before: RebalanceBalances,
+    amounts_after: RebalanceBalances,
+    rebalance_effects: &[RebalanceReserveEffects],
+    initial_holdings_total: Fraction,
+    final_holdings_total: Fraction,
+    aum_before_transfers: Fraction,
+    aum_after_transfers: Fraction,
+) -> Result<()> {
+    require_eq!(amounts_before.reserves.len(), amounts_after.reserves.len());
+
+    let mut expected_token_vault_balance = i128::from(amounts_before.vault_token_balance);
+    for (reserve_index, (reserve_before, reserve_after)) in amounts_before
+        .reserves
+        .iter()
+        .zip(amounts_after.reserves.iter())
+        .enumerate()
+    {
+        let invest_effects = rebalance_effects
+            .iter()
+            .find(|e| e.reserve_index == reserve_index)
+            .map(|e| &e.invest_effects);
+
+        match invest_effects {
+            Some(InvestEffects {
+                direction: InvestingDirection::Add,
+                liquidity_amount,
+                collateral_amount,
+                rounding_loss,
+            }) => {
+                expected_token_vault_balance +=
+                    i128::from(*rounding_loss) - i128::from(*liquidity_amount);
+                require_eq!(
+                    reserve_before.vault_ctoken_balance + collateral_amount,
+                    reserve_after.vault_ctoken_balance
+                );
+                require_eq!(
+                    reserve_before.reserve_supply_liquidity_balance + liquidity_amount,
+                    reserve_after.reserve_supply_liquidity_balance
+                );
+            }
+            Some(InvestEffects {
+                direction: InvestingDirection::Subtract,
+                liquidity_amount,
+                collateral_amount,
+                rounding_loss,
+            }) => {
+                expected_token_vault_balance +=
+                    i128::from(*rounding_loss) + i128::from(*liquidity_amount);
+                require_eq!(
+                    reserve_before.vault_ctoken_balance - collateral_amount,
+                    reserve_after.vault_ctoken_balance
+                );
+                require_eq!(
+                    reserve_before.reserve_supply_liquidity_balance - liquidity_amount,
+                    reserve_after.reserve_supply_liquidity_balance
+                );
+            }
+            None => {
+                require_eq!(
+                    reserve_before.vault_ctoken_balance,
+                    reserve_after.vault_ctoken_balance
+                );
+                require_eq!(
+                    reserve_before.reserve_supply_liquidity_balance,
+                    reserve_after.reserve_supply_liquidity_balance
+                );
+            }
+        }
+    }
+
+    require_eq!(
+        expected_token_vault_balance,
+        i128::from(amounts_after.vault_token_balance)
+    );
+
+    require_gte!(
+        final_holdings_total,
+        initial_holdings_total,
+        KaminoVaultError::AUMDecreasedAfterInvest
+    );
+
     require_gte!(
         aum_after_transfers,
         aum_before_transfers,
@@ -1,15 +1,16 @@
 use anchor_lang::prelude::*;
 use borsh::BorshDeserialize;
-use kamino_lending::utils::FULL_BPS;
+use kamino_lending::{fraction::Fraction, utils::FULL_BPS};
 
 use crate::{
     operations::vault_operations::string_utils::{encoded_name_to_label, slice_to_array_padded},
     utils::consts::{
-        MAX_MGMT_FEE_BPS, MAX_WITHDRAWAL_PENALTY_BPS, MAX_WITHDRAWAL_PENALTY_LAMPORTS,
-        UPPER_LIMIT_MIN_WITHDRAW_AMOUNT,
+        MAX_CONFIG_CHANGE_DELAY_SECONDS, MAX_MGMT_FEE_BPS, MAX_WITHDRAWAL_PENALTY_BPS,
+        MAX_WITHDRAWAL_PENALTY_LAMPORTS, PAUSE_ALL_OPERATIONS, UPPER_LIMIT_MIN_WITHDRAW_AMOUNT,
     },
+    FeeRecipient,
     KaminoVaultError::{self, BPSValueTooBig},
-    VaultState,
+    PendingVaultConfigChange, VaultLifecycleState, VaultState, MAX_FEE_RECIPIENTS,
 };
 
 #[derive(Clone, Copy, Debug, AnchorSerialize, AnchorDeserialize)]
@@ -34,6 +35,37 @@ pub enum VaultConfigField {
     AllowAllocationsInWhitelistedReservesOnly,
     AllowInvestInWhitelistedReservesOnly,
     RewardPerSecond,
+    ConfigChangeDelaySeconds,
+    DepositCap,
+    UserSharesCap,
+    PermissionedDeposits,
+    // Permissioned share transfers are not a config field, they are turned on and off with
+    // `update_shares_transfer_hook`, along with the hook program of the shares mint
+    FeeRecipients,
+    FeesInShares,
+    FeeSharesRecipient,
+    PerformanceFeeHurdleBps,
+    FirstLossDepositor,
+    MaxReserveUtilizationBps,
+    MaxReserveBorrowRateBps,
+    ExcludeDistressedReserves,
+    PausedOperations,
+    EmergencyGuardian,
+    LifecycleState,
+}
+
+impl VaultConfigField {
+    pub fn timelock_slot(&self) -> Option<usize> {
+        match self {
+            VaultConfigField::PerformanceFeeBps => Some(0),
+            VaultConfigField::ManagementFeeBps => Some(1),
+            VaultConfigField::WithdrawalPenaltyLamports => Some(2),
+            VaultConfigField::WithdrawalPenaltyBps => Some(3),
+            VaultConfigField::ConfigChangeDelaySeconds => Some(4),
+            VaultConfigField::PerformanceFeeHurdleBps => Some(5),
+            _ => None,
+        }
+    }
 }
 
 pub fn check_if_signer_allowed_to_update_vault_config(
@@ -66,7 +98,13 @@ pub fn check_if_signer_allowed_to_update_vault_config(
         | VaultConfigField::CrankFundFeePerReserve
         | VaultConfigField::LookupTable
         | VaultConfigField::Name
-        | VaultConfigField::Farm => {
+        | VaultConfigField::Farm
+        | VaultConfigField::DepositCap
+        | VaultConfigField::UserSharesCap
+        | VaultConfigField::MaxReserveUtilizationBps
+        | VaultConfigField::MaxReserveBorrowRateBps
+        | VaultConfigField::ExcludeDistressedReserves
+        | VaultConfigField::PausedOperations => {
             require!(
                 is_global_admin || is_vault_admin,
                 KaminoVaultError::AdminAuthorityIncorrect
@@ -74,6 +112,7 @@ pub fn check_if_signer_allowed_to_update_vault_config(
         }
         VaultConfigField::PendingVaultAdmin
         | VaultConfigField::PerformanceFeeBps
+        | VaultConfigField::PerformanceFeeHurdleBps
         | VaultConfigField::ManagementFeeBps
         | VaultConfigField::FirstLossCapitalFarm
         | VaultConfigField::AllocationAdmin
@@ -81,7 +120,15 @@ pub fn check_if_signer_allowed_to_update_vault_config(
         | VaultConfigField::UnallocatedTokensCap
         | VaultConfigField::WithdrawalPenaltyLamports
         | VaultConfigField::WithdrawalPenaltyBps
-        | VaultConfigField::RewardPerSecond => {
+        | VaultConfigField::RewardPerSecond
+        | VaultConfigField::ConfigChangeDelaySeconds
+        | VaultConfigField::PermissionedDeposits
+        | VaultConfigField::FeeRecipients
+        | VaultConfigField::FeesInShares
+        | VaultConfigField::FeeSharesRecipient
+        | VaultConfigField::FirstLossDepositor
+        | VaultConfigField::EmergencyGuardian
+        | VaultConfigField::LifecycleState => {
            
             require!(is_vault_admin, KaminoVaultError::AdminAuthorityIncorrect);
         }
@@ -101,17 +148,20 @@ pub fn update_vault_config(
 
             msg!("Prv value is {:?}", vault.performance_fee_bps);
             msg!("New value is {:?}", performance_fee_bps);
-            let full_bps_u64: u64 = FULL_BPS.into();
-            if performance_fee_bps > full_bps_u64 {
-                return Err(BPSValueTooBig.into());
-            }
+            validate_bps(performance_fee_bps)?;
             vault.performance_fee_bps = performance_fee_bps;
         }
+        VaultConfigField::PerformanceFeeHurdleBps => {
+            let performance_fee_hurdle_bps = BorshDeserialize::try_from_slice(data)?;
+
+            msg!("Prv value is {:?}", vault.performance_fee_hurdle_bps);
+            msg!("New value is {:?}", performance_fee_hurdle_bps);
+            validate_bps(performance_fee_hurdle_bps)?;
+            vault.performance_fee_hurdle_bps = performance_fee_hurdle_bps;
+        }
         VaultConfigField::ManagementFeeBps => {
             let management_fee_bps = BorshDeserialize::try_from_slice(data)?;
-            if management_fee_bps > MAX_MGMT_FEE_BPS {
-                return err!(KaminoVaultError::ManagementFeeGreaterThanMaxAllowed);
-            }
+            validate_management_fee_bps(management_fee_bps)?;
 
             msg!("Prv value is {:?}", vault.management_fee_bps);
             msg!("New value is {:?}", management_fee_bps);
@@ -215,22 +265,14 @@ pub fn update_vault_config(
         }
         VaultConfigField::WithdrawalPenaltyLamports => {
             let withdrawal_penalty_lamports = BorshDeserialize::try_from_slice(data)?;
-            require_gte!(
-                MAX_WITHDRAWAL_PENALTY_LAMPORTS,
-                withdrawal_penalty_lamports,
-                KaminoVaultError::WithdrawalFeeLamportsGreaterThanMaxAllowed
-            );
+            validate_withdrawal_penalty_lamports(withdrawal_penalty_lamports)?;
             msg!("Prv value is {:?}", vault.withdrawal_penalty_lamports);
             msg!("New value is {:?}", withdrawal_penalty_lamports);
             vault.withdrawal_penalty_lamports = withdrawal_penalty_lamports;
         }
         VaultConfigField::WithdrawalPenaltyBps => {
             let withdrawal_penalty_bps = BorshDeserialize::try_from_slice(data)?;
-            require_gte!(
-                MAX_WITHDRAWAL_PENALTY_BPS,
-                withdrawal_penalty_bps,
-                KaminoVaultError::WithdrawalFeeBPSGreaterThanMaxAllowed
-            );
+            validate_withdrawal_penalty_bps(withdrawal_penalty_bps)?;
             msg!("Prv value is {:?}", vault.withdrawal_penalty_bps);
             msg!("New value is {:?}", withdrawal_penalty_bps);
             vault.withdrawal_penalty_bps = withdrawal_penalty_bps;
@@ -263,7 +305,320 @@ pub fn update_vault_config(
             vault.reward_info.reward_per_second = new_rps;
             vault.reward_info.last_issuance_ts = current_ts;
         }
+        VaultConfigField::ConfigChangeDelaySeconds => {
+            let delay_seconds = BorshDeserialize::try_from_slice(data)?;
+            validate_config_change_delay_seconds(delay_seconds)?;
+            msg!("Prv value is {:?}", vault.config_timelock.delay_seconds);
+            msg!("New value is {:?}", delay_seconds);
+            vault.config_timelock.delay_seconds = delay_seconds;
+        }
+        VaultConfigField::DepositCap => {
+            let deposit_cap = BorshDeserialize::try_from_slice(data)?;
+            msg!("Prv value is {:?}", vault.deposit_cap);
+            msg!("New value is {:?}", deposit_cap);
+            vault.deposit_cap = deposit_cap;
+        }
+        VaultConfigField::UserSharesCap => {
+            let user_shares_cap = BorshDeserialize::try_from_slice(data)?;
+            msg!("Prv value is {:?}", vault.user_shares_cap);
+            msg!("New value is {:?}", user_shares_cap);
+            vault.user_shares_cap = user_shares_cap;
+        }
+        VaultConfigField::PermissionedDeposits => {
+            let value: u8 = BorshDeserialize::try_from_slice(data)?;
+            require!(value <= 1, KaminoVaultError::InvalidBoolLikeValue);
+            msg!("Prv value is {:?}", vault.permissioned_deposits);
+            msg!("New value is {:?}", value);
+            vault.permissioned_deposits = value;
+        }
+        VaultConfigField::FeeRecipients => {
+            let fee_recipients: Vec<(Pubkey, u64)> = BorshDeserialize::try_from_slice(data)?;
+            validate_fee_recipients(&fee_recipients)?;
+
+            msg!(
+                "Prv value is {:?}",
+                vault
+                    .fee_recipients
+                    .iter()
+                    .filter(|r| r.recipient != Pubkey::default())
+                    .map(|r| (r.recipient, r.bps))
+                    .collect::<Vec<_>>()
+            );
+            msg!("New value is {:?}", fee_recipients);
+            vault.fee_recipients = [FeeRecipient::default(); MAX_FEE_RECIPIENTS];
+            for (fee_recipient, (recipient, bps)) in
+                vault.fee_recipients.iter_mut().zip(fee_recipients)
+            {
+                fee_recipient.recipient = recipient;
+                fee_recipient.bps = bps;
+            }
+        }
+        VaultConfigField::FeesInShares => {
+            let value: u8 = BorshDeserialize::try_from_slice(data)?;
+            require!(value <= 1, KaminoVaultError::InvalidBoolLikeValue);
+            if value == 1 {
+                require!(
+                    vault.get_pending_fees() == Fraction::ZERO,
+                    KaminoVaultError::PendingFeesMustBeConvertedToShares
+                );
+                require!(
+                    vault.fee_shares_recipient != Pubkey::default(),
+                    KaminoVaultError::FeeSharesRecipientNotSet
+                );
+            }
+            msg!("Prv value is {:?}", vault.fees_in_shares);
+            msg!("New value is {:?}", value);
+            vault.fees_in_shares = value;
+        }
+        VaultConfigField::FeeSharesRecipient => {
+            let pubkey: Pubkey = BorshDeserialize::try_from_slice(data)?;
+            require!(
+                pubkey != Pubkey::default() || !vault.vault_takes_fees_in_shares(),
+                KaminoVaultError::FeeSharesRecipientNotSet
+            );
+
+            msg!("Prv value is {:?}", vault.fee_shares_recipient);
+            msg!("New value is {:?}", pubkey);
+            vault.fee_shares_recipient = pubkey;
+        }
+        VaultConfigField::FirstLossDepositor => {
+            let pubkey: Pubkey = BorshDeserialize::try_from_slice(data)?;
+            require!(
+                vault.first_loss_shares == 0 && vault.first_loss_shares_pending_burn == 0,
+                KaminoVaultError::FirstLossSharesOutstanding
+            );
+
+            msg!("Prv value is {:?}", vault.first_loss_depositor);
+            msg!("New value is {:?}", pubkey);
+            vault.first_loss_depositor = pubkey;
+        }
+        VaultConfigField::MaxReserveUtilizationBps => {
+            let max_reserve_utilization_bps = BorshDeserialize::try_from_slice(data)?;
+
+            msg!("Prv value is {:?}", vault.max_reserve_utilization_bps);
+            msg!("New value is {:?}", max_reserve_utilization_bps);
+            validate_bps(max_reserve_utilization_bps)?;
+            vault.max_reserve_utilization_bps = max_reserve_utilization_bps;
+        }
+        VaultConfigField::MaxReserveBorrowRateBps => {
+            let max_reserve_borrow_rate_bps = BorshDeserialize::try_from_slice(data)?;
+            msg!("Prv value is {:?}", vault.max_reserve_borrow_rate_bps);
+            msg!("New value is {:?}", max_reserve_borrow_rate_bps);
+            vault.max_reserve_borrow_rate_bps = max_reserve_borrow_rate_bps;
+        }
+        VaultConfigField::ExcludeDistressedReserves => {
+            let value: u8 = BorshDeserialize::try_from_slice(data)?;
+            require!(value <= 1, KaminoVaultError::InvalidBoolLikeValue);
+            msg!("Prv value is {:?}", vault.exclude_distressed_reserves);
+            msg!("New value is {:?}", value);
+            vault.exclude_distressed_reserves = value;
+        }
+        VaultConfigField::PausedOperations => {
+            let paused_operations = BorshDeserialize::try_from_slice(data)?;
+            require_gte!(
+                PAUSE_ALL_OPERATIONS,
+                paused_operations,
+                KaminoVaultError::InvalidPausedOperations
+            );
+            msg!("Prv value is {:?}", vault.paused_operations);
+            msg!("New value is {:?}", paused_operations);
+            vault.paused_operations = paused_operations;
+        }
+        VaultConfigField::EmergencyGuardian => {
+            let pubkey: Pubkey = BorshDeserialize::try_from_slice(data)?;
+            msg!("Prv value is {:?}", vault.emergency_guardian);
+            msg!("New value is {:?}", pubkey);
+            vault.emergency_guardian = pubkey;
+        }
+        VaultConfigField::LifecycleState => {
+            let lifecycle_state: VaultLifecycleState = BorshDeserialize::try_from_slice(data)?;
+            require!(
+                vault.get_lifecycle_state()?.can_transition_to(lifecycle_state),
+                KaminoVaultError::InvalidLifecycleStateTransition
+            );
+            msg!("Prv value is {:?}", vault.get_lifecycle_state()?);
+            msg!("New value is {:?}", lifecycle_state);
+            vault.lifecycle_state = lifecycle_state.into();
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_fee_recipients(fee_recipients: &[(Pubkey, u64)]) -> Result<()> {
+    if fee_recipients.is_empty() {
+        return Ok(());
+    }
+
+    require_gte!(
+        MAX_FEE_RECIPIENTS,
+        fee_recipients.len(),
+        KaminoVaultError::InvalidFeeRecipients
+    );
+
+    let mut total_bps: u64 = 0;
+    for (i, (recipient, bps)) in fee_recipients.iter().enumerate() {
+        require!(
+            *recipient != Pubkey::default() && *bps > 0,
+            KaminoVaultError::InvalidFeeRecipients
+        );
+        require!(
+            fee_recipients[..i]
+                .iter()
+                .all(|(other, _)| other != recipient),
+            KaminoVaultError::InvalidFeeRecipients
+        );
+        total_bps += bps;
+    }
+
+    let full_bps_u64: u64 = FULL_BPS.into();
+    require_eq!(
+        total_bps,
+        full_bps_u64,
+        KaminoVaultError::InvalidFeeRecipients
+    );
+
+    Ok(())
+}
+
+pub fn check_vault_config_update_not_timelocked(
+    vault: &VaultState,
+    entry: &VaultConfigField,
+) -> Result<()> {
+    if entry.timelock_slot().is_some() && vault.config_timelock.delay_seconds > 0 {
+        return err!(KaminoVaultError::ConfigFieldTimelocked);
+    }
+    Ok(())
+}
+
+// Validators shared by `update_vault_config` and the timelock proposals, so that a proposed
+// value is rejected upfront for the same reasons it would be when executed
+fn validate_timelocked_config_value(entry: &VaultConfigField, value: u64) -> Result<()> {
+    match entry {
+        VaultConfigField::PerformanceFeeBps | VaultConfigField::PerformanceFeeHurdleBps => {
+            validate_bps(value)
+        }
+        VaultConfigField::ManagementFeeBps => validate_management_fee_bps(value),
+        VaultConfigField::WithdrawalPenaltyLamports => validate_withdrawal_penalty_lamports(value),
+        VaultConfigField::WithdrawalPenaltyBps => validate_withdrawal_penalty_bps(value),
+        VaultConfigField::ConfigChangeDelaySeconds => validate_config_change_delay_seconds(value),
+        _ => err!(KaminoVaultError::ConfigFieldNotTimelockable),
+    }
+}
+
+fn validate_bps(value: u64) -> Result<()> {
+    let full_bps_u64: u64 = FULL_BPS.into();
+    if value > full_bps_u64 {
+        return Err(BPSValueTooBig.into());
+    }
+    Ok(())
+}
+
+fn validate_management_fee_bps(value: u64) -> Result<()> {
+    if value > MAX_MGMT_FEE_BPS {
+        return err!(KaminoVaultError::ManagementFeeGreaterThanMaxAllowed);
     }
+    Ok(())
+}
+
+fn validate_withdrawal_penalty_lamports(value: u64) -> Result<()> {
+    require_gte!(
+        MAX_WITHDRAWAL_PENALTY_LAMPORTS,
+        value,
+        KaminoVaultError::WithdrawalFeeLamportsGreaterThanMaxAllowed
+    );
+    Ok(())
+}
 
+fn validate_withdrawal_penalty_bps(value: u64) -> Result<()> {
+    require_gte!(
+        MAX_WITHDRAWAL_PENALTY_BPS,
+        value,
+        KaminoVaultError::WithdrawalFeeBPSGreaterThanMaxAllowed
+    );
     Ok(())
 }
+
+fn validate_config_change_delay_seconds(value: u64) -> Result<()> {
+    require_gte!(
+        MAX_CONFIG_CHANGE_DELAY_SECONDS,
+        value,
+        KaminoVaultError::ConfigChangeDelayTooBig
+    );
+    Ok(())
+}
+
+pub fn propose_vault_config_change(
+    vault: &mut VaultState,
+    entry: VaultConfigField,
+    data: &[u8],
+    current_timestamp: u64,
+) -> Result<PendingVaultConfigChange> {
+    let slot = entry
+        .timelock_slot()
+        .ok_or_else(|| error!(KaminoVaultError::ConfigFieldNotTimelockable))?;
+    let value: u64 = BorshDeserialize::try_from_slice(data)?;
+    validate_timelocked_config_value(&entry, value)?;
+
+    let pending_change = PendingVaultConfigChange {
+        value,
+        activation_timestamp: current_timestamp + vault.config_timelock.delay_seconds,
+    };
+
+    msg!(
+        "Proposing vault config field {:?} value {} active from {}",
+        entry,
+        value,
+        pending_change.activation_timestamp
+    );
+    vault.config_timelock.pending_changes[slot] = pending_change;
+
+    Ok(pending_change)
+}
+
+pub fn cancel_vault_config_change(
+    vault: &mut VaultState,
+    entry: VaultConfigField,
+) -> Result<PendingVaultConfigChange> {
+    let slot = entry
+        .timelock_slot()
+        .ok_or_else(|| error!(KaminoVaultError::ConfigFieldNotTimelockable))?;
+    let pending_change = vault.config_timelock.pending_changes[slot];
+    require!(
+        pending_change.is_pending(),
+        KaminoVaultError::NoPendingConfigChange
+    );
+
+    msg!(
+        "Cancelling vault config field {:?} value {}",
+        entry,
+        pending_change.value
+    );
+    vault.config_timelock.pending_changes[slot] = PendingVaultConfigChange::default();
+
+    Ok(pending_change)
+}
+
+pub fn execute_vault_config_change(
+    vault: &mut VaultState,
+    entry: VaultConfigField,
+    current_timestamp: u64,
+) -> Result<PendingVaultConfigChange> {
+    let slot = entry
+        .timelock_slot()
+        .ok_or_else(|| error!(KaminoVaultError::ConfigFieldNotTimelockable))?;
+    let pending_change = vault.config_timelock.pending_changes[slot];
+    require!(
+        pending_change.is_pending(),
+        KaminoVaultError::NoPendingConfigChange
+    );
+    require!(
+        pending_change.is_active(current_timestamp),
+        KaminoVaultError::ConfigChangeNotActive
+    );
+
+    vault.config_timelock.pending_changes[slot] = PendingVaultConfigChange::default();
+    update_vault_config(vault, entry, &pending_change.value.try_to_vec()?)?;
+
+    Ok(pending_change)
+}
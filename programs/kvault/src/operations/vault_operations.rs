@@ -12,14 +12,19 @@ use rust_decimal::prelude::ToPrimitive;
 use solana_program::pubkey::Pubkey;
 
 use super::effects::{
-    DepositEffects, InvestEffects, InvestingDirection, RedeemInKindEffects, WithdrawEffects,
-    WithdrawPendingFeesEffects,
+    ClaimWithdrawalRequestEffects, DepositEffects, DistributePendingFeesEffects, InvestEffects,
+    InvestingDirection, RebalanceReserveEffects, RedeemInKindEffects, WithdrawEffects,
+    WithdrawFromReserveEffects, WithdrawPendingFeesEffects,
 };
 use crate::{
     kmsg, kmsg_sized,
     operations::vault_operations::common::{get_shares_to_mint, holdings},
-    utils::consts::SECONDS_PER_YEAR,
-    xmsg, GlobalConfig, KaminoVaultError, ReserveWhitelistEntry, VaultState, MAX_RESERVES,
+    utils::consts::{
+        FIRST_LOSS_WITHDRAWAL_NOTICE_SECONDS, FIRST_LOSS_WITHDRAWAL_WINDOW_SECONDS,
+        INITIAL_DEPOSIT_AMOUNT, SECONDS_PER_YEAR,
+    },
+    xmsg, GlobalConfig, KaminoVaultError, ReserveWhitelistEntry, VaultLifecycleState, VaultState,
+    WithdrawalRequest, WithdrawalRequestStatus, MAX_FEE_RECIPIENTS, MAX_RESERVES,
 };
 
 pub fn initialize(
@@ -48,12 +53,18 @@ pub fn deposit<'info, T>(
     vault: &mut VaultState,
     reserves_iter: impl Iterator<Item = T>,
     max_amount: u64,
+    min_shares_out: u64,
     current_slot: Slot,
     current_timestamp: u64,
 ) -> Result<DepositEffects>
 where
     T: AnyAccountLoader<'info, Reserve>,
 {
+    require!(
+        vault.get_lifecycle_state()? == VaultLifecycleState::Active,
+        KaminoVaultError::VaultNotActive
+    );
+
     refresh_rewards(vault, current_timestamp)?;
 
     let num_reserve: u64 = vault
@@ -76,6 +87,17 @@ where
     charge_fees(vault, &holdings.invested, current_timestamp)?;
     let current_vault_aum = vault.compute_aum(&holdings.invested.total)?;
 
+    let deposit_cap_headroom = common::deposit_cap_headroom(vault, current_vault_aum)?;
+    let truncated_by_deposit_cap = max_user_tokens_to_deposit > deposit_cap_headroom;
+    let max_user_tokens_to_deposit = max_user_tokens_to_deposit.min(deposit_cap_headroom);
+    if truncated_by_deposit_cap {
+        kmsg!(
+            "Deposit truncated to {} by the vault deposit cap {}",
+            max_user_tokens_to_deposit,
+            vault.deposit_cap
+        );
+    }
+
     let shares_to_mint = get_shares_to_mint(
         current_vault_aum,
         max_user_tokens_to_deposit,
@@ -95,7 +117,118 @@ where
         return err!(KaminoVaultError::DepositAmountsZeroShares);
     }
 
+    if shares_to_mint < min_shares_out {
+        kmsg!(
+            "Shares to mint {} less than min shares out {}",
+            shares_to_mint,
+            min_shares_out
+        );
+        return err!(KaminoVaultError::SharesToMintBelowMinSharesOut);
+    }
+
    
+    Ok(apply_deposit(
+        vault,
+        current_vault_aum,
+        user_tokens_to_deposit,
+        shares_to_mint,
+        crank_funds_to_deposit,
+        truncated_by_deposit_cap,
+    ))
+}
+
+#[inline(never)]
+pub fn mint<'info, T>(
+    vault: &mut VaultState,
+    reserves_iter: impl Iterator<Item = T>,
+    shares_to_mint: u64,
+    max_tokens_in: u64,
+    current_slot: Slot,
+    current_timestamp: u64,
+) -> Result<DepositEffects>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    require!(
+        shares_to_mint > 0,
+        KaminoVaultError::DepositAmountsZeroShares
+    );
+    require!(
+        vault.get_lifecycle_state()? == VaultLifecycleState::Active,
+        KaminoVaultError::VaultNotActive
+    );
+
+    refresh_rewards(vault, current_timestamp)?;
+
+    let num_reserve: u64 = vault
+        .get_reserves_with_allocation_count()
+        .try_into()
+        .unwrap();
+    let crank_funds_to_deposit = num_reserve * vault.crank_fund_fee_per_reserve;
+
+    let holdings = holdings(vault, reserves_iter, current_slot)?;
+
+    kmsg!(
+        "holdings available {} total invested {}",
+        holdings.available,
+        holdings.invested.total
+    );
+    kmsg!("shares_issued before mint {}", vault.shares_issued);
+
+    charge_fees(vault, &holdings.invested, current_timestamp)?;
+    let current_vault_aum = vault.compute_aum(&holdings.invested.total)?;
+
+    if vault.shares_issued != 0 && current_vault_aum == Fraction::ZERO {
+        return err!(KaminoVaultError::VaultAUMZero);
+    }
+
+    let user_tokens_to_deposit = common::compute_amount_to_deposit_from_shares_to_mint(
+        vault.shares_issued,
+        current_vault_aum,
+        shares_to_mint,
+    );
+
+    if user_tokens_to_deposit < vault.min_deposit_amount {
+        return err!(KaminoVaultError::DepositAmountBelowMinimum);
+    }
+
+    if user_tokens_to_deposit + crank_funds_to_deposit > max_tokens_in {
+        kmsg!(
+            "Tokens to deposit {} (crank funds {}) more than max tokens in {}",
+            user_tokens_to_deposit,
+            crank_funds_to_deposit,
+            max_tokens_in
+        );
+        return err!(KaminoVaultError::TokensToDepositAboveMaxTokensIn);
+    }
+
+    if user_tokens_to_deposit > common::deposit_cap_headroom(vault, current_vault_aum)? {
+        kmsg!(
+            "Tokens to deposit {} exceed the vault deposit cap {}",
+            user_tokens_to_deposit,
+            vault.deposit_cap
+        );
+        return err!(KaminoVaultError::DepositCapExceeded);
+    }
+
+    Ok(apply_deposit(
+        vault,
+        current_vault_aum,
+        user_tokens_to_deposit,
+        shares_to_mint,
+        crank_funds_to_deposit,
+        false,
+    ))
+}
+
+fn apply_deposit(
+    vault: &mut VaultState,
+    current_vault_aum: Fraction,
+    user_tokens_to_deposit: u64,
+    shares_to_mint: u64,
+    crank_funds_to_deposit: u64,
+    truncated_by_deposit_cap: bool,
+) -> DepositEffects {
     common::deposit_into_vault(vault, user_tokens_to_deposit);
     common::mint_shares(vault, shares_to_mint);
     common::update_prev_aum(
@@ -104,11 +237,12 @@ where
     );
     common::deposit_crank_funds(vault, crank_funds_to_deposit);
 
-    Ok(DepositEffects {
+    DepositEffects {
         shares_to_mint,
         token_to_deposit: user_tokens_to_deposit,
         crank_funds_to_deposit,
-    })
+        truncated_by_deposit_cap,
+    }
 }
 
 struct VaultHoldingsAndCurrentAUM {
@@ -148,6 +282,36 @@ where
     })
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepositAmount {
+    Tokens {
+        max_amount: u64,
+        min_shares_out: u64,
+    },
+    Shares {
+        shares_out: u64,
+        max_tokens_in: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WithdrawAmount {
+    Shares {
+        shares_amount: u64,
+        min_tokens_out: u64,
+    },
+    Tokens {
+        tokens_out: u64,
+        max_shares_in: u64,
+    },
+}
+
+pub struct ReserveToWithdrawFrom<'a> {
+    pub reserve_address: &'a Pubkey,
+    pub reserve_state: &'a Reserve,
+    pub ctokens_owned: u64,
+}
+
 #[allow(clippy::too_many_arguments)]
 #[inline(never)]
 pub fn withdraw<'info, T>(
@@ -160,14 +324,98 @@ pub fn withdraw<'info, T>(
     current_slot: Slot,
     number_of_shares: u64,
     reserve_ctokens_owned: Option<u64>,
+    min_tokens_out: u64,
 ) -> Result<WithdrawEffects>
 where
     T: AnyAccountLoader<'info, Reserve>,
 {
-    require!(
-        number_of_shares > 0,
-        KaminoVaultError::CannotWithdrawZeroShares
-    );
+    let reserves_to_withdraw_from = match (
+        reserve_address_to_withdraw_from,
+        reserve_state_to_withdraw_from,
+    ) {
+        (Some(reserve_address), Some(reserve_state)) => vec![ReserveToWithdrawFrom {
+            reserve_address,
+            reserve_state,
+            ctokens_owned: reserve_ctokens_owned.unwrap_or(0),
+        }],
+        _ => vec![],
+    };
+
+    withdraw_from_reserves(
+        vault,
+        global_config,
+        &reserves_to_withdraw_from,
+        reserves_iter,
+        current_timestamp,
+        current_slot,
+        WithdrawAmount::Shares {
+            shares_amount: number_of_shares,
+            min_tokens_out,
+        },
+    )
+}
+
+#[inline(never)]
+pub fn withdraw_from_reserves<'info, T>(
+    vault: &mut VaultState,
+    global_config: &GlobalConfig,
+    reserves_to_withdraw_from: &[ReserveToWithdrawFrom],
+    reserves_iter: impl Iterator<Item = T>,
+    current_timestamp: u64,
+    current_slot: Slot,
+    withdraw_amount: WithdrawAmount,
+) -> Result<WithdrawEffects>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    let min_withdraw_amount = vault.min_withdraw_amount;
+    withdraw_from_reserves_above_min_amount(
+        vault,
+        global_config,
+        reserves_to_withdraw_from,
+        reserves_iter,
+        current_timestamp,
+        current_slot,
+        withdraw_amount,
+        min_withdraw_amount,
+    )
+}
+
+// Withdrawal request fills are not bound by the vault minimum withdraw amount, the queue
+// must be able to pay out the liquidity as it comes back to the reserves
+#[allow(clippy::too_many_arguments)]
+#[inline(never)]
+fn withdraw_from_reserves_above_min_amount<'info, T>(
+    vault: &mut VaultState,
+    global_config: &GlobalConfig,
+    reserves_to_withdraw_from: &[ReserveToWithdrawFrom],
+    reserves_iter: impl Iterator<Item = T>,
+    current_timestamp: u64,
+    current_slot: Slot,
+    withdraw_amount: WithdrawAmount,
+    min_withdraw_amount: u64,
+) -> Result<WithdrawEffects>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    match withdraw_amount {
+        WithdrawAmount::Shares { shares_amount, .. } => require!(
+            shares_amount > 0,
+            KaminoVaultError::CannotWithdrawZeroShares
+        ),
+        WithdrawAmount::Tokens { tokens_out, .. } => {
+            require!(tokens_out > 0, KaminoVaultError::CannotWithdrawZeroLamports)
+        }
+    }
+
+    for (idx, reserve_to_withdraw_from) in reserves_to_withdraw_from.iter().enumerate() {
+        require!(
+            reserves_to_withdraw_from[..idx]
+                .iter()
+                .all(|r| r.reserve_address != reserve_to_withdraw_from.reserve_address),
+            KaminoVaultError::ReserveSpecifiedMultipleTimes
+        );
+    }
 
     refresh_rewards(vault, current_timestamp)?;
 
@@ -183,94 +431,134 @@ where
 
     let total_shares_supply = vault.shares_issued;
 
-    let total_liquidity_for_user_with_penalty = common::compute_user_total_received_on_withdraw(
-        total_shares_supply,
-        current_vault_aum,
-        number_of_shares,
-    );
-    require!(
-        total_liquidity_for_user_with_penalty > 0,
-        KaminoVaultError::CannotWithdrawZeroLamports
-    );
-
-    let withdrawal_penalty =
-        common::get_withdrawal_penalty(vault, global_config, total_liquidity_for_user_with_penalty);
-    require!(
-        withdrawal_penalty < total_liquidity_for_user_with_penalty,
-        KaminoVaultError::WithdrawAmountLessThanWithdrawalPenalty
-    );
-
-    let total_for_user = total_liquidity_for_user_with_penalty - withdrawal_penalty;
+    let (number_of_shares, total_for_user, withdrawal_penalty, min_tokens_out) =
+        match withdraw_amount {
+            WithdrawAmount::Shares {
+                shares_amount,
+                min_tokens_out,
+            } => {
+                let total_liquidity_for_user_with_penalty =
+                    common::compute_user_total_received_on_withdraw(
+                        total_shares_supply,
+                        current_vault_aum,
+                        shares_amount,
+                    );
+                require!(
+                    total_liquidity_for_user_with_penalty > 0,
+                    KaminoVaultError::CannotWithdrawZeroLamports
+                );
+
+                let withdrawal_penalty = common::get_withdrawal_penalty(
+                    vault,
+                    global_config,
+                    total_liquidity_for_user_with_penalty,
+                );
+                require!(
+                    withdrawal_penalty < total_liquidity_for_user_with_penalty,
+                    KaminoVaultError::WithdrawAmountLessThanWithdrawalPenalty
+                );
+
+                (
+                    shares_amount,
+                    total_liquidity_for_user_with_penalty - withdrawal_penalty,
+                    withdrawal_penalty,
+                    min_tokens_out,
+                )
+            }
+            WithdrawAmount::Tokens {
+                tokens_out,
+                max_shares_in,
+            } => {
+                let withdrawal_penalty =
+                    common::get_withdrawal_penalty_exclusive(vault, global_config, tokens_out);
+                let shares_amount = common::calculate_shares_to_burn(
+                    Fraction::from(tokens_out + withdrawal_penalty),
+                    total_shares_supply,
+                    current_vault_aum,
+                    u64::MAX,
+                );
+                if shares_amount > max_shares_in {
+                    kmsg!(
+                        "Shares to burn {} more than max shares in {}",
+                        shares_amount,
+                        max_shares_in
+                    );
+                    return err!(KaminoVaultError::SharesToBurnAboveMaxSharesIn);
+                }
+
+                (shares_amount, tokens_out, withdrawal_penalty, tokens_out)
+            }
+        };
 
    
    
     let available_to_send_to_user = holdings.available.min(total_for_user);
 
-    let (
-        invested_liquidity_to_send_to_user_f,
-        invested_liquidity_to_disinvest,
-        invested_to_disinvest_ctokens,
-        liquidity_rounding_error,
-    ) = if let Some(reserve_address) = reserve_address_to_withdraw_from {
+    let mut liquidity_left_to_send_to_user_f =
+        Fraction::from(total_for_user - available_to_send_to_user);
+    let mut invested_liquidity_to_send_to_user_f = Fraction::ZERO;
+    let mut invested_liquidity_to_send_to_user: u64 = 0;
+    let mut reserves_effects = Vec::with_capacity(reserves_to_withdraw_from.len());
+
+    for ReserveToWithdrawFrom {
+        reserve_address,
+        reserve_state,
+        ctokens_owned,
+    } in reserves_to_withdraw_from.iter()
+    {
         let invested_in_reserve = holdings.invested.in_reserve(reserve_address);
        
 
-        let invested_liquidity_to_send_to_user_f = invested_in_reserve
+        let reserve_liquidity_to_send_to_user_f = invested_in_reserve
             .liquidity_amount
-            .min(Fraction::from(total_for_user - available_to_send_to_user));
+            .min(liquidity_left_to_send_to_user_f);
+
+        if reserve_liquidity_to_send_to_user_f.eq(&Fraction::ZERO) {
+            reserves_effects.push(WithdrawFromReserveEffects {
+                reserve: **reserve_address,
+                ..Default::default()
+            });
+            continue;
+        }
 
-       
-        if invested_liquidity_to_send_to_user_f.eq(&Fraction::ZERO) {
-            (Fraction::ZERO, 0, 0, 0)
+        let exchange_rate = reserve_state.collateral_exchange_rate();
+
+        let invested_to_disinvest_ctokens: u64 = exchange_rate
+            .fraction_liquidity_to_collateral_ceil(reserve_liquidity_to_send_to_user_f.floor())
+            .to_ceil();
+        let invested_to_disinvest_ctokens = invested_to_disinvest_ctokens.min(*ctokens_owned);
+
+        let invested_liquidity_to_disinvest_f = exchange_rate
+            .fraction_collateral_to_liquidity(Fraction::from_num(invested_to_disinvest_ctokens));
+        let invested_liquidity_to_disinvest = invested_liquidity_to_disinvest_f.to_floor::<u64>();
+
+        let liquidity_rounding_error: u64 = if invested_liquidity_to_disinvest_f.frac()
+            > Fraction::ZERO
+            && invested_liquidity_to_disinvest_f.frac() > reserve_liquidity_to_send_to_user_f.frac()
+        {
+            1
         } else {
-            let exchange_rate = reserve_state_to_withdraw_from
-                .unwrap()
-                .collateral_exchange_rate();
+            0
+        };
 
-           
-            let invested_to_disinvest_ctokens: u64 = exchange_rate
-                .fraction_liquidity_to_collateral_ceil(invested_liquidity_to_send_to_user_f.floor())
-                .to_ceil();
-            let max_ctokens_to_disinvest = reserve_ctokens_owned.unwrap_or(0);
-           
-            let invested_to_disinvest_ctokens =
-                invested_to_disinvest_ctokens.min(max_ctokens_to_disinvest);
+        let reserve_liquidity_to_send_to_user: u64 = reserve_liquidity_to_send_to_user_f.to_floor();
 
-           
-            let invested_liquidity_to_disinvest_f = exchange_rate.fraction_collateral_to_liquidity(
-                Fraction::from_num(invested_to_disinvest_ctokens),
-            );
-            let invested_liquidity_to_disinvest =
-                invested_liquidity_to_disinvest_f.to_floor::<u64>();
+        liquidity_left_to_send_to_user_f -= reserve_liquidity_to_send_to_user_f;
+        invested_liquidity_to_send_to_user_f += reserve_liquidity_to_send_to_user_f;
+        invested_liquidity_to_send_to_user += reserve_liquidity_to_send_to_user;
 
-           
-            let liquidity_rounding_error: u64 = if invested_liquidity_to_disinvest_f.frac()
-                > Fraction::ZERO
-                && invested_liquidity_to_disinvest_f.frac()
-                    > invested_liquidity_to_send_to_user_f.frac()
-            {
-                1
-            } else {
-                0
-            };
-            (
-                invested_liquidity_to_send_to_user_f,
-                invested_liquidity_to_disinvest,
-                invested_to_disinvest_ctokens,
-                liquidity_rounding_error,
-            )
-        }
-    } else {
-        (Fraction::ZERO, 0, 0, 0)
-    };
+        reserves_effects.push(WithdrawFromReserveEffects {
+            reserve: **reserve_address,
+            invested_to_disinvest_ctokens,
+            invested_liquidity_to_send_to_user: reserve_liquidity_to_send_to_user
+                - liquidity_rounding_error,
+            invested_liquidity_to_disinvest,
+        });
+    }
 
-    let invested_liquidity_to_send_to_user: u64 = invested_liquidity_to_send_to_user_f.to_floor();
-   
     let theoretical_amount_to_send_to_user_f =
         Fraction::from(available_to_send_to_user + withdrawal_penalty)
             + invested_liquidity_to_send_to_user_f;
-    let actual_invested_liquidity_to_send_to_user =
-        invested_liquidity_to_send_to_user - liquidity_rounding_error;
 
     let shares_to_burn = common::calculate_shares_to_burn(
         theoretical_amount_to_send_to_user_f,
@@ -279,14 +567,23 @@ where
         number_of_shares,
     );
 
-    let disinvested_amount_left_in_vault =
-        invested_liquidity_to_disinvest - actual_invested_liquidity_to_send_to_user;
-
-   
     if shares_to_burn == 0 {
         return err!(KaminoVaultError::WithdrawResultsInZeroShares);
     }
 
+    let actual_invested_liquidity_to_send_to_user: u64 = reserves_effects
+        .iter()
+        .map(|r| r.invested_liquidity_to_send_to_user)
+        .sum();
+    let invested_to_disinvest_ctokens: u64 = reserves_effects
+        .iter()
+        .map(|r| r.invested_to_disinvest_ctokens)
+        .sum();
+    let invested_liquidity_to_disinvest: u64 = reserves_effects
+        .iter()
+        .map(|r| r.invested_liquidity_to_disinvest)
+        .sum();
+
     kmsg!("Available {}", holdings.available);
     kmsg!("Total invested {:?}", holdings.invested.total.to_display());
     kmsg!("Available to send to user {}", available_to_send_to_user);
@@ -299,24 +596,40 @@ where
     kmsg!("Expected c tokens {}", invested_to_disinvest_ctokens);
     kmsg!("Expected liq {}", invested_liquidity_to_disinvest);
 
-    if available_to_send_to_user + invested_liquidity_to_send_to_user <= vault.min_withdraw_amount {
+    if available_to_send_to_user + invested_liquidity_to_send_to_user <= min_withdraw_amount {
         return err!(KaminoVaultError::WithdrawAmountBelowMinimum);
     }
 
-    if let Some(reserve_address) = reserve_address_to_withdraw_from {
-        if !vault.is_allocated_to_reserve(*reserve_address) {
+    if available_to_send_to_user + actual_invested_liquidity_to_send_to_user < min_tokens_out {
+        kmsg!(
+            "Tokens to send {} less than min tokens out {}",
+            available_to_send_to_user + actual_invested_liquidity_to_send_to_user,
+            min_tokens_out
+        );
+        return err!(KaminoVaultError::TokensToSendBelowMinTokensOut);
+    }
+
+    for ReserveToWithdrawFrom {
+        reserve_address, ..
+    } in reserves_to_withdraw_from.iter()
+    {
+        if !vault.is_allocated_to_reserve(**reserve_address) {
             return err!(KaminoVaultError::ReserveNotPartOfAllocations);
         }
     }
 
    
     common::withdraw_from_accounting(vault, available_to_send_to_user, shares_to_burn);
-    common::deposit_into_vault(vault, disinvested_amount_left_in_vault);
-    if let Some(reserve_address) = reserve_address_to_withdraw_from {
+    for reserve_effects in reserves_effects.iter() {
+        common::deposit_into_vault(
+            vault,
+            reserve_effects.invested_liquidity_to_disinvest
+                - reserve_effects.invested_liquidity_to_send_to_user,
+        );
         common::withdraw_from_vault_allocation(
             vault,
-            invested_to_disinvest_ctokens,
-            reserve_address,
+            reserve_effects.invested_to_disinvest_ctokens,
+            &reserve_effects.reserve,
         )?;
     }
 
@@ -331,6 +644,253 @@ where
         invested_to_disinvest_ctokens,
         invested_liquidity_to_send_to_user: actual_invested_liquidity_to_send_to_user,
         invested_liquidity_to_disinvest,
+        reserves: reserves_effects,
+    })
+}
+
+pub fn request_withdrawal(
+    vault: &mut VaultState,
+    withdrawal_request: &mut WithdrawalRequest,
+    vault_address: Pubkey,
+    user: Pubkey,
+    shares_amount: u64,
+    current_timestamp: u64,
+) -> Result<()> {
+    require!(
+        shares_amount > 0,
+        KaminoVaultError::CannotWithdrawZeroShares
+    );
+
+    let queue = &mut vault.withdrawal_queue;
+    *withdrawal_request = WithdrawalRequest {
+        vault_state: vault_address,
+        user,
+        request_id: queue.next_request_id,
+        request_timestamp: current_timestamp,
+        shares_requested: shares_amount,
+        shares_escrowed: shares_amount,
+        ..Default::default()
+    };
+
+    queue.next_request_id += 1;
+    queue.escrowed_shares += shares_amount;
+
+    kmsg!(
+        "Withdrawal request {} for {} shares",
+        withdrawal_request.request_id,
+        shares_amount
+    );
+
+    Ok(())
+}
+
+pub fn cancel_withdrawal_request(
+    vault: &mut VaultState,
+    withdrawal_request: &mut WithdrawalRequest,
+) -> Result<u64> {
+    require!(
+        withdrawal_request.status == WithdrawalRequestStatus::Pending,
+        KaminoVaultError::WithdrawalRequestNotPending
+    );
+
+    let shares_to_return = withdrawal_request.shares_escrowed;
+    withdrawal_request.shares_escrowed = 0;
+    withdrawal_request.status = WithdrawalRequestStatus::Cancelled;
+
+    let queue = &mut vault.withdrawal_queue;
+    queue.escrowed_shares -= shares_to_return;
+    if queue.is_next_to_fulfil(withdrawal_request.request_id) {
+        queue.next_request_id_to_fulfil += 1;
+    }
+
+    kmsg!(
+        "Cancelled withdrawal request {} returning {} shares",
+        withdrawal_request.request_id,
+        shares_to_return
+    );
+
+    Ok(shares_to_return)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[inline(never)]
+pub fn fulfil_withdrawal_request<'info, T>(
+    vault: &mut VaultState,
+    global_config: &GlobalConfig,
+    withdrawal_request: &mut WithdrawalRequest,
+    reserve_address_to_withdraw_from: Option<&Pubkey>,
+    reserve_state_to_withdraw_from: Option<&Reserve>,
+    reserves_iter: impl Iterator<Item = T> + Clone,
+    current_timestamp: u64,
+    current_slot: Slot,
+    reserve_ctokens_owned: Option<u64>,
+) -> Result<WithdrawEffects>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    require!(
+        vault
+            .withdrawal_queue
+            .is_next_to_fulfil(withdrawal_request.request_id),
+        KaminoVaultError::WithdrawalRequestNotNextInQueue
+    );
+
+    if withdrawal_request.status == WithdrawalRequestStatus::Cancelled {
+        kmsg!(
+            "Skipping cancelled withdrawal request {}",
+            withdrawal_request.request_id
+        );
+        vault.withdrawal_queue.next_request_id_to_fulfil += 1;
+        return Ok(WithdrawEffects::default());
+    }
+
+    require!(
+        withdrawal_request.status == WithdrawalRequestStatus::Pending,
+        KaminoVaultError::WithdrawalRequestNotPending
+    );
+
+    refresh_rewards(vault, current_timestamp)?;
+
+    let VaultHoldingsAndCurrentAUM {
+        holdings,
+        current_vault_aum,
+    } = update_vault_fees_and_validate_holdings_aum(
+        vault,
+        reserves_iter.clone(),
+        current_timestamp,
+        current_slot,
+    )?;
+
+    if common::is_withdrawal_dust(
+        vault,
+        global_config,
+        current_vault_aum,
+        withdrawal_request.shares_escrowed,
+    ) {
+        complete_withdrawal_request(vault, withdrawal_request);
+        return Ok(WithdrawEffects::default());
+    }
+
+   
+    let reserves_to_withdraw_from = match (
+        reserve_address_to_withdraw_from,
+        reserve_state_to_withdraw_from,
+    ) {
+        (Some(reserve_address), Some(reserve_state)) => vec![ReserveToWithdrawFrom {
+            reserve_address,
+            reserve_state,
+            ctokens_owned: reserve_ctokens_owned.unwrap_or(0),
+        }],
+        _ => vec![],
+    };
+
+    let reserve_liquidity_available_for_request = reserves_to_withdraw_from
+        .first()
+        .map_or(Fraction::ZERO, |reserve_to_withdraw_from| {
+            let reserve_free_liquidity = reserve_to_withdraw_from
+                .reserve_state
+                .liquidity
+                .available_amount
+                .saturating_sub(1);
+            holdings
+                .invested
+                .in_reserve(reserve_to_withdraw_from.reserve_address)
+                .liquidity_amount
+                .min(Fraction::from(reserve_free_liquidity))
+        });
+    let liquidity_available_for_request =
+        Fraction::from(holdings.available) + reserve_liquidity_available_for_request;
+    let withdrawal_penalty_f = common::get_withdrawal_penalty_for_fraction_amount_exclusive(
+        vault,
+        global_config,
+        liquidity_available_for_request,
+    );
+    let shares_for_available_liquidity: u64 =
+        ((liquidity_available_for_request + withdrawal_penalty_f) / current_vault_aum
+            * Fraction::from(vault.shares_issued))
+        .to_floor();
+
+    let shares_to_withdraw = withdrawal_request
+        .shares_escrowed
+        .min(shares_for_available_liquidity);
+    require!(
+        shares_to_withdraw > 0,
+        KaminoVaultError::NotEnoughLiquidityToFulfilWithdrawalRequest
+    );
+
+    let withdraw_effects = withdraw_from_reserves_above_min_amount(
+        vault,
+        global_config,
+        &reserves_to_withdraw_from,
+        reserves_iter,
+        current_timestamp,
+        current_slot,
+        WithdrawAmount::Shares {
+            shares_amount: shares_to_withdraw,
+            min_tokens_out: 0,
+        },
+        0,
+    )?;
+
+    let tokens_for_user = withdraw_effects.available_to_send_to_user
+        + withdraw_effects.invested_liquidity_to_send_to_user;
+
+    withdrawal_request.shares_escrowed -= withdraw_effects.shares_to_burn;
+    withdrawal_request.shares_burned += withdraw_effects.shares_to_burn;
+    withdrawal_request.tokens_claimable += tokens_for_user;
+
+    vault.withdrawal_queue.escrowed_shares -= withdraw_effects.shares_to_burn;
+    vault.withdrawal_queue.claimable_tokens += tokens_for_user;
+
+    kmsg!(
+        "Fulfilled withdrawal request {} burning {} shares for {} tokens",
+        withdrawal_request.request_id,
+        withdraw_effects.shares_to_burn,
+        tokens_for_user
+    );
+
+    if withdrawal_request.shares_escrowed == 0
+        || common::is_withdrawal_dust(
+            vault,
+            global_config,
+            vault.get_prev_aum(),
+            withdrawal_request.shares_escrowed,
+        )
+    {
+        complete_withdrawal_request(vault, withdrawal_request);
+    }
+
+    Ok(withdraw_effects)
+}
+
+fn complete_withdrawal_request(vault: &mut VaultState, withdrawal_request: &mut WithdrawalRequest) {
+    withdrawal_request.status = WithdrawalRequestStatus::Fulfilled;
+    vault.withdrawal_queue.next_request_id_to_fulfil += 1;
+}
+
+pub fn claim_withdrawal_request(
+    vault: &mut VaultState,
+    withdrawal_request: &mut WithdrawalRequest,
+) -> Result<ClaimWithdrawalRequestEffects> {
+    require!(
+        withdrawal_request.status != WithdrawalRequestStatus::Pending
+            && vault
+                .withdrawal_queue
+                .was_processed(withdrawal_request.request_id),
+        KaminoVaultError::WithdrawalRequestNotClaimable
+    );
+
+    let tokens_to_send_to_user = withdrawal_request.tokens_claimable;
+    let shares_to_return_to_user = withdrawal_request.shares_escrowed;
+    withdrawal_request.tokens_claimable = 0;
+    withdrawal_request.shares_escrowed = 0;
+
+    vault.withdrawal_queue.claimable_tokens -= tokens_to_send_to_user;
+    vault.withdrawal_queue.escrowed_shares -= shares_to_return_to_user;
+
+    Ok(ClaimWithdrawalRequestEffects {
+        tokens_to_send_to_user,
+        shares_to_return_to_user,
     })
 }
 
@@ -343,6 +903,33 @@ pub fn withdraw_pending_fees<'info, T>(
     current_slot: Slot,
     current_timestamp: u64,
 ) -> Result<WithdrawPendingFeesEffects>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    // The fees of a vault with fee recipients only leave through `distribute_pending_fees`
+    require!(
+        vault.get_fee_recipients_count() == 0,
+        KaminoVaultError::FeeRecipientsConfigured
+    );
+
+    take_pending_fees(
+        vault,
+        reserve_address_to_withdraw_from,
+        reserve_state_to_withdraw_from,
+        reserves_iter,
+        current_slot,
+        current_timestamp,
+    )
+}
+
+fn take_pending_fees<'info, T>(
+    vault: &mut VaultState,
+    reserve_address_to_withdraw_from: &Pubkey,
+    reserve_state_to_withdraw_from: &Reserve,
+    reserves_iter: impl Iterator<Item = T>,
+    current_slot: Slot,
+    current_timestamp: u64,
+) -> Result<WithdrawPendingFeesEffects>
 where
     T: AnyAccountLoader<'info, Reserve>,
 {
@@ -413,12 +1000,217 @@ where
             - Fraction::from(invested_liquidity_to_send_to_user),
     );
 
-    Ok(WithdrawPendingFeesEffects {
-        available_to_send_to_user,
-        invested_to_disinvest_ctokens,
-        invested_liquidity_to_send_to_user: actual_invested_liquidity_to_send_to_user,
-        invested_liquidity_to_disinvest,
-    })
+    Ok(WithdrawPendingFeesEffects {
+        available_to_send_to_user,
+        invested_to_disinvest_ctokens,
+        invested_liquidity_to_send_to_user: actual_invested_liquidity_to_send_to_user,
+        invested_liquidity_to_disinvest,
+    })
+}
+
+pub fn distribute_pending_fees<'info, T>(
+    vault: &mut VaultState,
+    reserve_address_to_withdraw_from: &Pubkey,
+    reserve_state_to_withdraw_from: &Reserve,
+    reserves_iter: impl Iterator<Item = T>,
+    current_slot: Slot,
+    current_timestamp: u64,
+) -> Result<DistributePendingFeesEffects>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    require!(
+        vault.get_fee_recipients_count() > 0,
+        KaminoVaultError::FeeRecipientsNotConfigured
+    );
+
+    let withdraw_pending_fees_effects = take_pending_fees(
+        vault,
+        reserve_address_to_withdraw_from,
+        reserve_state_to_withdraw_from,
+        reserves_iter,
+        current_slot,
+        current_timestamp,
+    )?;
+
+    let total_fees_to_distribute = withdraw_pending_fees_effects.available_to_send_to_user
+        + withdraw_pending_fees_effects.invested_liquidity_to_send_to_user;
+    let amounts_to_recipients =
+        common::split_between_fee_recipients(vault, total_fees_to_distribute);
+
+    Ok(DistributePendingFeesEffects {
+        withdraw_pending_fees_effects,
+        amounts_to_recipients,
+    })
+}
+
+/// Converts the pending fees to shares, the returned shares are counted in `shares_issued`
+/// and must be minted to the fee shares recipients in the same instruction
+pub fn convert_pending_fees_to_shares<'info, T>(
+    vault: &mut VaultState,
+    reserves_iter: impl Iterator<Item = T>,
+    current_slot: Slot,
+    current_timestamp: u64,
+) -> Result<u64>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    require!(
+        vault.fee_shares_recipient != Pubkey::default() || vault.get_fee_recipients_count() > 0,
+        KaminoVaultError::FeeSharesRecipientNotSet
+    );
+
+    refresh_rewards(vault, current_timestamp)?;
+    let holdings = holdings(vault, reserves_iter, current_slot)?;
+    charge_fees(vault, &holdings.invested, current_timestamp)?;
+
+    let pending_fees = vault.get_pending_fees();
+    let fee_shares =
+        common::get_fee_shares_to_mint(pending_fees, holdings.total_sum, vault.shares_issued);
+
+    msg!(
+        "Converting {} pending fees to {} fee shares",
+        pending_fees.to_display(),
+        fee_shares
+    );
+
+    common::update_pending_fees(vault, Fraction::ZERO);
+    vault.shares_issued += fee_shares;
+    common::update_prev_aum(vault, holdings.total_sum);
+    common::update_high_water_mark(vault, holdings.total_sum);
+
+    Ok(fee_shares)
+}
+
+pub fn claim_fee_shares(vault: &mut VaultState) -> Result<u64> {
+    let fee_shares = vault.pending_fee_shares;
+    vault.pending_fee_shares = 0;
+
+    msg!("Claiming {} fee shares", fee_shares);
+
+    Ok(fee_shares)
+}
+
+pub fn close_vault(vault: &mut VaultState) -> Result<()> {
+    require!(
+        vault.is_withdraw_only()?,
+        KaminoVaultError::InvalidLifecycleStateTransition
+    );
+    // The shares of the initial deposit made at init are never minted, they are all that
+    // is left once every holder has exited
+    require!(
+        vault.shares_issued == INITIAL_DEPOSIT_AMOUNT
+            && vault.pending_fee_shares == 0
+            && vault.first_loss_shares == 0
+            && vault.first_loss_shares_pending_burn == 0
+            && vault.withdrawal_queue.escrowed_shares == 0
+            && vault.withdrawal_queue.claimable_tokens == 0,
+        KaminoVaultError::VaultNotEmpty
+    );
+    require!(
+        vault
+            .vault_allocation_strategy
+            .iter()
+            .all(|allocation| allocation.ctoken_allocation == 0),
+        KaminoVaultError::VaultNotEmpty
+    );
+    // The admin only receives the crank funds and the tokens backing the initial deposit,
+    // fees and rewards leave through their own instructions first
+    require!(
+        vault.get_pending_fees() < Fraction::ONE,
+        KaminoVaultError::PendingFeesNotWithdrawn
+    );
+    require!(
+        vault.reward_info.rewards_available == 0,
+        KaminoVaultError::RewardsNotWithdrawn
+    );
+
+    vault.lifecycle_state = VaultLifecycleState::Closed.into();
+
+    Ok(())
+}
+
+pub fn deposit_first_loss_shares(vault: &mut VaultState, shares_amount: u64) -> Result<()> {
+    vault.first_loss_shares += shares_amount;
+
+    msg!(
+        "Deposited {} first-loss shares, total {}",
+        shares_amount,
+        vault.first_loss_shares
+    );
+
+    Ok(())
+}
+
+pub fn request_first_loss_withdrawal(vault: &mut VaultState, current_timestamp: u64) -> Result<u64> {
+    require!(
+        vault.first_loss_shares > 0,
+        KaminoVaultError::NotEnoughFirstLossShares
+    );
+
+    // The shares keep absorbing losses during the notice period, the depositor cannot leave
+    // right before a loss it sees coming is realised
+    let unlock_timestamp = current_timestamp + FIRST_LOSS_WITHDRAWAL_NOTICE_SECONDS;
+    vault.first_loss_withdrawal_unlock_timestamp = unlock_timestamp;
+
+    msg!(
+        "Requested first-loss shares withdrawal, unlocked at {}",
+        unlock_timestamp
+    );
+
+    Ok(unlock_timestamp)
+}
+
+pub fn withdraw_first_loss_shares<'info, T>(
+    vault: &mut VaultState,
+    reserves_iter: impl Iterator<Item = T>,
+    current_slot: Slot,
+    current_timestamp: u64,
+    shares_amount: u64,
+) -> Result<()>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    let unlock_timestamp = vault.first_loss_withdrawal_unlock_timestamp;
+    require!(
+        unlock_timestamp != 0,
+        KaminoVaultError::FirstLossWithdrawalNotRequested
+    );
+    require!(
+        current_timestamp >= unlock_timestamp
+            && current_timestamp < unlock_timestamp + FIRST_LOSS_WITHDRAWAL_WINDOW_SECONDS,
+        KaminoVaultError::FirstLossWithdrawalNotInWindow
+    );
+
+    // Losses realised since the last fee charge are absorbed before any share is released
+    refresh_rewards(vault, current_timestamp)?;
+    let holdings = holdings(vault, reserves_iter, current_slot)?;
+    charge_fees(vault, &holdings.invested, current_timestamp)?;
+
+    require_gte!(
+        vault.first_loss_shares,
+        shares_amount,
+        KaminoVaultError::NotEnoughFirstLossShares
+    );
+    vault.first_loss_shares -= shares_amount;
+    vault.first_loss_withdrawal_unlock_timestamp = 0;
+
+    msg!(
+        "Withdrew {} first-loss shares, remaining {}",
+        shares_amount,
+        vault.first_loss_shares
+    );
+
+    Ok(())
+}
+
+pub fn burn_absorbed_first_loss_shares(vault: &mut VaultState) -> Result<u64> {
+    let shares_to_burn = vault.first_loss_shares_pending_burn;
+    vault.first_loss_shares_pending_burn = 0;
+
+    msg!("Burning {} absorbed first-loss shares", shares_to_burn);
+
+    Ok(shares_to_burn)
 }
 
 pub fn give_up_pending_fee<'info, T>(
@@ -506,6 +1298,50 @@ where
         return err!(KaminoVaultError::InvestTooSoon);
     }
 
+    let (liquidity_f, direction) = compute_invest_move(vault, &invested, reserve_address)?;
+
+    if liquidity_f <= vault.min_invest_amount {
+        return err!(KaminoVaultError::InvestAmountBelowMinimum);
+    }
+
+    match direction {
+        InvestingDirection::Add if vault.is_withdraw_only()? => {
+            return err!(KaminoVaultError::VaultIsWithdrawOnly);
+        }
+        InvestingDirection::Add if vault.vault_allows_invest_in_whitelisted_reserves_only() => {
+            let reserve_whitelist_entry =
+                reserve_whitelist_entry.ok_or(KaminoVaultError::ReserveNotWhitelisted)?;
+            require!(
+                reserve_whitelist_entry.is_invest_whitelisted(),
+                KaminoVaultError::ReserveNotWhitelisted
+            );
+        }
+        InvestingDirection::Add | InvestingDirection::Subtract => {}
+    }
+
+    if matches!(direction, InvestingDirection::Add) {
+        if let Some(violation) = common::reserve_health_violation(vault, reserve, reserve_address)?
+        {
+            return Err(error!(violation));
+        }
+    }
+
+    execute_invest_move(
+        vault,
+        reserve,
+        reserve_address,
+        liquidity_f,
+        direction,
+        current_slot,
+    )
+}
+
+fn compute_invest_move(
+    vault: &VaultState,
+    invested: &Invested,
+    reserve_address: &Pubkey,
+) -> Result<(Fraction, InvestingDirection)> {
+    let allocation_for_reserve = vault.allocation_for_reserve(reserve_address)?;
     let invested_in_reserve = invested.in_reserve(reserve_address);
 
     let actual_tokens_invested = invested_in_reserve.liquidity_amount;
@@ -534,22 +1370,18 @@ where
         (diff.min(Fraction::from(available)), InvestingDirection::Add)
     };
 
-    if liquidity_f <= vault.min_invest_amount {
-        return err!(KaminoVaultError::InvestAmountBelowMinimum);
-    }
-
-    match direction {
-        InvestingDirection::Add if vault.vault_allows_invest_in_whitelisted_reserves_only() => {
-            let reserve_whitelist_entry =
-                reserve_whitelist_entry.ok_or(KaminoVaultError::ReserveNotWhitelisted)?;
-            require!(
-                reserve_whitelist_entry.is_invest_whitelisted(),
-                KaminoVaultError::ReserveNotWhitelisted
-            );
-        }
-        InvestingDirection::Add | InvestingDirection::Subtract => {}
-    }
+    Ok((liquidity_f, direction))
+}
 
+fn execute_invest_move(
+    vault: &mut VaultState,
+    reserve: &Reserve,
+    reserve_address: &Pubkey,
+    liquidity_f: Fraction,
+    direction: InvestingDirection,
+    current_slot: Slot,
+) -> Result<InvestEffects> {
+    let allocation_for_reserve = vault.allocation_for_reserve(reserve_address)?;
     let exchange_rate = reserve.collateral_exchange_rate();
     let collateral_amount = if allocation_for_reserve.target_allocation_weight == 0 {
         allocation_for_reserve.ctoken_allocation
@@ -590,6 +1422,7 @@ where
         rounding_loss = 0;
     }
 
+    common::update_max_liquidity_per_ctoken(vault, reserve, reserve_address)?;
     vault.set_allocation_last_invest_slot(reserve_address, current_slot)?;
     Ok(InvestEffects {
         liquidity_amount,
@@ -599,6 +1432,115 @@ where
     })
 }
 
+pub struct ReserveToRebalance<'a> {
+    pub reserve_address: &'a Pubkey,
+    pub reserve_state: &'a Reserve,
+    pub reserve_whitelist_entry: Option<&'a ReserveWhitelistEntry>,
+}
+
+#[inline(never)]
+pub fn rebalance<'info, T>(
+    vault: &mut VaultState,
+    reserves_iter: impl Iterator<Item = T>,
+    reserves_to_rebalance: &[ReserveToRebalance],
+    current_slot: Slot,
+    current_timestamp: u64,
+) -> Result<Vec<RebalanceReserveEffects>>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    for (idx, reserve_to_rebalance) in reserves_to_rebalance.iter().enumerate() {
+        require!(
+            reserves_to_rebalance[..idx]
+                .iter()
+                .all(|r| r.reserve_address != reserve_to_rebalance.reserve_address),
+            KaminoVaultError::ReserveSpecifiedMultipleTimes
+        );
+        if !vault.is_allocated_to_reserve(*reserve_to_rebalance.reserve_address) {
+            return err!(KaminoVaultError::ReserveNotPartOfAllocations);
+        }
+    }
+
+    let holdings = holdings(vault, reserves_iter, current_slot)?;
+    let invested = holdings.invested;
+
+    charge_fees(vault, &invested, current_timestamp)?;
+
+    vault.refresh_target_allocations(&invested)?;
+
+    let mut rebalance_effects = Vec::with_capacity(reserves_to_rebalance.len());
+
+    for direction_to_execute in [InvestingDirection::Subtract, InvestingDirection::Add] {
+        for (reserve_index, reserve_to_rebalance) in reserves_to_rebalance.iter().enumerate() {
+            let ReserveToRebalance {
+                reserve_address,
+                reserve_state,
+                reserve_whitelist_entry,
+            } = reserve_to_rebalance;
+
+            let last_invest_slot = vault
+                .allocation_for_reserve(reserve_address)?
+                .last_invest_slot;
+            if current_slot < last_invest_slot + vault.min_invest_delay_slots {
+                kmsg!("Skipping reserve {}, invested too soon", reserve_address);
+                continue;
+            }
+
+            let (liquidity_f, direction) = compute_invest_move(vault, &invested, reserve_address)?;
+
+            let is_direction_to_execute = matches!(
+                (direction, direction_to_execute),
+                (InvestingDirection::Add, InvestingDirection::Add)
+                    | (InvestingDirection::Subtract, InvestingDirection::Subtract)
+            );
+            if !is_direction_to_execute || liquidity_f <= vault.min_invest_amount {
+                continue;
+            }
+
+            if matches!(direction, InvestingDirection::Add) && vault.is_withdraw_only()? {
+                kmsg!(
+                    "Skipping reserve {}, vault is withdraw-only",
+                    reserve_address
+                );
+                continue;
+            }
+
+            if matches!(direction, InvestingDirection::Add)
+                && vault.vault_allows_invest_in_whitelisted_reserves_only()
+                && !reserve_whitelist_entry.map_or(false, |e| e.is_invest_whitelisted())
+            {
+                kmsg!("Skipping reserve {}, not whitelisted", reserve_address);
+                continue;
+            }
+
+            if matches!(direction, InvestingDirection::Add) {
+                if let Some(violation) =
+                    common::reserve_health_violation(vault, reserve_state, reserve_address)?
+                {
+                    kmsg!("Skipping reserve {}, {:?}", reserve_address, violation);
+                    continue;
+                }
+            }
+
+            let invest_effects = execute_invest_move(
+                vault,
+                reserve_state,
+                reserve_address,
+                liquidity_f,
+                direction,
+                current_slot,
+            )?;
+
+            rebalance_effects.push(RebalanceReserveEffects {
+                reserve_index,
+                invest_effects,
+            });
+        }
+    }
+
+    Ok(rebalance_effects)
+}
+
 pub struct RedeemInKindParams<'a, T> {
     pub vault_state: &'a mut VaultState,
     pub global_config: &'a GlobalConfig,
@@ -606,6 +1548,7 @@ pub struct RedeemInKindParams<'a, T> {
     pub reserve_state: &'a Reserve,
     pub reserves_iter: T,
     pub shares_amount: u64,
+    pub min_ctokens_out: u64,
     pub clock: &'a Clock,
 }
 
@@ -618,6 +1561,7 @@ pub fn redeem_in_kind<'info, T>(
         reserve_state,
         reserves_iter,
         shares_amount,
+        min_ctokens_out,
         clock,
     }: RedeemInKindParams<'_, T>,
 ) -> Result<RedeemInKindEffects>
@@ -736,6 +1680,10 @@ where
         return err!(KaminoVaultError::WithdrawAmountBelowMinimum);
     }
 
+    if ctokens_to_send_to_user < min_ctokens_out {
+        return err!(KaminoVaultError::CTokensToSendBelowMinCTokensOut);
+    }
+
    
     common::burn_shares(vault_state, shares_to_burn);
     common::withdraw_from_vault_allocation(vault_state, ctokens_to_send_to_user, reserve_address)?;
@@ -755,6 +1703,65 @@ where
     })
 }
 
+#[inline(never)]
+pub fn convert_to_shares<'info, T>(
+    vault: &mut VaultState,
+    reserves_iter: impl Iterator<Item = T>,
+    assets: u64,
+    current_slot: Slot,
+    current_timestamp: u64,
+) -> Result<u64>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    let current_vault_aum =
+        refresh_and_compute_aum(vault, reserves_iter, current_slot, current_timestamp)?;
+
+    get_shares_to_mint(current_vault_aum, assets, vault.shares_issued)
+}
+
+#[inline(never)]
+pub fn convert_to_assets<'info, T>(
+    vault: &mut VaultState,
+    reserves_iter: impl Iterator<Item = T>,
+    shares: u64,
+    current_slot: Slot,
+    current_timestamp: u64,
+) -> Result<u64>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    let current_vault_aum =
+        refresh_and_compute_aum(vault, reserves_iter, current_slot, current_timestamp)?;
+
+    if vault.shares_issued == 0 {
+        return Ok(shares);
+    }
+
+    Ok(common::compute_user_total_received_on_withdraw(
+        vault.shares_issued,
+        current_vault_aum,
+        shares,
+    ))
+}
+
+fn refresh_and_compute_aum<'info, T>(
+    vault: &mut VaultState,
+    reserves_iter: impl Iterator<Item = T>,
+    current_slot: Slot,
+    current_timestamp: u64,
+) -> Result<Fraction>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    refresh_rewards(vault, current_timestamp)?;
+
+    let holdings = holdings(vault, reserves_iter, current_slot)?;
+    charge_fees(vault, &holdings.invested, current_timestamp)?;
+
+    vault.compute_aum(&holdings.invested.total)
+}
+
 pub fn charge_fees(vault: &mut VaultState, invested: &Invested, timestamp: u64) -> Result<()> {
     if vault.last_fee_charge_timestamp == 0 {
         vault.last_fee_charge_timestamp = timestamp;
@@ -766,6 +1773,8 @@ pub fn charge_fees(vault: &mut VaultState, invested: &Invested, timestamp: u64)
     let new_aum = vault.compute_aum(&invested.total).unwrap_or(Fraction::ZERO);
     let prev_aum = vault.get_prev_aum();
 
+    common::absorb_loss_with_first_loss_shares(vault, prev_aum, new_aum);
+
    
     crate::kmsg_sized!(
         300,
@@ -797,13 +1806,29 @@ pub fn charge_fees(vault: &mut VaultState, invested: &Invested, timestamp: u64)
 
    
     let earned_interest = new_aum.saturating_sub(prev_aum);
-    let perf_charge = Fraction::from_bps(vault.performance_fee_bps) * earned_interest;
+    let earned_above_high_water_mark =
+        common::earned_above_high_water_mark(vault, new_aum).min(earned_interest);
+
+    let hurdle_amount = if seconds_passed == 0 {
+        Fraction::ZERO
+    } else {
+        let hurdle_yearly = Fraction::from_bps(vault.performance_fee_hurdle_bps);
+        let hurdle =
+            hurdle_yearly * u128::from(seconds_passed) / SECONDS_PER_YEAR.ceil().to_u128().unwrap();
+        Fraction::from(prev_aum).mul(hurdle)
+    };
+    let earned_above_hurdle = earned_above_high_water_mark.saturating_sub(hurdle_amount);
+    let hurdle_excluded_interest = earned_above_high_water_mark - earned_above_hurdle;
+
+    let perf_charge = Fraction::from_bps(vault.performance_fee_bps) * earned_above_hurdle;
 
     crate::kmsg_sized!(
-        250,
-        "perf_charge {} earned_interest {}",
+        300,
+        "perf_charge {} earned_interest {} earned_above_high_water_mark {} hurdle_amount {}",
         perf_charge.to_display(),
-        earned_interest.to_display()
+        earned_interest.to_display(),
+        earned_above_high_water_mark.to_display(),
+        hurdle_amount.to_display()
     );
 
     vault.set_cumulative_mgmt_fees(vault.get_cumulative_mgmt_fees().saturating_add(mgmt_charge));
@@ -813,11 +1838,28 @@ pub fn charge_fees(vault: &mut VaultState, invested: &Invested, timestamp: u64)
             .get_cumulative_earned_interest()
             .saturating_add(earned_interest),
     );
+    vault.set_cumulative_hurdle_excluded_interest(
+        vault
+            .get_cumulative_hurdle_excluded_interest()
+            .saturating_add(hurdle_excluded_interest),
+    );
 
     let new_fees = (mgmt_charge + perf_charge).min(new_aum);
-    let pending_fees = vault.get_pending_fees() + new_fees;
-    vault.set_pending_fees(pending_fees);
-    update_prev_aum(vault, new_aum - new_fees);
+    if vault.vault_takes_fees_in_shares() {
+        // The fees stay invested, the existing holders are diluted by the fee shares instead
+        let fee_shares = common::get_fee_shares_to_mint(new_fees, new_aum, vault.shares_issued);
+        crate::kmsg_sized!(100, "fee_shares {}", fee_shares);
+
+        vault.shares_issued += fee_shares;
+        vault.pending_fee_shares += fee_shares;
+        update_prev_aum(vault, new_aum);
+        common::update_high_water_mark(vault, new_aum);
+    } else {
+        let pending_fees = vault.get_pending_fees() + new_fees;
+        vault.set_pending_fees(pending_fees);
+        update_prev_aum(vault, new_aum - new_fees);
+        common::update_high_water_mark(vault, new_aum - new_fees);
+    }
     vault.last_fee_charge_timestamp = timestamp;
 
     Ok(())
@@ -895,8 +1937,8 @@ pub fn withdraw_rewards(vault: &mut VaultState, amount: u64, current_ts: u64) ->
 pub mod common {
     use anchor_lang::{error, Result};
     use kamino_lending::{
-        utils::{AnyAccountLoader, FULL_BPS},
-        PriceStatusFlags, Reserve,
+        utils::{AnyAccountLoader, FULL_BPS, U256},
+        PriceStatusFlags, Reserve, ReserveStatus,
     };
     use solana_program::pubkey::Pubkey;
 
@@ -904,6 +1946,180 @@ pub mod common {
 
     use super::*;
 
+    pub fn liquidity_per_ctoken(reserve: &Reserve) -> Fraction {
+        reserve
+            .collateral_exchange_rate()
+            .fraction_collateral_to_liquidity(Fraction::ONE)
+    }
+
+    pub fn update_max_liquidity_per_ctoken(
+        vault: &mut VaultState,
+        reserve: &Reserve,
+        reserve_address: &Pubkey,
+    ) -> Result<()> {
+        let idx = vault
+            .get_reserve_idx_in_allocation(reserve_address)
+            .ok_or_else(|| error!(KaminoVaultError::ReserveNotPartOfAllocations))?;
+        let allocation = vault.get_reserve_allocation_mut(idx)?;
+
+        let liquidity_per_ctoken = liquidity_per_ctoken(reserve);
+        if liquidity_per_ctoken > allocation.get_max_liquidity_per_ctoken() {
+            allocation.set_max_liquidity_per_ctoken(liquidity_per_ctoken);
+        }
+
+        Ok(())
+    }
+
+    // A drop of the ctoken value below the highest one observed by the vault means the reserve
+    // socialized a loss, the reserve stays excluded until interest brings the value back
+    pub fn reserve_health_violation(
+        vault: &VaultState,
+        reserve: &Reserve,
+        reserve_address: &Pubkey,
+    ) -> Result<Option<KaminoVaultError>> {
+        if vault.max_reserve_utilization_bps > 0 {
+            let utilization_rate = reserve.liquidity.utilization_rate();
+            if utilization_rate > Fraction::from_bps(vault.max_reserve_utilization_bps) {
+                return Ok(Some(KaminoVaultError::ReserveUtilizationTooHigh));
+            }
+        }
+
+        if vault.max_reserve_borrow_rate_bps > 0 {
+            let borrow_rate = reserve.current_borrow_rate()?;
+            if borrow_rate > Fraction::from_bps(vault.max_reserve_borrow_rate_bps) {
+                return Ok(Some(KaminoVaultError::ReserveBorrowRateTooHigh));
+            }
+        }
+
+        if vault.vault_excludes_distressed_reserves() {
+            if reserve.config.status() != ReserveStatus::Active {
+                return Ok(Some(KaminoVaultError::ReserveDistressed));
+            }
+
+            let max_liquidity_per_ctoken = vault
+                .allocation_for_reserve(reserve_address)?
+                .get_max_liquidity_per_ctoken();
+            if liquidity_per_ctoken(reserve) < max_liquidity_per_ctoken {
+                return Ok(Some(KaminoVaultError::ReserveDistressed));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn earned_above_high_water_mark(vault: &VaultState, aum: Fraction) -> Fraction {
+        let high_water_mark = vault.get_high_water_mark();
+        if high_water_mark == Fraction::ZERO {
+            // Vaults created before the mark existed start tracking it from the last charge
+            return aum.saturating_sub(vault.get_prev_aum());
+        }
+
+        aum.saturating_sub(high_water_mark.saturating_mul_int(u128::from(vault.shares_issued)))
+    }
+
+    pub fn update_high_water_mark(vault: &mut VaultState, aum: Fraction) {
+        if vault.shares_issued == 0 {
+            return;
+        }
+
+        let share_price = aum / u128::from(vault.shares_issued);
+        if share_price > vault.get_high_water_mark() {
+            vault.set_high_water_mark(share_price);
+        }
+    }
+
+    pub fn absorb_loss_with_first_loss_shares(
+        vault: &mut VaultState,
+        prev_aum: Fraction,
+        new_aum: Fraction,
+    ) {
+        if new_aum >= prev_aum || vault.first_loss_shares == 0 || vault.shares_issued == 0 {
+            return;
+        }
+
+        let loss = prev_aum - new_aum;
+        let shares_for_loss = U256::from(vault.shares_issued) * U256::from(loss.to_bits())
+            / U256::from(prev_aum.to_bits());
+        let shares_absorbed = u64::try_from(u128::try_from(shares_for_loss).unwrap())
+            .unwrap_or(u64::MAX)
+            .min(vault.first_loss_shares);
+        let loss_absorbed = prev_aum.full_mul_int_ratio(shares_absorbed, vault.shares_issued);
+
+        crate::kmsg_sized!(
+            200,
+            "loss {} absorbed {} with first-loss shares {}",
+            loss.to_display(),
+            loss_absorbed.to_display(),
+            shares_absorbed
+        );
+
+        vault.shares_issued -= shares_absorbed;
+        vault.first_loss_shares -= shares_absorbed;
+        vault.first_loss_shares_pending_burn += shares_absorbed;
+        vault.set_cumulative_first_loss_absorbed(
+            vault
+                .get_cumulative_first_loss_absorbed()
+                .saturating_add(loss_absorbed),
+        );
+    }
+
+    pub fn get_fee_shares_to_mint(fees: Fraction, aum: Fraction, shares_issued: u64) -> u64 {
+        if shares_issued == 0 || fees == Fraction::ZERO || fees >= aum {
+            return 0;
+        }
+
+        let fee_shares = U256::from(shares_issued) * U256::from(fees.to_bits())
+            / U256::from((aum - fees).to_bits());
+
+        u64::try_from(u128::try_from(fee_shares).unwrap()).unwrap()
+    }
+
+    /// Splits `fee_shares` between the fee recipients when the vault has some, otherwise
+    /// they all go to the fee shares recipient. Returns the owner of the shares token
+    /// account each amount is minted to
+    pub fn split_fee_shares(vault: &VaultState, fee_shares: u64) -> Result<Vec<(Pubkey, u64)>> {
+        if vault.get_fee_recipients_count() == 0 {
+            require!(
+                vault.fee_shares_recipient != Pubkey::default(),
+                KaminoVaultError::FeeSharesRecipientNotSet
+            );
+            return Ok(vec![(vault.fee_shares_recipient, fee_shares)]);
+        }
+
+        let amounts = split_between_fee_recipients(vault, fee_shares);
+        Ok(vault
+            .fee_recipients
+            .iter()
+            .zip(amounts)
+            .filter(|(r, _)| r.recipient != Pubkey::default())
+            .map(|(r, amount)| (r.recipient, amount))
+            .collect())
+    }
+
+    pub fn split_between_fee_recipients(
+        vault: &VaultState,
+        amount: u64,
+    ) -> [u64; MAX_FEE_RECIPIENTS] {
+        let mut amounts = [0; MAX_FEE_RECIPIENTS];
+        let mut distributed = 0;
+        for (recipient_amount, fee_recipient) in amounts
+            .iter_mut()
+            .zip(vault.fee_recipients.iter())
+            .filter(|(_, r)| r.recipient != Pubkey::default())
+        {
+            *recipient_amount = u64::try_from(
+                u128::from(amount) * u128::from(fee_recipient.bps) / u128::from(FULL_BPS),
+            )
+            .unwrap();
+            distributed += *recipient_amount;
+        }
+
+        // Recipients are stored contiguously, the rounding dust goes to the first one
+        amounts[0] += amount - distributed;
+
+        amounts
+    }
+
     pub fn get_shares_to_mint(
         holdings_aum: Fraction,
         user_token_amount: u64,
@@ -1110,13 +2326,18 @@ pub mod common {
         .to_ceil::<u64>()
     }
 
-
-
-
-
-
-
-
+    pub fn get_withdrawal_penalty_exclusive(
+        vault: &VaultState,
+        global_config: &GlobalConfig,
+        total_amount_withdrawn_without_penalty: u64,
+    ) -> u64 {
+        get_withdrawal_penalty_for_fraction_amount_exclusive(
+            vault,
+            global_config,
+            Fraction::from(total_amount_withdrawn_without_penalty),
+        )
+        .to_ceil::<u64>()
+    }
 
     pub fn get_withdrawal_penalty_for_fraction_amount_exclusive(
         vault: &VaultState,
@@ -1133,7 +2354,18 @@ pub mod common {
         withdrawal_penalty_from_bps.max(Fraction::from(penalty_lamports))
     }
 
-
+    pub fn is_withdrawal_dust(
+        vault: &VaultState,
+        global_config: &GlobalConfig,
+        vault_aum: Fraction,
+        shares: u64,
+    ) -> bool {
+        let total_for_user =
+            compute_user_total_received_on_withdraw(vault.shares_issued, vault_aum, shares);
+        let withdrawal_penalty = get_withdrawal_penalty(vault, global_config, total_for_user);
+        total_for_user <= withdrawal_penalty
+            || total_for_user - withdrawal_penalty <= vault.min_withdraw_amount
+    }
 
     pub fn calculate_withdrawal_penalty_params(
         global_config: &GlobalConfig,
@@ -1152,6 +2384,17 @@ pub mod common {
         }
     }
 
+    pub fn deposit_cap_headroom(vault: &VaultState, vault_aum: Fraction) -> Result<u64> {
+        if vault.deposit_cap == 0 {
+            return Ok(u64::MAX);
+        }
+
+        let headroom = vault.deposit_cap.saturating_sub(vault_aum.to_ceil());
+        require!(headroom > 0, KaminoVaultError::DepositCapReached);
+
+        Ok(headroom)
+    }
+
     pub fn compute_amount_to_deposit_from_shares_to_mint(
         vault_total_shares: u64,
         vault_total_holdings: Fraction,
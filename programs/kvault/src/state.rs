@@ -5,16 +5,20 @@ use crate::{
     operations::vault_operations::common::Invested,
     utils::{
         consts::{
-            GLOBAL_CONFIG_SIZE, MAX_WITHDRAWAL_PENALTY_BPS, MAX_WITHDRAWAL_PENALTY_LAMPORTS,
-            RESERVE_WHITELIST_ENTRY_SIZE, VAULT_ALLOCATION_SIZE, VAULT_STATE_SIZE,
+            DEPOSITOR_ALLOWLIST_ENTRY_SIZE, GLOBAL_CONFIG_SIZE, MAX_WITHDRAWAL_PENALTY_BPS,
+            MAX_WITHDRAWAL_PENALTY_LAMPORTS, PAUSE_ALL_OPERATIONS, RESERVE_WHITELIST_ENTRY_SIZE,
+            VAULT_ALLOCATION_SIZE, VAULT_STATE_SIZE, WITHDRAWAL_REQUEST_SIZE,
         },
         global_config::UpdateGlobalConfigMode,
     },
     KaminoVaultError,
 };
 use bytemuck::Zeroable;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 pub const MAX_RESERVES: usize = 25;
+pub const MAX_PENDING_CONFIG_CHANGES: usize = 8;
+pub const MAX_FEE_RECIPIENTS: usize = 8;
 
 static_assertions::const_assert_eq!(GLOBAL_CONFIG_SIZE, std::mem::size_of::<GlobalConfig>());
 static_assertions::const_assert_eq!(0, std::mem::size_of::<GlobalConfig>() % 8);
@@ -29,7 +33,10 @@ pub struct GlobalConfig {
     pub withdrawal_penalty_lamports: u64,
     pub withdrawal_penalty_bps: u64,
 
-    pub padding: [u8; 944],
+    pub paused_operations: u64,
+    pub emergency_guardian: Pubkey,
+
+    pub padding: [u8; 904],
 }
 
 impl Default for GlobalConfig {
@@ -79,6 +86,21 @@ impl GlobalConfig {
                 msg!("New value is: {:?}", new_value);
                 global_config.withdrawal_penalty_bps = new_value;
             }
+            UpdateGlobalConfigMode::PausedOperations(new_value) => {
+                require_gte!(
+                    PAUSE_ALL_OPERATIONS,
+                    new_value,
+                    KaminoVaultError::InvalidPausedOperations
+                );
+                msg!("Prv value is: {:?}", global_config.paused_operations);
+                msg!("New value is: {:?}", new_value);
+                global_config.paused_operations = new_value;
+            }
+            UpdateGlobalConfigMode::EmergencyGuardian(new_guardian) => {
+                msg!("Prv value is: {:?}", global_config.emergency_guardian);
+                msg!("New value is: {:?}", new_guardian);
+                global_config.emergency_guardian = new_guardian;
+            }
         }
         Ok(())
     }
@@ -158,11 +180,59 @@ pub struct VaultState {
     pub allow_allocations_in_whitelisted_reserves_only: u8,
     pub allow_invest_in_whitelisted_reserves_only: u8,
 
-    pub padding_2: [u8; 14],
+    pub permissioned_deposits: u8,
+    // Set by `update_shares_transfer_hook` once `initialize_shares_transfer_hook` created
+    // the extra account metas of the shares mint
+    pub permissioned_share_transfers: u8,
+
+    pub fees_in_shares: u8,
+    pub exclude_distressed_reserves: u8,
+    pub lifecycle_state: u8,
+
+    pub padding_2: [u8; 9],
 
     pub reward_info: VaultRewardInfo,
 
-    pub padding_3: [u128; 232],
+    pub config_timelock: VaultConfigTimelock,
+
+    pub withdrawal_queue: VaultWithdrawalQueue,
+
+    pub deposit_cap: u64,
+    pub user_shares_cap: u64,
+
+    pub fee_recipients: [FeeRecipient; MAX_FEE_RECIPIENTS],
+
+    pub fee_shares_recipient: Pubkey,
+    // Fee shares charged in fees in shares mode, already accounted in `shares_issued` but not
+    // yet minted to the fee shares recipients, see `mint_fee_shares`
+    pub pending_fee_shares: u64,
+    pub fee_shares_padding: u64,
+
+    pub high_water_mark_sf: u128,
+
+    pub performance_fee_hurdle_bps: u64,
+    pub hurdle_padding: u64,
+    pub cumulative_hurdle_excluded_interest_sf: u128,
+
+    pub first_loss_depositor: Pubkey,
+    pub first_loss_shares: u64,
+    // First-loss shares already removed from `shares_issued` but still held by the escrow
+    pub first_loss_shares_pending_burn: u64,
+    pub cumulative_first_loss_absorbed_sf: u128,
+
+    pub max_reserve_utilization_bps: u64,
+    pub max_reserve_borrow_rate_bps: u64,
+
+    pub paused_operations: u64,
+    pub pause_padding: u64,
+    pub emergency_guardian: Pubkey,
+
+    // Start of the window in which the first-loss depositor can withdraw its shares, set by
+    // `request_first_loss_withdrawal`, zero when no withdrawal is requested
+    pub first_loss_withdrawal_unlock_timestamp: u64,
+    pub first_loss_withdrawal_padding: u64,
+
+    pub padding_3: [u128; 179],
 }
 
 impl Default for VaultState {
@@ -188,6 +258,14 @@ impl VaultState {
         self.prev_aum_sf = current_aum.to_bits();
     }
 
+    pub fn get_high_water_mark(&self) -> Fraction {
+        Fraction::from_bits(self.high_water_mark_sf)
+    }
+
+    pub fn set_high_water_mark(&mut self, high_water_mark: Fraction) {
+        self.high_water_mark_sf = high_water_mark.to_bits();
+    }
+
     pub fn get_reserves_count(&self) -> usize {
         self.vault_allocation_strategy
             .iter()
@@ -230,6 +308,25 @@ impl VaultState {
         self.cumulative_perf_fees_sf = cumulative_perf_fees.to_bits();
     }
 
+    pub fn get_cumulative_hurdle_excluded_interest(&self) -> Fraction {
+        Fraction::from_bits(self.cumulative_hurdle_excluded_interest_sf)
+    }
+
+    pub fn set_cumulative_hurdle_excluded_interest(
+        &mut self,
+        cumulative_hurdle_excluded_interest: Fraction,
+    ) {
+        self.cumulative_hurdle_excluded_interest_sf = cumulative_hurdle_excluded_interest.to_bits();
+    }
+
+    pub fn get_cumulative_first_loss_absorbed(&self) -> Fraction {
+        Fraction::from_bits(self.cumulative_first_loss_absorbed_sf)
+    }
+
+    pub fn set_cumulative_first_loss_absorbed(&mut self, cumulative_first_loss_absorbed: Fraction) {
+        self.cumulative_first_loss_absorbed_sf = cumulative_first_loss_absorbed.to_bits();
+    }
+
     pub fn vault_allows_allocations_in_whitelisted_reserves_only(&self) -> bool {
         self.allow_allocations_in_whitelisted_reserves_only == 1
     }
@@ -238,6 +335,55 @@ impl VaultState {
         self.allow_invest_in_whitelisted_reserves_only == 1
     }
 
+    pub fn get_lifecycle_state(&self) -> Result<VaultLifecycleState> {
+        VaultLifecycleState::try_from(self.lifecycle_state)
+            .map_err(|_| error!(KaminoVaultError::InvalidLifecycleState))
+    }
+
+    pub fn is_withdraw_only(&self) -> Result<bool> {
+        Ok(self.get_lifecycle_state()? == VaultLifecycleState::WithdrawOnly)
+    }
+
+    pub fn vault_excludes_distressed_reserves(&self) -> bool {
+        self.exclude_distressed_reserves == 1
+    }
+
+    pub fn check_operation_not_paused(
+        &self,
+        global_config: &GlobalConfig,
+        operation: u64,
+    ) -> Result<()> {
+        if (self.paused_operations | global_config.paused_operations) & operation != 0 {
+            msg!(
+                "Operation {} paused, vault={} global={}",
+                operation,
+                self.paused_operations,
+                global_config.paused_operations
+            );
+            return err!(KaminoVaultError::OperationPaused);
+        }
+        Ok(())
+    }
+
+    pub fn vault_has_permissioned_deposits(&self) -> bool {
+        self.permissioned_deposits == 1
+    }
+
+    pub fn vault_has_permissioned_share_transfers(&self) -> bool {
+        self.permissioned_share_transfers == 1
+    }
+
+    pub fn vault_takes_fees_in_shares(&self) -> bool {
+        self.fees_in_shares == 1
+    }
+
+    pub fn get_fee_recipients_count(&self) -> usize {
+        self.fee_recipients
+            .iter()
+            .filter(|r| r.recipient != Pubkey::default())
+            .count()
+    }
+
     pub fn compute_aum(&self, invested_total: &Fraction) -> Result<Fraction> {
        
         let pending_fees = self.get_pending_fees();
@@ -360,11 +506,12 @@ impl VaultState {
                     target_allocation_weight,
                     ctoken_allocation: 0,
                     token_target_allocation_sf: 0,
+                    max_liquidity_per_ctoken_sf: 0,
                     token_allocation_cap: allocation_cap,
                     last_invest_slot: 0,
                     ctoken_vault_bump,
                     config_padding: [0; 127],
-                    state_padding: [0; 128],
+                    state_padding: [0; 126],
                 };
             }
         }
@@ -391,6 +538,14 @@ impl VaultState {
     }
 
     pub fn refresh_target_allocations(&mut self, invested: &Invested) -> Result<()> {
+        if self.is_withdraw_only()? {
+            // Everything is unwound back to the vault, there is nothing to allocate anymore
+            for allocation in self.vault_allocation_strategy.iter_mut() {
+                allocation.set_token_target_allocation(Fraction::ZERO);
+            }
+            return Ok(());
+        }
+
         let total_tokens = self.compute_aum(&invested.total)?;
         let total_weight = self
             .vault_allocation_strategy
@@ -536,8 +691,9 @@ pub struct VaultAllocation {
     pub ctoken_allocation: u64,
     pub last_invest_slot: u64,
     pub token_target_allocation_sf: u128,
+    pub max_liquidity_per_ctoken_sf: u128,
 
-    pub state_padding: [u64; 128],
+    pub state_padding: [u64; 126],
 }
 
 impl VaultAllocation {
@@ -549,6 +705,14 @@ impl VaultAllocation {
         self.token_target_allocation_sf = token_target_allocation.to_bits();
     }
 
+    pub fn get_max_liquidity_per_ctoken(&self) -> Fraction {
+        Fraction::from_bits(self.max_liquidity_per_ctoken_sf)
+    }
+
+    pub fn set_max_liquidity_per_ctoken(&mut self, max_liquidity_per_ctoken: Fraction) {
+        self.max_liquidity_per_ctoken_sf = max_liquidity_per_ctoken.to_bits();
+    }
+
     pub fn can_be_removed(&self) -> bool {
        
         self.ctoken_allocation == 0 && self.target_allocation_weight == 0
@@ -571,7 +735,7 @@ impl Default for VaultAllocation {
             last_invest_slot: 0,
             ctoken_vault_bump: 0,
             config_padding: [0; 127],
-            state_padding: [0; 128],
+            state_padding: [0; 126],
         }
     }
 }
@@ -617,6 +781,28 @@ impl Default for ReserveWhitelistEntry {
     }
 }
 
+static_assertions::const_assert_eq!(
+    DEPOSITOR_ALLOWLIST_ENTRY_SIZE,
+    std::mem::size_of::<DepositorAllowlistEntry>()
+);
+static_assertions::const_assert_eq!(0, std::mem::size_of::<DepositorAllowlistEntry>() % 8);
+#[account]
+pub struct DepositorAllowlistEntry {
+    pub vault_state: Pubkey,
+    pub depositor: Pubkey,
+    pub padding: [u8; 64],
+}
+
+impl Default for DepositorAllowlistEntry {
+    fn default() -> Self {
+        Self {
+            vault_state: Pubkey::default(),
+            depositor: Pubkey::default(),
+            padding: [0; 64],
+        }
+    }
+}
+
 #[zero_copy]
 #[derive(AnchorDeserialize, Debug, PartialEq, Eq, Default)]
 pub struct VaultRewardInfo {
@@ -636,3 +822,137 @@ impl VaultRewardInfo {
         self.reward_per_second > 0 && self.rewards_available > 0
     }
 }
+
+#[zero_copy]
+#[derive(AnchorDeserialize, Debug, PartialEq, Eq, Default)]
+pub struct VaultConfigTimelock {
+    pub delay_seconds: u64,
+    pub padding: u64,
+
+    pub pending_changes: [PendingVaultConfigChange; MAX_PENDING_CONFIG_CHANGES],
+}
+
+#[zero_copy]
+#[derive(AnchorDeserialize, Debug, PartialEq, Eq, Default)]
+pub struct PendingVaultConfigChange {
+    pub value: u64,
+    pub activation_timestamp: u64,
+}
+
+impl PendingVaultConfigChange {
+    pub fn is_pending(&self) -> bool {
+        self.activation_timestamp > 0
+    }
+
+    pub fn is_active(&self, current_timestamp: u64) -> bool {
+        self.is_pending() && current_timestamp >= self.activation_timestamp
+    }
+}
+
+#[zero_copy]
+#[derive(AnchorDeserialize, Debug, PartialEq, Eq, Default)]
+pub struct FeeRecipient {
+    pub recipient: Pubkey,
+    pub bps: u64,
+    pub padding: u64,
+}
+
+#[zero_copy]
+#[derive(AnchorDeserialize, Debug, PartialEq, Eq, Default)]
+pub struct VaultWithdrawalQueue {
+    pub next_request_id: u64,
+    pub next_request_id_to_fulfil: u64,
+
+    pub escrowed_shares: u64,
+    pub claimable_tokens: u64,
+
+    pub padding: [u64; 4],
+}
+
+impl VaultWithdrawalQueue {
+    pub fn is_next_to_fulfil(&self, request_id: u64) -> bool {
+        self.next_request_id_to_fulfil == request_id
+    }
+
+    pub fn was_processed(&self, request_id: u64) -> bool {
+        request_id < self.next_request_id_to_fulfil
+    }
+}
+
+#[derive(
+    AnchorSerialize,
+    AnchorDeserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Default,
+    TryFromPrimitive,
+    IntoPrimitive,
+)]
+#[repr(u8)]
+pub enum VaultLifecycleState {
+    #[default]
+    Active,
+    WithdrawOnly,
+    Closed,
+}
+
+impl VaultLifecycleState {
+    /// Transitions allowed through the vault config. `Closed` is final and only reached
+    /// through `close_vault`
+    pub fn can_transition_to(self, next: VaultLifecycleState) -> bool {
+        matches!(
+            (self, next),
+            (VaultLifecycleState::Active, VaultLifecycleState::WithdrawOnly)
+                | (VaultLifecycleState::WithdrawOnly, VaultLifecycleState::Active)
+        )
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum WithdrawalRequestStatus {
+    #[default]
+    Pending,
+    Fulfilled,
+    Cancelled,
+}
+
+static_assertions::const_assert_eq!(
+    WITHDRAWAL_REQUEST_SIZE,
+    std::mem::size_of::<WithdrawalRequest>()
+);
+static_assertions::const_assert_eq!(0, std::mem::size_of::<WithdrawalRequest>() % 8);
+#[account]
+pub struct WithdrawalRequest {
+    pub vault_state: Pubkey,
+    pub user: Pubkey,
+    pub request_id: u64,
+    pub request_timestamp: u64,
+
+    pub shares_requested: u64,
+    pub shares_escrowed: u64,
+    pub shares_burned: u64,
+    pub tokens_claimable: u64,
+
+    pub status: WithdrawalRequestStatus,
+    pub padding: [u8; 127],
+}
+
+impl Default for WithdrawalRequest {
+    fn default() -> Self {
+        Self {
+            vault_state: Pubkey::default(),
+            user: Pubkey::default(),
+            request_id: 0,
+            request_timestamp: 0,
+            shares_requested: 0,
+            shares_escrowed: 0,
+            shares_burned: 0,
+            tokens_claimable: 0,
+            status: WithdrawalRequestStatus::default(),
+            padding: [0; 127],
+        }
+    }
+}
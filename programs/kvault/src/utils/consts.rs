@@ -6,12 +6,18 @@ pub const METADATA_SEEDS: &[u8; 8] = b"metadata";
 pub const EVENT_AUTHORITY: &[u8] = b"__event_authority";
 pub const GLOBAL_CONFIG_STATE_SEEDS: &[u8] = b"global_config";
 pub const WHITELISTED_RESERVES_SEED: &[u8] = b"whitelisted_reserves";
+pub const WITHDRAWAL_REQUEST_SEED: &[u8] = b"withdrawal_request";
+pub const WITHDRAWAL_QUEUE_ESCROW_SEED: &[u8] = b"withdrawal_queue_escrow";
+pub const DEPOSITOR_ALLOWLIST_SEED: &[u8] = b"depositor_allowlist";
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+pub const FIRST_LOSS_ESCROW_SEED: &[u8] = b"first_loss_escrow";
 
 pub const VAULT_STATE_SIZE: usize = 62544;
 pub const VAULT_ALLOCATION_SIZE: usize = 2160;
 pub const GLOBAL_CONFIG_SIZE: usize = 1024;
 pub const RESERVE_WHITELIST_ENTRY_SIZE: usize = 128;
-
+pub const WITHDRAWAL_REQUEST_SIZE: usize = 240;
+pub const DEPOSITOR_ALLOWLIST_ENTRY_SIZE: usize = 128;
 
 pub const MAX_MGMT_FEE_BPS: u64 = 1000;
 
@@ -30,3 +36,21 @@ pub const MAX_WITHDRAWAL_PENALTY_LAMPORTS: u64 = 10_000;
 
 
 pub const MAX_REWARDS_STALENESS_FOR_FEE_UPDATE: u64 = SECONDS_PER_HOUR * 12;
+
+pub const MAX_CONFIG_CHANGE_DELAY_SECONDS: u64 = SECONDS_PER_DAY * 30;
+
+pub const FIRST_LOSS_WITHDRAWAL_NOTICE_SECONDS: u64 = SECONDS_PER_DAY * 7;
+pub const FIRST_LOSS_WITHDRAWAL_WINDOW_SECONDS: u64 = SECONDS_PER_DAY * 2;
+
+pub const PAUSE_DEPOSITS: u64 = 1 << 0;
+pub const PAUSE_WITHDRAWALS: u64 = 1 << 1;
+pub const PAUSE_INVEST: u64 = 1 << 2;
+pub const PAUSE_REDEEM_IN_KIND: u64 = 1 << 3;
+pub const PAUSE_FEE_WITHDRAWAL: u64 = 1 << 4;
+pub const PAUSE_REWARD_DISTRIBUTION: u64 = 1 << 5;
+pub const PAUSE_ALL_OPERATIONS: u64 = PAUSE_DEPOSITS
+    | PAUSE_WITHDRAWALS
+    | PAUSE_INVEST
+    | PAUSE_REDEEM_IN_KIND
+    | PAUSE_FEE_WITHDRAWAL
+    | PAUSE_REWARD_DISTRIBUTION;
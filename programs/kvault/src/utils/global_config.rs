@@ -9,4 +9,6 @@ pub enum UpdateGlobalConfigMode {
     PendingAdmin(Pubkey),
     MinWithdrawalPenaltyLamports(u64),
     MinWithdrawalPenaltyBPS(u64),
+    PausedOperations(u64),
+    EmergencyGuardian(Pubkey),
 }
@@ -0,0 +1,133 @@
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    mem::size_of,
+    ptr::null_mut,
+};
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{pubkey, sysvar::instructions::load_instruction_at_checked},
+};
+
+use crate::KaminoVaultError;
+
+const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+// Borsh tag of `ComputeBudgetInstruction::RequestHeapFrame(u32)`
+const REQUEST_HEAP_FRAME_TAG: u8 = 1;
+
+// Heap frame mapped for every transaction, whatever it requests through the compute budget
+// program
+pub const DEFAULT_HEAP_FRAME_BYTES: usize = 32 * 1024;
+
+// Heap frame the preview instructions check the transaction requested, they copy the vault
+// state to the heap
+pub const PREVIEW_HEAP_FRAME_BYTES: usize = 128 * 1024;
+
+// Bump allocator growing from the start of the heap region. The first word of the region
+// holds the bump position and the second one the heap frame the allocator may use.
+//
+// Allocations are bounded by the default 32KB frame, the only one that is known to be
+// mapped, so that running out of heap is an allocation failure rather than an access to
+// an unmapped page. Instructions that checked the transaction requested a larger frame
+// can extend the bound with `extend_heap_frame`
+pub struct BumpUpAllocator {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl BumpUpAllocator {
+    const HEADER_BYTES: usize = 2 * size_of::<usize>();
+
+    fn frame_len_ptr(&self) -> *mut usize {
+        (self.start + size_of::<usize>()) as *mut usize
+    }
+}
+
+unsafe impl GlobalAlloc for BumpUpAllocator {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pos_ptr = self.start as *mut usize;
+
+        let mut pos = *pos_ptr;
+        if pos == 0 {
+            pos = self.start + Self::HEADER_BYTES;
+        }
+
+        let frame_len = std::cmp::max(*self.frame_len_ptr(), self.len);
+
+        let begin = pos.saturating_add(layout.align() - 1) & !(layout.align() - 1);
+        let end = begin.saturating_add(layout.size());
+        if end > self.start + frame_len {
+            return null_mut();
+        }
+
+        *pos_ptr = end;
+        begin as *mut u8
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, _: *mut u8, _: Layout) {}
+}
+
+#[cfg(all(
+    feature = "custom-heap",
+    not(feature = "no-entrypoint"),
+    target_os = "solana"
+))]
+#[global_allocator]
+static ALLOCATOR: BumpUpAllocator = BumpUpAllocator {
+    start: solana_program::entrypoint::HEAP_START_ADDRESS as usize,
+    len: DEFAULT_HEAP_FRAME_BYTES,
+};
+
+/// Lets the allocator use a heap frame of `frame_len` bytes.
+///
+/// The caller must have checked that the transaction requested a heap frame at least that
+/// large, see `check_heap_frame_requested`
+pub fn extend_heap_frame(frame_len: usize) {
+    #[cfg(all(
+        feature = "custom-heap",
+        not(feature = "no-entrypoint"),
+        target_os = "solana"
+    ))]
+    unsafe {
+        *ALLOCATOR.frame_len_ptr() = frame_len;
+    }
+
+    #[cfg(not(all(
+        feature = "custom-heap",
+        not(feature = "no-entrypoint"),
+        target_os = "solana"
+    )))]
+    let _ = frame_len;
+}
+
+/// Checks that the transaction requests a heap frame of at least `min_frame_len` bytes
+/// through the compute budget program
+pub fn check_heap_frame_requested(
+    instruction_sysvar: &AccountInfo,
+    min_frame_len: usize,
+) -> Result<()> {
+    let mut idx = 0;
+    while let Ok(instruction) = load_instruction_at_checked(idx, instruction_sysvar) {
+        if instruction.program_id == COMPUTE_BUDGET_PROGRAM_ID {
+            if let [REQUEST_HEAP_FRAME_TAG, frame_len @ ..] = instruction.data.as_slice() {
+                let frame_len = u32::from_le_bytes(
+                    frame_len
+                        .try_into()
+                        .map_err(|_| error!(KaminoVaultError::HeapFrameTooSmall))?,
+                );
+                if usize::try_from(frame_len).unwrap() >= min_frame_len {
+                    return Ok(());
+                }
+            }
+        }
+        idx += 1;
+    }
+
+    msg!(
+        "The transaction must request a heap frame of at least {} bytes",
+        min_frame_len
+    );
+    err!(KaminoVaultError::HeapFrameTooSmall)
+}
@@ -1,8 +1,16 @@
 use anchor_lang::{
-    prelude::{AccountInfo, CpiContext},
-    Key, Result, ToAccountInfo,
+    prelude::{AccountInfo, CpiContext, Rent, SolanaSysvar},
+    solana_program::program::invoke_signed,
+    system_program, Key, Result, ToAccountInfo,
 };
-use anchor_spl::metadata::mpl_token_metadata::types::DataV2;
+use anchor_spl::{
+    metadata::mpl_token_metadata::types::DataV2,
+    token_2022::spl_token_2022::{
+        extension::{BaseStateWithExtensions, StateWithExtensions},
+        state::Mint,
+    },
+};
+use spl_token_metadata_interface::state::{Field, TokenMetadata as Token2022Metadata};
 
 use super::consts::BASE_VAULT_AUTHORITY_SEED;
 use crate::gen_signer_seeds;
@@ -107,3 +115,129 @@ pub fn update<'info>(
 
     Ok(())
 }
+
+#[allow(clippy::too_many_arguments)]
+pub fn init_token_2022<'info>(
+    vault_state: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    shares_mint: AccountInfo<'info>,
+    shares_mint_authority: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    mint_authority_bump: u64,
+    TokenMetadata { name, symbol, uri }: TokenMetadata,
+) -> Result<()> {
+    let vault_state_key = vault_state.key();
+    let seeds = gen_signer_seeds!(
+        BASE_VAULT_AUTHORITY_SEED,
+        vault_state_key.as_ref(),
+        mint_authority_bump as u8
+    );
+    let signer_seeds: &[&[&[u8]]] = &[seeds];
+
+    let token_metadata = Token2022Metadata {
+        name: name.clone(),
+        symbol: symbol.clone(),
+        uri: uri.clone(),
+        ..Default::default()
+    };
+    let new_len = shares_mint.data_len() + token_metadata.tlv_size_of()?;
+    fund_rent_for_realloc(&shares_mint, payer, system_program, new_len)?;
+
+    invoke_signed(
+        &spl_token_metadata_interface::instruction::initialize(
+            token_program.key,
+            shares_mint.key,
+            shares_mint_authority.key,
+            shares_mint.key,
+            shares_mint_authority.key,
+            name,
+            symbol,
+            uri,
+        ),
+        &[shares_mint, shares_mint_authority],
+        signer_seeds,
+    )?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_token_2022<'info>(
+    vault_state: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    shares_mint: AccountInfo<'info>,
+    shares_mint_authority: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    mint_authority_bump: u64,
+    TokenMetadata { name, symbol, uri }: TokenMetadata,
+) -> Result<()> {
+    let vault_state_key = vault_state.key();
+    let seeds = gen_signer_seeds!(
+        BASE_VAULT_AUTHORITY_SEED,
+        vault_state_key.as_ref(),
+        mint_authority_bump as u8
+    );
+    let signer_seeds: &[&[&[u8]]] = &[seeds];
+
+    let current_metadata = {
+        let data = shares_mint.try_borrow_data()?;
+        let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+        mint.get_variable_len_extension::<Token2022Metadata>()?
+    };
+    let updated_metadata = Token2022Metadata {
+        name: name.clone(),
+        symbol: symbol.clone(),
+        uri: uri.clone(),
+        ..current_metadata.clone()
+    };
+    let new_len = (shares_mint.data_len() + updated_metadata.tlv_size_of()?)
+        .saturating_sub(current_metadata.tlv_size_of()?);
+    fund_rent_for_realloc(&shares_mint, payer, system_program, new_len)?;
+
+    for (field, value) in [
+        (Field::Name, name),
+        (Field::Symbol, symbol),
+        (Field::Uri, uri),
+    ] {
+        invoke_signed(
+            &spl_token_metadata_interface::instruction::update_field(
+                token_program.key,
+                shares_mint.key,
+                shares_mint_authority.key,
+                field,
+                value,
+            ),
+            &[shares_mint.clone(), shares_mint_authority.clone()],
+            signer_seeds,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn fund_rent_for_realloc<'info>(
+    account: &AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    new_len: usize,
+) -> Result<()> {
+    let required_lamports = Rent::get()?.minimum_balance(new_len);
+    let current_lamports = account.lamports();
+
+    if required_lamports > current_lamports {
+        system_program::transfer(
+            CpiContext::new(
+                system_program,
+                system_program::Transfer {
+                    from: payer,
+                    to: account.clone(),
+                },
+            ),
+            required_lamports - current_lamports,
+        )?;
+    }
+
+    Ok(())
+}
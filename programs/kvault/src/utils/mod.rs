@@ -2,6 +2,7 @@ pub mod consts;
 pub mod cpi_mem;
 pub mod fraction_utils;
 pub mod global_config;
+pub mod heap;
 pub mod macros;
 pub mod metadata;
 pub mod pda;
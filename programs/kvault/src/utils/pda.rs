@@ -1,6 +1,11 @@
 use anchor_lang::prelude::Pubkey;
 
-use crate::utils::consts::GLOBAL_CONFIG_STATE_SEEDS;
+use crate::utils::consts::{
+    BASE_VAULT_AUTHORITY_SEED, CTOKEN_VAULT_SEED, DEPOSITOR_ALLOWLIST_SEED, EVENT_AUTHORITY,
+    EXTRA_ACCOUNT_METAS_SEED, FIRST_LOSS_ESCROW_SEED, GLOBAL_CONFIG_STATE_SEEDS, SHARES_SEEDS,
+    TOKEN_VAULT_SEED, WHITELISTED_RESERVES_SEED, WITHDRAWAL_QUEUE_ESCROW_SEED,
+    WITHDRAWAL_REQUEST_SEED,
+};
 
 pub fn program_data() -> Pubkey {
     program_data_program_id(&crate::ID)
@@ -21,3 +26,137 @@ pub fn global_config() -> Pubkey {
 pub fn global_config_program_id(program_id: &Pubkey) -> Pubkey {
     Pubkey::find_program_address(&[GLOBAL_CONFIG_STATE_SEEDS], program_id).0
 }
+
+pub fn event_authority() -> Pubkey {
+    event_authority_program_id(&crate::ID)
+}
+
+pub fn event_authority_program_id(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY], program_id).0
+}
+
+pub fn base_vault_authority(vault_state: &Pubkey) -> Pubkey {
+    base_vault_authority_program_id(&crate::ID, vault_state)
+}
+
+pub fn base_vault_authority_program_id(program_id: &Pubkey, vault_state: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[BASE_VAULT_AUTHORITY_SEED, vault_state.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+pub fn token_vault(vault_state: &Pubkey) -> Pubkey {
+    token_vault_program_id(&crate::ID, vault_state)
+}
+
+pub fn token_vault_program_id(program_id: &Pubkey, vault_state: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[TOKEN_VAULT_SEED, vault_state.as_ref()], program_id).0
+}
+
+pub fn shares_mint(vault_state: &Pubkey) -> Pubkey {
+    shares_mint_program_id(&crate::ID, vault_state)
+}
+
+pub fn shares_mint_program_id(program_id: &Pubkey, vault_state: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SHARES_SEEDS, vault_state.as_ref()], program_id).0
+}
+
+pub fn ctoken_vault(vault_state: &Pubkey, reserve: &Pubkey) -> Pubkey {
+    ctoken_vault_program_id(&crate::ID, vault_state, reserve)
+}
+
+pub fn ctoken_vault_program_id(
+    program_id: &Pubkey,
+    vault_state: &Pubkey,
+    reserve: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[CTOKEN_VAULT_SEED, vault_state.as_ref(), reserve.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+pub fn reserve_whitelist_entry(reserve: &Pubkey) -> Pubkey {
+    reserve_whitelist_entry_program_id(&crate::ID, reserve)
+}
+
+pub fn reserve_whitelist_entry_program_id(program_id: &Pubkey, reserve: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[WHITELISTED_RESERVES_SEED, reserve.as_ref()], program_id).0
+}
+
+pub fn withdrawal_request(vault_state: &Pubkey, user: &Pubkey, request_id: u64) -> Pubkey {
+    withdrawal_request_program_id(&crate::ID, vault_state, user, request_id)
+}
+
+pub fn withdrawal_request_program_id(
+    program_id: &Pubkey,
+    vault_state: &Pubkey,
+    user: &Pubkey,
+    request_id: u64,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            WITHDRAWAL_REQUEST_SEED,
+            vault_state.as_ref(),
+            user.as_ref(),
+            &request_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+    .0
+}
+
+pub fn withdrawal_queue_escrow(vault_state: &Pubkey) -> Pubkey {
+    withdrawal_queue_escrow_program_id(&crate::ID, vault_state)
+}
+
+pub fn withdrawal_queue_escrow_program_id(program_id: &Pubkey, vault_state: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[WITHDRAWAL_QUEUE_ESCROW_SEED, vault_state.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+pub fn depositor_allowlist_entry(vault_state: &Pubkey, depositor: &Pubkey) -> Pubkey {
+    depositor_allowlist_entry_program_id(&crate::ID, vault_state, depositor)
+}
+
+pub fn depositor_allowlist_entry_program_id(
+    program_id: &Pubkey,
+    vault_state: &Pubkey,
+    depositor: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            DEPOSITOR_ALLOWLIST_SEED,
+            vault_state.as_ref(),
+            depositor.as_ref(),
+        ],
+        program_id,
+    )
+    .0
+}
+
+pub fn extra_account_metas(shares_mint: &Pubkey) -> Pubkey {
+    extra_account_metas_program_id(&crate::ID, shares_mint)
+}
+
+pub fn extra_account_metas_program_id(program_id: &Pubkey, shares_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[EXTRA_ACCOUNT_METAS_SEED, shares_mint.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+pub fn first_loss_escrow(vault_state: &Pubkey) -> Pubkey {
+    first_loss_escrow_program_id(&crate::ID, vault_state)
+}
+
+pub fn first_loss_escrow_program_id(program_id: &Pubkey, vault_state: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[FIRST_LOSS_ESCROW_SEED, vault_state.as_ref()], program_id).0
+}
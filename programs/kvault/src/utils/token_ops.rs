@@ -1,13 +1,138 @@
 use anchor_lang::prelude::{AccountInfo, CpiContext, Result};
 use anchor_spl::token_interface::{self};
 
-use super::consts::BASE_VAULT_AUTHORITY_SEED;
+use super::consts::{BASE_VAULT_AUTHORITY_SEED, SHARES_SEEDS};
 use crate::gen_signer_seeds;
 
 pub mod shares {
 
+    use anchor_lang::{
+        prelude::{Pubkey, Rent, SolanaSysvar},
+        solana_program::program::{invoke, invoke_signed},
+        system_program,
+    };
+    use anchor_spl::token_2022::spl_token_2022::{
+        self,
+        extension::{metadata_pointer, transfer_hook, ExtensionType},
+        onchain::invoke_transfer_checked,
+        solana_program::program_pack::Pack,
+    };
+
+    use super::tokens::{UserTransferAccounts, VaultTransferAccounts};
+
     use super::*;
 
+    pub fn is_token_2022(token_program: &AccountInfo) -> bool {
+        *token_program.key == spl_token_2022::ID
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_mint<'info>(
+        token_program: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+        payer: AccountInfo<'info>,
+        shares_mint: AccountInfo<'info>,
+        vault_state: AccountInfo<'info>,
+        base_vault_authority: AccountInfo<'info>,
+        shares_mint_bump: u8,
+        decimals: u8,
+    ) -> Result<()> {
+        let with_extensions = is_token_2022(&token_program);
+        let space = if with_extensions {
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+                ExtensionType::MetadataPointer,
+                ExtensionType::TransferHook,
+            ])?
+        } else {
+            spl_token_2022::state::Mint::LEN
+        };
+
+        let signer_seeds =
+            gen_signer_seeds!(SHARES_SEEDS, vault_state.key.as_ref(), shares_mint_bump);
+
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program,
+                system_program::CreateAccount {
+                    from: payer,
+                    to: shares_mint.clone(),
+                },
+                &[signer_seeds],
+            ),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            token_program.key,
+        )?;
+
+        if with_extensions {
+            // The token metadata lives on the mint itself, written by `initialize_shares_metadata`
+            invoke(
+                &metadata_pointer::instruction::initialize(
+                    token_program.key,
+                    shares_mint.key,
+                    Some(*base_vault_authority.key),
+                    Some(*shares_mint.key),
+                )?,
+                &[shares_mint.clone()],
+            )?;
+
+            // No hook program is set at creation, the extension has to exist before the mint
+            // is initialized for the vault to be able to enable one later
+            invoke(
+                &transfer_hook::instruction::initialize(
+                    token_program.key,
+                    shares_mint.key,
+                    Some(*base_vault_authority.key),
+                    None,
+                )?,
+                &[shares_mint.clone()],
+            )?;
+        }
+
+        invoke(
+            &spl_token_2022::instruction::initialize_mint2(
+                token_program.key,
+                shares_mint.key,
+                base_vault_authority.key,
+                None,
+                decimals,
+            )?,
+            &[shares_mint],
+        )?;
+
+        Ok(())
+    }
+
+    /// Sets the program Token-2022 invokes on every shares transfer, `None` disables the hook
+    pub fn update_transfer_hook_program_id<'info>(
+        token_program: AccountInfo<'info>,
+        shares_mint: AccountInfo<'info>,
+        vault_state: AccountInfo<'info>,
+        base_vault_authority: AccountInfo<'info>,
+        base_vault_authority_bump: u64,
+        hook_program_id: Option<Pubkey>,
+    ) -> Result<()> {
+        let signer_seeds = gen_signer_seeds!(
+            BASE_VAULT_AUTHORITY_SEED,
+            vault_state.key.as_ref(),
+            base_vault_authority_bump as u8
+        );
+
+        invoke_signed(
+            &transfer_hook::instruction::update(
+                token_program.key,
+                shares_mint.key,
+                base_vault_authority.key,
+                &[],
+                hook_program_id,
+            )?,
+            &[shares_mint, base_vault_authority],
+            &[signer_seeds],
+        )?;
+
+        Ok(())
+    }
+
     pub fn mint<'info>(
         token_program: AccountInfo<'info>,
         shares_mint: AccountInfo<'info>,
@@ -23,10 +148,10 @@ pub mod shares {
             base_vault_authority_bump as u8
         );
 
-        anchor_spl::token::mint_to(
+        token_interface::mint_to(
             CpiContext::new_with_signer(
                 token_program,
-                anchor_spl::token::MintTo {
+                token_interface::MintTo {
                     mint: shares_mint,
                     to: user_shares_ata,
                     authority: base_vault_authority,
@@ -46,10 +171,10 @@ pub mod shares {
         token_program: AccountInfo<'info>,
         shares_to_burn: u64,
     ) -> Result<()> {
-        anchor_spl::token::burn(
+        token_interface::burn(
             CpiContext::new(
                 token_program,
-                anchor_spl::token::Burn {
+                token_interface::Burn {
                     mint: shares_mint,
                     from: user_shares_ata,
                     authority: user,
@@ -60,6 +185,120 @@ pub mod shares {
 
         Ok(())
     }
+
+    /// Shares transfers go through the transfer hook of the mint, if one is set. Its program,
+    /// its `ExtraAccountMetaList` and the extra accounts the list resolves to are looked up
+    /// in `hook_accounts`
+    pub fn transfer_to_vault<'info>(
+        accounts: &UserTransferAccounts<'info>,
+        hook_accounts: &[AccountInfo<'info>],
+        amount: u64,
+        decimals: u8,
+    ) -> Result<()> {
+        invoke_transfer_checked(
+            accounts.token_program.key,
+            accounts.token_ata.clone(),
+            accounts.token_mint.clone(),
+            accounts.token_vault.clone(),
+            accounts.user_authority.clone(),
+            hook_accounts,
+            amount,
+            decimals,
+            &[],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn transfer_to_token_account<'info>(
+        accounts: &VaultTransferAccounts<'info>,
+        hook_accounts: &[AccountInfo<'info>],
+        base_vault_authority_bump: u8,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<()> {
+        let signer_seeds = gen_signer_seeds!(
+            BASE_VAULT_AUTHORITY_SEED,
+            accounts.vault_state.key.as_ref(),
+            base_vault_authority_bump
+        );
+
+        if amount > 0 {
+            invoke_transfer_checked(
+                accounts.token_program.key,
+                accounts.token_vault.clone(),
+                accounts.token_mint.clone(),
+                accounts.token_ata.clone(),
+                accounts.base_vault_authority.clone(),
+                hook_accounts,
+                amount,
+                decimals,
+                &[signer_seeds],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn burn_from_escrow<'info>(
+        token_program: AccountInfo<'info>,
+        shares_mint: AccountInfo<'info>,
+        vault_state: AccountInfo<'info>,
+        base_vault_authority: AccountInfo<'info>,
+        shares_escrow: AccountInfo<'info>,
+        base_vault_authority_bump: u64,
+        shares_to_burn: u64,
+    ) -> Result<()> {
+        let signer_seeds = gen_signer_seeds!(
+            BASE_VAULT_AUTHORITY_SEED,
+            vault_state.key.as_ref(),
+            base_vault_authority_bump as u8
+        );
+
+        token_interface::burn(
+            CpiContext::new_with_signer(
+                token_program,
+                token_interface::Burn {
+                    mint: shares_mint,
+                    from: shares_escrow,
+                    authority: base_vault_authority,
+                },
+                &[signer_seeds],
+            ),
+            shares_to_burn,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn revoke_mint_authority<'info>(
+        token_program: AccountInfo<'info>,
+        shares_mint: AccountInfo<'info>,
+        vault_state: AccountInfo<'info>,
+        base_vault_authority: AccountInfo<'info>,
+        base_vault_authority_bump: u64,
+    ) -> Result<()> {
+        let signer_seeds = gen_signer_seeds!(
+            BASE_VAULT_AUTHORITY_SEED,
+            vault_state.key.as_ref(),
+            base_vault_authority_bump as u8
+        );
+
+        token_interface::set_authority(
+            CpiContext::new_with_signer(
+                token_program,
+                token_interface::SetAuthority {
+                    current_authority: base_vault_authority,
+                    account_or_mint: shares_mint,
+                },
+                &[signer_seeds],
+            ),
+            spl_token_2022::instruction::AuthorityType::MintTokens,
+            None,
+        )?;
+
+        Ok(())
+    }
 }
 
 pub mod tokens {
@@ -116,6 +355,31 @@ pub mod tokens {
         Ok(())
     }
 
+    pub fn close_vault_token_account<'info>(
+        token_program: AccountInfo<'info>,
+        token_vault: AccountInfo<'info>,
+        destination: AccountInfo<'info>,
+        vault_state: AccountInfo<'info>,
+        base_vault_authority: AccountInfo<'info>,
+        base_vault_authority_bump: u8,
+    ) -> Result<()> {
+        let signer_seeds = gen_signer_seeds!(
+            BASE_VAULT_AUTHORITY_SEED,
+            vault_state.key.as_ref(),
+            base_vault_authority_bump
+        );
+
+        token_interface::close_account(CpiContext::new_with_signer(
+            token_program,
+            token_interface::CloseAccount {
+                account: token_vault,
+                destination,
+                authority: base_vault_authority,
+            },
+            &[signer_seeds],
+        ))
+    }
+
     pub fn transfer_to_vault(
         accounts: &UserTransferAccounts,
         amount: u64,
@@ -0,0 +1,975 @@
+//! Property based tests of the vault accounting in `vault_operations`.
+//!
+//! The vault is built through the operations themselves (deposit, invest into mocked
+//! reserves, interest accrual and time passing) and every user or admin operation is
+//! then checked against the accounting invariants of the vault. The fees accrued
+//! until the time of an operation are charged before the state is captured, so the
+//! share price comparisons only cover the effect of the operation itself.
+
+use std::cell::{Ref, RefCell, RefMut};
+
+use anchor_lang::prelude::{Clock, Pubkey, Result};
+use kamino_lending::{
+    fraction::Fraction,
+    utils::{AnyAccountLoader, FractionExtra, U256},
+    Reserve,
+};
+use kamino_vault::{
+    operations::{
+        effects::{
+            DepositEffects, InvestingDirection, RedeemInKindEffects, WithdrawEffects,
+            WithdrawPendingFeesEffects,
+        },
+        vault_operations::{
+            self, common, RedeemInKindParams, ReserveToWithdrawFrom, WithdrawAmount,
+        },
+    },
+    utils::consts::{SECONDS_PER_YEAR, SECONDS_PER_YEAR_U64},
+    GlobalConfig, VaultState, WithdrawalRequest, WithdrawalRequestStatus,
+};
+use proptest::prelude::*;
+
+const SLOT: u64 = 250_000_000;
+const START_TIMESTAMP: u64 = 1_700_000_000;
+
+// Precision lost by the exchange rate conversions of the reserves, well below a lamport
+const ROUNDING_TOLERANCE: Fraction = Fraction::from_bits(1 << 16);
+
+struct MockReserve {
+    address: Pubkey,
+    reserve: RefCell<Reserve>,
+}
+
+impl<'a> AnyAccountLoader<'a, Reserve> for &MockReserve {
+    fn get_mut(&self) -> Result<RefMut<Reserve>> {
+        Ok(self.reserve.borrow_mut())
+    }
+
+    fn get(&self) -> Result<Ref<Reserve>> {
+        Ok(self.reserve.borrow())
+    }
+
+    fn get_pubkey(&self) -> Pubkey {
+        self.address
+    }
+}
+
+impl MockReserve {
+    fn new(params: &ReserveParams) -> Self {
+        let total_liquidity = params.available_liquidity + params.borrowed_liquidity;
+
+        let mut reserve: Reserve = bytemuck::Zeroable::zeroed();
+        reserve.last_update.slot = SLOT;
+        reserve.liquidity.available_amount = params.available_liquidity;
+        reserve.liquidity.borrowed_amount_sf = Fraction::from(params.borrowed_liquidity).to_bits();
+        reserve.collateral.mint_total_supply = u64::try_from(
+            u128::from(total_liquidity) * 10_000 / u128::from(params.liquidity_per_ctoken_bps),
+        )
+        .unwrap();
+
+        Self {
+            address: Pubkey::new_unique(),
+            reserve: RefCell::new(reserve),
+        }
+    }
+
+    fn accrue_interest(&self, interest_bps: u64) {
+        let reserve = &mut self.reserve.borrow_mut();
+        let borrowed = Fraction::from_bits(reserve.liquidity.borrowed_amount_sf);
+        let total_liquidity = Fraction::from(reserve.liquidity.available_amount) + borrowed;
+        let interest = total_liquidity * u128::from(interest_bps) / 10_000;
+
+        reserve.liquidity.borrowed_amount_sf = (borrowed + interest).to_bits();
+    }
+
+    fn socialize_loss(&self, loss_bps: u64) {
+        let reserve = &mut self.reserve.borrow_mut();
+        let borrowed = Fraction::from_bits(reserve.liquidity.borrowed_amount_sf);
+        let total_liquidity = Fraction::from(reserve.liquidity.available_amount) + borrowed;
+        let loss = (total_liquidity * u128::from(loss_bps) / 10_000).min(borrowed);
+
+        reserve.liquidity.borrowed_amount_sf = (borrowed - loss).to_bits();
+    }
+
+    fn supply(&self, liquidity_amount: u64, collateral_amount: u64) {
+        let reserve = &mut self.reserve.borrow_mut();
+        reserve.liquidity.available_amount += liquidity_amount;
+        reserve.collateral.mint_total_supply += collateral_amount;
+    }
+
+    fn redeem(&self, liquidity_amount: u64, collateral_amount: u64) {
+        let reserve = &mut self.reserve.borrow_mut();
+        reserve.liquidity.available_amount -= liquidity_amount;
+        reserve.collateral.mint_total_supply -= collateral_amount;
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ReserveParams {
+    available_liquidity: u64,
+    borrowed_liquidity: u64,
+    liquidity_per_ctoken_bps: u64,
+    target_allocation_weight: u64,
+    interest_bps: u64,
+}
+
+#[derive(Clone, Debug)]
+struct VaultParams {
+    initial_deposit: u64,
+    management_fee_bps: u64,
+    performance_fee_bps: u64,
+    fees_in_shares: bool,
+    reserves: Vec<ReserveParams>,
+    seconds_elapsed: u64,
+}
+
+struct TestVault {
+    vault: Box<VaultState>,
+    global_config: Box<GlobalConfig>,
+    reserves: Vec<MockReserve>,
+    now: u64,
+}
+
+impl TestVault {
+    fn build(params: &VaultParams) -> Self {
+        let mut vault: Box<VaultState> = Box::new(bytemuck::Zeroable::zeroed());
+        vault.token_mint_decimals = 6;
+        vault.shares_mint_decimals = 6;
+        vault.management_fee_bps = params.management_fee_bps;
+        vault.performance_fee_bps = params.performance_fee_bps;
+        vault.fees_in_shares = u8::from(params.fees_in_shares);
+
+        let reserves: Vec<MockReserve> = params.reserves.iter().map(MockReserve::new).collect();
+        for (reserve, reserve_params) in reserves.iter().zip(params.reserves.iter()) {
+            vault
+                .upsert_reserve_allocation(
+                    reserve.address,
+                    Pubkey::new_unique(),
+                    0,
+                    reserve_params.target_allocation_weight,
+                    u64::MAX,
+                )
+                .unwrap();
+        }
+
+        let mut test_vault = Self {
+            vault,
+            global_config: Box::new(bytemuck::Zeroable::zeroed()),
+            reserves,
+            now: START_TIMESTAMP,
+        };
+
+        test_vault.deposit(params.initial_deposit).unwrap();
+        for reserve_idx in 0..test_vault.reserves.len() {
+            // Reserves with a zero target weight have nothing to invest
+            let _ = test_vault.invest(reserve_idx);
+        }
+
+        for (reserve, reserve_params) in test_vault.reserves.iter().zip(params.reserves.iter()) {
+            reserve.accrue_interest(reserve_params.interest_bps);
+        }
+        test_vault.now += params.seconds_elapsed;
+
+        test_vault
+    }
+
+    fn reserves_iter(&self) -> impl Iterator<Item = &MockReserve> + Clone {
+        self.reserves.iter()
+    }
+
+    fn holdings_total(&self) -> Fraction {
+        common::holdings(&self.vault, self.reserves_iter(), SLOT)
+            .unwrap()
+            .total_sum
+    }
+
+    fn aum(&self) -> Fraction {
+        let holdings = common::holdings(&self.vault, self.reserves_iter(), SLOT).unwrap();
+        self.vault.compute_aum(&holdings.invested.total).unwrap()
+    }
+
+    fn settle_fees(&mut self) {
+        let holdings = common::holdings(&self.vault, self.reserves.iter(), SLOT).unwrap();
+        vault_operations::refresh_rewards(&mut self.vault, self.now).unwrap();
+        vault_operations::charge_fees(&mut self.vault, &holdings.invested, self.now).unwrap();
+    }
+
+    fn deposit(&mut self, amount: u64) -> Result<DepositEffects> {
+        vault_operations::deposit(
+            &mut self.vault,
+            self.reserves.iter(),
+            amount,
+            0,
+            SLOT,
+            self.now,
+        )
+    }
+
+    fn invest(&mut self, reserve_idx: usize) -> Result<()> {
+        let reserve = &self.reserves[reserve_idx];
+        let invest_effects = {
+            let reserve_state = reserve.reserve.borrow();
+            vault_operations::invest(
+                &mut self.vault,
+                self.reserves.iter(),
+                &reserve_state,
+                &reserve.address,
+                SLOT,
+                self.now,
+                None,
+            )?
+        };
+
+        match invest_effects.direction {
+            InvestingDirection::Add => reserve.supply(
+                invest_effects.liquidity_amount,
+                invest_effects.collateral_amount,
+            ),
+            InvestingDirection::Subtract => reserve.redeem(
+                invest_effects.liquidity_amount,
+                invest_effects.collateral_amount,
+            ),
+        }
+
+        Ok(())
+    }
+
+    fn withdraw(&mut self, shares_amount: u64) -> Result<WithdrawEffects> {
+        let reserve_states: Vec<_> = self.reserves.iter().map(|r| r.reserve.borrow()).collect();
+        let reserves_to_withdraw_from: Vec<ReserveToWithdrawFrom> = self
+            .reserves
+            .iter()
+            .zip(reserve_states.iter())
+            .map(|(reserve, reserve_state)| ReserveToWithdrawFrom {
+                reserve_address: &reserve.address,
+                reserve_state,
+                ctokens_owned: self
+                    .vault
+                    .allocation_for_reserve(&reserve.address)
+                    .unwrap()
+                    .ctoken_allocation,
+            })
+            .collect();
+
+        let withdraw_effects = vault_operations::withdraw_from_reserves(
+            &mut self.vault,
+            &self.global_config,
+            &reserves_to_withdraw_from,
+            self.reserves.iter(),
+            self.now,
+            SLOT,
+            WithdrawAmount::Shares {
+                shares_amount,
+                min_tokens_out: 0,
+            },
+        );
+
+        drop(reserves_to_withdraw_from);
+        drop(reserve_states);
+
+        let withdraw_effects = withdraw_effects?;
+        self.redeem_from_reserves(&withdraw_effects);
+
+        Ok(withdraw_effects)
+    }
+
+    fn fulfil_withdrawal_request(
+        &mut self,
+        reserve_idx: Option<usize>,
+        withdrawal_request: &mut WithdrawalRequest,
+    ) -> Result<WithdrawEffects> {
+        let reserve = reserve_idx.map(|reserve_idx| &self.reserves[reserve_idx]);
+        let ctokens_owned = reserve.map(|reserve| {
+            self.vault
+                .allocation_for_reserve(&reserve.address)
+                .unwrap()
+                .ctoken_allocation
+        });
+        let withdraw_effects = {
+            let reserve_state = reserve.map(|reserve| reserve.reserve.borrow());
+            vault_operations::fulfil_withdrawal_request(
+                &mut self.vault,
+                &self.global_config,
+                withdrawal_request,
+                reserve.map(|reserve| &reserve.address),
+                reserve_state.as_deref(),
+                self.reserves.iter(),
+                self.now,
+                SLOT,
+                ctokens_owned,
+            )?
+        };
+
+        self.redeem_from_reserves(&withdraw_effects);
+
+        Ok(withdraw_effects)
+    }
+
+    fn redeem_from_reserves(&self, withdraw_effects: &WithdrawEffects) {
+        for reserve_effects in withdraw_effects.reserves.iter() {
+            let reserve = self
+                .reserves
+                .iter()
+                .find(|r| r.address == reserve_effects.reserve)
+                .unwrap();
+            reserve.redeem(
+                reserve_effects.invested_liquidity_to_disinvest,
+                reserve_effects.invested_to_disinvest_ctokens,
+            );
+        }
+    }
+
+    fn redeem_in_kind(
+        &mut self,
+        reserve_idx: usize,
+        shares_amount: u64,
+    ) -> Result<RedeemInKindEffects> {
+        let reserve = &self.reserves[reserve_idx];
+        let reserve_state = reserve.reserve.borrow();
+        let clock = Clock {
+            slot: SLOT,
+            unix_timestamp: self.now.try_into().unwrap(),
+            ..Default::default()
+        };
+
+        // The ctokens are handed over to the user, the reserve itself is left untouched
+        vault_operations::redeem_in_kind(RedeemInKindParams {
+            vault_state: &mut self.vault,
+            global_config: &self.global_config,
+            reserve_address: &reserve.address,
+            reserve_state: &reserve_state,
+            reserves_iter: self.reserves.iter(),
+            shares_amount,
+            min_ctokens_out: 0,
+            clock: &clock,
+        })
+    }
+
+    fn withdraw_pending_fees(&mut self, reserve_idx: usize) -> Result<WithdrawPendingFeesEffects> {
+        let reserve = &self.reserves[reserve_idx];
+        let effects = {
+            let reserve_state = reserve.reserve.borrow();
+            vault_operations::withdraw_pending_fees(
+                &mut self.vault,
+                &reserve.address,
+                &reserve_state,
+                self.reserves.iter(),
+                SLOT,
+                self.now,
+            )?
+        };
+
+        reserve.redeem(
+            effects.invested_liquidity_to_disinvest,
+            effects.invested_to_disinvest_ctokens,
+        );
+
+        Ok(effects)
+    }
+
+    fn assert_holdings_cover_pending_fees(&self) {
+        let holdings_total = self.holdings_total();
+        let pending_fees = self.vault.get_pending_fees();
+        assert!(
+            holdings_total >= pending_fees,
+            "holdings {} below pending fees {}",
+            holdings_total,
+            pending_fees
+        );
+    }
+}
+
+// aum_after / shares_after >= aum_before / shares_before, up to the reserves rounding
+fn share_price_not_decreased(
+    aum_before: Fraction,
+    shares_before: u64,
+    aum_after: Fraction,
+    shares_after: u64,
+) -> bool {
+    if shares_before == 0 || shares_after == 0 {
+        return true;
+    }
+
+    U256::from((aum_after + ROUNDING_TOLERANCE).to_bits()) * U256::from(shares_before)
+        >= U256::from(aum_before.to_bits()) * U256::from(shares_after)
+}
+
+// The value of `shares` at the share price, what a user can at most receive for them
+fn fair_value_of_shares(aum: Fraction, shares_issued: u64, shares: u64) -> Fraction {
+    aum * u128::from(shares) / u128::from(shares_issued)
+}
+
+fn reserve_params() -> impl Strategy<Value = ReserveParams> {
+    (
+        1_000_000_000..1_000_000_000_000_000u64,
+        0..1_000_000_000_000_000u64,
+        10_000..30_000u64,
+        0..100u64,
+        0..2_000u64,
+    )
+        .prop_map(
+            |(
+                available_liquidity,
+                borrowed_liquidity,
+                liquidity_per_ctoken_bps,
+                target_allocation_weight,
+                interest_bps,
+            )| ReserveParams {
+                available_liquidity,
+                borrowed_liquidity,
+                liquidity_per_ctoken_bps,
+                target_allocation_weight,
+                interest_bps,
+            },
+        )
+}
+
+fn vault_params() -> impl Strategy<Value = VaultParams> {
+    (
+        1_000_000..1_000_000_000_000u64,
+        0..500u64,
+        0..3_000u64,
+        any::<bool>(),
+        prop::collection::vec(reserve_params(), 1..=3),
+        0..SECONDS_PER_YEAR_U64,
+    )
+        .prop_map(
+            |(
+                initial_deposit,
+                management_fee_bps,
+                performance_fee_bps,
+                fees_in_shares,
+                reserves,
+                seconds_elapsed,
+            )| VaultParams {
+                initial_deposit,
+                management_fee_bps,
+                performance_fee_bps,
+                fees_in_shares,
+                reserves,
+                seconds_elapsed,
+            },
+        )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn deposit_mints_at_most_the_fair_amount_of_shares(
+        params in vault_params(),
+        amount in 1_000..1_000_000_000_000u64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        test_vault.settle_fees();
+        let aum_before = test_vault.aum();
+        let shares_before = test_vault.vault.shares_issued;
+        let token_available_before = test_vault.vault.token_available;
+
+        let deposit_result = test_vault.deposit(amount);
+        prop_assume!(deposit_result.is_ok());
+        let DepositEffects { shares_to_mint, token_to_deposit, crank_funds_to_deposit, .. } =
+            deposit_result.unwrap();
+
+        prop_assert!(token_to_deposit + crank_funds_to_deposit <= amount);
+        prop_assert_eq!(test_vault.vault.shares_issued, shares_before + shares_to_mint);
+        prop_assert_eq!(
+            test_vault.vault.token_available,
+            token_available_before + token_to_deposit + crank_funds_to_deposit
+        );
+        // The user pays at least the share price for every share minted
+        prop_assert!(
+            Fraction::from(token_to_deposit) + ROUNDING_TOLERANCE
+                >= fair_value_of_shares(aum_before, shares_before, shares_to_mint)
+        );
+        prop_assert!(share_price_not_decreased(
+            aum_before,
+            shares_before,
+            test_vault.aum(),
+            test_vault.vault.shares_issued
+        ));
+        test_vault.assert_holdings_cover_pending_fees();
+    }
+
+    #[test]
+    fn withdraw_burns_at_least_the_fair_amount_of_shares(
+        params in vault_params(),
+        withdraw_bps in 1..=10_000u64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        test_vault.settle_fees();
+        let aum_before = test_vault.aum();
+        let shares_before = test_vault.vault.shares_issued;
+        let shares_amount = (shares_before * withdraw_bps / 10_000).max(1);
+
+        let withdraw_result = test_vault.withdraw(shares_amount);
+        prop_assume!(withdraw_result.is_ok());
+        let WithdrawEffects {
+            shares_to_burn,
+            available_to_send_to_user,
+            invested_liquidity_to_send_to_user,
+            ..
+        } = withdraw_result.unwrap();
+
+        prop_assert!(shares_to_burn <= shares_amount);
+        prop_assert_eq!(test_vault.vault.shares_issued, shares_before - shares_to_burn);
+        // The user never receives more than the value of the shares burnt
+        prop_assert!(
+            Fraction::from(available_to_send_to_user + invested_liquidity_to_send_to_user)
+                <= fair_value_of_shares(aum_before, shares_before, shares_to_burn)
+                    + ROUNDING_TOLERANCE
+        );
+        prop_assert!(share_price_not_decreased(
+            aum_before,
+            shares_before,
+            test_vault.aum(),
+            test_vault.vault.shares_issued
+        ));
+        test_vault.assert_holdings_cover_pending_fees();
+    }
+
+    #[test]
+    fn invest_does_not_decrease_aum(
+        params in vault_params(),
+        reserve_idx in 0..3usize,
+        reweight in 0..100u64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        let reserve_idx = reserve_idx % test_vault.reserves.len();
+        let reserve_address = test_vault.reserves[reserve_idx].address;
+        test_vault
+            .vault
+            .upsert_reserve_allocation(reserve_address, Pubkey::default(), 0, reweight, u64::MAX)
+            .unwrap();
+        test_vault.settle_fees();
+        let aum_before = test_vault.aum();
+        let shares_before = test_vault.vault.shares_issued;
+
+        let invest_result = test_vault.invest(reserve_idx);
+        prop_assume!(invest_result.is_ok());
+
+        prop_assert_eq!(test_vault.vault.shares_issued, shares_before);
+        prop_assert!(test_vault.aum() + ROUNDING_TOLERANCE >= aum_before);
+        test_vault.assert_holdings_cover_pending_fees();
+    }
+
+    #[test]
+    fn redeem_in_kind_hands_out_at_most_the_fair_value(
+        params in vault_params(),
+        reserve_idx in 0..3usize,
+        redeem_bps in 1..=10_000u64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        let reserve_idx = reserve_idx % test_vault.reserves.len();
+        test_vault.settle_fees();
+        let aum_before = test_vault.aum();
+        let shares_before = test_vault.vault.shares_issued;
+        let shares_amount = (shares_before * redeem_bps / 10_000).max(1);
+
+        let redeem_result = test_vault.redeem_in_kind(reserve_idx, shares_amount);
+        prop_assume!(redeem_result.is_ok());
+        let RedeemInKindEffects { shares_to_burn, actual_liquidity_value, .. } =
+            redeem_result.unwrap();
+
+        prop_assert!(shares_to_burn <= shares_amount);
+        prop_assert_eq!(test_vault.vault.shares_issued, shares_before - shares_to_burn);
+        prop_assert!(
+            actual_liquidity_value
+                <= fair_value_of_shares(aum_before, shares_before, shares_to_burn)
+                    + ROUNDING_TOLERANCE
+        );
+        prop_assert!(share_price_not_decreased(
+            aum_before,
+            shares_before,
+            test_vault.aum(),
+            test_vault.vault.shares_issued
+        ));
+        test_vault.assert_holdings_cover_pending_fees();
+    }
+
+    #[test]
+    fn charge_fees_only_moves_value_to_pending_fees(
+        params in vault_params(),
+        seconds in 0..SECONDS_PER_YEAR_U64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        test_vault.settle_fees();
+        let holdings_before = test_vault.holdings_total();
+        let pending_fees_before = test_vault.vault.get_pending_fees();
+        let shares_before = test_vault.vault.shares_issued;
+
+        test_vault.now += seconds;
+        test_vault.settle_fees();
+
+        prop_assert_eq!(test_vault.holdings_total(), holdings_before);
+        prop_assert!(test_vault.vault.get_pending_fees() >= pending_fees_before);
+        prop_assert!(test_vault.vault.shares_issued >= shares_before);
+        if params.fees_in_shares {
+            prop_assert_eq!(test_vault.vault.get_pending_fees(), pending_fees_before);
+        } else {
+            prop_assert_eq!(test_vault.vault.shares_issued, shares_before);
+        }
+        test_vault.assert_holdings_cover_pending_fees();
+    }
+
+    #[test]
+    fn give_up_pending_fee_reduces_pending_fees_by_the_amount(
+        params in vault_params(),
+        max_amount_to_give_up in 0..1_000_000_000_000u64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        test_vault.settle_fees();
+        let pending_fees_before = test_vault.vault.get_pending_fees();
+        let holdings_before = test_vault.holdings_total();
+
+        vault_operations::give_up_pending_fee(
+            &mut test_vault.vault,
+            test_vault.reserves.iter(),
+            SLOT,
+            test_vault.now,
+            max_amount_to_give_up,
+        )
+        .unwrap();
+
+        let given_up = Fraction::from(max_amount_to_give_up).min(pending_fees_before);
+        prop_assert_eq!(test_vault.vault.get_pending_fees(), pending_fees_before - given_up);
+        prop_assert_eq!(test_vault.holdings_total(), holdings_before);
+        test_vault.assert_holdings_cover_pending_fees();
+    }
+
+    #[test]
+    fn withdraw_pending_fees_never_sends_more_than_pending(
+        params in vault_params(),
+        reserve_idx in 0..3usize,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        let reserve_idx = reserve_idx % test_vault.reserves.len();
+        test_vault.settle_fees();
+        let pending_fees_before = test_vault.vault.get_pending_fees();
+        let aum_before = test_vault.aum();
+        let shares_before = test_vault.vault.shares_issued;
+
+        let withdraw_result = test_vault.withdraw_pending_fees(reserve_idx);
+        prop_assume!(withdraw_result.is_ok());
+        let WithdrawPendingFeesEffects {
+            available_to_send_to_user,
+            invested_liquidity_to_send_to_user,
+            ..
+        } = withdraw_result.unwrap();
+
+        let pending_fees_after = test_vault.vault.get_pending_fees();
+        let sent = Fraction::from(available_to_send_to_user + invested_liquidity_to_send_to_user);
+        prop_assert!(pending_fees_after <= pending_fees_before);
+        prop_assert!(sent <= pending_fees_before - pending_fees_after);
+        prop_assert_eq!(test_vault.vault.shares_issued, shares_before);
+        // Paying out the fees does not take anything from the share holders
+        prop_assert!(test_vault.aum() + ROUNDING_TOLERANCE >= aum_before);
+        test_vault.assert_holdings_cover_pending_fees();
+    }
+
+    #[test]
+    fn refresh_rewards_moves_rewards_into_available(
+        reward_per_second in 0..1_000_000u64,
+        rewards_available in 0..1_000_000_000_000u64,
+        token_available in 0..1_000_000_000_000u64,
+        seconds in 0..SECONDS_PER_YEAR_U64,
+    ) {
+        let mut vault: Box<VaultState> = Box::new(bytemuck::Zeroable::zeroed());
+        vault.token_available = token_available;
+        vault.reward_info.reward_per_second = reward_per_second;
+        vault.reward_info.rewards_available = rewards_available;
+        vault.reward_info.last_issuance_ts = START_TIMESTAMP;
+
+        let distributed =
+            vault_operations::refresh_rewards(&mut vault, START_TIMESTAMP + seconds).unwrap();
+
+        prop_assert!(distributed <= rewards_available);
+        prop_assert!(u128::from(distributed) <= u128::from(reward_per_second) * u128::from(seconds));
+        prop_assert_eq!(vault.token_available, token_available + distributed);
+        prop_assert_eq!(vault.reward_info.rewards_available, rewards_available - distributed);
+        prop_assert_eq!(
+            vault.reward_info.cumulative_rewards_distributed_analytics,
+            distributed
+        );
+    }
+
+    #[test]
+    fn fulfilled_withdrawal_request_is_claimable_for_at_most_the_fair_value(
+        params in vault_params(),
+        reserve_idx in 0..3usize,
+        request_bps in 1..=10_000u64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        let reserve_idx = reserve_idx % test_vault.reserves.len();
+        test_vault.settle_fees();
+        let aum_before = test_vault.aum();
+        let shares_before = test_vault.vault.shares_issued;
+        let shares_amount = (shares_before * request_bps / 10_000).max(1);
+
+        let mut withdrawal_request = WithdrawalRequest::default();
+        vault_operations::request_withdrawal(
+            &mut test_vault.vault,
+            &mut withdrawal_request,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            shares_amount,
+            test_vault.now,
+        )
+        .unwrap();
+        prop_assert_eq!(test_vault.vault.withdrawal_queue.escrowed_shares, shares_amount);
+
+        let fulfil_result = test_vault.fulfil_withdrawal_request(Some(reserve_idx), &mut withdrawal_request);
+        prop_assume!(fulfil_result.is_ok());
+        let WithdrawEffects {
+            shares_to_burn,
+            available_to_send_to_user,
+            invested_liquidity_to_send_to_user,
+            ..
+        } = fulfil_result.unwrap();
+
+        let queue = &test_vault.vault.withdrawal_queue;
+        prop_assert_eq!(test_vault.vault.shares_issued, shares_before - shares_to_burn);
+        prop_assert_eq!(withdrawal_request.shares_burned, shares_to_burn);
+        prop_assert_eq!(
+            withdrawal_request.shares_escrowed + withdrawal_request.shares_burned,
+            shares_amount
+        );
+        prop_assert_eq!(queue.escrowed_shares, withdrawal_request.shares_escrowed);
+        prop_assert_eq!(
+            withdrawal_request.tokens_claimable,
+            available_to_send_to_user + invested_liquidity_to_send_to_user
+        );
+        prop_assert_eq!(queue.claimable_tokens, withdrawal_request.tokens_claimable);
+        // The requester never gets more than the value of the shares burnt
+        prop_assert!(
+            Fraction::from(withdrawal_request.tokens_claimable)
+                <= fair_value_of_shares(aum_before, shares_before, shares_to_burn)
+                    + ROUNDING_TOLERANCE
+        );
+        prop_assert!(share_price_not_decreased(
+            aum_before,
+            shares_before,
+            test_vault.aum(),
+            test_vault.vault.shares_issued
+        ));
+        test_vault.assert_holdings_cover_pending_fees();
+
+        let tokens_claimable = withdrawal_request.tokens_claimable;
+        let shares_escrowed = withdrawal_request.shares_escrowed;
+        let claim_result =
+            vault_operations::claim_withdrawal_request(&mut test_vault.vault, &mut withdrawal_request);
+        if withdrawal_request.status == WithdrawalRequestStatus::Pending {
+            prop_assert!(claim_result.is_err());
+        } else {
+            let claim_effects = claim_result.unwrap();
+            prop_assert_eq!(claim_effects.tokens_to_send_to_user, tokens_claimable);
+            prop_assert_eq!(claim_effects.shares_to_return_to_user, shares_escrowed);
+            prop_assert_eq!(test_vault.vault.withdrawal_queue.claimable_tokens, 0);
+            prop_assert_eq!(test_vault.vault.withdrawal_queue.escrowed_shares, 0);
+
+            // A request is only ever paid out once
+            let claim_effects =
+                vault_operations::claim_withdrawal_request(&mut test_vault.vault, &mut withdrawal_request)
+                    .unwrap();
+            prop_assert_eq!(claim_effects.tokens_to_send_to_user, 0);
+            prop_assert_eq!(claim_effects.shares_to_return_to_user, 0);
+        }
+    }
+
+    #[test]
+    fn first_loss_shares_absorb_losses_before_other_holders(
+        params in vault_params(),
+        first_loss_bps in 1..=10_000u64,
+        loss_bps in 1..5_000u64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        test_vault.settle_fees();
+        let shares_before = test_vault.vault.shares_issued;
+        let first_loss_shares_before = shares_before * first_loss_bps / 10_000;
+        test_vault.vault.first_loss_shares = first_loss_shares_before;
+        let prev_aum = test_vault.vault.get_prev_aum();
+        let absorbed_before = test_vault.vault.get_cumulative_first_loss_absorbed();
+
+        for reserve in test_vault.reserves.iter() {
+            reserve.socialize_loss(loss_bps);
+        }
+        let holdings = common::holdings(&test_vault.vault, test_vault.reserves_iter(), SLOT).unwrap();
+        let new_aum = test_vault.vault.compute_aum(&holdings.invested.total);
+        prop_assume!(new_aum.is_ok());
+        let new_aum = new_aum.unwrap();
+        prop_assume!(new_aum < prev_aum);
+
+        test_vault.settle_fees();
+
+        let shares_absorbed = first_loss_shares_before - test_vault.vault.first_loss_shares;
+        prop_assert_eq!(test_vault.vault.shares_issued, shares_before - shares_absorbed);
+        prop_assert_eq!(test_vault.vault.first_loss_shares_pending_burn, shares_absorbed);
+        prop_assert!(
+            test_vault.vault.get_cumulative_first_loss_absorbed() - absorbed_before
+                <= prev_aum - new_aum
+        );
+        if test_vault.vault.first_loss_shares > 0 {
+            // The loss is covered, the other holders lose at most the value of one share
+            prop_assert!(
+                U256::from(new_aum.to_bits()) * U256::from(shares_before)
+                    + U256::from(prev_aum.to_bits())
+                    >= U256::from(prev_aum.to_bits())
+                        * U256::from(test_vault.vault.shares_issued)
+            );
+        } else {
+            prop_assert_eq!(shares_absorbed, first_loss_shares_before);
+        }
+    }
+
+    #[test]
+    fn performance_fees_are_only_charged_above_high_water_mark_and_hurdle(
+        params in vault_params(),
+        high_water_mark_premium_bps in 0..3_000u64,
+        hurdle_bps in 0..2_000u64,
+        interest_bps in 0..2_000u64,
+        seconds in 1..SECONDS_PER_YEAR_U64,
+    ) {
+        let mut test_vault = TestVault::build(&params);
+        test_vault.settle_fees();
+        let shares = test_vault.vault.shares_issued;
+        prop_assume!(shares > 0);
+        let high_water_mark = test_vault.vault.get_high_water_mark()
+            * u128::from(10_000 + high_water_mark_premium_bps)
+            / 10_000;
+        test_vault.vault.set_high_water_mark(high_water_mark);
+        test_vault.vault.performance_fee_hurdle_bps = hurdle_bps;
+        let prev_aum = test_vault.vault.get_prev_aum();
+        let perf_fees_before = test_vault.vault.get_cumulative_perf_fees();
+
+        for reserve in test_vault.reserves.iter() {
+            reserve.accrue_interest(interest_bps);
+        }
+        test_vault.now += seconds;
+        let new_aum = test_vault.aum();
+        test_vault.settle_fees();
+
+        let perf_fees = test_vault.vault.get_cumulative_perf_fees() - perf_fees_before;
+        let high_water_mark_aum = high_water_mark.saturating_mul_int(u128::from(shares));
+        let earned_above_high_water_mark = new_aum
+            .saturating_sub(high_water_mark_aum)
+            .min(new_aum.saturating_sub(prev_aum));
+        let hurdle = prev_aum
+            * (Fraction::from_bps(hurdle_bps) * u128::from(seconds)
+                / SECONDS_PER_YEAR.ceil() as u128);
+        let max_perf_fees = Fraction::from_bps(params.performance_fee_bps)
+            * earned_above_high_water_mark.saturating_sub(hurdle);
+
+        prop_assert!(perf_fees <= max_perf_fees + ROUNDING_TOLERANCE);
+        if new_aum <= high_water_mark_aum {
+            prop_assert_eq!(perf_fees, Fraction::ZERO);
+        }
+        prop_assert!(test_vault.vault.get_high_water_mark() >= high_water_mark);
+    }
+}
+
+fn single_reserve_vault_params() -> VaultParams {
+    VaultParams {
+        initial_deposit: 1_000_000_000,
+        management_fee_bps: 0,
+        performance_fee_bps: 0,
+        fees_in_shares: false,
+        reserves: vec![ReserveParams {
+            available_liquidity: 1_000_000_000,
+            borrowed_liquidity: 0,
+            liquidity_per_ctoken_bps: 10_000,
+            target_allocation_weight: 100,
+            interest_bps: 0,
+        }],
+        seconds_elapsed: 0,
+    }
+}
+
+fn request_withdrawal(test_vault: &mut TestVault, shares_amount: u64) -> WithdrawalRequest {
+    let mut withdrawal_request = WithdrawalRequest::default();
+    vault_operations::request_withdrawal(
+        &mut test_vault.vault,
+        &mut withdrawal_request,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        shares_amount,
+        test_vault.now,
+    )
+    .unwrap();
+
+    withdrawal_request
+}
+
+#[test]
+fn withdrawal_request_is_partly_filled_with_liquidity_below_min_withdraw_amount() {
+    let mut test_vault = TestVault::build(&single_reserve_vault_params());
+    test_vault.settle_fees();
+    test_vault.vault.min_withdraw_amount = 10_000_000;
+
+    // Borrowers take all but one token of the reserve liquidity
+    {
+        let reserve = &mut test_vault.reserves[0].reserve.borrow_mut();
+        let borrowed = reserve.liquidity.available_amount - 1_000_001;
+        reserve.liquidity.available_amount -= borrowed;
+        reserve.liquidity.borrowed_amount_sf =
+            (Fraction::from_bits(reserve.liquidity.borrowed_amount_sf) + Fraction::from(borrowed))
+                .to_bits();
+    }
+
+    let shares_requested = test_vault.vault.shares_issued / 2;
+    let mut withdrawal_request = request_withdrawal(&mut test_vault, shares_requested);
+
+    let withdraw_effects = test_vault
+        .fulfil_withdrawal_request(Some(0), &mut withdrawal_request)
+        .unwrap();
+
+    let tokens_claimable = withdrawal_request.tokens_claimable;
+    assert!(tokens_claimable > 0);
+    assert!(tokens_claimable <= test_vault.vault.min_withdraw_amount);
+    assert_eq!(
+        tokens_claimable,
+        withdraw_effects.available_to_send_to_user
+            + withdraw_effects.invested_liquidity_to_send_to_user
+    );
+    assert_eq!(withdrawal_request.status, WithdrawalRequestStatus::Pending);
+    assert_eq!(
+        withdrawal_request.shares_escrowed,
+        shares_requested - withdraw_effects.shares_to_burn
+    );
+    assert_eq!(
+        test_vault.vault.withdrawal_queue.claimable_tokens,
+        tokens_claimable
+    );
+    assert!(test_vault
+        .vault
+        .withdrawal_queue
+        .is_next_to_fulfil(withdrawal_request.request_id));
+}
+
+#[test]
+fn withdrawal_request_is_filled_from_available_without_a_reserve() {
+    let mut test_vault = TestVault::build(&single_reserve_vault_params());
+    test_vault.deposit(500_000_000).unwrap();
+    test_vault.settle_fees();
+
+    let shares_requested = test_vault.vault.shares_issued / 10;
+    let mut withdrawal_request = request_withdrawal(&mut test_vault, shares_requested);
+
+    let withdraw_effects = test_vault
+        .fulfil_withdrawal_request(None, &mut withdrawal_request)
+        .unwrap();
+
+    assert_eq!(
+        withdrawal_request.status,
+        WithdrawalRequestStatus::Fulfilled
+    );
+    assert_eq!(
+        withdrawal_request.shares_burned,
+        withdraw_effects.shares_to_burn
+    );
+    assert_eq!(withdraw_effects.invested_to_disinvest_ctokens, 0);
+    assert!(withdraw_effects.reserves.is_empty());
+    assert_eq!(
+        withdrawal_request.tokens_claimable,
+        withdraw_effects.available_to_send_to_user
+    );
+}
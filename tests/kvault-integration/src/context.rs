@@ -0,0 +1,299 @@
+use anchor_lang::{prelude::Clock, AccountDeserialize, AnchorDeserialize};
+use kvault_client::{
+    accounts,
+    kamino_vault::{self, GlobalConfig, VaultState},
+    pda,
+};
+use solana_program_test::{
+    find_file, read_file, BanksClientError, ProgramTest, ProgramTestContext,
+};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+const COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+const HEAP_FRAME_BYTES: u32 = 256 * 1024;
+const RETURN_DATA_PADDING: usize = 1024;
+
+pub struct TestContext {
+    pub context: ProgramTestContext,
+    // Upgrade authority of the kvault program, which makes it the global admin once
+    // the global config is initialized
+    pub admin: Keypair,
+}
+
+pub struct TransactionOutcome {
+    pub result: Result<(), TransactionError>,
+    pub logs: Vec<String>,
+    pub return_data: Option<Vec<u8>>,
+}
+
+impl TransactionOutcome {
+    pub fn assert_ok(&self) -> &Self {
+        if let Err(err) = &self.result {
+            panic!(
+                "Transaction failed with {err:?}, logs:\n{}",
+                self.logs.join("\n")
+            );
+        }
+        self
+    }
+
+    pub fn assert_error(&self, error: impl Into<u32>) -> &Self {
+        let expected_code = error.into();
+        match &self.result {
+            Err(TransactionError::InstructionError(_, InstructionError::Custom(code)))
+                if *code == expected_code => {}
+            result => panic!(
+                "Expected custom error {expected_code}, got {result:?}, logs:\n{}",
+                self.logs.join("\n")
+            ),
+        }
+        self
+    }
+
+    /// Number of events emitted with `emit_cpi!`, each of them shows up as a
+    /// self-invocation of the kvault program
+    pub fn events_emitted(&self) -> usize {
+        let self_invocation = format!("Program {} invoke [2]", kamino_vault::ID);
+        self.logs
+            .iter()
+            .filter(|log| **log == self_invocation)
+            .count()
+    }
+
+    /// Decodes the value returned by the program, which the bank records with its
+    /// trailing zero bytes trimmed
+    pub fn return_value<T: AnchorDeserialize>(&self) -> T {
+        let mut return_data = self
+            .return_data
+            .clone()
+            .expect("Transaction did not return any data");
+        return_data.resize(return_data.len() + RETURN_DATA_PADDING, 0);
+        T::deserialize(&mut return_data.as_slice()).unwrap()
+    }
+
+    pub fn logs_contain(&self, message: &str) -> bool {
+        self.logs.iter().any(|log| log.contains(message))
+    }
+}
+
+impl TestContext {
+    pub async fn start() -> Self {
+        let admin = Keypair::new();
+
+        let mut program_test = ProgramTest::default();
+        program_test.add_program("kamino_lending", kamino_lending::ID, None);
+        add_upgradeable_program(
+            &mut program_test,
+            "kamino_vault",
+            kamino_vault::ID,
+            admin.pubkey(),
+        );
+        program_test.add_account(
+            admin.pubkey(),
+            Account::new(1_000 * LAMPORTS_PER_SOL, 0, &system_program::ID),
+        );
+
+        Self {
+            context: program_test.start_with_context().await,
+            admin,
+        }
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    /// Sends the instructions in one transaction paid by the context payer, with the
+    /// maximum compute budget and heap frame
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> TransactionOutcome {
+        self.try_process(instructions, signers).await.unwrap()
+    }
+
+    /// Same as `process`, with the transactions the bank rejects before executing them,
+    /// such as oversized ones, returned as an error
+    pub async fn try_process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<TransactionOutcome, BanksClientError> {
+        let mut all_instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(COMPUTE_UNIT_LIMIT),
+            ComputeBudgetInstruction::request_heap_frame(HEAP_FRAME_BYTES),
+        ];
+        all_instructions.extend_from_slice(instructions);
+
+        // A fresh blockhash keeps a test from having identical transactions deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            &all_instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+
+        let outcome = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await?;
+        let metadata = outcome.metadata.unwrap_or_default();
+
+        Ok(TransactionOutcome {
+            result: outcome.result,
+            logs: metadata.log_messages,
+            return_data: metadata.return_data.map(|return_data| return_data.data),
+        })
+    }
+
+    /// Same as `process` with the admin as an additional signer
+    pub async fn process_as_admin(&mut self, instructions: &[Instruction]) -> TransactionOutcome {
+        let admin = self.admin.insecure_clone();
+        self.process(instructions, &[&admin]).await
+    }
+
+    pub async fn get_account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+    }
+
+    pub async fn account_exists(&mut self, address: &Pubkey) -> bool {
+        self.get_account(address).await.is_some()
+    }
+
+    /// Writes an account straight into the bank, rent exempt
+    pub fn set_account(&mut self, address: &Pubkey, owner: &Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: *owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.context
+            .set_account(address, &AccountSharedData::from(account));
+    }
+
+    pub async fn anchor_account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self
+            .get_account(address)
+            .await
+            .unwrap_or_else(|| panic!("Account {address} not found"));
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn vault_state(&mut self, vault_state: &Pubkey) -> Box<VaultState> {
+        let account = self
+            .get_account(vault_state)
+            .await
+            .unwrap_or_else(|| panic!("Vault {vault_state} not found"));
+        accounts::decode_vault_state(&account.data).unwrap()
+    }
+
+    pub async fn global_config(&mut self) -> Box<GlobalConfig> {
+        let account = self
+            .get_account(&pda::global_config())
+            .await
+            .expect("Global config not initialized");
+        accounts::decode_global_config(&account.data).unwrap()
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+    }
+
+    /// Moves to the next slot with the clock `seconds` later
+    pub async fn advance_time(&mut self, seconds: u64) {
+        let clock = self.clock().await;
+        self.context.warp_to_slot(clock.slot + 1).unwrap();
+
+        let mut clock = self.clock().await;
+        clock.unix_timestamp += i64::try_from(seconds).unwrap();
+        self.context.set_sysvar(&clock);
+    }
+
+    pub async fn create_funded_keypair(&mut self) -> Keypair {
+        let keypair = Keypair::new();
+        self.set_lamports(&keypair.pubkey(), 10 * LAMPORTS_PER_SOL);
+        keypair
+    }
+
+    pub fn set_lamports(&mut self, address: &Pubkey, lamports: u64) {
+        self.context.set_account(
+            address,
+            &AccountSharedData::new(lamports, 0, &system_program::ID),
+        );
+    }
+}
+
+// Deploys the program through the upgradeable loader, `add_program` would load it
+// without the program data account the global config initialization checks
+fn add_upgradeable_program(
+    program_test: &mut ProgramTest,
+    program_name: &str,
+    program_id: Pubkey,
+    upgrade_authority: Pubkey,
+) {
+    let program_file = find_file(&format!("{program_name}.so")).unwrap_or_else(|| {
+        panic!("{program_name}.so not found, build it with `cargo build-sbf` and set SBF_OUT_DIR")
+    });
+    let elf = read_file(program_file);
+    let rent = Rent::default();
+    let programdata_address = bpf_loader_upgradeable::get_program_data_address(&program_id);
+
+    let program_data = bincode::serialize(&UpgradeableLoaderState::Program {
+        programdata_address,
+    })
+    .unwrap();
+    program_test.add_account(
+        program_id,
+        Account {
+            lamports: rent.minimum_balance(program_data.len()),
+            data: program_data,
+            owner: bpf_loader_upgradeable::ID,
+            executable: true,
+            rent_epoch: 0,
+        },
+    );
+
+    let mut programdata_data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(upgrade_authority),
+    })
+    .unwrap();
+    programdata_data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+    programdata_data.extend_from_slice(&elf);
+    program_test.add_account(
+        programdata_address,
+        Account {
+            lamports: rent.minimum_balance(programdata_data.len()),
+            data: programdata_data,
+            owner: bpf_loader_upgradeable::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
@@ -0,0 +1,253 @@
+use anchor_lang::{AccountSerialize, Discriminator, InstructionData, ToAccountMetas};
+use kamino_lending::{
+    fraction::Fraction,
+    state::{
+        InitLendingMarketParams, InitReserveParams, LendingMarket, NewReserveCollateralParams,
+        NewReserveLiquidityParams, ReserveCollateral, ReserveConfig, ReserveLiquidity,
+    },
+    utils::seeds::LENDING_MARKET_AUTH,
+    Reserve,
+};
+use kvault_client::{
+    accounts::{self, VaultReserve},
+    pda,
+};
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use pythnet_sdk::messages::PriceFeedMessage;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Signer};
+
+use crate::{
+    token::{mint_data, token_account_data},
+    TestContext,
+};
+
+// Every reserve is priced at 1 USD, the vault accounting does not depend on prices
+const ORACLE_PRICE: i64 = 100_000_000;
+const ORACLE_EXPONENT: i32 = -8;
+const MAX_PRICE_AGE_SECONDS: u64 = 10 * 365 * 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReserveFixture {
+    pub address: Pubkey,
+    pub lending_market: Pubkey,
+    pub liquidity_mint: Pubkey,
+    pub liquidity_supply: Pubkey,
+    pub fee_vault: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub collateral_supply: Pubkey,
+    pub oracle: Pubkey,
+}
+
+impl ReserveFixture {
+    pub fn vault_reserve(&self) -> VaultReserve {
+        VaultReserve {
+            address: self.address,
+            lending_market: self.lending_market,
+            lending_market_authority: pda::lending_market_authority(&self.lending_market),
+            liquidity_supply: self.liquidity_supply,
+            collateral_mint: self.collateral_mint,
+        }
+    }
+}
+
+fn lending_market_authority_bump(lending_market: &Pubkey) -> u8 {
+    Pubkey::find_program_address(
+        &[LENDING_MARKET_AUTH, lending_market.as_ref()],
+        &kamino_lending::ID,
+    )
+    .1
+}
+
+/// Refreshes the reserve with its oracle price, the vault instructions themselves
+/// refresh the reserves without price updates
+pub fn refresh_reserve_ix(reserve: &ReserveFixture) -> Instruction {
+    Instruction {
+        program_id: kamino_lending::ID,
+        accounts: kamino_lending::accounts::RefreshReserve {
+            reserve: reserve.address,
+            lending_market: reserve.lending_market,
+            pyth_oracle: Some(reserve.oracle),
+            switchboard_price_oracle: None,
+            switchboard_twap_oracle: None,
+            scope_prices: None,
+        }
+        .to_account_metas(None),
+        data: kamino_lending::instruction::RefreshReserve {}.data(),
+    }
+}
+
+fn zero_copy_account_data<T: Discriminator + bytemuck::Pod>(account: &T) -> Vec<u8> {
+    let mut data = T::discriminator().to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(account));
+    data
+}
+
+impl TestContext {
+    pub fn create_lending_market(&mut self) -> Pubkey {
+        let lending_market = Pubkey::new_unique();
+        let bump_seed = lending_market_authority_bump(&lending_market);
+
+        let mut market: LendingMarket = bytemuck::Zeroable::zeroed();
+        market.init(InitLendingMarketParams {
+            quote_currency: [0; 32],
+            lending_market_owner: self.admin.pubkey(),
+            bump_seed,
+        });
+
+        self.set_account(
+            &lending_market,
+            &kamino_lending::ID,
+            zero_copy_account_data(&market),
+        );
+        lending_market
+    }
+
+    /// Creates a reserve with `available_liquidity` in its supply vault and
+    /// `borrowed_liquidity` lent out, at an exchange rate of one collateral token per
+    /// liquidity token. The borrow rate is zero, interest only accrues through
+    /// `accrue_reserve_interest`
+    pub async fn create_reserve(
+        &mut self,
+        lending_market: &Pubkey,
+        liquidity_mint: &Pubkey,
+        decimals: u8,
+        available_liquidity: u64,
+        borrowed_liquidity: u64,
+    ) -> ReserveFixture {
+        let market_authority = pda::lending_market_authority(lending_market);
+        let collateral_supply_amount = available_liquidity + borrowed_liquidity;
+        let fixture = ReserveFixture {
+            address: Pubkey::new_unique(),
+            lending_market: *lending_market,
+            liquidity_mint: *liquidity_mint,
+            liquidity_supply: Pubkey::new_unique(),
+            fee_vault: Pubkey::new_unique(),
+            collateral_mint: Pubkey::new_unique(),
+            collateral_supply: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+        };
+
+        self.set_account(
+            &fixture.liquidity_supply,
+            &spl_token::ID,
+            token_account_data(liquidity_mint, &market_authority, available_liquidity),
+        );
+        self.set_account(
+            &fixture.fee_vault,
+            &spl_token::ID,
+            token_account_data(liquidity_mint, &market_authority, 0),
+        );
+        self.set_account(
+            &fixture.collateral_mint,
+            &spl_token::ID,
+            mint_data(&market_authority, collateral_supply_amount, decimals),
+        );
+        self.set_account(
+            &fixture.collateral_supply,
+            &spl_token::ID,
+            token_account_data(&fixture.collateral_mint, &market_authority, 0),
+        );
+        self.set_oracle_price(&fixture.oracle, ORACLE_PRICE, ORACLE_EXPONENT)
+            .await;
+
+        let mut config: ReserveConfig = bytemuck::Zeroable::zeroed();
+        config.deposit_limit = u64::MAX;
+        config.borrow_limit = u64::MAX;
+        config.token_info.name[..4].copy_from_slice(b"TEST");
+        config.token_info.max_age_price_seconds = MAX_PRICE_AGE_SECONDS;
+        config.token_info.max_age_twap_seconds = MAX_PRICE_AGE_SECONDS;
+        config.token_info.pyth_configuration.price = fixture.oracle;
+        for (idx, point) in config.borrow_rate_curve.points.iter_mut().enumerate() {
+            point.utilization_rate_bps = if idx == 0 { 0 } else { 10_000 };
+            point.borrow_rate_bps = 0;
+        }
+
+        let current_slot = self.clock().await.slot;
+        let mut reserve: Reserve = bytemuck::Zeroable::zeroed();
+        reserve.init(InitReserveParams {
+            current_slot,
+            lending_market: *lending_market,
+            liquidity: Box::new(ReserveLiquidity::new(NewReserveLiquidityParams {
+                mint_pubkey: *liquidity_mint,
+                mint_decimals: decimals,
+                mint_token_program: spl_token::ID,
+                supply_vault: fixture.liquidity_supply,
+                fee_vault: fixture.fee_vault,
+                market_price_sf: Fraction::ONE.to_bits(),
+            })),
+            collateral: Box::new(ReserveCollateral::new(NewReserveCollateralParams {
+                mint_pubkey: fixture.collateral_mint,
+                supply_vault: fixture.collateral_supply,
+            })),
+            config: Box::new(config),
+        });
+        reserve.liquidity.available_amount = available_liquidity;
+        reserve.liquidity.borrowed_amount_sf = Fraction::from(borrowed_liquidity).to_bits();
+        reserve.collateral.mint_total_supply = collateral_supply_amount;
+
+        self.set_account(
+            &fixture.address,
+            &kamino_lending::ID,
+            zero_copy_account_data(&reserve),
+        );
+        fixture
+    }
+
+    pub async fn reserve_state(&mut self, reserve: &Pubkey) -> Box<Reserve> {
+        let account = self
+            .get_account(reserve)
+            .await
+            .unwrap_or_else(|| panic!("Reserve {reserve} not found"));
+        accounts::decode_reserve(&account.data).unwrap()
+    }
+
+    /// Grows the debt of the reserve borrowers by `interest`, which raises the value
+    /// of the reserve collateral tokens
+    pub async fn accrue_reserve_interest(&mut self, reserve: &Pubkey, interest: u64) {
+        let mut reserve_state = self.reserve_state(reserve).await;
+        let borrowed = Fraction::from_bits(reserve_state.liquidity.borrowed_amount_sf);
+        reserve_state.liquidity.borrowed_amount_sf =
+            (borrowed + Fraction::from(interest)).to_bits();
+        self.set_account(
+            reserve,
+            &kamino_lending::ID,
+            zero_copy_account_data(reserve_state.as_ref()),
+        );
+    }
+
+    /// Writes off `loss` of the reserve debt, as a bad debt socialization would
+    pub async fn socialize_reserve_loss(&mut self, reserve: &Pubkey, loss: u64) {
+        let mut reserve_state = self.reserve_state(reserve).await;
+        let borrowed = Fraction::from_bits(reserve_state.liquidity.borrowed_amount_sf);
+        reserve_state.liquidity.borrowed_amount_sf = (borrowed - Fraction::from(loss)).to_bits();
+        self.set_account(
+            reserve,
+            &kamino_lending::ID,
+            zero_copy_account_data(reserve_state.as_ref()),
+        );
+    }
+
+    /// Writes a verified Pyth pull oracle price update published at the current time
+    pub async fn set_oracle_price(&mut self, oracle: &Pubkey, price: i64, exponent: i32) {
+        let clock = self.clock().await;
+        let price_update = PriceUpdateV2 {
+            write_authority: Pubkey::default(),
+            verification_level: VerificationLevel::Full,
+            price_message: PriceFeedMessage {
+                feed_id: oracle.to_bytes(),
+                price,
+                conf: 0,
+                exponent,
+                publish_time: clock.unix_timestamp,
+                prev_publish_time: clock.unix_timestamp,
+                ema_price: price,
+                ema_conf: 0,
+            },
+            posted_slot: clock.slot,
+        };
+
+        let mut data = Vec::new();
+        price_update.try_serialize(&mut data).unwrap();
+        self.set_account(oracle, &pyth_solana_receiver_sdk::ID, data);
+    }
+}
@@ -0,0 +1,29 @@
+#![allow(clippy::result_large_err)]
+
+//! Harness for the end-to-end tests of the Kamino lending vault program.
+//!
+//! The tests boot `solana-program-test` with the kvault program deployed as an
+//! upgradeable program, so that the global config can be initialized from its
+//! upgrade authority, and with the klend program loaded from a local fixture. The
+//! lending markets, reserves and their Pyth oracles are written directly into the
+//! test bank, which lets the tests accrue interest or socialize losses on a reserve
+//! without going through obligations.
+//!
+//! The programs have to be built or fetched before running the tests:
+//!
+//! ```sh
+//! cargo build-sbf --manifest-path programs/kvault/Cargo.toml
+//! solana program dump -um KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD \
+//!     tests/kvault-integration/tests/fixtures/kamino_lending.so
+//! SBF_OUT_DIR=$PWD/target/deploy cargo test -p kvault-integration-tests --features test-sbf
+//! ```
+
+pub mod context;
+pub mod klend;
+pub mod token;
+pub mod vault;
+
+pub use context::{TestContext, TransactionOutcome};
+pub use klend::ReserveFixture;
+pub use kvault_client;
+pub use vault::VaultFixture;
@@ -0,0 +1,95 @@
+use anchor_spl::token_2022::spl_token_2022::{self, extension::StateWithExtensionsOwned};
+use solana_sdk::pubkey::Pubkey;
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+};
+use spl_token::{
+    solana_program::{program_option::COption, program_pack::Pack},
+    state::{Account, AccountState, Mint},
+};
+
+use crate::TestContext;
+
+pub fn mint_data(mint_authority: &Pubkey, supply: u64, decimals: u8) -> Vec<u8> {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::Some(*mint_authority),
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    data
+}
+
+pub fn token_account_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0; Account::LEN];
+    Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: AccountState::Initialized,
+        ..Account::default()
+    }
+    .pack_into_slice(&mut data);
+    data
+}
+
+impl TestContext {
+    /// Creates a mint of the token program with the context payer as mint authority
+    pub fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        self.set_account(&mint, &spl_token::ID, mint_data(&self.payer(), 0, decimals));
+        mint
+    }
+
+    pub async fn create_ata(
+        &mut self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Pubkey {
+        let ata = get_associated_token_address_with_program_id(owner, mint, token_program);
+        if !self.account_exists(&ata).await {
+            let create_ata_ix =
+                create_associated_token_account(&self.payer(), owner, mint, token_program);
+            self.process(&[create_ata_ix], &[]).await.assert_ok();
+        }
+        ata
+    }
+
+    /// Mints tokens of a mint created by `create_mint` to the ATA of `owner`
+    pub async fn mint_to(&mut self, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+        let ata = self.create_ata(owner, mint, &spl_token::ID).await;
+        let mint_to_ix =
+            spl_token::instruction::mint_to(&spl_token::ID, mint, &ata, &self.payer(), &[], amount)
+                .unwrap();
+        self.process(&[mint_to_ix], &[]).await.assert_ok();
+        ata
+    }
+
+    // Both token programs share the base layout, the Token-2022 unpacking reads either
+
+    pub async fn token_balance(&mut self, token_account: &Pubkey) -> u64 {
+        let account = self
+            .get_account(token_account)
+            .await
+            .unwrap_or_else(|| panic!("Token account {token_account} not found"));
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(account.data)
+            .unwrap()
+            .base
+            .amount
+    }
+
+    pub async fn mint_supply(&mut self, mint: &Pubkey) -> u64 {
+        let account = self
+            .get_account(mint)
+            .await
+            .unwrap_or_else(|| panic!("Mint {mint} not found"));
+        StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(account.data)
+            .unwrap()
+            .base
+            .supply
+    }
+}
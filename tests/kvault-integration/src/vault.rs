@@ -0,0 +1,242 @@
+use anchor_lang::AnchorSerialize;
+use anchor_spl::token_2022;
+use kvault_client::{
+    accounts::{VaultAccounts, VaultReserve},
+    instructions,
+    kamino_vault::{self, utils::consts::VAULT_STATE_SIZE},
+    pda, VaultConfigField,
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+};
+
+use crate::{ReserveFixture, TestContext, TransactionOutcome};
+
+pub const BASE_DECIMALS: u8 = 6;
+pub const ONE_TOKEN: u64 = 10_u64.pow(BASE_DECIMALS as u32);
+
+// Liquidity of the reserves before any vault deposit, half of it is borrowed
+const RESERVE_AVAILABLE_LIQUIDITY: u64 = 1_000_000 * ONE_TOKEN;
+const RESERVE_BORROWED_LIQUIDITY: u64 = 1_000_000 * ONE_TOKEN;
+
+// Covers the initial deposit of the vault, and leaves the admin enough to pay for the
+// invest rounding losses and the rewards
+const ADMIN_BASE_BALANCE: u64 = 1_000 * ONE_TOKEN;
+
+pub const DEFAULT_ALLOCATION_WEIGHT: u64 = 100;
+
+pub struct VaultFixture {
+    pub accounts: VaultAccounts,
+    // All the reserves created for the vault, allocated or not
+    pub reserves: Vec<ReserveFixture>,
+    pub lending_market: Pubkey,
+}
+
+impl VaultFixture {
+    pub fn vault_state(&self) -> Pubkey {
+        self.accounts.vault_state
+    }
+
+    pub fn reserve(&self, idx: usize) -> VaultReserve {
+        self.reserves[idx].vault_reserve()
+    }
+
+    fn vault_reserves(&self) -> Vec<VaultReserve> {
+        self.reserves
+            .iter()
+            .map(ReserveFixture::vault_reserve)
+            .collect()
+    }
+}
+
+impl TestContext {
+    pub async fn init_global_config(&mut self) {
+        if self.account_exists(&pda::global_config()).await {
+            return;
+        }
+
+        let init_global_config_ix =
+            instructions::init_global_config(kamino_vault::ID, self.payer());
+        self.process(&[init_global_config_ix], &[])
+            .await
+            .assert_ok();
+    }
+
+    /// Creates a vault with Token-2022 shares over a new base token, allocated with the
+    /// same weight and no cap to `reserve_count` new reserves of a new lending market
+    pub async fn setup_vault(&mut self, reserve_count: usize) -> VaultFixture {
+        self.init_global_config().await;
+
+        let admin = self.admin.pubkey();
+        let base_mint = self.create_mint(BASE_DECIMALS);
+        self.mint_to(&base_mint, &admin, ADMIN_BASE_BALANCE).await;
+
+        let lending_market = self.create_lending_market();
+        let mut reserves = Vec::with_capacity(reserve_count);
+        for _ in 0..reserve_count {
+            let reserve = self
+                .create_reserve(
+                    &lending_market,
+                    &base_mint,
+                    BASE_DECIMALS,
+                    RESERVE_AVAILABLE_LIQUIDITY,
+                    RESERVE_BORROWED_LIQUIDITY,
+                )
+                .await;
+            reserves.push(reserve);
+        }
+
+        // The vault state is too large to be created by the program, it is created in
+        // the same transaction and initialized through the `zero` constraint
+        let vault_state = Keypair::new();
+        let vault_state_space = 8 + VAULT_STATE_SIZE;
+        let create_vault_state_ix = system_instruction::create_account(
+            &self.payer(),
+            &vault_state.pubkey(),
+            Rent::default().minimum_balance(vault_state_space),
+            vault_state_space as u64,
+            &kamino_vault::ID,
+        );
+        let init_vault_ix = instructions::init_vault(
+            kamino_vault::ID,
+            admin,
+            vault_state.pubkey(),
+            base_mint,
+            spl_token::ID,
+            token_2022::ID,
+        );
+        let admin_keypair = self.admin.insecure_clone();
+        self.process(
+            &[create_vault_state_ix, init_vault_ix],
+            &[&vault_state, &admin_keypair],
+        )
+        .await
+        .assert_ok();
+
+        let mut vault = VaultFixture {
+            accounts: self.vault_accounts(&vault_state.pubkey(), &[]).await,
+            reserves,
+            lending_market,
+        };
+        for idx in 0..reserve_count {
+            self.update_reserve_allocation(&vault, idx, DEFAULT_ALLOCATION_WEIGHT, u64::MAX)
+                .await
+                .assert_ok();
+        }
+        self.reload_vault(&mut vault).await;
+        vault
+    }
+
+    async fn vault_accounts(
+        &mut self,
+        vault_state: &Pubkey,
+        reserves: &[VaultReserve],
+    ) -> VaultAccounts {
+        let state = self.vault_state(vault_state).await;
+        VaultAccounts::new(
+            kamino_vault::ID,
+            *vault_state,
+            &state,
+            token_2022::ID,
+            reserves,
+        )
+        .unwrap()
+    }
+
+    /// Refreshes the cached vault accounts, needed after any change to the allocations,
+    /// the fee recipients or the permissioned deposits of the vault
+    pub async fn reload_vault(&mut self, vault: &mut VaultFixture) {
+        vault.accounts = self
+            .vault_accounts(&vault.vault_state(), &vault.vault_reserves())
+            .await;
+    }
+
+    pub async fn update_reserve_allocation(
+        &mut self,
+        vault: &VaultFixture,
+        reserve_idx: usize,
+        weight: u64,
+        cap: u64,
+    ) -> TransactionOutcome {
+        let update_reserve_allocation_ix = instructions::update_reserve_allocation(
+            &vault.accounts,
+            self.admin.pubkey(),
+            &vault.reserve(reserve_idx),
+            false,
+            weight,
+            cap,
+        );
+        self.process_as_admin(&[update_reserve_allocation_ix]).await
+    }
+
+    /// Updates a vault config field as the vault admin, `value` is borsh serialized
+    pub async fn update_vault_config(
+        &mut self,
+        vault: &VaultFixture,
+        entry: VaultConfigField,
+        value: impl AnchorSerialize,
+    ) -> TransactionOutcome {
+        let update_vault_config_ix = instructions::update_vault_config(
+            &vault.accounts,
+            self.admin.pubkey(),
+            entry,
+            value.try_to_vec().unwrap(),
+        );
+        self.process_as_admin(&[update_vault_config_ix]).await
+    }
+
+    /// Creates a user holding `base_amount` of the vault base token, with its shares
+    /// token account already created
+    pub async fn create_user(&mut self, vault: &VaultFixture, base_amount: u64) -> Keypair {
+        let user = self.create_funded_keypair().await;
+        self.mint_to(&vault.accounts.token_mint, &user.pubkey(), base_amount)
+            .await;
+        self.create_ata(
+            &user.pubkey(),
+            &vault.accounts.shares_mint,
+            &vault.accounts.shares_token_program,
+        )
+        .await;
+        user
+    }
+
+    pub async fn deposit(
+        &mut self,
+        vault: &VaultFixture,
+        user: &Keypair,
+        amount: u64,
+    ) -> TransactionOutcome {
+        let deposit_ix = instructions::deposit(&vault.accounts, user.pubkey(), amount);
+        self.process(&[deposit_ix], &[user]).await
+    }
+
+    pub async fn invest(&mut self, vault: &VaultFixture, reserve_idx: usize) -> TransactionOutcome {
+        let invest_ix = instructions::invest(
+            &vault.accounts,
+            self.admin.pubkey(),
+            &vault.reserve(reserve_idx),
+            false,
+        );
+        self.process_as_admin(&[invest_ix]).await
+    }
+
+    pub async fn shares_balance(&mut self, vault: &VaultFixture, user: &Pubkey) -> u64 {
+        let user_shares_ata = vault.accounts.user_shares_ata(user);
+        self.token_balance(&user_shares_ata).await
+    }
+
+    pub async fn base_balance(&mut self, vault: &VaultFixture, user: &Pubkey) -> u64 {
+        let user_token_ata = vault.accounts.user_token_ata(user);
+        self.token_balance(&user_token_ata).await
+    }
+
+    pub async fn ctoken_vault_balance(&mut self, vault: &VaultFixture, reserve_idx: usize) -> u64 {
+        let ctoken_vault = vault
+            .accounts
+            .ctoken_vault(&vault.reserves[reserve_idx].address);
+        self.token_balance(&ctoken_vault).await
+    }
+}
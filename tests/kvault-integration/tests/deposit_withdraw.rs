@@ -0,0 +1,381 @@
+#![cfg(feature = "test-sbf")]
+
+use anchor_lang::AnchorDeserialize;
+use kvault_integration_tests::{
+    kvault_client::{
+        instructions,
+        kamino_vault::{utils::consts::INITIAL_DEPOSIT_AMOUNT, KaminoVaultError},
+    },
+    vault::ONE_TOKEN,
+    TestContext, VaultFixture,
+};
+use solana_sdk::signature::{Keypair, Signer};
+
+// Mirrors of the results returned by the preview instructions
+
+#[derive(AnchorDeserialize, Debug)]
+struct PreviewDepositResult {
+    shares_to_mint: u64,
+    token_to_deposit: u64,
+    _crank_funds_to_deposit: u64,
+    _high_water_mark_sf: u128,
+}
+
+#[derive(AnchorDeserialize, Debug)]
+struct PreviewWithdrawResult {
+    shares_to_burn: u64,
+    available_to_send_to_user: u64,
+    invested_liquidity_to_send_to_user: u64,
+    _high_water_mark_sf: u128,
+}
+
+#[derive(AnchorDeserialize, Debug)]
+struct PreviewRedeemInKindResult {
+    shares_to_burn: u64,
+    ctokens_to_send_to_user: u64,
+    _actual_liquidity_value: u64,
+    _high_water_mark_sf: u128,
+}
+
+async fn setup_with_user(
+    reserve_count: usize,
+    amount: u64,
+) -> (TestContext, VaultFixture, Keypair) {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(reserve_count).await;
+    let user = ctx.create_user(&vault, amount).await;
+    (ctx, vault, user)
+}
+
+#[tokio::test]
+async fn test_deposit() {
+    let (mut ctx, vault, user) = setup_with_user(1, 100 * ONE_TOKEN).await;
+
+    let preview_ix = instructions::preview_deposit(&vault.accounts, 40 * ONE_TOKEN);
+    let preview: PreviewDepositResult = ctx
+        .process(&[preview_ix], &[])
+        .await
+        .assert_ok()
+        .return_value();
+    assert_eq!(preview.token_to_deposit, 40 * ONE_TOKEN);
+
+    let outcome = ctx.deposit(&vault, &user, 40 * ONE_TOKEN).await;
+    outcome.assert_ok();
+    // At least the user balance and the deposit result events
+    assert!(outcome.events_emitted() >= 2);
+
+    let user_shares = ctx.shares_balance(&vault, &user.pubkey()).await;
+    assert_eq!(user_shares, preview.shares_to_mint);
+    // The initial deposit set the share price to one
+    assert_eq!(user_shares, 40 * ONE_TOKEN);
+    assert_eq!(
+        ctx.base_balance(&vault, &user.pubkey()).await,
+        60 * ONE_TOKEN
+    );
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(
+        ctx.token_balance(&vault.accounts.token_vault).await,
+        state.token_available
+    );
+    assert_eq!(
+        ctx.mint_supply(&vault.accounts.shares_mint).await + INITIAL_DEPOSIT_AMOUNT,
+        state.shares_issued
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_variants() {
+    let (mut ctx, vault, user) = setup_with_user(1, 100 * ONE_TOKEN).await;
+
+    let buy_ix = instructions::buy(&vault.accounts, user.pubkey(), 10 * ONE_TOKEN);
+    ctx.process(&[buy_ix], &[&user]).await.assert_ok();
+    assert_eq!(
+        ctx.shares_balance(&vault, &user.pubkey()).await,
+        10 * ONE_TOKEN
+    );
+
+    let deposit_ix = instructions::deposit_with_min_shares(
+        &vault.accounts,
+        user.pubkey(),
+        10 * ONE_TOKEN,
+        11 * ONE_TOKEN,
+    );
+    ctx.process(&[deposit_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::SharesToMintBelowMinSharesOut);
+
+    let deposit_ix = instructions::deposit_with_min_shares(
+        &vault.accounts,
+        user.pubkey(),
+        10 * ONE_TOKEN,
+        10 * ONE_TOKEN,
+    );
+    ctx.process(&[deposit_ix], &[&user]).await.assert_ok();
+    assert_eq!(
+        ctx.shares_balance(&vault, &user.pubkey()).await,
+        20 * ONE_TOKEN
+    );
+
+    let mint_shares_ix =
+        instructions::mint_shares(&vault.accounts, user.pubkey(), 5 * ONE_TOKEN, ONE_TOKEN);
+    ctx.process(&[mint_shares_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::TokensToDepositAboveMaxTokensIn);
+
+    let mint_shares_ix =
+        instructions::mint_shares(&vault.accounts, user.pubkey(), 5 * ONE_TOKEN, 6 * ONE_TOKEN);
+    ctx.process(&[mint_shares_ix], &[&user]).await.assert_ok();
+    assert_eq!(
+        ctx.shares_balance(&vault, &user.pubkey()).await,
+        25 * ONE_TOKEN
+    );
+    assert_eq!(
+        ctx.base_balance(&vault, &user.pubkey()).await,
+        75 * ONE_TOKEN
+    );
+}
+
+#[tokio::test]
+async fn test_convert_shares_and_assets() {
+    let (mut ctx, vault, user) = setup_with_user(1, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.invest(&vault, 0).await.assert_ok();
+
+    let convert_to_shares_ix = instructions::convert_to_shares(&vault.accounts, 10 * ONE_TOKEN);
+    let shares: u64 = ctx
+        .process(&[convert_to_shares_ix], &[])
+        .await
+        .assert_ok()
+        .return_value();
+    assert_eq!(shares, 10 * ONE_TOKEN);
+
+    let convert_to_assets_ix = instructions::convert_to_assets(&vault.accounts, shares);
+    let assets: u64 = ctx
+        .process(&[convert_to_assets_ix], &[])
+        .await
+        .assert_ok()
+        .return_value();
+    assert!(assets <= 10 * ONE_TOKEN && assets + 1 >= 10 * ONE_TOKEN);
+}
+
+#[tokio::test]
+async fn test_withdraw_from_available() {
+    let (mut ctx, vault, user) = setup_with_user(1, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+
+    let preview_ix = instructions::preview_withdraw(&vault.accounts, 30 * ONE_TOKEN);
+    let preview: PreviewWithdrawResult = ctx
+        .process(&[preview_ix], &[])
+        .await
+        .assert_ok()
+        .return_value();
+    assert_eq!(preview.shares_to_burn, 30 * ONE_TOKEN);
+    assert_eq!(preview.available_to_send_to_user, 30 * ONE_TOKEN);
+    assert_eq!(preview.invested_liquidity_to_send_to_user, 0);
+
+    let withdraw_ix =
+        instructions::withdraw_from_available(&vault.accounts, user.pubkey(), 30 * ONE_TOKEN);
+    let outcome = ctx.process(&[withdraw_ix], &[&user]).await;
+    outcome.assert_ok();
+    assert!(outcome.events_emitted() >= 2);
+    assert_eq!(
+        ctx.shares_balance(&vault, &user.pubkey()).await,
+        70 * ONE_TOKEN
+    );
+    assert_eq!(
+        ctx.base_balance(&vault, &user.pubkey()).await,
+        30 * ONE_TOKEN
+    );
+
+    let withdraw_ix = instructions::withdraw_from_available_with_min_tokens_out(
+        &vault.accounts,
+        user.pubkey(),
+        10 * ONE_TOKEN,
+        11 * ONE_TOKEN,
+    );
+    ctx.process(&[withdraw_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::TokensToSendBelowMinTokensOut);
+}
+
+#[tokio::test]
+async fn test_withdraw_from_invested_reserve() {
+    let (mut ctx, vault, user) = setup_with_user(1, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.invest(&vault, 0).await.assert_ok();
+
+    let preview_ix = instructions::preview_withdraw(&vault.accounts, 50 * ONE_TOKEN);
+    let preview: PreviewWithdrawResult = ctx
+        .process(&[preview_ix], &[])
+        .await
+        .assert_ok()
+        .return_value();
+    assert!(preview.invested_liquidity_to_send_to_user > 0);
+
+    let reserve = vault.reserve(0);
+    let reserve_supply_before = ctx.token_balance(&reserve.liquidity_supply).await;
+    let withdraw_ix =
+        instructions::withdraw(&vault.accounts, user.pubkey(), &reserve, 50 * ONE_TOKEN);
+    let outcome = ctx.process(&[withdraw_ix], &[&user]).await;
+    outcome.assert_ok();
+    assert!(outcome.events_emitted() >= 2);
+
+    let received = ctx.base_balance(&vault, &user.pubkey()).await;
+    assert_eq!(
+        received,
+        preview.available_to_send_to_user + preview.invested_liquidity_to_send_to_user
+    );
+    assert_eq!(
+        reserve_supply_before - ctx.token_balance(&reserve.liquidity_supply).await,
+        preview.invested_liquidity_to_send_to_user
+    );
+
+    let withdraw_ix = instructions::withdraw_with_min_tokens_out(
+        &vault.accounts,
+        user.pubkey(),
+        &reserve,
+        10 * ONE_TOKEN,
+        11 * ONE_TOKEN,
+    );
+    ctx.process(&[withdraw_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::TokensToSendBelowMinTokensOut);
+
+    let sell_ix = instructions::sell(&vault.accounts, user.pubkey(), &reserve, 10 * ONE_TOKEN);
+    ctx.process(&[sell_ix], &[&user]).await.assert_ok();
+    assert_eq!(
+        ctx.shares_balance(&vault, &user.pubkey()).await,
+        40 * ONE_TOKEN
+    );
+
+    let withdraw_assets_ix = instructions::withdraw_assets(
+        &vault.accounts,
+        user.pubkey(),
+        &reserve,
+        10 * ONE_TOKEN,
+        ONE_TOKEN,
+    );
+    ctx.process(&[withdraw_assets_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::SharesToBurnAboveMaxSharesIn);
+
+    let balance_before = ctx.base_balance(&vault, &user.pubkey()).await;
+    let withdraw_assets_ix = instructions::withdraw_assets(
+        &vault.accounts,
+        user.pubkey(),
+        &reserve,
+        10 * ONE_TOKEN,
+        11 * ONE_TOKEN,
+    );
+    ctx.process(&[withdraw_assets_ix], &[&user])
+        .await
+        .assert_ok();
+    assert_eq!(
+        ctx.base_balance(&vault, &user.pubkey()).await - balance_before,
+        10 * ONE_TOKEN
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_from_reserves() {
+    let (mut ctx, vault, user) = setup_with_user(2, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.invest(&vault, 0).await.assert_ok();
+    ctx.invest(&vault, 1).await.assert_ok();
+    let ctokens_before = [
+        ctx.ctoken_vault_balance(&vault, 0).await,
+        ctx.ctoken_vault_balance(&vault, 1).await,
+    ];
+
+    // Each reserve to withdraw from is given once, in the order to withdraw in
+    let reserves = [vault.reserve(1), vault.reserve(1)];
+    let withdraw_ix = instructions::withdraw_from_reserves(
+        &vault.accounts,
+        user.pubkey(),
+        &reserves,
+        ONE_TOKEN,
+        0,
+    );
+    ctx.process(&[withdraw_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::ReserveSpecifiedMultipleTimes);
+
+    let reserves = [vault.reserve(1), vault.reserve(0)];
+    let withdraw_ix = instructions::withdraw_from_reserves(
+        &vault.accounts,
+        user.pubkey(),
+        &reserves,
+        80 * ONE_TOKEN,
+        79 * ONE_TOKEN,
+    );
+    let outcome = ctx.process(&[withdraw_ix], &[&user]).await;
+    outcome.assert_ok();
+    assert!(outcome.events_emitted() >= 2);
+
+    assert!(ctx.base_balance(&vault, &user.pubkey()).await >= 79 * ONE_TOKEN);
+    assert!(ctx.ctoken_vault_balance(&vault, 1).await < ctokens_before[1]);
+    assert!(ctx.ctoken_vault_balance(&vault, 0).await <= ctokens_before[0]);
+}
+
+#[tokio::test]
+async fn test_redeem_in_kind() {
+    let (mut ctx, vault, user) = setup_with_user(1, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.invest(&vault, 0).await.assert_ok();
+
+    let reserve = vault.reserve(0);
+    let user_ctoken_ata = ctx
+        .create_ata(&user.pubkey(), &reserve.collateral_mint, &spl_token::ID)
+        .await;
+
+    let preview_ix =
+        instructions::preview_redeem_in_kind(&vault.accounts, 20 * ONE_TOKEN, reserve.address);
+    let preview: PreviewRedeemInKindResult = ctx
+        .process(&[preview_ix], &[])
+        .await
+        .assert_ok()
+        .return_value();
+    assert_eq!(preview.shares_to_burn, 20 * ONE_TOKEN);
+
+    let redeem_ix = instructions::redeem_in_kind_with_min_ctokens_out(
+        &vault.accounts,
+        user.pubkey(),
+        &reserve,
+        20 * ONE_TOKEN,
+        preview.ctokens_to_send_to_user + 1,
+    );
+    ctx.process(&[redeem_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::CTokensToSendBelowMinCTokensOut);
+
+    let ctoken_vault_before = ctx.ctoken_vault_balance(&vault, 0).await;
+    let redeem_ix =
+        instructions::redeem_in_kind(&vault.accounts, user.pubkey(), &reserve, 20 * ONE_TOKEN);
+    let outcome = ctx.process(&[redeem_ix], &[&user]).await;
+    outcome.assert_ok();
+    assert!(outcome.events_emitted() >= 2);
+
+    assert_eq!(
+        ctx.token_balance(&user_ctoken_ata).await,
+        preview.ctokens_to_send_to_user
+    );
+    assert_eq!(
+        ctoken_vault_before - ctx.ctoken_vault_balance(&vault, 0).await,
+        preview.ctokens_to_send_to_user
+    );
+    assert_eq!(
+        ctx.shares_balance(&vault, &user.pubkey()).await,
+        80 * ONE_TOKEN
+    );
+}
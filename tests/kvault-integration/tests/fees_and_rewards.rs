@@ -0,0 +1,398 @@
+#![cfg(feature = "test-sbf")]
+
+use kamino_lending::fraction::Fraction;
+use kvault_integration_tests::{
+    kvault_client::{
+        instructions,
+        kamino_vault::{
+            utils::consts::{FIRST_LOSS_WITHDRAWAL_NOTICE_SECONDS, SECONDS_PER_YEAR_U64},
+            KaminoVaultError,
+        },
+        pda, VaultConfigField,
+    },
+    vault::ONE_TOKEN,
+    TestContext, VaultFixture,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+
+const MANAGEMENT_FEE_BPS: u64 = 1_000;
+
+/// Vault with 100 tokens invested that charged a year of 10% management fees
+async fn setup_with_pending_fees(ctx: &mut TestContext) -> VaultFixture {
+    let vault = ctx.setup_vault(1).await;
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::ManagementFeeBps,
+        MANAGEMENT_FEE_BPS,
+    )
+    .await
+    .assert_ok();
+    let user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.invest(&vault, 0).await.assert_ok();
+
+    ctx.advance_time(SECONDS_PER_YEAR_U64).await;
+    // Giving up nothing only charges the fees
+    let give_up_nothing_ix = instructions::give_up_pending_fees(&vault.accounts, 0);
+    ctx.process_as_admin(&[give_up_nothing_ix])
+        .await
+        .assert_ok();
+
+    vault
+}
+
+async fn pending_fees(ctx: &mut TestContext, vault: &VaultFixture) -> u64 {
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    Fraction::from_bits(state.pending_fees_sf).to_num::<u64>()
+}
+
+#[tokio::test]
+async fn test_withdraw_pending_fees() {
+    let mut ctx = TestContext::start().await;
+    let vault = setup_with_pending_fees(&mut ctx).await;
+
+    let fees = pending_fees(&mut ctx, &vault).await;
+    assert!(fees >= 9 * ONE_TOKEN && fees <= 11 * ONE_TOKEN);
+
+    let admin = ctx.admin.pubkey();
+    let admin_balance_before = ctx.base_balance(&vault, &admin).await;
+    let withdraw_fees_ix = instructions::withdraw_pending_fees(&vault.accounts, &vault.reserve(0));
+    ctx.process_as_admin(&[withdraw_fees_ix]).await.assert_ok();
+
+    let received = ctx.base_balance(&vault, &admin).await - admin_balance_before;
+    assert!(received >= fees);
+    assert!(pending_fees(&mut ctx, &vault).await < ONE_TOKEN / 1_000);
+}
+
+#[tokio::test]
+async fn test_give_up_pending_fees() {
+    let mut ctx = TestContext::start().await;
+    let vault = setup_with_pending_fees(&mut ctx).await;
+    let fees = pending_fees(&mut ctx, &vault).await;
+
+    let give_up_ix = instructions::give_up_pending_fees(&vault.accounts, fees / 2);
+    ctx.process_as_admin(&[give_up_ix]).await.assert_ok();
+    assert!(pending_fees(&mut ctx, &vault).await <= fees - fees / 2 + 1);
+
+    let give_up_ix = instructions::give_up_pending_fees(&vault.accounts, u64::MAX);
+    ctx.process_as_admin(&[give_up_ix]).await.assert_ok();
+    assert_eq!(pending_fees(&mut ctx, &vault).await, 0);
+}
+
+#[tokio::test]
+async fn test_distribute_pending_fees() {
+    let mut ctx = TestContext::start().await;
+    let mut vault = setup_with_pending_fees(&mut ctx).await;
+
+    let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+    for recipient in recipients.iter() {
+        ctx.create_ata(recipient, &vault.accounts.token_mint, &spl_token::ID)
+            .await;
+    }
+
+    // Without recipients the fees can not be distributed
+    let distribute_ix = instructions::distribute_pending_fees(&vault.accounts, &vault.reserve(0));
+    ctx.process(&[distribute_ix], &[])
+        .await
+        .assert_error(KaminoVaultError::FeeRecipientsNotConfigured);
+
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::FeeRecipients,
+        vec![(recipients[0], 7_500_u64), (recipients[1], 2_500_u64)],
+    )
+    .await
+    .assert_ok();
+    ctx.reload_vault(&mut vault).await;
+
+    // The admin can no longer take the fees for itself
+    let withdraw_ix = instructions::withdraw_pending_fees(&vault.accounts, &vault.reserve(0));
+    ctx.process_as_admin(&[withdraw_ix])
+        .await
+        .assert_error(KaminoVaultError::FeeRecipientsConfigured);
+
+    let fees = pending_fees(&mut ctx, &vault).await;
+    let distribute_ix = instructions::distribute_pending_fees(&vault.accounts, &vault.reserve(0));
+    ctx.process(&[distribute_ix], &[]).await.assert_ok();
+
+    let received = [
+        ctx.base_balance(&vault, &recipients[0]).await,
+        ctx.base_balance(&vault, &recipients[1]).await,
+    ];
+    assert!(received[0] + received[1] >= fees);
+    assert!(received[0].abs_diff(3 * received[1]) <= 3);
+}
+
+#[tokio::test]
+async fn test_fees_in_shares() {
+    let mut ctx = TestContext::start().await;
+    let mut vault = setup_with_pending_fees(&mut ctx).await;
+    let fee_shares_recipient = ctx.create_user(&vault, 0).await.pubkey();
+
+    ctx.update_vault_config(&vault, VaultConfigField::FeesInShares, 1_u8)
+        .await
+        .assert_error(KaminoVaultError::PendingFeesMustBeConvertedToShares);
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::FeeSharesRecipient,
+        fee_shares_recipient,
+    )
+    .await
+    .assert_ok();
+    ctx.reload_vault(&mut vault).await;
+
+    // The pending fees are minted as shares right away, the fee mode is left unchanged
+    let shares_issued_before = ctx.vault_state(&vault.vault_state()).await.shares_issued;
+    let convert_ix = instructions::convert_pending_fees_to_shares(&vault.accounts);
+    let outcome = ctx.process_as_admin(&[convert_ix]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    let converted_shares = ctx.shares_balance(&vault, &fee_shares_recipient).await;
+    assert!(converted_shares > 0);
+    assert_eq!(state.shares_issued, shares_issued_before + converted_shares);
+    assert_eq!(state.pending_fee_shares, 0);
+    assert!(!state.vault_takes_fees_in_shares());
+    assert_eq!(pending_fees(&mut ctx, &vault).await, 0);
+
+    ctx.update_vault_config(&vault, VaultConfigField::FeesInShares, 1_u8)
+        .await
+        .assert_ok();
+
+    // New fees are accounted as shares to mint instead of tokens
+    ctx.advance_time(SECONDS_PER_YEAR_U64 / 12).await;
+    let give_up_nothing_ix = instructions::give_up_pending_fees(&vault.accounts, 0);
+    ctx.process_as_admin(&[give_up_nothing_ix])
+        .await
+        .assert_ok();
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    let pending_fee_shares = state.pending_fee_shares;
+    assert!(pending_fee_shares > 0);
+    assert_eq!(pending_fees(&mut ctx, &vault).await, 0);
+
+    let mint_fee_shares_ix = instructions::mint_fee_shares(&vault.accounts);
+    let outcome = ctx.process(&[mint_fee_shares_ix], &[]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+    assert_eq!(
+        ctx.shares_balance(&vault, &fee_shares_recipient).await,
+        converted_shares + pending_fee_shares
+    );
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.pending_fee_shares, 0);
+}
+
+#[tokio::test]
+async fn test_fee_shares_split_between_fee_recipients() {
+    let mut ctx = TestContext::start().await;
+    let mut vault = setup_with_pending_fees(&mut ctx).await;
+
+    let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+    for recipient in recipients.iter() {
+        ctx.create_ata(
+            recipient,
+            &vault.accounts.shares_mint,
+            &vault.accounts.shares_token_program,
+        )
+        .await;
+    }
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::FeeRecipients,
+        vec![(recipients[0], 7_500_u64), (recipients[1], 2_500_u64)],
+    )
+    .await
+    .assert_ok();
+    ctx.reload_vault(&mut vault).await;
+
+    let convert_ix = instructions::convert_pending_fees_to_shares(&vault.accounts);
+    let outcome = ctx.process_as_admin(&[convert_ix]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 2);
+
+    let received = [
+        ctx.shares_balance(&vault, &recipients[0]).await,
+        ctx.shares_balance(&vault, &recipients[1]).await,
+    ];
+    assert!(received[1] > 0);
+    assert!(received[0].abs_diff(3 * received[1]) <= 3);
+    assert_eq!(pending_fees(&mut ctx, &vault).await, 0);
+}
+
+#[tokio::test]
+async fn test_rewards() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+
+    let admin = ctx.admin.pubkey();
+    let topup_ix = instructions::topup_rewards(&vault.accounts, admin, 10 * ONE_TOKEN);
+    ctx.process_as_admin(&[topup_ix]).await.assert_ok();
+    ctx.update_vault_config(&vault, VaultConfigField::RewardPerSecond, 1_000_u64)
+        .await
+        .assert_ok();
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.reward_info.rewards_available, 10 * ONE_TOKEN);
+
+    // A thousand seconds at 1000 per second distributes one token to the holders
+    ctx.advance_time(1_000).await;
+    let user_shares = ctx.shares_balance(&vault, &user.pubkey()).await;
+    let withdraw_ix =
+        instructions::withdraw_from_available(&vault.accounts, user.pubkey(), user_shares);
+    ctx.process(&[withdraw_ix], &[&user]).await.assert_ok();
+    assert!(ctx.base_balance(&vault, &user.pubkey()).await > 100 * ONE_TOKEN);
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    let rewards_left = state.reward_info.rewards_available;
+    assert!(rewards_left < 10 * ONE_TOKEN);
+
+    let admin_balance_before = ctx.base_balance(&vault, &admin).await;
+    let withdraw_rewards_ix = instructions::withdraw_rewards(&vault.accounts, rewards_left);
+    ctx.process_as_admin(&[withdraw_rewards_ix])
+        .await
+        .assert_ok();
+    assert_eq!(
+        ctx.base_balance(&vault, &admin).await - admin_balance_before,
+        rewards_left
+    );
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.reward_info.rewards_available, 0);
+}
+
+#[tokio::test]
+async fn test_first_loss_shares_absorb_reserve_loss() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let first_loss_depositor = ctx.create_user(&vault, 50 * ONE_TOKEN).await;
+    let user = ctx.create_user(&vault, 102 * ONE_TOKEN).await;
+
+    ctx.deposit(&vault, &first_loss_depositor, 50 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::FirstLossDepositor,
+        first_loss_depositor.pubkey(),
+    )
+    .await
+    .assert_ok();
+    let deposit_first_loss_ix = instructions::deposit_first_loss_shares(
+        &vault.accounts,
+        first_loss_depositor.pubkey(),
+        20 * ONE_TOKEN,
+    );
+    ctx.process(&[deposit_first_loss_ix], &[&first_loss_depositor])
+        .await
+        .assert_ok();
+
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.invest(&vault, 0).await.assert_ok();
+
+    // The reserve writes off 5% of its liquidity, about 7.5 tokens of the vault holdings
+    ctx.socialize_reserve_loss(&vault.reserves[0].address, 100_000 * ONE_TOKEN)
+        .await;
+
+    let outcome = ctx.deposit(&vault, &user, ONE_TOKEN).await;
+    outcome.assert_ok();
+    // The deposit events plus the loss absorption
+    assert!(outcome.events_emitted() >= 3);
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    let absorbed = state.first_loss_shares_pending_burn;
+    assert!(absorbed > 7 * ONE_TOKEN && absorbed < 8 * ONE_TOKEN);
+    assert_eq!(state.first_loss_shares, 20 * ONE_TOKEN - absorbed);
+
+    // The regular holders kept the value of their shares
+    let user_shares = ctx.shares_balance(&vault, &user.pubkey()).await;
+    let convert_to_assets_ix = instructions::convert_to_assets(&vault.accounts, user_shares);
+    let assets: u64 = ctx
+        .process(&[convert_to_assets_ix], &[])
+        .await
+        .assert_ok()
+        .return_value();
+    assert!(assets + 10 >= 101 * ONE_TOKEN);
+
+    let escrow = pda::first_loss_escrow(&vault.vault_state());
+    let burn_ix = instructions::burn_absorbed_first_loss_shares(&vault.accounts);
+    let outcome = ctx.process(&[burn_ix], &[]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+    assert_eq!(ctx.token_balance(&escrow).await, 20 * ONE_TOKEN - absorbed);
+
+    // The shares can only leave after the notice period, within the withdrawal window
+    let withdraw_first_loss_ix = instructions::withdraw_first_loss_shares(
+        &vault.accounts,
+        first_loss_depositor.pubkey(),
+        ONE_TOKEN,
+    );
+    ctx.process(&[withdraw_first_loss_ix.clone()], &[&first_loss_depositor])
+        .await
+        .assert_error(KaminoVaultError::FirstLossWithdrawalNotRequested);
+
+    let request_ix =
+        instructions::request_first_loss_withdrawal(&vault.accounts, first_loss_depositor.pubkey());
+    let outcome = ctx.process(&[request_ix], &[&first_loss_depositor]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+    ctx.process(&[withdraw_first_loss_ix], &[&first_loss_depositor])
+        .await
+        .assert_error(KaminoVaultError::FirstLossWithdrawalNotInWindow);
+
+    ctx.advance_time(FIRST_LOSS_WITHDRAWAL_NOTICE_SECONDS).await;
+
+    let withdraw_first_loss_ix = instructions::withdraw_first_loss_shares(
+        &vault.accounts,
+        first_loss_depositor.pubkey(),
+        20 * ONE_TOKEN,
+    );
+    ctx.process(&[withdraw_first_loss_ix], &[&first_loss_depositor])
+        .await
+        .assert_error(KaminoVaultError::NotEnoughFirstLossShares);
+
+    // A loss realised during the notice period is absorbed before the shares are released
+    ctx.socialize_reserve_loss(&vault.reserves[0].address, 100_000 * ONE_TOKEN)
+        .await;
+    let withdraw_first_loss_ix = instructions::withdraw_first_loss_shares(
+        &vault.accounts,
+        first_loss_depositor.pubkey(),
+        20 * ONE_TOKEN - absorbed,
+    );
+    ctx.process(&[withdraw_first_loss_ix], &[&first_loss_depositor])
+        .await
+        .assert_error(KaminoVaultError::NotEnoughFirstLossShares);
+
+    ctx.deposit(&vault, &user, ONE_TOKEN).await.assert_ok();
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    let remaining = state.first_loss_shares;
+    assert!(remaining < 20 * ONE_TOKEN - absorbed);
+
+    let withdraw_first_loss_ix = instructions::withdraw_first_loss_shares(
+        &vault.accounts,
+        first_loss_depositor.pubkey(),
+        remaining,
+    );
+    let outcome = ctx
+        .process(&[withdraw_first_loss_ix.clone()], &[&first_loss_depositor])
+        .await;
+    outcome.assert_ok();
+    assert!(outcome.events_emitted() >= 1);
+    assert_eq!(
+        ctx.shares_balance(&vault, &first_loss_depositor.pubkey())
+            .await,
+        30 * ONE_TOKEN + remaining
+    );
+
+    // Every withdrawal needs its own notice
+    ctx.process(&[withdraw_first_loss_ix], &[&first_loss_depositor])
+        .await
+        .assert_error(KaminoVaultError::FirstLossWithdrawalNotRequested);
+}
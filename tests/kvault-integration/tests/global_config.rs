@@ -0,0 +1,158 @@
+#![cfg(feature = "test-sbf")]
+
+use kvault_integration_tests::{
+    kvault_client::{
+        instructions,
+        kamino_vault::{
+            self,
+            utils::{consts::PAUSE_DEPOSITS, global_config::UpdateGlobalConfigMode},
+            KaminoVaultError, ReserveWhitelistEntry, UpdateReserveWhitelistMode,
+        },
+        pda,
+    },
+    vault::ONE_TOKEN,
+    TestContext,
+};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn test_init_global_config_sets_upgrade_authority_as_admin() {
+    let mut ctx = TestContext::start().await;
+
+    ctx.init_global_config().await;
+
+    let global_config = ctx.global_config().await;
+    assert_eq!(global_config.global_admin, ctx.admin.pubkey());
+    assert_eq!(global_config.paused_operations, 0);
+}
+
+#[tokio::test]
+async fn test_global_admin_handover() {
+    let mut ctx = TestContext::start().await;
+    ctx.init_global_config().await;
+    let new_admin = ctx.create_funded_keypair().await;
+
+    let set_pending_admin_ix = instructions::update_global_config(
+        kamino_vault::ID,
+        ctx.admin.pubkey(),
+        UpdateGlobalConfigMode::PendingAdmin(new_admin.pubkey()),
+    );
+    ctx.process_as_admin(&[set_pending_admin_ix])
+        .await
+        .assert_ok();
+    assert_eq!(ctx.global_config().await.global_admin, ctx.admin.pubkey());
+
+    let accept_admin_ix =
+        instructions::update_global_config_admin(kamino_vault::ID, new_admin.pubkey());
+    ctx.process(&[accept_admin_ix], &[&new_admin])
+        .await
+        .assert_ok();
+
+    let global_config = ctx.global_config().await;
+    assert_eq!(global_config.global_admin, new_admin.pubkey());
+
+    // The previous admin can no longer update the global config
+    let update_ix = instructions::update_global_config(
+        kamino_vault::ID,
+        ctx.admin.pubkey(),
+        UpdateGlobalConfigMode::MinWithdrawalPenaltyLamports(1),
+    );
+    assert!(ctx.process_as_admin(&[update_ix]).await.result.is_err());
+}
+
+#[tokio::test]
+async fn test_reserve_whitelist() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let reserve = vault.reserves[0].address;
+
+    for update in [
+        UpdateReserveWhitelistMode::Invest(1),
+        UpdateReserveWhitelistMode::AddAllocation(1),
+    ] {
+        let whitelist_ix = instructions::add_update_whitelisted_reserve(
+            kamino_vault::ID,
+            ctx.admin.pubkey(),
+            reserve,
+            update,
+        );
+        ctx.process_as_admin(&[whitelist_ix]).await.assert_ok();
+    }
+
+    let entry: ReserveWhitelistEntry = ctx
+        .anchor_account(&pda::reserve_whitelist_entry(&reserve))
+        .await;
+    assert_eq!(entry.reserve, reserve);
+    assert_eq!(entry.token_mint, vault.accounts.token_mint);
+    assert_eq!(entry.whitelist_invest, 1);
+    assert_eq!(entry.whitelist_add_allocation, 1);
+
+    // Only the global admin manages the whitelist
+    let outsider = ctx.create_funded_keypair().await;
+    let whitelist_ix = instructions::add_update_whitelisted_reserve(
+        kamino_vault::ID,
+        outsider.pubkey(),
+        reserve,
+        UpdateReserveWhitelistMode::Invest(0),
+    );
+    assert!(ctx
+        .process(&[whitelist_ix], &[&outsider])
+        .await
+        .result
+        .is_err());
+
+    // A whitelisted reserve is invested in with its whitelist entry
+    let user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    let invest_ix =
+        instructions::invest(&vault.accounts, ctx.admin.pubkey(), &vault.reserve(0), true);
+    ctx.process_as_admin(&[invest_ix]).await.assert_ok();
+    assert!(ctx.ctoken_vault_balance(&vault, 0).await > 0);
+}
+
+#[tokio::test]
+async fn test_global_pause() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+
+    let pause_ix =
+        instructions::pause_global_operations(kamino_vault::ID, ctx.admin.pubkey(), PAUSE_DEPOSITS);
+    ctx.process_as_admin(&[pause_ix]).await.assert_ok();
+    assert_eq!(ctx.global_config().await.paused_operations, PAUSE_DEPOSITS);
+
+    ctx.deposit(&vault, &user, 10 * ONE_TOKEN)
+        .await
+        .assert_error(KaminoVaultError::OperationPaused);
+
+    // Only the global admin and the global guardian can pause every vault
+    let guardian = ctx.create_funded_keypair().await;
+    let pause_ix =
+        instructions::pause_global_operations(kamino_vault::ID, guardian.pubkey(), PAUSE_DEPOSITS);
+    ctx.process(&[pause_ix], &[&guardian])
+        .await
+        .assert_error(KaminoVaultError::PauseAuthorityIncorrect);
+
+    let set_guardian_ix = instructions::update_global_config(
+        kamino_vault::ID,
+        ctx.admin.pubkey(),
+        UpdateGlobalConfigMode::EmergencyGuardian(guardian.pubkey()),
+    );
+    ctx.process_as_admin(&[set_guardian_ix]).await.assert_ok();
+    let pause_ix =
+        instructions::pause_global_operations(kamino_vault::ID, guardian.pubkey(), PAUSE_DEPOSITS);
+    ctx.process(&[pause_ix], &[&guardian]).await.assert_ok();
+
+    // Unpausing is left to the global admin
+    let unpause_ix = instructions::update_global_config(
+        kamino_vault::ID,
+        ctx.admin.pubkey(),
+        UpdateGlobalConfigMode::PausedOperations(0),
+    );
+    ctx.process_as_admin(&[unpause_ix]).await.assert_ok();
+
+    ctx.deposit(&vault, &user, 10 * ONE_TOKEN).await.assert_ok();
+    assert!(ctx.shares_balance(&vault, &user.pubkey()).await > 0);
+}
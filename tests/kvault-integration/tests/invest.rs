@@ -0,0 +1,162 @@
+#![cfg(feature = "test-sbf")]
+
+use kvault_integration_tests::{
+    klend::refresh_reserve_ix,
+    kvault_client::{
+        instructions,
+        kamino_vault::{utils::consts::PAUSE_INVEST, KaminoVaultError},
+    },
+    vault::ONE_TOKEN,
+    TestContext,
+};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn test_invest() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+
+    let reserve = vault.reserves[0];
+    let token_vault_before = ctx.token_balance(&vault.accounts.token_vault).await;
+    let reserve_supply_before = ctx.token_balance(&reserve.liquidity_supply).await;
+    let collateral_supply_before = ctx.mint_supply(&reserve.collateral_mint).await;
+
+    ctx.invest(&vault, 0).await.assert_ok();
+
+    let invested = token_vault_before - ctx.token_balance(&vault.accounts.token_vault).await;
+    assert!(invested > 0);
+    assert_eq!(
+        ctx.token_balance(&reserve.liquidity_supply).await - reserve_supply_before,
+        invested
+    );
+
+    // The ctokens minted by klend are all held by the vault and tracked in its allocation
+    let ctokens = ctx.ctoken_vault_balance(&vault, 0).await;
+    assert_eq!(
+        ctx.mint_supply(&reserve.collateral_mint).await - collateral_supply_before,
+        ctokens
+    );
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    let allocation = state.allocation_for_reserve(&reserve.address).unwrap();
+    assert_eq!(allocation.ctoken_allocation, ctokens);
+    assert_eq!(
+        state.token_available,
+        ctx.token_balance(&vault.accounts.token_vault).await
+    );
+
+    let reserve_state = ctx.reserve_state(&reserve.address).await;
+    assert_eq!(
+        reserve_state.liquidity.available_amount,
+        ctx.token_balance(&reserve.liquidity_supply).await
+    );
+
+    // The reserve stays usable with its oracle after the vault deposit
+    ctx.process(&[refresh_reserve_ix(&reserve)], &[])
+        .await
+        .assert_ok();
+}
+
+#[tokio::test]
+async fn test_invest_paused() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+
+    let pause_ix =
+        instructions::pause_vault_operations(&vault.accounts, ctx.admin.pubkey(), PAUSE_INVEST);
+    ctx.process_as_admin(&[pause_ix]).await.assert_ok();
+
+    ctx.invest(&vault, 0)
+        .await
+        .assert_error(KaminoVaultError::OperationPaused);
+    // Deposits are not affected
+    ctx.deposit(&vault, &user, ONE_TOKEN).await.assert_ok();
+}
+
+#[tokio::test]
+async fn test_rebalance() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(2).await;
+    let user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+
+    let rebalance_ix = instructions::rebalance(&vault.accounts, ctx.admin.pubkey(), &[]);
+    let outcome = ctx.process_as_admin(&[rebalance_ix.clone()]).await;
+    outcome.assert_ok();
+    // One invest result per reserve moved
+    assert_eq!(outcome.events_emitted(), 2);
+
+    let ctokens = [
+        ctx.ctoken_vault_balance(&vault, 0).await,
+        ctx.ctoken_vault_balance(&vault, 1).await,
+    ];
+    assert!(ctokens[0] > 0);
+    assert!(ctokens[0].abs_diff(ctokens[1]) <= 1);
+
+    // Dropping the weight of a reserve moves all its liquidity to the other one
+    ctx.update_reserve_allocation(&vault, 1, 0, 0)
+        .await
+        .assert_ok();
+    ctx.process_as_admin(&[rebalance_ix]).await.assert_ok();
+
+    assert_eq!(ctx.ctoken_vault_balance(&vault, 1).await, 0);
+    assert!(ctx.ctoken_vault_balance(&vault, 0).await > ctokens[0]);
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    let allocation = state
+        .allocation_for_reserve(&vault.reserves[1].address)
+        .unwrap();
+    assert_eq!(allocation.ctoken_allocation, 0);
+}
+
+#[tokio::test]
+async fn test_interest_raises_share_price() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.invest(&vault, 0).await.assert_ok();
+    let high_water_mark_before = ctx
+        .vault_state(&vault.vault_state())
+        .await
+        .high_water_mark_sf;
+
+    // The reserves are created with 2M tokens of liquidity, a 200k interest is close to
+    // a 10% gain on the collateral held by the vault
+    ctx.accrue_reserve_interest(&vault.reserves[0].address, 200_000 * ONE_TOKEN)
+        .await;
+
+    let user_shares = ctx.shares_balance(&vault, &user.pubkey()).await;
+    let convert_to_assets_ix = instructions::convert_to_assets(&vault.accounts, user_shares);
+    let assets: u64 = ctx
+        .process(&[convert_to_assets_ix], &[])
+        .await
+        .assert_ok()
+        .return_value();
+    assert!(assets > 109 * ONE_TOKEN);
+
+    let withdraw_ix = instructions::withdraw(
+        &vault.accounts,
+        user.pubkey(),
+        &vault.reserve(0),
+        user_shares,
+    );
+    let outcome = ctx.process(&[withdraw_ix], &[&user]).await;
+    outcome.assert_ok();
+    // The gain also moves the high water mark
+    assert!(outcome.events_emitted() >= 3);
+
+    assert!(ctx.base_balance(&vault, &user.pubkey()).await > 109 * ONE_TOKEN);
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert!(state.high_water_mark_sf > high_water_mark_before);
+}
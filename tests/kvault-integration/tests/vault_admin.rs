@@ -0,0 +1,509 @@
+#![cfg(feature = "test-sbf")]
+
+use anchor_lang::AnchorSerialize;
+use kvault_integration_tests::{
+    kvault_client::{
+        instructions,
+        kamino_vault::{
+            utils::consts::{
+                INITIAL_DEPOSIT_AMOUNT, PAUSE_DEPOSITS, PAUSE_WITHDRAWALS, SECONDS_PER_YEAR_U64,
+            },
+            DepositorAllowlistEntry, KaminoVaultError, VaultLifecycleState,
+        },
+        pda, VaultConfigField,
+    },
+    vault::{BASE_DECIMALS, DEFAULT_ALLOCATION_WEIGHT, ONE_TOKEN},
+    TestContext,
+};
+use solana_sdk::signature::Signer;
+
+const CONFIG_CHANGE_DELAY_SECONDS: u64 = 24 * 60 * 60;
+
+#[tokio::test]
+async fn test_init_vault() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(2).await;
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.vault_admin_authority, ctx.admin.pubkey());
+    assert_eq!(state.token_mint, vault.accounts.token_mint);
+    assert_eq!(state.get_reserves_count(), 2);
+    assert_eq!(
+        state.get_lifecycle_state().unwrap(),
+        VaultLifecycleState::Active
+    );
+
+    // The initial deposit is accounted for but its shares are never minted
+    assert_eq!(state.shares_issued, INITIAL_DEPOSIT_AMOUNT);
+    assert_eq!(state.token_available, INITIAL_DEPOSIT_AMOUNT);
+    assert_eq!(
+        ctx.token_balance(&vault.accounts.token_vault).await,
+        INITIAL_DEPOSIT_AMOUNT
+    );
+    assert_eq!(ctx.mint_supply(&vault.accounts.shares_mint).await, 0);
+
+    for (allocation, reserve) in state.vault_allocation_strategy.iter().zip(&vault.reserves) {
+        assert_eq!(allocation.reserve, reserve.address);
+        assert_eq!(
+            allocation.target_allocation_weight,
+            DEFAULT_ALLOCATION_WEIGHT
+        );
+        assert_eq!(allocation.ctoken_allocation, 0);
+        assert!(ctx.account_exists(&allocation.ctoken_vault).await);
+    }
+}
+
+#[tokio::test]
+async fn test_update_and_remove_allocation() {
+    let mut ctx = TestContext::start().await;
+    let mut vault = ctx.setup_vault(2).await;
+
+    ctx.update_reserve_allocation(&vault, 1, 50, 1_000 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    let allocation = state
+        .allocation_for_reserve(&vault.reserves[1].address)
+        .unwrap();
+    assert_eq!(allocation.target_allocation_weight, 50);
+    assert_eq!(allocation.token_allocation_cap, 1_000 * ONE_TOKEN);
+
+    // Only a reserve without weight nor ctokens can be removed
+    let remove_allocation_ix =
+        instructions::remove_allocation(&vault.accounts, vault.reserves[1].address);
+    ctx.process_as_admin(&[remove_allocation_ix.clone()])
+        .await
+        .assert_error(KaminoVaultError::ReserveHasNonZeroAllocationOrCTokens);
+
+    ctx.update_reserve_allocation(&vault, 1, 0, 0)
+        .await
+        .assert_ok();
+    ctx.process_as_admin(&[remove_allocation_ix])
+        .await
+        .assert_ok();
+    ctx.reload_vault(&mut vault).await;
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.get_reserves_count(), 1);
+    assert_eq!(vault.accounts.reserves, vec![vault.reserve(0)]);
+
+    // The remaining accounts follow the new allocations
+    let user = ctx.create_user(&vault, 10 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 10 * ONE_TOKEN).await.assert_ok();
+}
+
+#[tokio::test]
+async fn test_update_vault_config() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+
+    ctx.update_vault_config(&vault, VaultConfigField::MinDepositAmount, 5 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.update_vault_config(&vault, VaultConfigField::ManagementFeeBps, 100_u64)
+        .await
+        .assert_ok();
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.min_deposit_amount, 5 * ONE_TOKEN);
+    assert_eq!(state.management_fee_bps, 100);
+
+    let user = ctx.create_user(&vault, 10 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, ONE_TOKEN)
+        .await
+        .assert_error(KaminoVaultError::DepositAmountBelowMinimum);
+
+    // Only the vault admin can update the vault config
+    let update_ix = instructions::update_vault_config(
+        &vault.accounts,
+        user.pubkey(),
+        VaultConfigField::MinDepositAmount,
+        0_u64.try_to_vec().unwrap(),
+    );
+    ctx.process(&[update_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::AdminAuthorityIncorrect);
+}
+
+#[tokio::test]
+async fn test_timelocked_config_change() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::ConfigChangeDelaySeconds,
+        CONFIG_CHANGE_DELAY_SECONDS,
+    )
+    .await
+    .assert_ok();
+
+    // Timelocked fields can no longer be updated directly
+    ctx.update_vault_config(&vault, VaultConfigField::PerformanceFeeBps, 1_000_u64)
+        .await
+        .assert_error(KaminoVaultError::ConfigFieldTimelocked);
+
+    let propose_ix = instructions::propose_vault_config_change(
+        &vault.accounts,
+        VaultConfigField::PerformanceFeeBps,
+        1_000_u64.try_to_vec().unwrap(),
+    );
+    let outcome = ctx.process_as_admin(&[propose_ix]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+
+    let execute_ix = instructions::execute_vault_config_change(
+        &vault.accounts,
+        ctx.admin.pubkey(),
+        VaultConfigField::PerformanceFeeBps,
+    );
+    ctx.process_as_admin(&[execute_ix.clone()])
+        .await
+        .assert_error(KaminoVaultError::ConfigChangeNotActive);
+
+    ctx.advance_time(CONFIG_CHANGE_DELAY_SECONDS).await;
+    let outcome = ctx.process_as_admin(&[execute_ix.clone()]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.performance_fee_bps, 1_000);
+
+    // A cancelled change can no longer be executed
+    let propose_ix = instructions::propose_vault_config_change(
+        &vault.accounts,
+        VaultConfigField::PerformanceFeeBps,
+        2_000_u64.try_to_vec().unwrap(),
+    );
+    ctx.process_as_admin(&[propose_ix]).await.assert_ok();
+    let cancel_ix = instructions::cancel_vault_config_change(
+        &vault.accounts,
+        VaultConfigField::PerformanceFeeBps,
+    );
+    let outcome = ctx.process_as_admin(&[cancel_ix]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+
+    ctx.advance_time(CONFIG_CHANGE_DELAY_SECONDS).await;
+    ctx.process_as_admin(&[execute_ix])
+        .await
+        .assert_error(KaminoVaultError::NoPendingConfigChange);
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.performance_fee_bps, 1_000);
+}
+
+#[tokio::test]
+async fn test_update_admin() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let new_admin = ctx.create_funded_keypair().await;
+
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::PendingVaultAdmin,
+        new_admin.pubkey(),
+    )
+    .await
+    .assert_ok();
+    let update_admin_ix = instructions::update_admin(&vault.accounts, new_admin.pubkey());
+    ctx.process(&[update_admin_ix], &[&new_admin])
+        .await
+        .assert_ok();
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.vault_admin_authority, new_admin.pubkey());
+
+    ctx.update_vault_config(&vault, VaultConfigField::MinDepositAmount, ONE_TOKEN)
+        .await
+        .assert_error(KaminoVaultError::AdminAuthorityIncorrect);
+}
+
+#[tokio::test]
+async fn test_shares_metadata() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+
+    let initialize_metadata_ix = instructions::initialize_shares_metadata(
+        &vault.accounts,
+        "Test Vault".to_string(),
+        "kvTEST".to_string(),
+        "https://example.com/kvtest.json".to_string(),
+    );
+    ctx.process_as_admin(&[initialize_metadata_ix])
+        .await
+        .assert_ok();
+    assert!(shares_mint_contains(&mut ctx, &vault.accounts.shares_mint, "kvTEST").await);
+
+    // The longer name needs the mint to be reallocated
+    let update_metadata_ix = instructions::update_shares_metadata(
+        &vault.accounts,
+        "Test Vault with a longer name".to_string(),
+        "kvTEST2".to_string(),
+        "https://example.com/kvtest.json".to_string(),
+    );
+    ctx.process_as_admin(&[update_metadata_ix])
+        .await
+        .assert_ok();
+    assert!(
+        shares_mint_contains(
+            &mut ctx,
+            &vault.accounts.shares_mint,
+            "Test Vault with a longer name"
+        )
+        .await
+    );
+}
+
+// The Token-2022 metadata lives in a variable length extension of the shares mint
+async fn shares_mint_contains(
+    ctx: &mut TestContext,
+    shares_mint: &solana_sdk::pubkey::Pubkey,
+    text: &str,
+) -> bool {
+    let account = ctx.get_account(shares_mint).await.unwrap();
+    account
+        .data
+        .windows(text.len())
+        .any(|window| window == text.as_bytes())
+}
+
+#[tokio::test]
+async fn test_permissioned_deposits() {
+    let mut ctx = TestContext::start().await;
+    let mut vault = ctx.setup_vault(1).await;
+    let user = ctx.create_user(&vault, 10 * ONE_TOKEN).await;
+
+    ctx.update_vault_config(&vault, VaultConfigField::PermissionedDeposits, 1_u8)
+        .await
+        .assert_ok();
+    ctx.reload_vault(&mut vault).await;
+
+    ctx.deposit(&vault, &user, ONE_TOKEN)
+        .await
+        .assert_error(KaminoVaultError::DepositorNotAllowlisted);
+
+    let add_ix = instructions::add_depositor_to_allowlist(&vault.accounts, user.pubkey());
+    ctx.process_as_admin(&[add_ix]).await.assert_ok();
+    let entry: DepositorAllowlistEntry = ctx
+        .anchor_account(&pda::depositor_allowlist_entry(
+            &vault.vault_state(),
+            &user.pubkey(),
+        ))
+        .await;
+    assert_eq!(entry.depositor, user.pubkey());
+
+    ctx.deposit(&vault, &user, ONE_TOKEN).await.assert_ok();
+
+    let remove_ix = instructions::remove_depositor_from_allowlist(&vault.accounts, user.pubkey());
+    ctx.process_as_admin(&[remove_ix]).await.assert_ok();
+    ctx.deposit(&vault, &user, ONE_TOKEN)
+        .await
+        .assert_error(KaminoVaultError::DepositorNotAllowlisted);
+}
+
+#[tokio::test]
+async fn test_shares_transfer_hook() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let sender = ctx.create_user(&vault, 10 * ONE_TOKEN).await;
+    let receiver = ctx.create_user(&vault, 0).await;
+    ctx.deposit(&vault, &sender, 10 * ONE_TOKEN)
+        .await
+        .assert_ok();
+
+    // The hook cannot be enabled before its extra accounts exist
+    let enable_hook_ix = instructions::update_shares_transfer_hook(&vault.accounts, true);
+    ctx.process_as_admin(&[enable_hook_ix.clone()])
+        .await
+        .assert_error(KaminoVaultError::SharesTransferHookNotInitialized);
+
+    let initialize_hook_ix = instructions::initialize_shares_transfer_hook(&vault.accounts);
+    ctx.process_as_admin(&[initialize_hook_ix])
+        .await
+        .assert_ok();
+    assert!(
+        ctx.account_exists(&pda::extra_account_metas(&vault.accounts.shares_mint))
+            .await
+    );
+    ctx.process_as_admin(&[enable_hook_ix]).await.assert_ok();
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert!(state.vault_has_permissioned_share_transfers());
+
+    let transfer_ix = instructions::transfer_shares(
+        &vault.accounts,
+        sender.pubkey(),
+        receiver.pubkey(),
+        ONE_TOKEN,
+        BASE_DECIMALS,
+    );
+    ctx.process(&[transfer_ix.clone()], &[&sender])
+        .await
+        .assert_error(KaminoVaultError::DepositorNotAllowlisted);
+
+    // Only Token-2022 can invoke the hook, while the source account is transferring
+    let transfer_hook_ix = instructions::shares_transfer_hook(
+        &vault.accounts,
+        vault.accounts.user_shares_ata(&sender.pubkey()),
+        vault.accounts.user_shares_ata(&receiver.pubkey()),
+        receiver.pubkey(),
+        sender.pubkey(),
+        ONE_TOKEN,
+    );
+    ctx.process(&[transfer_hook_ix], &[])
+        .await
+        .assert_error(KaminoVaultError::SharesTransferHookNotTransferring);
+
+    let add_ix = instructions::add_depositor_to_allowlist(&vault.accounts, receiver.pubkey());
+    ctx.process_as_admin(&[add_ix]).await.assert_ok();
+    ctx.process(&[transfer_ix], &[&sender]).await.assert_ok();
+    assert_eq!(
+        ctx.shares_balance(&vault, &receiver.pubkey()).await,
+        ONE_TOKEN
+    );
+
+    // Without the hook, shares go to anyone
+    let other_receiver = ctx.create_user(&vault, 0).await;
+    let disable_hook_ix = instructions::update_shares_transfer_hook(&vault.accounts, false);
+    ctx.process_as_admin(&[disable_hook_ix]).await.assert_ok();
+    let transfer_ix = instructions::transfer_shares(
+        &vault.accounts,
+        sender.pubkey(),
+        other_receiver.pubkey(),
+        ONE_TOKEN,
+        BASE_DECIMALS,
+    );
+    ctx.process(&[transfer_ix], &[&sender]).await.assert_ok();
+    assert_eq!(
+        ctx.shares_balance(&vault, &other_receiver.pubkey()).await,
+        ONE_TOKEN
+    );
+}
+
+#[tokio::test]
+async fn test_vault_pause() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let user = ctx.create_user(&vault, 10 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 5 * ONE_TOKEN).await.assert_ok();
+
+    let pause_ix = instructions::pause_vault_operations(
+        &vault.accounts,
+        ctx.admin.pubkey(),
+        PAUSE_DEPOSITS | PAUSE_WITHDRAWALS,
+    );
+    ctx.process_as_admin(&[pause_ix]).await.assert_ok();
+
+    ctx.deposit(&vault, &user, ONE_TOKEN)
+        .await
+        .assert_error(KaminoVaultError::OperationPaused);
+    let withdraw_ix = instructions::withdraw_from_available(&vault.accounts, user.pubkey(), 1_000);
+    ctx.process(&[withdraw_ix.clone()], &[&user])
+        .await
+        .assert_error(KaminoVaultError::OperationPaused);
+
+    // Anyone else can not pause the vault
+    let pause_ix =
+        instructions::pause_vault_operations(&vault.accounts, user.pubkey(), PAUSE_DEPOSITS);
+    ctx.process(&[pause_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::PauseAuthorityIncorrect);
+
+    ctx.update_vault_config(&vault, VaultConfigField::PausedOperations, 0_u64)
+        .await
+        .assert_ok();
+    ctx.process(&[withdraw_ix], &[&user]).await.assert_ok();
+}
+
+#[tokio::test]
+async fn test_close_vault() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    let user = ctx.create_user(&vault, 10 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 10 * ONE_TOKEN).await.assert_ok();
+
+    let close_vault_ix = instructions::close_vault(&vault.accounts);
+    ctx.process_as_admin(&[close_vault_ix.clone()])
+        .await
+        .assert_error(KaminoVaultError::InvalidLifecycleStateTransition);
+
+    // Only the transitions between active and withdraw-only go through the config
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::LifecycleState,
+        VaultLifecycleState::Active,
+    )
+    .await
+    .assert_error(KaminoVaultError::InvalidLifecycleStateTransition);
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::LifecycleState,
+        VaultLifecycleState::WithdrawOnly,
+    )
+    .await
+    .assert_ok();
+    ctx.deposit(&vault, &user, ONE_TOKEN)
+        .await
+        .assert_error(KaminoVaultError::VaultNotActive);
+    ctx.process_as_admin(&[close_vault_ix.clone()])
+        .await
+        .assert_error(KaminoVaultError::VaultNotEmpty);
+
+    let user_shares = ctx.shares_balance(&vault, &user.pubkey()).await;
+    let withdraw_ix =
+        instructions::withdraw_from_available(&vault.accounts, user.pubkey(), user_shares);
+    ctx.process(&[withdraw_ix], &[&user]).await.assert_ok();
+
+    let admin = ctx.admin.pubkey();
+    let admin_balance_before = ctx.base_balance(&vault, &admin).await;
+    let vault_token_balance = ctx.token_balance(&vault.accounts.token_vault).await;
+    let outcome = ctx.process_as_admin(&[close_vault_ix]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+
+    // The crank funds and the initial deposit go back to the admin, with the rent of the
+    // accounts
+    assert_eq!(
+        ctx.base_balance(&vault, &admin).await,
+        admin_balance_before + vault_token_balance
+    );
+    assert!(!ctx.account_exists(&vault.vault_state()).await);
+    assert!(!ctx.account_exists(&vault.accounts.token_vault).await);
+    assert!(
+        !ctx.account_exists(&vault.accounts.ctoken_vault(&vault.reserves[0].address))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn test_close_vault_with_pending_fees() {
+    let mut ctx = TestContext::start().await;
+    let vault = ctx.setup_vault(1).await;
+    ctx.update_vault_config(&vault, VaultConfigField::ManagementFeeBps, 1_000_u64)
+        .await
+        .assert_ok();
+    let user = ctx.create_user(&vault, 10 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &user, 10 * ONE_TOKEN).await.assert_ok();
+    ctx.advance_time(SECONDS_PER_YEAR_U64).await;
+
+    ctx.update_vault_config(
+        &vault,
+        VaultConfigField::LifecycleState,
+        VaultLifecycleState::WithdrawOnly,
+    )
+    .await
+    .assert_ok();
+    let user_shares = ctx.shares_balance(&vault, &user.pubkey()).await;
+    let withdraw_ix =
+        instructions::withdraw_from_available(&vault.accounts, user.pubkey(), user_shares);
+    ctx.process(&[withdraw_ix], &[&user]).await.assert_ok();
+
+    // The fees are not swept to the admin with the rest of the token vault
+    let close_vault_ix = instructions::close_vault(&vault.accounts);
+    ctx.process_as_admin(&[close_vault_ix.clone()])
+        .await
+        .assert_error(KaminoVaultError::PendingFeesNotWithdrawn);
+
+    let withdraw_fees_ix = instructions::withdraw_pending_fees(&vault.accounts, &vault.reserve(0));
+    ctx.process_as_admin(&[withdraw_fees_ix]).await.assert_ok();
+    ctx.process_as_admin(&[close_vault_ix]).await.assert_ok();
+    assert!(!ctx.account_exists(&vault.vault_state()).await);
+}
@@ -0,0 +1,211 @@
+#![cfg(feature = "test-sbf")]
+
+use kvault_integration_tests::{
+    kvault_client::{
+        instructions,
+        kamino_vault::{
+            utils::consts::PAUSE_WITHDRAWALS, KaminoVaultError, WithdrawalRequest,
+            WithdrawalRequestStatus,
+        },
+        pda,
+    },
+    vault::ONE_TOKEN,
+    TestContext, VaultFixture,
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+async fn withdrawal_request(
+    ctx: &mut TestContext,
+    vault: &VaultFixture,
+    user: &Pubkey,
+    request_id: u64,
+) -> WithdrawalRequest {
+    ctx.anchor_account(&pda::withdrawal_request(
+        &vault.vault_state(),
+        user,
+        request_id,
+    ))
+    .await
+}
+
+/// Vault with everything invested and two users holding 100 shares each
+async fn setup_invested_vault(ctx: &mut TestContext) -> (VaultFixture, Keypair, Keypair) {
+    let vault = ctx.setup_vault(1).await;
+    let first_user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    let second_user = ctx.create_user(&vault, 100 * ONE_TOKEN).await;
+    ctx.deposit(&vault, &first_user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.deposit(&vault, &second_user, 100 * ONE_TOKEN)
+        .await
+        .assert_ok();
+    ctx.invest(&vault, 0).await.assert_ok();
+
+    (vault, first_user, second_user)
+}
+
+#[tokio::test]
+async fn test_request_fulfil_claim() {
+    let mut ctx = TestContext::start().await;
+    let (vault, user, _) = setup_invested_vault(&mut ctx).await;
+
+    let request_ix =
+        instructions::request_withdrawal(&vault.accounts, user.pubkey(), 0, 40 * ONE_TOKEN);
+    let outcome = ctx.process(&[request_ix], &[&user]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+
+    let escrow = pda::withdrawal_queue_escrow(&vault.vault_state());
+    assert_eq!(ctx.token_balance(&escrow).await, 40 * ONE_TOKEN);
+    assert_eq!(
+        ctx.shares_balance(&vault, &user.pubkey()).await,
+        60 * ONE_TOKEN
+    );
+    let request = withdrawal_request(&mut ctx, &vault, &user.pubkey(), 0).await;
+    assert_eq!(request.status, WithdrawalRequestStatus::Pending);
+    assert_eq!(request.shares_escrowed, 40 * ONE_TOKEN);
+
+    let claim_ix = instructions::claim_withdrawal_request(&vault.accounts, user.pubkey(), 0);
+    ctx.process(&[claim_ix.clone()], &[&user])
+        .await
+        .assert_error(KaminoVaultError::WithdrawalRequestNotClaimable);
+
+    // Anyone can fulfil the next request, disinvesting from the given reserve
+    let fulfil_ix = instructions::fulfil_withdrawal_request(
+        &vault.accounts,
+        user.pubkey(),
+        0,
+        &vault.reserve(0),
+    );
+    let outcome = ctx.process(&[fulfil_ix], &[]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+
+    let request = withdrawal_request(&mut ctx, &vault, &user.pubkey(), 0).await;
+    assert_eq!(request.status, WithdrawalRequestStatus::Fulfilled);
+    assert!(request.tokens_claimable > 0);
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(
+        state.withdrawal_queue.claimable_tokens,
+        request.tokens_claimable
+    );
+
+    let outcome = ctx.process(&[claim_ix], &[&user]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+
+    assert_eq!(
+        ctx.base_balance(&vault, &user.pubkey()).await,
+        request.tokens_claimable
+    );
+    assert!(request.tokens_claimable + 1 >= 40 * ONE_TOKEN);
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.withdrawal_queue.claimable_tokens, 0);
+    assert_eq!(state.withdrawal_queue.escrowed_shares, 0);
+}
+
+#[tokio::test]
+async fn test_requests_are_fulfilled_in_order() {
+    let mut ctx = TestContext::start().await;
+    let (vault, first_user, second_user) = setup_invested_vault(&mut ctx).await;
+
+    for (request_id, user) in [&first_user, &second_user].into_iter().enumerate() {
+        let request_ix = instructions::request_withdrawal(
+            &vault.accounts,
+            user.pubkey(),
+            request_id as u64,
+            10 * ONE_TOKEN,
+        );
+        ctx.process(&[request_ix], &[user]).await.assert_ok();
+    }
+
+    let fulfil_second_ix = instructions::fulfil_withdrawal_request(
+        &vault.accounts,
+        second_user.pubkey(),
+        1,
+        &vault.reserve(0),
+    );
+    ctx.process(&[fulfil_second_ix.clone()], &[])
+        .await
+        .assert_error(KaminoVaultError::WithdrawalRequestNotNextInQueue);
+
+    let fulfil_first_ix = instructions::fulfil_withdrawal_request(
+        &vault.accounts,
+        first_user.pubkey(),
+        0,
+        &vault.reserve(0),
+    );
+    ctx.process(&[fulfil_first_ix], &[]).await.assert_ok();
+    ctx.process(&[fulfil_second_ix], &[]).await.assert_ok();
+
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.withdrawal_queue.next_request_id_to_fulfil, 2);
+}
+
+#[tokio::test]
+async fn test_cancel_withdrawal_request() {
+    let mut ctx = TestContext::start().await;
+    let (vault, user, _) = setup_invested_vault(&mut ctx).await;
+
+    let request_ix =
+        instructions::request_withdrawal(&vault.accounts, user.pubkey(), 0, 25 * ONE_TOKEN);
+    ctx.process(&[request_ix], &[&user]).await.assert_ok();
+
+    let cancel_ix = instructions::cancel_withdrawal_request(&vault.accounts, user.pubkey(), 0);
+    let outcome = ctx.process(&[cancel_ix], &[&user]).await;
+    outcome.assert_ok();
+    assert_eq!(outcome.events_emitted(), 1);
+
+    assert_eq!(
+        ctx.shares_balance(&vault, &user.pubkey()).await,
+        100 * ONE_TOKEN
+    );
+    let request = withdrawal_request(&mut ctx, &vault, &user.pubkey(), 0).await;
+    assert_eq!(request.status, WithdrawalRequestStatus::Cancelled);
+    let state = ctx.vault_state(&vault.vault_state()).await;
+    assert_eq!(state.withdrawal_queue.escrowed_shares, 0);
+
+    // The cancelled request no longer holds up the queue
+    assert_eq!(state.withdrawal_queue.next_request_id_to_fulfil, 1);
+    let cancel_ix = instructions::cancel_withdrawal_request(&vault.accounts, user.pubkey(), 0);
+    ctx.process(&[cancel_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::WithdrawalRequestNotPending);
+
+    // Each request has its own account, the user can request again
+    let request_ix =
+        instructions::request_withdrawal(&vault.accounts, user.pubkey(), 1, 25 * ONE_TOKEN);
+    ctx.process(&[request_ix], &[&user]).await.assert_ok();
+    let request = withdrawal_request(&mut ctx, &vault, &user.pubkey(), 1).await;
+    assert_eq!(request.status, WithdrawalRequestStatus::Pending);
+}
+
+#[tokio::test]
+async fn test_withdrawal_requests_paused() {
+    let mut ctx = TestContext::start().await;
+    let (vault, user, _) = setup_invested_vault(&mut ctx).await;
+
+    let request_ix =
+        instructions::request_withdrawal(&vault.accounts, user.pubkey(), 0, 25 * ONE_TOKEN);
+    ctx.process(&[request_ix], &[&user]).await.assert_ok();
+
+    let pause_ix = instructions::pause_vault_operations(
+        &vault.accounts,
+        ctx.admin.pubkey(),
+        PAUSE_WITHDRAWALS,
+    );
+    ctx.process_as_admin(&[pause_ix]).await.assert_ok();
+
+    let request_ix =
+        instructions::request_withdrawal(&vault.accounts, user.pubkey(), 1, 25 * ONE_TOKEN);
+    ctx.process(&[request_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::OperationPaused);
+    let cancel_ix = instructions::cancel_withdrawal_request(&vault.accounts, user.pubkey(), 0);
+    ctx.process(&[cancel_ix], &[&user])
+        .await
+        .assert_error(KaminoVaultError::OperationPaused);
+}
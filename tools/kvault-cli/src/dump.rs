@@ -0,0 +1,95 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+
+/// Account dump given on the command line as `[ADDRESS=]FILE`
+#[derive(Clone, Debug)]
+pub struct DumpArg {
+    pub address: Option<Pubkey>,
+    pub path: PathBuf,
+}
+
+impl FromStr for DumpArg {
+    type Err = anyhow::Error;
+
+    fn from_str(arg: &str) -> Result<Self> {
+        match arg.split_once('=') {
+            Some((address, path)) => Ok(Self {
+                address: Some(
+                    Pubkey::from_str(address)
+                        .with_context(|| format!("Invalid address {address}"))?,
+                ),
+                path: path.into(),
+            }),
+            None => Ok(Self {
+                address: None,
+                path: arg.into(),
+            }),
+        }
+    }
+}
+
+pub struct AccountDump {
+    pub address: Option<Pubkey>,
+    pub data: Vec<u8>,
+}
+
+impl AccountDump {
+    /// Reads either the JSON written by `solana account --output json` or a file holding
+    /// the base64 encoded account data. The address given on the command line takes
+    /// precedence over the one of the JSON dump
+    pub fn load(arg: &DumpArg) -> Result<Self> {
+        let content = fs::read_to_string(&arg.path)
+            .with_context(|| format!("Could not read {}", arg.path.display()))?;
+        let content = content.trim();
+
+        let dump = if content.starts_with('{') {
+            parse_json(content)
+        } else {
+            decode_base64(content).map(|data| AccountDump {
+                address: None,
+                data,
+            })
+        }
+        .with_context(|| format!("Invalid account dump {}", arg.path.display()))?;
+
+        Ok(AccountDump {
+            address: arg.address.or(dump.address),
+            data: dump.data,
+        })
+    }
+}
+
+fn parse_json(content: &str) -> Result<AccountDump> {
+    let dump: Value = serde_json::from_str(content)?;
+    let address = dump
+        .get("pubkey")
+        .and_then(Value::as_str)
+        .map(Pubkey::from_str)
+        .transpose()?;
+    // Plain account JSON, as returned by `getAccountInfo`, is accepted as well
+    let account = dump.get("account").unwrap_or(&dump);
+
+    let data = match account.get("data") {
+        Some(Value::Array(data)) => match data.as_slice() {
+            [Value::String(data), Value::String(encoding)] if encoding == "base64" => {
+                decode_base64(data)?
+            }
+            [_, encoding] => bail!("Unsupported data encoding {encoding}, use base64"),
+            _ => bail!("Expected the account data as [data, encoding]"),
+        },
+        _ => return Err(anyhow!("No account data found")),
+    };
+
+    Ok(AccountDump { address, data })
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>> {
+    let data: String = data.split_whitespace().collect();
+    STANDARD
+        .decode(data)
+        .context("Account data is not valid base64")
+}
@@ -0,0 +1,75 @@
+//! Offline inspector of a kvault vault state.
+//!
+//! Prints a report of a vault from account dumps, so it does not need an RPC node:
+//!
+//! ```sh
+//! solana account <VAULT> --output json > vault.json
+//! solana account <RESERVE> --output json > reserve.json
+//! kvault-cli --vault vault.json --reserve reserve.json
+//! ```
+//!
+//! Dumps holding only the base64 encoded account data are accepted as well, the reserve
+//! addresses then have to be given as `--reserve <ADDRESS>=<FILE>`. The allocations are
+//! only valued when the dumps of all the reserves the vault is allocated to are given.
+
+mod dump;
+mod report;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Parser;
+use kvault_client::accounts::{decode_reserve, decode_vault_state};
+
+use crate::{
+    dump::{AccountDump, DumpArg},
+    report::VaultReport,
+};
+
+#[derive(Parser)]
+#[command(about = "Prints a report of a kvault vault state from account dumps")]
+struct Args {
+    /// Vault state dump, as `[ADDRESS=]FILE`
+    #[arg(long)]
+    vault: DumpArg,
+
+    /// Dump of a reserve the vault is allocated to, as `[ADDRESS=]FILE`
+    #[arg(long = "reserve")]
+    reserves: Vec<DumpArg>,
+
+    /// Prints the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let vault_dump = AccountDump::load(&args.vault)?;
+    let vault = decode_vault_state(&vault_dump.data)
+        .map_err(|err| anyhow!("{err}"))
+        .with_context(|| format!("{} is not a vault state", args.vault.path.display()))?;
+
+    let mut reserves = Vec::with_capacity(args.reserves.len());
+    for reserve_arg in args.reserves.iter() {
+        let reserve_dump = AccountDump::load(reserve_arg)?;
+        let Some(address) = reserve_dump.address else {
+            bail!(
+                "The address of reserve {} is unknown, pass it as ADDRESS={}",
+                reserve_arg.path.display(),
+                reserve_arg.path.display()
+            );
+        };
+        let reserve = decode_reserve(&reserve_dump.data)
+            .map_err(|err| anyhow!("{err}"))
+            .with_context(|| format!("{} is not a reserve", reserve_arg.path.display()))?;
+        reserves.push((address, reserve));
+    }
+
+    let report = VaultReport::new(vault_dump.address, &vault, &reserves)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{report}");
+    }
+
+    Ok(())
+}
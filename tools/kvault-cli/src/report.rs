@@ -0,0 +1,505 @@
+use std::fmt;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Result};
+use kamino_lending::{fraction::Fraction, utils::FractionExtra, Reserve};
+use kamino_vault::{
+    operations::vault_operations::string_utils::encoded_name_to_label,
+    utils::consts::{
+        PAUSE_DEPOSITS, PAUSE_FEE_WITHDRAWAL, PAUSE_INVEST, PAUSE_REDEEM_IN_KIND,
+        PAUSE_REWARD_DISTRIBUTION, PAUSE_WITHDRAWALS,
+    },
+    VaultState,
+};
+use kvault_client::math::{self, ReserveSnapshot};
+use serde::Serialize;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+const PAUSE_FLAGS: [(u64, &str); 6] = [
+    (PAUSE_DEPOSITS, "deposits"),
+    (PAUSE_WITHDRAWALS, "withdrawals"),
+    (PAUSE_INVEST, "invest"),
+    (PAUSE_REDEEM_IN_KIND, "redeem in kind"),
+    (PAUSE_FEE_WITHDRAWAL, "fee withdrawal"),
+    (PAUSE_REWARD_DISTRIBUTION, "reward distribution"),
+];
+
+/// Token amounts are in token lamports, the fractions are serialized as decimal strings
+#[derive(Serialize)]
+pub struct VaultReport {
+    pub address: Option<String>,
+    pub name: String,
+    pub lifecycle_state: String,
+    pub token_mint: String,
+    pub token_decimals: u64,
+    pub shares_mint: String,
+    pub shares_issued: u64,
+    pub authorities: Authorities,
+    pub fees: Fees,
+    pub paused_operations: Vec<&'static str>,
+    pub holdings: Holdings,
+    pub allocations: Vec<AllocationReport>,
+    pub pending_fees: PendingFees,
+    pub rewards: Rewards,
+    pub analytics: Analytics,
+}
+
+#[derive(Serialize)]
+pub struct Authorities {
+    pub vault_admin: String,
+    pub pending_admin: String,
+    pub allocation_admin: String,
+    pub emergency_guardian: String,
+}
+
+#[derive(Serialize)]
+pub struct Fees {
+    pub performance_fee_bps: u64,
+    pub management_fee_bps: u64,
+    pub performance_fee_hurdle_bps: u64,
+    pub withdrawal_penalty_lamports: u64,
+    pub withdrawal_penalty_bps: u64,
+    pub fees_in_shares: bool,
+    pub fee_recipients: Vec<FeeRecipientReport>,
+}
+
+#[derive(Serialize)]
+pub struct FeeRecipientReport {
+    pub recipient: String,
+    pub bps: u64,
+}
+
+/// The valuation needs the dumps of all the allocated reserves, it is made at the
+/// exchange rates of the dumps, as of the latest slot they were refreshed at
+#[derive(Serialize)]
+pub struct Holdings {
+    pub token_available: u64,
+    pub unallocated_weight: u64,
+    pub unallocated_tokens_cap: u64,
+    pub invested: Option<Fraction>,
+    pub aum: Option<Fraction>,
+    pub share_price: Option<Fraction>,
+    pub valued_at_slot: Option<u64>,
+    pub missing_reserves: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct AllocationReport {
+    pub reserve: String,
+    pub ctoken_vault: String,
+    pub weight: u64,
+    pub cap: u64,
+    pub ctokens: u64,
+    pub last_invest_slot: u64,
+    pub liquidity_value: Option<Fraction>,
+    pub target_liquidity: Option<Fraction>,
+}
+
+#[derive(Serialize)]
+pub struct PendingFees {
+    pub pending_fees: Fraction,
+    pub pending_fee_shares: u64,
+    pub fee_shares_recipient: String,
+}
+
+#[derive(Serialize)]
+pub struct Rewards {
+    pub reward_per_second: u64,
+    pub rewards_available: u64,
+    pub last_issuance_ts: u64,
+    pub runway_seconds: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct Analytics {
+    pub cumulative_earned_interest: Fraction,
+    pub cumulative_mgmt_fees: Fraction,
+    pub cumulative_perf_fees: Fraction,
+    pub cumulative_hurdle_excluded_interest: Fraction,
+    pub cumulative_first_loss_absorbed: Fraction,
+    pub cumulative_rewards_distributed: u64,
+    pub high_water_mark: Fraction,
+}
+
+struct Valuation {
+    invested: Fraction,
+    aum: Fraction,
+    slot: u64,
+    // Indexed as the vault allocations
+    liquidity_targets: Vec<Fraction>,
+}
+
+impl VaultReport {
+    /// `reserves` can hold any reserve, the ones the vault is not allocated to are ignored
+    pub fn new(
+        address: Option<Pubkey>,
+        vault: &VaultState,
+        reserves: &[(Pubkey, Box<Reserve>)],
+    ) -> Result<Self> {
+        let find_reserve = |address: &Pubkey| {
+            reserves
+                .iter()
+                .find(|(reserve_address, _)| reserve_address == address)
+                .map(|(_, reserve)| reserve)
+        };
+        let allocations: Vec<_> = vault
+            .vault_allocation_strategy
+            .iter()
+            .filter(|allocation| allocation.reserve != Pubkey::default())
+            .collect();
+        let missing_reserves: Vec<Pubkey> = allocations
+            .iter()
+            .map(|allocation| allocation.reserve)
+            .filter(|reserve| find_reserve(reserve).is_none())
+            .collect();
+
+        let valuation = if missing_reserves.is_empty() {
+            let reserves = allocations
+                .iter()
+                .map(|allocation| {
+                    (
+                        allocation.reserve,
+                        find_reserve(&allocation.reserve).unwrap().as_ref(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            Some(value_vault(vault, &reserves)?)
+        } else {
+            None
+        };
+
+        let allocations = allocations
+            .iter()
+            .enumerate()
+            .map(|(idx, allocation)| AllocationReport {
+                reserve: allocation.reserve.to_string(),
+                ctoken_vault: allocation.ctoken_vault.to_string(),
+                weight: allocation.target_allocation_weight,
+                cap: allocation.token_allocation_cap,
+                ctokens: allocation.ctoken_allocation,
+                last_invest_slot: allocation.last_invest_slot,
+                liquidity_value: find_reserve(&allocation.reserve).map(|reserve| {
+                    reserve
+                        .collateral_exchange_rate()
+                        .fraction_collateral_to_liquidity(allocation.ctoken_allocation.into())
+                }),
+                target_liquidity: valuation
+                    .as_ref()
+                    .map(|valuation| valuation.liquidity_targets[idx]),
+            })
+            .collect();
+
+        let fee_recipients = vault
+            .fee_recipients
+            .iter()
+            .filter(|fee_recipient| fee_recipient.recipient != Pubkey::default())
+            .map(|fee_recipient| FeeRecipientReport {
+                recipient: fee_recipient.recipient.to_string(),
+                bps: fee_recipient.bps,
+            })
+            .collect();
+
+        let reward_info = &vault.reward_info;
+        let runway_seconds = (reward_info.reward_per_second > 0)
+            .then(|| reward_info.rewards_available / reward_info.reward_per_second);
+
+        Ok(Self {
+            address: address.map(|address| address.to_string()),
+            name: encoded_name_to_label(&vault.name, vault.token_mint),
+            lifecycle_state: format!(
+                "{:?}",
+                vault
+                    .get_lifecycle_state()
+                    .map_err(|err| anyhow!("Could not read the vault lifecycle state: {err}"))?
+            ),
+            token_mint: vault.token_mint.to_string(),
+            token_decimals: vault.token_mint_decimals,
+            shares_mint: vault.shares_mint.to_string(),
+            shares_issued: vault.shares_issued,
+            authorities: Authorities {
+                vault_admin: vault.vault_admin_authority.to_string(),
+                pending_admin: vault.pending_admin.to_string(),
+                allocation_admin: vault.allocation_admin.to_string(),
+                emergency_guardian: vault.emergency_guardian.to_string(),
+            },
+            fees: Fees {
+                performance_fee_bps: vault.performance_fee_bps,
+                management_fee_bps: vault.management_fee_bps,
+                performance_fee_hurdle_bps: vault.performance_fee_hurdle_bps,
+                withdrawal_penalty_lamports: vault.withdrawal_penalty_lamports,
+                withdrawal_penalty_bps: vault.withdrawal_penalty_bps,
+                fees_in_shares: vault.vault_takes_fees_in_shares(),
+                fee_recipients,
+            },
+            paused_operations: PAUSE_FLAGS
+                .iter()
+                .filter(|(flag, _)| vault.paused_operations & flag != 0)
+                .map(|(_, operation)| *operation)
+                .collect(),
+            holdings: Holdings {
+                token_available: vault.token_available,
+                unallocated_weight: vault.unallocated_weight,
+                unallocated_tokens_cap: vault.unallocated_tokens_cap,
+                invested: valuation.as_ref().map(|valuation| valuation.invested),
+                aum: valuation.as_ref().map(|valuation| valuation.aum),
+                share_price: valuation
+                    .as_ref()
+                    .filter(|_| vault.shares_issued > 0)
+                    .map(|valuation| valuation.aum / Fraction::from(vault.shares_issued)),
+                valued_at_slot: valuation.as_ref().map(|valuation| valuation.slot),
+                missing_reserves: missing_reserves.iter().map(Pubkey::to_string).collect(),
+            },
+            allocations,
+            pending_fees: PendingFees {
+                pending_fees: vault.get_pending_fees(),
+                pending_fee_shares: vault.pending_fee_shares,
+                fee_shares_recipient: vault.fee_shares_recipient.to_string(),
+            },
+            rewards: Rewards {
+                reward_per_second: reward_info.reward_per_second,
+                rewards_available: reward_info.rewards_available,
+                last_issuance_ts: reward_info.last_issuance_ts,
+                runway_seconds,
+            },
+            analytics: Analytics {
+                cumulative_earned_interest: vault.get_cumulative_earned_interest(),
+                cumulative_mgmt_fees: vault.get_cumulative_mgmt_fees(),
+                cumulative_perf_fees: vault.get_cumulative_perf_fees(),
+                cumulative_hurdle_excluded_interest: vault
+                    .get_cumulative_hurdle_excluded_interest(),
+                cumulative_first_loss_absorbed: vault.get_cumulative_first_loss_absorbed(),
+                cumulative_rewards_distributed: reward_info
+                    .cumulative_rewards_distributed_analytics,
+                high_water_mark: vault.get_high_water_mark(),
+            },
+        })
+    }
+}
+
+// Values the vault as the program would after refreshing the reserves at the latest slot
+// of the dumps, without the interest accrued since the older dumps were refreshed
+fn value_vault(vault: &VaultState, reserves: &[(Pubkey, &Reserve)]) -> Result<Valuation> {
+    let slot = reserves
+        .iter()
+        .map(|(_, reserve)| reserve.last_update.slot)
+        .max()
+        .unwrap_or_default();
+    let reserve_snapshots: Vec<ReserveSnapshot> = reserves
+        .iter()
+        .map(|(address, reserve)| {
+            let mut reserve = **reserve;
+            reserve.last_update.slot = slot;
+            reserve.last_update.stale = 0;
+            ReserveSnapshot::new(*address, reserve)
+        })
+        .collect();
+
+    let holdings = math::holdings(vault, &reserve_snapshots, slot)
+        .map_err(|err| anyhow!("Could not compute the vault holdings: {err}"))?;
+    let aum = vault
+        .compute_aum(&holdings.invested.total)
+        .map_err(|err| anyhow!("Could not compute the vault AUM: {err}"))?;
+
+    let mut vault_with_targets = Box::new(*vault);
+    vault_with_targets
+        .refresh_target_allocations(&holdings.invested)
+        .map_err(|err| anyhow!("Could not compute the target allocations: {err}"))?;
+    let liquidity_targets = vault_with_targets
+        .vault_allocation_strategy
+        .iter()
+        .filter(|allocation| allocation.reserve != Pubkey::default())
+        .map(|allocation| allocation.get_token_target_allocation())
+        .collect();
+
+    Ok(Valuation {
+        invested: holdings.invested.total,
+        aum,
+        slot,
+        liquidity_targets,
+    })
+}
+
+fn display_optional(value: Option<Fraction>) -> String {
+    value.map_or_else(|| "n/a".to_string(), |value| value.to_display().to_string())
+}
+
+impl fmt::Display for VaultReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Vault {}",
+            self.address.as_deref().unwrap_or("(unknown address)")
+        )?;
+        writeln!(f, "  name:               {}", self.name)?;
+        writeln!(f, "  lifecycle state:    {}", self.lifecycle_state)?;
+        writeln!(
+            f,
+            "  token mint:         {} ({} decimals)",
+            self.token_mint, self.token_decimals
+        )?;
+        writeln!(f, "  shares mint:        {}", self.shares_mint)?;
+        writeln!(f, "  shares issued:      {}", self.shares_issued)?;
+        let paused_operations = if self.paused_operations.is_empty() {
+            "none".to_string()
+        } else {
+            self.paused_operations.join(", ")
+        };
+        writeln!(f, "  paused operations:  {paused_operations}")?;
+
+        writeln!(f, "\nAuthorities")?;
+        writeln!(f, "  vault admin:        {}", self.authorities.vault_admin)?;
+        writeln!(
+            f,
+            "  pending admin:      {}",
+            self.authorities.pending_admin
+        )?;
+        writeln!(
+            f,
+            "  allocation admin:   {}",
+            self.authorities.allocation_admin
+        )?;
+        writeln!(
+            f,
+            "  emergency guardian: {}",
+            self.authorities.emergency_guardian
+        )?;
+
+        let fees = &self.fees;
+        writeln!(f, "\nFees")?;
+        writeln!(f, "  performance fee:    {} bps", fees.performance_fee_bps)?;
+        writeln!(f, "  management fee:     {} bps", fees.management_fee_bps)?;
+        writeln!(
+            f,
+            "  performance hurdle: {} bps",
+            fees.performance_fee_hurdle_bps
+        )?;
+        writeln!(
+            f,
+            "  withdrawal penalty: {} bps, min {} lamports",
+            fees.withdrawal_penalty_bps, fees.withdrawal_penalty_lamports
+        )?;
+        writeln!(f, "  fees in shares:     {}", fees.fees_in_shares)?;
+        for fee_recipient in fees.fee_recipients.iter() {
+            writeln!(
+                f,
+                "  fee recipient:      {} ({} bps)",
+                fee_recipient.recipient, fee_recipient.bps
+            )?;
+        }
+
+        let holdings = &self.holdings;
+        writeln!(f, "\nHoldings")?;
+        writeln!(f, "  token available:    {}", holdings.token_available)?;
+        writeln!(
+            f,
+            "  unallocated:        weight {}, cap {}",
+            holdings.unallocated_weight, holdings.unallocated_tokens_cap
+        )?;
+        writeln!(
+            f,
+            "  invested:           {}",
+            display_optional(holdings.invested)
+        )?;
+        writeln!(
+            f,
+            "  AUM:                {}",
+            display_optional(holdings.aum)
+        )?;
+        writeln!(
+            f,
+            "  share price:        {}",
+            display_optional(holdings.share_price)
+        )?;
+        if let Some(slot) = holdings.valued_at_slot {
+            writeln!(f, "  valued at slot:     {slot}")?;
+        }
+        for reserve in holdings.missing_reserves.iter() {
+            writeln!(f, "  missing reserve:    {reserve}")?;
+        }
+
+        writeln!(f, "\nAllocations")?;
+        for allocation in self.allocations.iter() {
+            writeln!(f, "  reserve {}", allocation.reserve)?;
+            writeln!(f, "    ctoken vault:     {}", allocation.ctoken_vault)?;
+            writeln!(
+                f,
+                "    weight:           {}, cap {}",
+                allocation.weight, allocation.cap
+            )?;
+            writeln!(f, "    ctokens:          {}", allocation.ctokens)?;
+            writeln!(
+                f,
+                "    liquidity:        {} (target {})",
+                display_optional(allocation.liquidity_value),
+                display_optional(allocation.target_liquidity)
+            )?;
+            writeln!(f, "    last invest slot: {}", allocation.last_invest_slot)?;
+        }
+
+        let pending_fees = &self.pending_fees;
+        writeln!(f, "\nPending fees")?;
+        writeln!(
+            f,
+            "  pending fees:       {}",
+            pending_fees.pending_fees.to_display()
+        )?;
+        writeln!(
+            f,
+            "  pending fee shares: {} (recipient {})",
+            pending_fees.pending_fee_shares, pending_fees.fee_shares_recipient
+        )?;
+
+        let rewards = &self.rewards;
+        writeln!(f, "\nRewards")?;
+        writeln!(f, "  reward per second:  {}", rewards.reward_per_second)?;
+        writeln!(f, "  rewards available:  {}", rewards.rewards_available)?;
+        writeln!(f, "  last issuance:      {}", rewards.last_issuance_ts)?;
+        match rewards.runway_seconds {
+            Some(runway_seconds) => writeln!(
+                f,
+                "  runway:             {runway_seconds} s ({} days)",
+                runway_seconds / SECONDS_PER_DAY
+            )?,
+            None => writeln!(f, "  runway:             n/a")?,
+        }
+
+        let analytics = &self.analytics;
+        writeln!(f, "\nAnalytics")?;
+        writeln!(
+            f,
+            "  earned interest:    {}",
+            analytics.cumulative_earned_interest.to_display()
+        )?;
+        writeln!(
+            f,
+            "  management fees:    {}",
+            analytics.cumulative_mgmt_fees.to_display()
+        )?;
+        writeln!(
+            f,
+            "  performance fees:   {}",
+            analytics.cumulative_perf_fees.to_display()
+        )?;
+        writeln!(
+            f,
+            "  hurdle excluded:    {}",
+            analytics.cumulative_hurdle_excluded_interest.to_display()
+        )?;
+        writeln!(
+            f,
+            "  first loss taken:   {}",
+            analytics.cumulative_first_loss_absorbed.to_display()
+        )?;
+        writeln!(
+            f,
+            "  rewards paid out:   {}",
+            analytics.cumulative_rewards_distributed
+        )?;
+        write!(
+            f,
+            "  high water mark:    {}",
+            analytics.high_water_mark.to_display()
+        )
+    }
+}